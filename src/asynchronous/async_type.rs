@@ -1,5 +1,64 @@
-use crate::components::{buffer::Buffer, packet::Packet};
+use crate::components::{buffer::Buffer, envelope::PacketEnvelope};
+use crate::subsystems::qtp_report::QtpStep;
+use crate::subsystems::run_monitor::RunOutcome;
+use crate::subsystems::sensor_positions::Pose;
+use crate::subsystems::sensor_subsystem::incidence::IncidenceReport;
+use crate::subsystems::state_navigation::navcon::NavConDecision;
+use crate::subsystems::test_bench::Prompt;
 use std::sync::{Arc, Mutex};
 
 pub type PositionsEndpoint = Arc<Mutex<Buffer<[(f32, f32); 5]>>>;
-pub type PacketsEndpoint = Arc<Mutex<Buffer<Packet>>>;
+pub type PacketsEndpoint = Arc<Mutex<Buffer<PacketEnvelope>>>;
+pub type DecisionsEndpoint = Arc<Mutex<Buffer<NavConDecision>>>;
+pub type IncidenceEndpoint = Arc<Mutex<Buffer<IncidenceReport>>>;
+pub type PosesEndpoint = Arc<Mutex<Buffer<Pose>>>;
+pub type OutcomeEndpoint = Arc<Mutex<Buffer<RunOutcome>>>;
+pub type StepsEndpoint = Arc<Mutex<Buffer<QtpStep>>>;
+pub type PromptsEndpoint = Arc<Mutex<Buffer<Prompt>>>;
+pub type MeasurementsEndpoint = Arc<Mutex<Buffer<Option<f32>>>>;
+
+/// The endpoints on which the GUI receives what happens during a run
+#[derive(Clone)]
+pub struct GuiEndpoints {
+    /// the positions of the sensors
+    pub positions: PositionsEndpoint,
+    /// every packet sent by the subsystems, with who sent it, to whom and when
+    pub packets: PacketsEndpoint,
+    /// every decision made by NAVCON
+    pub decisions: DecisionsEndpoint,
+    /// the incidences worked out by the emulated SS
+    pub incidence: IncidenceEndpoint,
+    /// the ground-truth poses of the emulated MARV
+    pub poses: PosesEndpoint,
+    /// how the run ended
+    pub outcome: OutcomeEndpoint,
+    /// each step of a QTP run on the test bench, once it has been carried out
+    pub steps: StepsEndpoint,
+    /// what the operator is asked to do during a QTP run on the test bench
+    pub prompts: PromptsEndpoint,
+    /// what the operator measured when asked to (`None` if they chose not to), which the
+    /// GUI sends back to the test bench
+    pub measurements: MeasurementsEndpoint,
+}
+
+impl GuiEndpoints {
+    pub fn new() -> Self {
+        Self {
+            positions: Arc::new(Mutex::new(Buffer::new())),
+            packets: Arc::new(Mutex::new(Buffer::new())),
+            decisions: Arc::new(Mutex::new(Buffer::new())),
+            incidence: Arc::new(Mutex::new(Buffer::new())),
+            poses: Arc::new(Mutex::new(Buffer::new())),
+            outcome: Arc::new(Mutex::new(Buffer::new())),
+            steps: Arc::new(Mutex::new(Buffer::new())),
+            prompts: Arc::new(Mutex::new(Buffer::new())),
+            measurements: Arc::new(Mutex::new(Buffer::new())),
+        }
+    }
+}
+
+impl Default for GuiEndpoints {
+    fn default() -> Self {
+        Self::new()
+    }
+}
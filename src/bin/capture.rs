@@ -0,0 +1,52 @@
+//! Exports the packets of a recorded QTP run, decoded, for analysis in a spreadsheet or
+//! for a lab report, e.g.
+//!
+//! ```sh
+//! cargo run --bin capture -- sessions/navcon_qtp1_1675677095.csv qtp1.jsonl
+//! ```
+//!
+//! The format (CSV or JSONL) is chosen by the extension of the output file.
+
+use std::process;
+
+use epr320_dev_test::subsystems::{
+    packet_capture::{CaptureFormat, PacketCapture},
+    session::Session,
+};
+
+const USAGE: &str = "usage: capture <recording> <output.csv | output.jsonl>";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let (recording, output) = match &args[..] {
+        [recording, output] => (recording, output),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+
+    let format = match CaptureFormat::from_path(output) {
+        Some(format) => format,
+        None => {
+            eprintln!("{}: unknown format\n{}", output, USAGE);
+            process::exit(1);
+        }
+    };
+
+    let capture = match Session::load(recording) {
+        Ok(session) => PacketCapture::from(&session),
+        Err(err) => {
+            eprintln!("{}: {}", recording, err);
+            process::exit(1);
+        }
+    };
+
+    if let Err(err) = capture.export(output, format) {
+        eprintln!("{}: {}", output, err);
+        process::exit(1);
+    }
+
+    println!("exported {} packets to {}", capture.packets().len(), output);
+}
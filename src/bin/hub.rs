@@ -0,0 +1,129 @@
+//! Runs the kit as the hub between the physical subsystems, without the GUI, e.g.
+//!
+//! ```sh
+//! cargo run --bin hub -- 3 4 5 hub.jsonl
+//! ```
+//!
+//! connects the SNC on COM3, the SS on COM4 and the MDPS on COM5 to each other through the
+//! kit, prints every packet passed on and every violation of the protocol, and exports
+//! every packet captured to `hub.jsonl` once the hub is quit. While it runs, the hub reads
+//! commands from the terminal: `start` and `end` send the hub's start and end-of-maze
+//! packets, and `quit` disconnects the subsystems.
+
+use std::{
+    io, process,
+    sync::mpsc,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use epr320_dev_test::{
+    components::constants::{HUB_END_OF_MAZE, HUB_START},
+    subsystems::{hub::BusMonitor, packet_capture::CaptureFormat},
+};
+
+const USAGE: &str = "usage: hub <SNC port> <SS port> <MDPS port> [output.csv | output.jsonl]";
+const COMMANDS: &str = "commands: start, end, quit";
+
+/// how long to wait for packets sent after `quit`, before the subsystems are disconnected
+const SETTLE_PERIOD: Duration = Duration::from_millis(200);
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let (ports, output) = match &args[..] {
+        [snc, ss, mdps] => ([snc, ss, mdps], None),
+        [snc, ss, mdps, output] => ([snc, ss, mdps], Some(output)),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+
+    let format = match output {
+        Some(output) => match CaptureFormat::from_path(output) {
+            Some(format) => Some(format),
+            None => {
+                eprintln!("{}: unknown format\n{}", output, USAGE);
+                process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    // the commands are read on a thread of their own, so that no packet waits for them
+    let (commands, received) = mpsc::channel();
+
+    thread::spawn(move || {
+        for line in io::stdin().lines().map_while(Result::ok) {
+            if commands.send(line.trim().to_lowercase()).is_err() {
+                break;
+            }
+        }
+    });
+
+    println!("{}", COMMANDS);
+
+    let mut hub = BusMonitor::connect(&ports.map(|port| port.trim_start_matches("COM").into()));
+    let mut reported = 0;
+
+    loop {
+        match received.try_recv() {
+            Ok(command) => match command.as_str() {
+                "start" => hub.inject(HUB_START),
+                "end" => hub.inject(HUB_END_OF_MAZE),
+                "quit" => break,
+                _ => eprintln!("unknown command `{}`\n{}", command, COMMANDS),
+            },
+            // the terminal was closed
+            Err(mpsc::TryRecvError::Disconnected) => break,
+            Err(mpsc::TryRecvError::Empty) => (),
+        }
+
+        match hub.poll() {
+            Some(envelope) => println!("{}", envelope),
+            None => thread::sleep(Duration::from_millis(1)),
+        }
+
+        reported = report(&hub, reported);
+    }
+
+    // catch the packets sent just before the hub was quit
+    let quit = SystemTime::now();
+
+    while quit.elapsed().unwrap_or_default() < SETTLE_PERIOD {
+        match hub.poll() {
+            Some(envelope) => println!("{}", envelope),
+            None => thread::sleep(Duration::from_millis(1)),
+        }
+    }
+
+    hub.flush();
+    report(&hub, reported);
+
+    println!("{}", hub.monitor());
+
+    if let (Some(output), Some(format)) = (output, format) {
+        if let Err(err) = hub.capture().export(output, format) {
+            eprintln!("{}: {}", output, err);
+            process::exit(1);
+        }
+
+        println!(
+            "exported {} packets to {}",
+            hub.capture().packets().len(),
+            output
+        );
+    }
+}
+
+/// prints the violations found since the first `reported`, and returns how many have been
+fn report(hub: &BusMonitor, reported: usize) -> usize {
+    let violations = hub.monitor().violations();
+
+    for violation in &violations[reported..] {
+        println!("VIOLATION {}", violation);
+    }
+
+    violations.len()
+}
@@ -0,0 +1,149 @@
+//! Lists the QTPs that the kit can run, and runs one of them without the GUI, e.g.
+//!
+//! ```sh
+//! cargo run --bin qtp -- list
+//! cargo run --bin qtp -- "SS QTP 3"
+//! cargo run --bin qtp -- mdps-qtp-2 5
+//! ```
+//!
+//! lists every QTP, runs SS QTP 3 against the emulated SS, and runs MDPS QTP 2 against
+//! the physical MDPS on COM5. A physical QTP is given the port of each subsystem under
+//! test, in the order that `list` shows them in. What the operator is asked to do is
+//! printed as it is asked, and a measurement is answered by entering it (or nothing, to
+//! skip it). The QTP's report is printed once it has ended, and the exit code is 1 if
+//! it failed.
+
+use std::{io, process, sync::mpsc, thread, time::Duration};
+
+use epr320_dev_test::{
+    asynchronous::async_type::GuiEndpoints,
+    gui::test_windows::registry::{find, qtps},
+    subsystems::{
+        qtp::Qtp,
+        qtp_report::Verdict,
+        system::{EmulationConfig, Mode},
+        test_bench::Prompt,
+    },
+};
+
+const USAGE: &str = "usage: qtp list | qtp <QTP> [port of each subsystem under test]";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let (name, ports) = match &args[..] {
+        [command] if command == "list" => {
+            list();
+            return;
+        }
+        [name, ports @ ..] => (name, ports),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+
+    let qtp = match find(name) {
+        Some(qtp) => qtp,
+        None => {
+            eprintln!("{}: no such QTP\n{}", name, USAGE);
+            process::exit(1);
+        }
+    };
+
+    let mode = match ports.is_empty() {
+        true => Mode::Emulate,
+        false => Mode::Physical,
+    };
+
+    if !qtp.modes().contains(&mode) {
+        eprintln!("{} cannot be run in {} mode", qtp.name(), mode);
+        process::exit(1);
+    }
+
+    if mode == Mode::Physical && ports.len() != qtp.under_test().len() {
+        eprintln!(
+            "{} needs the port of {}\n{}",
+            qtp.name(),
+            subsystems(qtp),
+            USAGE
+        );
+        process::exit(1);
+    }
+
+    let ports: Vec<String> = ports
+        .iter()
+        .map(|port| port.trim_start_matches("COM").into())
+        .collect();
+
+    // the operator's measurements are read on a thread of their own, so that no step
+    // waits for them
+    let (lines, received) = mpsc::channel();
+
+    thread::spawn(move || {
+        for line in io::stdin().lines().map_while(Result::ok) {
+            if lines.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let gui = GuiEndpoints::new();
+    let endpoints = gui.clone();
+    let run = thread::spawn(move || qtp.run(mode, &ports, EmulationConfig::default(), &endpoints));
+
+    let mut measuring = false;
+
+    while !run.is_finished() {
+        while let Some(prompt) = gui.prompts.lock().unwrap().read() {
+            measuring = matches!(prompt, Prompt::Measure(_));
+            println!("OPERATOR: {}", prompt.text());
+        }
+
+        while let Some(step) = gui.steps.lock().unwrap().read() {
+            println!("{}", step);
+        }
+
+        if let Ok(line) = received.try_recv() {
+            if measuring {
+                gui.measurements
+                    .lock()
+                    .unwrap()
+                    .write(line.trim().parse().ok());
+                measuring = false;
+            }
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let report = run.join().expect("could not join QTP thread");
+    println!("\n{}", report);
+
+    if report.verdict() == Verdict::Fail {
+        process::exit(1);
+    }
+}
+
+/// prints every QTP, with the subsystems that it tests, the modes that they can be run
+/// in, and what it tests
+fn list() {
+    for qtp in qtps() {
+        let modes: Vec<String> = qtp.modes().iter().map(Mode::to_string).collect();
+
+        println!(
+            "{:<20} {:<14} {:<17} {}",
+            qtp.name(),
+            subsystems(qtp),
+            modes.join("/"),
+            qtp.description()
+        );
+    }
+}
+
+/// the subsystems under test by `qtp`, e.g. "SNC, SS, MDPS"
+fn subsystems(qtp: &dyn Qtp) -> String {
+    let subsystems: Vec<String> = qtp.under_test().iter().map(|s| s.to_string()).collect();
+
+    subsystems.join(", ")
+}
@@ -0,0 +1,102 @@
+//! Plays a recorded QTP run back in the terminal, without running any subsystems, e.g.
+//!
+//! ```sh
+//! cargo run --bin replay -- sessions/navcon_qtp1_1675677095.csv --from 10 --to 25 --speed 2
+//! ```
+//!
+//! prints the packets sent between 10 s and 25 s into the run at twice the speed that
+//! they were sent at. A speed of 0 prints them all at once.
+
+use std::{process, time::Duration};
+
+use epr320_dev_test::subsystems::session::Session;
+
+const USAGE: &str = "usage: replay <recording> [--from <s>] [--to <s>] [--speed <x>]";
+
+struct Args {
+    path: String,
+    from: f32,
+    to: f32,
+    speed: f32,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = std::env::args().skip(1);
+    let mut parsed = Args {
+        path: args.next().ok_or("no recording given")?,
+        from: 0.0,
+        to: f32::INFINITY,
+        speed: 1.0,
+    };
+
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .and_then(|value| value.parse::<f32>().ok())
+            .filter(|value| *value >= 0.0)
+            .ok_or(format!("`{}` needs a value of at least 0", flag))?;
+
+        match flag.as_str() {
+            "--from" => parsed.from = value,
+            "--to" => parsed.to = value,
+            "--speed" => parsed.speed = value,
+            _ => return Err(format!("unknown option `{}`", flag)),
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}\n{}", err, USAGE);
+            process::exit(1);
+        }
+    };
+
+    let session = match Session::load(&args.path) {
+        Ok(session) => session,
+        Err(err) => {
+            eprintln!("{}: {}", args.path, err);
+            process::exit(1);
+        }
+    };
+
+    println!(
+        "{}, {} packets over {:.1} s",
+        session.qtp.name(),
+        session.events.len(),
+        session.duration()
+    );
+
+    let mut previous = args.from;
+
+    for event in session
+        .events
+        .iter()
+        .filter(|event| (args.from..=args.to).contains(&event.time))
+    {
+        if args.speed > 0.0 {
+            std::thread::sleep(Duration::from_secs_f32(
+                (event.time - previous) / args.speed,
+            ));
+        }
+        previous = event.time;
+
+        let position = match event.positions {
+            Some(positions) => format!(" at ({:.0}, {:.0})", positions[2].0, positions[2].1),
+            None => String::new(),
+        };
+
+        println!(
+            "{:>8.3} s {:<4} {} {:?}{}",
+            event.time,
+            event.sender,
+            event.packet,
+            event.packet.control_byte(),
+            position
+        );
+    }
+}
@@ -0,0 +1,77 @@
+//! # Calibration
+//!
+//! In the CAL state the MDPS calibrates its tangential wheel speeds to the
+//! operational velocity (vop) that the SNC sent with the first touch, and reports
+//! the speeds it measured in packet 96 (DAT1 = vR, DAT0 = vL). Both speeds must be
+//! within 5% of vop.
+
+use std::fmt;
+
+use super::packet::Packet;
+
+/// the largest error (as a fraction of vop) allowed in a calibrated wheel speed
+pub const CALIBRATION_TOLERANCE: f32 = 0.05;
+
+/// The calibrated wheel speeds reported by the MDPS, checked against the requested vop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CalibrationCheck {
+    /// the operational velocity (in mm/s) that was requested
+    pub operational_velocity: u8,
+    /// the calibrated speed (in mm/s) of the right wheel
+    pub right: u8,
+    /// the calibrated speed (in mm/s) of the left wheel
+    pub left: u8,
+}
+
+impl CalibrationCheck {
+    /// checks the speeds in `packet` (a 96 packet) against `operational_velocity`
+    pub fn new(operational_velocity: u8, packet: Packet) -> Self {
+        Self {
+            operational_velocity,
+            right: packet.dat1(),
+            left: packet.dat0(),
+        }
+    }
+
+    /// the error of `speed` as a fraction of vop
+    fn error(&self, speed: u8) -> f32 {
+        if self.operational_velocity == 0 {
+            return if speed == 0 { 0.0 } else { f32::INFINITY };
+        }
+
+        (speed as f32 - self.operational_velocity as f32).abs() / self.operational_velocity as f32
+    }
+
+    /// the error of the right wheel's speed as a fraction of vop
+    pub fn right_error(&self) -> f32 {
+        self.error(self.right)
+    }
+
+    /// the error of the left wheel's speed as a fraction of vop
+    pub fn left_error(&self) -> f32 {
+        self.error(self.left)
+    }
+
+    /// whether both wheels were calibrated to within 5% of vop
+    pub fn passed(&self) -> bool {
+        self.right_error() <= CALIBRATION_TOLERANCE && self.left_error() <= CALIBRATION_TOLERANCE
+    }
+}
+
+impl fmt::Display for CalibrationCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "vR = {} mm/s ({:.1}%), vL = {} mm/s ({:.1}%) for vop = {} mm/s: {}",
+            self.right,
+            self.right_error() * 100.0,
+            self.left,
+            self.left_error() * 100.0,
+            self.operational_velocity,
+            match self.passed() {
+                true => "PASS",
+                false => "FAIL",
+            }
+        )
+    }
+}
@@ -23,15 +23,37 @@ impl From<char> for Colour {
     }
 }
 
-impl From<u8> for Colour {
-    fn from(number: u8) -> Self {
+/// A colour code that is not one of the five colours, e.g. after a bit of the colour
+/// word was flipped on its way from the SS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidColour {
+    /// the sensor (counted from 0, left to right) that read the code
+    pub sensor: usize,
+    pub code: u8,
+}
+
+impl std::fmt::Display for InvalidColour {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "sensor {} reads unknown colour {}",
+            self.sensor, self.code
+        )
+    }
+}
+
+impl TryFrom<u8> for Colour {
+    /// the code, which is not a colour
+    type Error = u8;
+
+    fn try_from(number: u8) -> Result<Self, u8> {
         match number {
-            0b000 => Colour::White,
-            0b001 => Colour::Red,
-            0b010 => Colour::Green,
-            0b011 => Colour::Blue,
-            0b100 => Colour::Black,
-            _ => panic!("Invalid conversion to colour"),
+            0b000 => Ok(Colour::White),
+            0b001 => Ok(Colour::Red),
+            0b010 => Ok(Colour::Green),
+            0b011 => Ok(Colour::Blue),
+            0b100 => Ok(Colour::Black),
+            code => Err(code),
         }
     }
 }
@@ -54,16 +76,27 @@ pub struct Colours {
     index: usize,
 }
 
-impl From<u16> for Colours {
-    fn from(colour_word: u16) -> Self {
+impl TryFrom<u16> for Colours {
+    type Error = InvalidColour;
+
+    /// decodes the colour word of a 113 or 177 packet, in which each sensor's colour is 3
+    /// bits, and only 0 to 4 are colours
+    fn try_from(colour_word: u16) -> Result<Self, InvalidColour> {
         let mut mask = 0b0111000000000000;
         let mut colours = [Colour::White; 5];
 
-        for i in 0..5 {
-            colours[i] = Colour::from(((colour_word & mask) >> (12 - (3 * i))) as u8);
+        for (sensor, colour) in colours.iter_mut().enumerate() {
+            *colour = Colour::try_from(((colour_word & mask) >> (12 - (3 * sensor))) as u8)
+                .map_err(|code| InvalidColour { sensor, code })?;
             mask >>= 3;
         }
 
+        Ok(Colours { colours, index: 0 })
+    }
+}
+
+impl From<[Colour; 5]> for Colours {
+    fn from(colours: [Colour; 5]) -> Self {
         Colours { colours, index: 0 }
     }
 }
@@ -88,6 +121,11 @@ impl Colours {
         }
     }
 
+    /// returns the colours seen by each of the five sensors, from left to right
+    pub fn colours(&self) -> [Colour; 5] {
+        self.colours
+    }
+
     pub fn all_white(&self) -> bool {
         self.colours.iter().all(|col| *col == Colour::White)
     }
@@ -97,13 +135,10 @@ impl Iterator for Colours {
     type Item = Colour;
 
     fn next(&mut self) -> Option<Colour> {
+        let colour = self.colours.get(self.index).copied();
         self.index += 1;
 
-        if self.index <= 4 {
-            Some(self.colours[self.index])
-        } else {
-            None
-        }
+        colour
     }
 }
 
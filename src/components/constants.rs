@@ -12,15 +12,6 @@ pub const MAZE_TOP_JUSTIFICATION: f32 = 100.0 + (MAZE_ROW_HEIGHT / 2.5);
 
 // =================================================================================
 
-// =================================================================================
-// Robot dimensions
-
-pub const B_ISD: u16 = 45; // big inter-sensor distance (in mm)
-pub const S_ISD: u16 = 15; // small inter-sensor distance (in mm)
-pub const AXLE_DIST: i16 = 75; // length of the axle (in mm)
-
-// =================================================================================
-
 // =================================================================================
 // Packets for comms
 
@@ -33,7 +24,6 @@ pub const IDLE_BUTTON_TOUCHED: Bytes = [16, 1, 30, 0];
 pub const IDLE_BUTTON_NOT_TOUCHED: Bytes = [16, 0, 0, 0];
 
 pub const CAL_CALIBRATED: Bytes = [112, 0, 0, 0];
-pub const CAL_OPERATIONAL_VELOCITY: Bytes = [96, 50, 0, 0];
 pub const CAL_BATTERY_LEVEL: Bytes = [97, 0, 0, 0];
 pub const CAL_COLOURS: Bytes = [113, 0, 0, 0];
 pub const CAL_BUTTON_TOUCHED: Bytes = [80, 1, 0, 0];
@@ -0,0 +1,50 @@
+use std::fmt;
+
+use super::{packet::Packet, subsystem::Subsystem};
+
+/// A packet as it was sent over the SCS: who sent it, who it was delivered to, and when
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PacketEnvelope {
+    /// the number of packets sent during the run before this one
+    pub sequence: u32,
+    /// when the packet was sent (in s), from the start of the run
+    pub time: f32,
+    /// the subsystem that sent the packet
+    pub source: Subsystem,
+    /// the subsystems that the packet was delivered to, i.e. the rest of the SCS
+    pub destinations: &'static [Subsystem],
+    pub packet: Packet,
+}
+
+impl PacketEnvelope {
+    pub fn new(sequence: u32, time: f32, source: Subsystem, packet: Packet) -> Self {
+        Self {
+            sequence,
+            time,
+            source,
+            destinations: source.others(),
+            packet,
+        }
+    }
+
+    /// the subsystems that the packet was delivered to, e.g. "SS, MDPS"
+    pub fn destination_names(&self) -> String {
+        let names: Vec<String> = self.destinations.iter().map(|d| d.to_string()).collect();
+
+        names.join(", ")
+    }
+}
+
+impl fmt::Display for PacketEnvelope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "#{} at {:.3} s, {} -> {}: {}",
+            self.sequence,
+            self.time,
+            self.source,
+            self.destination_names(),
+            self.packet
+        )
+    }
+}
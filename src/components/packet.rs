@@ -1,6 +1,8 @@
 use std::fmt;
 
-use super::comm_port::ControlByte;
+use super::{
+    adjacent_bytes::AdjacentBytes, colour::Colours, comm_port::ControlByte, state::SystemState,
+};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Packet {
@@ -29,6 +31,82 @@ impl Packet {
     pub fn dec(&self) -> u8 {
         self.bytes[3]
     }
+
+    /// the state that the sender was in, from the SYS bits of the control byte
+    pub fn state(&self) -> SystemState {
+        match self.bytes[0] >> 6 {
+            0 => SystemState::Idle,
+            1 => SystemState::Calibrate,
+            2 => SystemState::Maze,
+            _ => SystemState::Sos,
+        }
+    }
+
+    /// what the data bytes of the packet mean, e.g. "rotate 37° left" for a 147 packet
+    pub fn meaning(&self) -> String {
+        let word = u16::from(AdjacentBytes::make(self.dat1(), self.dat0()));
+        let touched = |byte: u8| match byte {
+            1 => "touched",
+            _ => "not touched",
+        };
+        let direction = |dec: u8| match dec {
+            2 => "left",
+            3 => "right",
+            _ => "in an unknown direction",
+        };
+
+        match self.control_byte() {
+            ControlByte::IdleButton if self.dat1() == 1 => {
+                format!("touched, vop {} mm/s", self.dat0())
+            }
+            ControlByte::IdleButton | ControlByte::CalibrateButton | ControlByte::MazeButton => {
+                touched(self.dat1()).to_string()
+            }
+            ControlByte::Calibrated => "calibrated".to_string(),
+            ControlByte::CalibrateOperationalVelocity => {
+                format!("vR {} mm/s, vL {} mm/s", self.dat1(), self.dat0())
+            }
+            ControlByte::CalibrateBatteryLevel | ControlByte::MazeBatteryLevel => {
+                format!("{}% ({:.1} V)", self.dat1(), self.dat0() as f32 / 10.0)
+            }
+            ControlByte::CalibrateColours | ControlByte::MazeColours => {
+                match Colours::try_from(word) {
+                    Ok(colours) => colours
+                        .colours()
+                        .map(|colour| colour.to_string())
+                        .join(", "),
+                    Err(_) => format!("invalid colours {:#06x}", word),
+                }
+            }
+            ControlByte::MazeClapSnap | ControlByte::SosClapSnap => match self.dat1() {
+                1 => "clap/snap".to_string(),
+                _ => "no clap/snap".to_string(),
+            },
+            ControlByte::MazeNavInstructions => match self.dec() {
+                0 => format!("forward, vL {} mm/s, vR {} mm/s", self.dat1(), self.dat0()),
+                1 => format!("reverse, vL {} mm/s, vR {} mm/s", self.dat1(), self.dat0()),
+                dec @ (2 | 3) => format!("rotate {}° {}", word, direction(dec)),
+                dec => format!("unknown instruction {}", dec),
+            },
+            ControlByte::MazeRotation => match self.dec() {
+                dec @ (2 | 3) => format!("rotated {}° {}", word, direction(dec)),
+                _ => format!("rotated {}°", word),
+            },
+            ControlByte::MazeSpeeds | ControlByte::SosSpeed => format!(
+                "vL {} mm/s, vR {} mm/s, {}",
+                self.dat1(),
+                self.dat0(),
+                match self.dec() {
+                    1 => "reverse",
+                    _ => "forward",
+                }
+            ),
+            ControlByte::MazeDistance => format!("{} mm", word),
+            ControlByte::MazeEndOfMaze => "end of maze".to_string(),
+            ControlByte::MazeIncidence => format!("{}°", self.dat1()),
+            ControlByte::Undefined => "unknown control byte".to_string(),
+        }
+    }
 }
 
 impl From<[u8; 4]> for Packet {
@@ -39,7 +117,8 @@ impl From<[u8; 4]> for Packet {
 
 impl From<Packet> for [u8; 4] {
     fn from(p: Packet) -> Self {
-        [p.control_byte().into(), p.dat1(), p.dat0(), p.dec()]
+        // not through `ControlByte`, which would turn an unknown control byte into 255
+        p.bytes
     }
 }
 
@@ -0,0 +1,169 @@
+//! # Robot configuration
+//!
+//! The geometry of the emulated MARV's chassis. Every team builds its own chassis, so
+//! instead of fixing the sensor layout and axle length at compile time, a team can
+//! describe its MARV in a text file of `key = value` lines, e.g.
+//!
+//! ```text
+//! # lengths in mm, speeds in mm/s
+//! axle_length = 75
+//! wheel_radius = 32
+//! max_speed = 500
+//!
+//! # sensor = forward, left (from the middle of the axle), from the leftmost sensor
+//! sensor = 75, 60
+//! sensor = 75, 15
+//! sensor = 75, 0
+//! sensor = 75, -15
+//! sensor = 75, -60
+//! ```
+//!
+//! The SCS sends exactly five colours in packet 177, so there must be five sensors,
+//! listed from the MARV's left to its right. Keys that are left out keep the values
+//! of `RobotConfig::default()`, which is the layout that the test kit has always used.
+
+use std::{fmt, fs, path::Path, str::FromStr};
+
+/// the number of colour sensors on the MARV, as required by the SCS
+pub const SENSOR_COUNT: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RobotConfigError {
+    /// the file could not be read
+    ReadFail(String),
+    /// the line (counted from 1) is not a `key = value` pair
+    InvalidLine(usize),
+    /// the line (counted from 1) has a key that is not known
+    UnknownKey(usize, String),
+    /// the line (counted from 1) has a value that is not valid for its key
+    InvalidValue(usize),
+    /// the file describes this many sensors instead of `SENSOR_COUNT`
+    SensorCount(usize),
+}
+
+impl fmt::Display for RobotConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RobotConfigError::ReadFail(reason) => write!(f, "could not read file: {}", reason),
+            RobotConfigError::InvalidLine(line) => {
+                write!(f, "line {}: expected `key = value`", line)
+            }
+            RobotConfigError::UnknownKey(line, key) => {
+                write!(f, "line {}: unknown key `{}`", line, key)
+            }
+            RobotConfigError::InvalidValue(line) => write!(f, "line {}: invalid value", line),
+            RobotConfigError::SensorCount(count) => write!(
+                f,
+                "{} sensors given, the MARV must have {}",
+                count, SENSOR_COUNT
+            ),
+        }
+    }
+}
+
+/// The geometry of the MARV's chassis, all lengths in mm
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RobotConfig {
+    /// the position of each sensor as (forward, left) from the middle of the axle,
+    /// from the leftmost sensor to the rightmost one
+    pub sensors: [(f32, f32); SENSOR_COUNT],
+    /// the distance between the wheels
+    pub axle_length: f32,
+    /// the radius of the wheels
+    pub wheel_radius: f32,
+    /// the fastest (in mm/s) that a wheel can turn
+    pub max_speed: f32,
+}
+
+impl RobotConfig {
+    /// reads the configuration from the file at `path`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RobotConfigError> {
+        fs::read_to_string(path)
+            .map_err(|err| RobotConfigError::ReadFail(err.to_string()))?
+            .parse()
+    }
+
+    /// the sideways distance between an outer sensor and the inner sensor next to it,
+    /// averaged over both sides
+    pub fn outer_sensor_spacing(&self) -> f32 {
+        let left = (self.sensors[0].1 - self.sensors[1].1).abs();
+        let right = (self.sensors[SENSOR_COUNT - 1].1 - self.sensors[SENSOR_COUNT - 2].1).abs();
+
+        (left + right) / 2.0
+    }
+}
+
+impl Default for RobotConfig {
+    fn default() -> Self {
+        Self {
+            sensors: [
+                (75.0, 60.0),
+                (75.0, 15.0),
+                (75.0, 0.0),
+                (75.0, -15.0),
+                (75.0, -60.0),
+            ],
+            axle_length: 75.0,
+            wheel_radius: 32.0,
+            max_speed: 500.0,
+        }
+    }
+}
+
+impl FromStr for RobotConfig {
+    type Err = RobotConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut config = RobotConfig::default();
+        let mut sensors = Vec::new();
+
+        for (index, line) in s.lines().enumerate() {
+            let number = index + 1;
+
+            // ignore comments and blank lines
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(RobotConfigError::InvalidLine(number)),
+            };
+
+            let length = || match value.parse::<f32>() {
+                Ok(length) if length.is_finite() && length > 0.0 => Ok(length),
+                _ => Err(RobotConfigError::InvalidValue(number)),
+            };
+
+            match key {
+                "axle_length" => config.axle_length = length()?,
+                "wheel_radius" => config.wheel_radius = length()?,
+                "max_speed" => config.max_speed = length()?,
+                "sensor" => {
+                    let offsets: Vec<f32> = value
+                        .split(',')
+                        .map(|offset| offset.trim().parse::<f32>())
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| RobotConfigError::InvalidValue(number))?;
+
+                    match offsets[..] {
+                        [forward, left] if forward.is_finite() && left.is_finite() => {
+                            sensors.push((forward, left))
+                        }
+                        _ => return Err(RobotConfigError::InvalidValue(number)),
+                    }
+                }
+                _ => return Err(RobotConfigError::UnknownKey(number, key.to_string())),
+            }
+        }
+
+        if !sensors.is_empty() {
+            config.sensors = sensors
+                .try_into()
+                .map_err(|sensors: Vec<_>| RobotConfigError::SensorCount(sensors.len()))?;
+        }
+
+        Ok(config)
+    }
+}
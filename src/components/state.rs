@@ -1,7 +1,20 @@
-#[derive(Debug)]
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SystemState {
     Idle,
     Calibrate,
     Maze,
     Sos,
 }
+
+impl fmt::Display for SystemState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SystemState::Idle => write!(f, "IDLE"),
+            SystemState::Calibrate => write!(f, "CAL"),
+            SystemState::Maze => write!(f, "MAZE"),
+            SystemState::Sos => write!(f, "SOS"),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::{fmt, str::FromStr};
+
+/// The subsystems of the MARV that send packets over the SCS, and the hub that connects
+/// them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subsystem {
+    Snc,
+    Ss,
+    Mdps,
+    /// the hub of the SCS (i.e. the kit), which only sends its own packets, e.g. `HUB_START`
+    Hub,
+}
+
+impl Subsystem {
+    /// the subsystems of the MARV on the SCS, other than this one, which receive every
+    /// packet that it sends
+    pub fn others(&self) -> &'static [Subsystem] {
+        match self {
+            Subsystem::Snc => &[Subsystem::Ss, Subsystem::Mdps],
+            Subsystem::Ss => &[Subsystem::Snc, Subsystem::Mdps],
+            Subsystem::Mdps => &[Subsystem::Snc, Subsystem::Ss],
+            Subsystem::Hub => &[Subsystem::Snc, Subsystem::Ss, Subsystem::Mdps],
+        }
+    }
+}
+
+impl fmt::Display for Subsystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Subsystem::Snc => write!(f, "SNC"),
+            Subsystem::Ss => write!(f, "SS"),
+            Subsystem::Mdps => write!(f, "MDPS"),
+            Subsystem::Hub => write!(f, "Hub"),
+        }
+    }
+}
+
+impl FromStr for Subsystem {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "SNC" => Ok(Subsystem::Snc),
+            "SS" => Ok(Subsystem::Ss),
+            "MDPS" => Ok(Subsystem::Mdps),
+            "Hub" => Ok(Subsystem::Hub),
+            _ => Err(()),
+        }
+    }
+}
@@ -2,37 +2,69 @@ extern crate crossbeam;
 extern crate eframe;
 
 use std::{
-    sync::{Arc, Mutex},
+    collections::HashMap,
+    path::{Path, PathBuf},
     thread::JoinHandle,
+    time::Duration,
 };
 
-use eframe::egui::{self, Response, Ui};
+use eframe::{
+    egui::{
+        self,
+        plot::{Line, Plot, PlotPoints},
+        Response, RichText, Ui,
+    },
+    epaint::{Color32, Pos2, Shape, Stroke},
+};
 
 use crate::{
-    asynchronous::async_type::{PacketsEndpoint, PositionsEndpoint},
+    asynchronous::async_type::GuiEndpoints,
     components::{
-        buffer::Buffer,
         colour::Colour,
+        comm_port::ControlByte,
         constants::{
-            DEFUALT_COM_PORT, DEFUALT_STARTING_POSITION, HUGE_PADDING, LARGE_PADDING,
-            MEDIUM_PADDING, NINETY_DEGREES, SMALL_PADDING,
+            DEFUALT_COM_PORT, HUB_END_OF_MAZE, HUB_START, HUGE_PADDING, LARGE_PADDING,
+            MAZE_LEFT_JUSTIFICATION, MAZE_LINE_WIDTH, MAZE_TOP_JUSTIFICATION, MEDIUM_PADDING,
+            SMALL_PADDING,
+        },
+        envelope::PacketEnvelope,
+        robot_config::RobotConfig,
+        subsystem::Subsystem,
+    },
+    gui::test_windows::registry::qtps,
+    subsystems::{
+        fault_injection::{FaultProfile, ScriptedFault, CONTROL_BYTES, FAULTS},
+        hub::{run_integration, BusMonitor, IntegrationQtp},
+        motor_subsystem::{
+            battery::Battery, motor_model::MotorModel, rotation_control::RotationControl,
         },
-        packet::Packet,
+        packet_capture::{CaptureFormat, PacketCapture},
+        qtp::Qtp,
+        qtp_report::{QtpReport, Verdict},
+        run_monitor::{RunLimits, RunOutcome},
+        sensor_positions::to_maze_coords,
+        sensor_subsystem::{
+            incidence::{IncidenceMode, IncidenceReport},
+            sensor_model::SensorModel,
+        },
+        session::{Recording, Session, SessionPlayer},
+        state_navigation::navcon::{NavConDecision, NavConState},
+        system::{run_navcon, EmulationConfig, Mode, NavconQtp},
+        test_bench::{run_bench, BenchQtp, Prompt},
+        trajectory::Trajectory,
     },
-    gui::test_windows::navcon::qtp1::generate_navcon_qtp_1_maze,
-    gui::test_windows::navcon::qtp2::generate_navcon_qtp_2_maze,
-    subsystems::system::{run_system, Mode},
 };
 
 use super::{
-    packet_display::LabelList,
-    test_windows::navcon::{
-        qtp3::generate_navcon_qtp_3_maze, qtp4::generate_navcon_qtp_4_maze,
-        qtp5::generate_navcon_qtp_5_maze,
-    },
-    window_stack::{QtpNo, Window, WindowHistory},
+    maze::MazeLineMap,
+    packet_display::{control_byte_name, PacketInspector, SOURCES},
+    sequence_diagram::paint_sequence_diagram,
+    window_stack::{Window, WindowHistory},
 };
 
+/// the heading of the QTPs run against the whole MARV in the main menu
+const INTEGRATION_TESTS: &str = "Integration Tests";
+
 enum QTPState {
     Busy,
     Idle,
@@ -42,12 +74,57 @@ pub struct MARVApp {
     state: WindowHistory,
     snc_mode: Mode,
     qtp_state: QTPState,
-    latest_packet: Option<Packet>,
-    sensor_positions: PositionsEndpoint,
-    subsystem_packets: PacketsEndpoint,
+    /// what the subsystems of the current (or last) QTP run send to the GUI
+    endpoints: GuiEndpoints,
+    /// every NAVCON decision made during the current (or last) QTP run
+    decision_log: Vec<NavConDecision>,
+    /// every incidence worked out by the emulated SS during the current (or last) QTP run
+    incidence_log: Vec<IncidenceReport>,
+    /// the ground-truth path of the emulated MARV during the current (or last) QTP run
+    trajectory: Trajectory,
+    /// how the current (or last) QTP run ended, once it has
+    run_outcome: Option<RunOutcome>,
+    /// how long a run of each NAVCON QTP may go on for, by the number of the QTP (the
+    /// default limits if they have not been changed)
+    run_limits: HashMap<u8, RunLimits>,
+    /// the file that the current (or last) QTP run is recorded to
+    last_recording: Option<PathBuf>,
+    /// the file that a recorded run is replayed from
+    replay_path: String,
+    /// the recorded run being replayed, once it has been loaded
+    replay: Option<SessionPlayer>,
+    /// why the recording could not be loaded from `replay_path`, if it could not
+    replay_error: Option<String>,
+    /// where the packets of a recording were last exported to, or why they could not be
+    capture_status: Option<String>,
     test_thread: Option<JoinHandle<()>>,
     com_no: Option<String>,
-    packet_labels: LabelList,
+    /// every packet sent during the current (or last) QTP run
+    packet_inspector: PacketInspector,
+    /// the settings of the emulated subsystems used in the next run
+    emulation_config: EmulationConfig,
+    /// the file that the chassis of the emulated MARV is loaded from
+    robot_config_path: String,
+    /// why the chassis could not be loaded from `robot_config_path`, if it could not
+    robot_config_error: Option<String>,
+    /// the last battery level reported by the MDPS during the run, as (percentage, decivolts)
+    battery_level: Option<(u8, u8)>,
+    /// whether the subsystem under test of a QTP on the test bench is emulated or physical
+    bench_mode: Mode,
+    /// whether the subsystems of the MARV are emulated or physical during an integration QTP
+    integration_mode: Mode,
+    /// the serial ports that the physical SNC, SS and MDPS are connected to, in that order
+    hub_ports: [Option<String>; 3],
+    /// the kit as the hub between the physical subsystems, while it is connected
+    bus_monitor: Option<BusMonitor>,
+    /// the steps of the current (or last) QTP run on the test bench or against the whole
+    /// MARV, so far
+    bench_report: Option<QtpReport>,
+    /// what the operator is asked to do during the current QTP run on the test bench or
+    /// against the whole MARV
+    bench_prompt: Option<Prompt>,
+    /// what the operator has entered so far, when asked to measure something
+    bench_measurement: String,
 }
 
 impl MARVApp {
@@ -61,12 +138,31 @@ impl MARVApp {
             state: WindowHistory::new(),
             snc_mode: Mode::Emulate,
             qtp_state: QTPState::Idle,
-            latest_packet: None,
-            sensor_positions: Arc::new(Mutex::new(Buffer::new())),
-            subsystem_packets: Arc::new(Mutex::new(Buffer::new())),
+            endpoints: GuiEndpoints::new(),
+            decision_log: Vec::new(),
+            incidence_log: Vec::new(),
+            trajectory: Trajectory::new(),
+            run_outcome: None,
+            run_limits: HashMap::new(),
+            last_recording: None,
+            replay_path: String::new(),
+            replay: None,
+            replay_error: None,
+            capture_status: None,
             test_thread: None,
             com_no: None,
-            packet_labels: LabelList::new(),
+            packet_inspector: PacketInspector::new(),
+            emulation_config: EmulationConfig::default(),
+            robot_config_path: String::new(),
+            robot_config_error: None,
+            battery_level: None,
+            bench_mode: Mode::Emulate,
+            integration_mode: Mode::Emulate,
+            hub_ports: [None, None, None],
+            bus_monitor: None,
+            bench_report: None,
+            bench_prompt: None,
+            bench_measurement: String::new(),
         }
     }
 
@@ -74,119 +170,87 @@ impl MARVApp {
         ui.heading("Welcome to the EPR 320 developmental test kit!");
         ui.add_space(LARGE_PADDING);
 
-        // SNC tests
-        ui.group(|ui| {
-            ui.heading("SNC Tests");
-
-            ui.add_space(MEDIUM_PADDING);
-
-            ui.label("SNC");
-            ui.horizontal(|ui| {
-                if ui.button("QTP1").clicked() {}
-                if ui.button("QTP2").clicked() {}
-                if ui.button("QTP3").clicked() {}
-            });
+        // the QTPs, grouped by the subsystems that they test, and listed by suite within
+        // each group
+        let mut groups: Vec<(String, Vec<&'static dyn Qtp>)> = Vec::new();
 
-            ui.add_space(SMALL_PADDING);
+        for qtp in qtps() {
+            let heading = match qtp.under_test() {
+                [subsystem] => format!("{} Tests", subsystem),
+                _ => String::from(INTEGRATION_TESTS),
+            };
 
-            ui.label("NAVCON");
-            ui.horizontal(|ui| {
-                if ui.button("QTP1").clicked() {
-                    self.state.push(Window::Navcon(QtpNo::Qtp1));
-                }
-                if ui.button("QTP2").clicked() {
-                    self.state.push(Window::Navcon(QtpNo::Qtp2));
-                }
-                if ui.button("QTP3").clicked() {
-                    self.state.push(Window::Navcon(QtpNo::Qtp3));
-                }
-                if ui.button("QTP4").clicked() {
-                    self.state.push(Window::Navcon(QtpNo::Qtp4));
-                }
-                if ui.button("QTP5").clicked() {
-                    self.state.push(Window::Navcon(QtpNo::Qtp5));
-                }
-            });
-        });
+            match groups.iter_mut().find(|(group, _)| *group == heading) {
+                Some((_, qtps)) => qtps.push(qtp),
+                None => groups.push((heading, vec![qtp])),
+            }
+        }
 
-        ui.add_space(HUGE_PADDING);
+        for (heading, qtps) in groups {
+            ui.group(|ui| {
+                ui.heading(&heading);
 
-        // SS tests
-        ui.group(|ui| {
-            ui.heading("SS Tests");
+                ui.add_space(MEDIUM_PADDING);
 
-            ui.add_space(MEDIUM_PADDING);
+                let mut suites: Vec<&str> = qtps.iter().map(|qtp| qtp.suite()).collect();
+                suites.dedup();
 
-            ui.horizontal(|ui| {
-                if ui.button("QTP1").clicked() {}
-                if ui.button("QTP2").clicked() {}
-                if ui.button("QTP3").clicked() {}
-                if ui.button("QTP4").clicked() {}
-                if ui.button("QTP5").clicked() {}
-            });
-        });
+                for (index, suite) in suites.iter().enumerate() {
+                    if index > 0 {
+                        ui.add_space(SMALL_PADDING);
+                    }
 
-        ui.add_space(HUGE_PADDING);
+                    // only name the suites of a group that has more than one
+                    if suites.len() > 1 {
+                        ui.label(*suite);
+                    }
 
-        // MDPS tests
-        ui.group(|ui| {
-            ui.heading("MDPS Tests");
+                    ui.horizontal(|ui| {
+                        for qtp in qtps.iter().filter(|qtp| qtp.suite() == *suite) {
+                            if ui
+                                .button(qtp.short_name())
+                                .on_hover_text(qtp.description())
+                                .clicked()
+                            {
+                                self.state.push(qtp.kind().into());
+                            }
+                        }
 
-            ui.add_space(MEDIUM_PADDING);
+                        if heading == INTEGRATION_TESTS {
+                            ui.add_space(MEDIUM_PADDING);
 
-            ui.horizontal(|ui| {
-                if ui.button("QTP1").clicked() {}
-                if ui.button("QTP2").clicked() {}
-                if ui.button("QTP3").clicked() {}
-                if ui.button("QTP4").clicked() {}
-                if ui.button("QTP5").clicked() {}
+                            if ui.button("Hub").clicked() {
+                                self.state.push(Window::Hub);
+                            }
+                        }
+                    });
+                }
             });
-        });
 
-        ui.add_space(HUGE_PADDING);
+            ui.add_space(HUGE_PADDING);
+        }
 
-        // Integration tests
+        // recorded runs
         ui.group(|ui| {
-            ui.heading("Integration Tests");
+            ui.heading("Recorded Runs");
 
             ui.add_space(MEDIUM_PADDING);
 
-            // placeholders
-            ui.horizontal(|ui| {
-                if ui.button("QTP1").clicked() {}
-                if ui.button("QTP2").clicked() {}
-                if ui.button("QTP3").clicked() {}
-                if ui.button("QTP4").clicked() {}
-                if ui.button("QTP5").clicked() {}
-            });
+            if ui.button("Replay").clicked() {
+                self.state.push(Window::Replay);
+            }
         });
     }
 
-    fn paint_navcon_qtp_window(&mut self, ui: &mut Ui, ctx: &egui::Context, qtp_no: QtpNo) {
+    fn paint_navcon_qtp_window(&mut self, ui: &mut Ui, ctx: &egui::Context, qtp: NavconQtp) {
         if ui.button("<").clicked() {
             self.state.pop();
         }
 
         ui.add_space(LARGE_PADDING);
 
-        // add the correct heading to the Window
-        match qtp_no {
-            QtpNo::Qtp1 => {
-                ui.heading("NAVCON QTP 1");
-            }
-            QtpNo::Qtp2 => {
-                ui.heading("NAVCON QTP 2");
-            }
-            QtpNo::Qtp3 => {
-                ui.heading("NAVCON QTP 3");
-            }
-            QtpNo::Qtp4 => {
-                ui.heading("NAVCON QTP 4");
-            }
-            QtpNo::Qtp5 => {
-                ui.heading("NAVCON QTP 5");
-            }
-        }
+        ui.heading(qtp.name);
+        ui.label(qtp.description);
 
         ui.add_space(MEDIUM_PADDING);
         ui.separator();
@@ -195,16 +259,68 @@ impl MARVApp {
         // =================================================================================
         // WINDOW PROCESSING:
 
+        // the outcome only arrives once the run has ended, which may be after the GUI has
+        // seen the end of the maze
+        if let Some(outcome) = self.endpoints.outcome.lock().unwrap().read() {
+            self.run_outcome = Some(outcome);
+
+            if outcome.failed() {
+                self.qtp_state = QTPState::Idle;
+            }
+        }
+
+        // the last poses and packets of a run may also arrive after the GUI has seen the end
+        // of the maze
+        while let Some(pose) = self.endpoints.poses.lock().unwrap().read() {
+            self.trajectory.push(pose);
+        }
+
+        while let Some(envelope) = self.endpoints.packets.lock().unwrap().read() {
+            let packet = envelope.packet;
+
+            if let ControlByte::CalibrateBatteryLevel | ControlByte::MazeBatteryLevel =
+                packet.control_byte()
+            {
+                self.battery_level = Some((packet.dat1(), packet.dat0()));
+            }
+
+            self.packet_inspector
+                .push(envelope, self.trajectory.latest());
+        }
+
         match self.qtp_state {
             QTPState::Idle => {
-                // generate the MazeLineMap based on qtp number
-                let maze = match qtp_no {
-                    QtpNo::Qtp1 => generate_navcon_qtp_1_maze(ui, [(0.1, 0.05); 5]),
-                    QtpNo::Qtp2 => generate_navcon_qtp_2_maze(ui, [(0.1, 0.05); 5]),
-                    QtpNo::Qtp3 => generate_navcon_qtp_3_maze(ui, [(0.1, 0.05); 5]),
-                    QtpNo::Qtp4 => generate_navcon_qtp_4_maze(ui, [(0.1, 0.05); 5]),
-                    QtpNo::Qtp5 => generate_navcon_qtp_5_maze(ui, [(0.1, 0.05); 5]),
-                };
+                paint_maze(ui, &(qtp.maze)(), [(0.1, 0.05); 5]);
+
+                // show where the MARV went during the last run, and where it failed
+                self.paint_trail(ui);
+                self.paint_chassis(ui);
+                self.paint_failure_location(ui);
+                self.paint_selected_pose(ui);
+
+                if let Some(outcome) = self.run_outcome {
+                    let colour = match outcome.failed() {
+                        true => Color32::RED,
+                        false => Color32::DARK_GREEN,
+                    };
+
+                    ui.colored_label(colour, outcome.to_string());
+                    ui.add_space(SMALL_PADDING);
+                }
+
+                if let Some(path) = self.last_recording.clone() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("recorded to {}", path.display()));
+
+                        if ui.button("Replay").clicked() {
+                            self.replay_path = path.display().to_string();
+                            self.load_replay();
+                            self.state.push(Window::Replay);
+                        }
+                    });
+                    self.paint_capture_export(ui, &path);
+                    ui.add_space(SMALL_PADDING);
+                }
 
                 ui.horizontal(|ui| {
                     if ui.button("Start").clicked() {
@@ -212,25 +328,25 @@ impl MARVApp {
                             || (self.snc_mode == Mode::Physical && self.com_no.is_some())
                         {
                             self.qtp_state = QTPState::Busy;
-                            self.sensor_positions = Arc::new(Mutex::new(Buffer::new()));
-                            self.subsystem_packets = Arc::new(Mutex::new(Buffer::new()));
-                            let gui_thread_origin = Arc::clone(&self.sensor_positions);
-                            let gui_packets_origin = Arc::clone(&self.subsystem_packets);
+                            self.endpoints = GuiEndpoints::new();
+                            self.decision_log.clear();
+                            self.incidence_log.clear();
+                            self.trajectory = Trajectory::new();
+                            self.run_outcome = None;
+                            self.battery_level = None;
+                            self.capture_status = None;
+                            self.packet_inspector = PacketInspector::new();
+                            let endpoints = self.endpoints.clone();
+                            let recording = Recording::new(&qtp);
+                            self.last_recording = Some(recording.path.clone());
+                            let emulation_config = EmulationConfig {
+                                run_limits: self.run_limits(qtp),
+                                ..self.emulation_config
+                            };
 
+                            // the outcome of the run is read from the endpoints, as it arrives
                             self.test_thread = Some(std::thread::spawn(move || {
-                                run_system(
-                                    Mode::Emulate,
-                                    Mode::Emulate,
-                                    Mode::Emulate,
-                                    DEFUALT_COM_PORT,
-                                    DEFUALT_COM_PORT,
-                                    DEFUALT_COM_PORT,
-                                    maze,
-                                    DEFUALT_STARTING_POSITION, // in meters
-                                    NINETY_DEGREES,
-                                    &gui_thread_origin,
-                                    &gui_packets_origin,
-                                );
+                                run_navcon(&qtp, emulation_config, Some(recording), &endpoints);
                             }));
                         }
                     }
@@ -246,75 +362,1651 @@ impl MARVApp {
 
                     ui.add_space(MEDIUM_PADDING);
 
-                    if self.snc_mode == Mode::Physical {
-                        let button_name = match &self.com_no {
-                            Some(com_no) => (*com_no).clone(),
-                            None => String::from("None"),
-                        };
-
-                        ui.menu_button(format!("Port: {}", button_name), |ui| {
-                            for port in serialport::available_ports().unwrap() {
-                                if ui
-                                    .button(format!("{} ({:?})", port.port_name, port.port_type))
-                                    .clicked()
-                                {
-                                    self.com_no = Some(port.port_name);
-                                }
-                            }
+                    if self.snc_mode == Mode::Emulate {
+                        ui.label("vop");
+                        ui.add(
+                            egui::DragValue::new(&mut self.emulation_config.operational_velocity)
+                                .clamp_range(10..=255)
+                                .suffix(" mm/s"),
+                        );
+                    }
 
-                            if ui.button("None").clicked() {
-                                self.com_no = None;
-                            }
-                        });
+                    if self.snc_mode == Mode::Physical {
+                        paint_port_menu(ui, "Port", &mut self.com_no);
                     }
                 });
+
+                ui.add_space(LARGE_PADDING);
+
+                ui.horizontal(|ui| {
+                    ui.add_space(300.0);
+                    self.paint_motor_model_settings(ui);
+                    ui.add_space(MEDIUM_PADDING);
+                    self.paint_rotation_control_settings(ui);
+                    ui.add_space(MEDIUM_PADDING);
+                    self.paint_battery_settings(ui);
+                    ui.add_space(MEDIUM_PADDING);
+                    self.paint_sensor_model_settings(ui);
+                    ui.add_space(MEDIUM_PADDING);
+                    self.paint_robot_config_settings(ui);
+                    ui.add_space(MEDIUM_PADDING);
+                    self.paint_run_limits_settings(ui, qtp);
+                    ui.add_space(MEDIUM_PADDING);
+                    self.paint_fault_settings(ui);
+                });
+
+                // keep the decisions of the last run visible after it has ended
+                if !self.decision_log.is_empty() {
+                    ui.add_space(LARGE_PADDING);
+
+                    ui.horizontal(|ui| {
+                        ui.add_space(300.0);
+                        self.paint_navcon_decisions(ui);
+
+                        if !self.incidence_log.is_empty() {
+                            ui.add_space(MEDIUM_PADDING);
+                            self.paint_incidence(ui);
+                        }
+                    });
+                }
+
+                // and the packets that were sent
+                if !self.packet_inspector.is_empty() {
+                    ui.add_space(MEDIUM_PADDING);
+
+                    ui.horizontal(|ui| {
+                        ui.add_space(300.0);
+                        self.packet_inspector.paint(ui);
+                    });
+
+                    ui.add_space(MEDIUM_PADDING);
+
+                    ui.horizontal(|ui| {
+                        ui.add_space(300.0);
+                        self.paint_packet_sequence(ui);
+                    });
+                }
+
+                // and the path that the MARV took
+                if self.trajectory.latest().is_some() {
+                    ui.add_space(MEDIUM_PADDING);
+
+                    ui.horizontal(|ui| {
+                        ui.add_space(300.0);
+                        self.paint_pose(ui);
+                    });
+                }
             }
             QTPState::Busy => {
-                if let Some(positions) = self.sensor_positions.lock().unwrap().read() {
+                if let Some(positions) = self.endpoints.positions.lock().unwrap().read() {
                     println!("painting with: {:?}", positions);
 
-                    let maze = match qtp_no {
-                        QtpNo::Qtp1 => generate_navcon_qtp_1_maze(ui, positions),
-                        QtpNo::Qtp2 => generate_navcon_qtp_2_maze(ui, positions),
-                        QtpNo::Qtp3 => generate_navcon_qtp_3_maze(ui, positions),
-                        QtpNo::Qtp4 => generate_navcon_qtp_4_maze(ui, positions),
-                        QtpNo::Qtp5 => generate_navcon_qtp_5_maze(ui, positions),
-                    };
+                    let maze = (qtp.maze)();
+                    paint_maze(ui, &maze, positions);
+
+                    self.paint_trail(ui);
+                    self.paint_chassis(ui);
+                    self.paint_selected_pose(ui);
 
                     let colours = maze.get_colours(positions);
 
                     if colours.iter().all(|colour| *colour == Colour::Red) {
                         self.qtp_state = QTPState::Idle;
-                        self.latest_packet = None;
                     }
 
                     ctx.request_repaint();
                 }
 
-                if let Some(latest_packet) = self.subsystem_packets.lock().unwrap().read() {
-                    self.latest_packet = Some(latest_packet);
+                while let Some(decision) = self.endpoints.decisions.lock().unwrap().read() {
+                    self.decision_log.push(decision);
                 }
 
-                if let Some(packet) = self.latest_packet {
-                    self.packet_labels.push(format!("{}", packet).as_str());
+                while let Some(report) = self.endpoints.incidence.lock().unwrap().read() {
+                    self.incidence_log.push(report);
                 }
 
                 ui.add_space(LARGE_PADDING);
 
                 ui.horizontal(|ui| {
                     ui.add_space(300.0);
+                    self.packet_inspector.paint(ui);
+                });
+
+                ui.add_space(MEDIUM_PADDING);
+
+                ui.horizontal(|ui| {
+                    ui.add_space(300.0);
+                    self.paint_packet_sequence(ui);
+                });
+
+                ui.add_space(MEDIUM_PADDING);
+
+                ui.horizontal(|ui| {
+                    ui.add_space(300.0);
+
+                    self.paint_navcon_decisions(ui);
+
+                    if !self.incidence_log.is_empty() {
+                        ui.add_space(MEDIUM_PADDING);
+                        self.paint_incidence(ui);
+                    }
+
+                    if self.battery_level.is_some() {
+                        ui.add_space(MEDIUM_PADDING);
+                        self.paint_battery_gauge(ui);
+                    }
+                });
+
+                if self.trajectory.latest().is_some() {
+                    ui.add_space(MEDIUM_PADDING);
 
-                    ui.group(|ui| {
-                        ui.vertical(|ui| {
-                            self.packet_labels.to_vec().into_iter().for_each(|string| {
-                                ui.label(string);
-                            })
-                        })
+                    ui.horizontal(|ui| {
+                        ui.add_space(300.0);
+                        self.paint_pose(ui);
                     });
+                }
+            }
+        }
+    }
+
+    /// runs a QTP against a single subsystem, with the kit standing in for the others,
+    /// and shows the result of each step as it is carried out
+    fn paint_bench_window(&mut self, ui: &mut Ui, ctx: &egui::Context, qtp: BenchQtp) {
+        if ui.button("<").clicked() {
+            self.state.pop();
+        }
+
+        ui.add_space(LARGE_PADDING);
+        ui.heading(qtp.name);
+        ui.label(qtp.description);
+        ui.add_space(MEDIUM_PADDING);
+        ui.separator();
+        ui.add_space(LARGE_PADDING);
+
+        self.read_bench_endpoints();
+
+        let running = matches!(&self.test_thread, Some(thread) if !thread.is_finished());
+
+        ui.horizontal(|ui| {
+            if running {
+                ctx.request_repaint();
+                ui.label(format!("running {}...", qtp.name));
+                return;
+            }
+
+            if ui.button("Start").clicked()
+                && (self.bench_mode == Mode::Emulate || self.com_no.is_some())
+            {
+                self.endpoints = GuiEndpoints::new();
+                self.bench_report = Some(QtpReport::new(qtp.name));
+                self.bench_prompt = None;
+                self.packet_inspector = PacketInspector::new();
+
+                let endpoints = self.endpoints.clone();
+                let mode = self.bench_mode;
+                // the port is opened by its number
+                let com = match &self.com_no {
+                    Some(com_no) => com_no.trim_start_matches("COM").to_string(),
+                    None => DEFUALT_COM_PORT.to_string(),
+                };
+                let emulation_config = self.emulation_config;
+                let recording = Recording::new(&qtp);
+
+                self.test_thread = Some(std::thread::spawn(move || {
+                    run_bench(
+                        &qtp,
+                        mode,
+                        &com,
+                        emulation_config,
+                        Some(recording),
+                        &endpoints,
+                    );
+                }));
+            }
+
+            ui.add_space(MEDIUM_PADDING);
+
+            if ui
+                .button(format!("{} Mode: {}", qtp.under_test, self.bench_mode))
+                .clicked()
+            {
+                self.bench_mode = match self.bench_mode {
+                    Mode::Emulate => Mode::Physical,
+                    Mode::Physical => Mode::Emulate,
+                }
+            }
+
+            ui.add_space(MEDIUM_PADDING);
+
+            if self.bench_mode == Mode::Physical {
+                paint_port_menu(ui, "Port", &mut self.com_no);
+            }
+        });
+
+        self.paint_bench_results(ui, qtp.name, running);
+    }
+
+    /// runs a QTP against the whole MARV, with the kit as the hub between its subsystems,
+    /// and shows the result of each step as it is carried out
+    fn paint_integration_window(&mut self, ui: &mut Ui, ctx: &egui::Context, qtp: IntegrationQtp) {
+        if ui.button("<").clicked() {
+            self.state.pop();
+        }
+
+        ui.add_space(LARGE_PADDING);
+        ui.heading(qtp.name);
+        ui.label(qtp.description);
+        ui.add_space(MEDIUM_PADDING);
+        ui.separator();
+        ui.add_space(LARGE_PADDING);
+
+        self.read_bench_endpoints();
+
+        let running = matches!(&self.test_thread, Some(thread) if !thread.is_finished());
+
+        ui.horizontal(|ui| {
+            if running {
+                ctx.request_repaint();
+                ui.label(format!("running {}...", qtp.name));
+                return;
+            }
+
+            if ui.button("Start").clicked()
+                && (self.integration_mode == Mode::Emulate
+                    || self.hub_ports.iter().all(Option::is_some))
+            {
+                self.endpoints = GuiEndpoints::new();
+                self.bench_report = Some(QtpReport::new(qtp.name));
+                self.bench_prompt = None;
+                self.packet_inspector = PacketInspector::new();
+
+                let endpoints = self.endpoints.clone();
+                let mode = self.integration_mode;
+                // the ports are opened by their numbers
+                let ports = self.hub_ports.clone().map(|port| match port {
+                    Some(com_no) => com_no.trim_start_matches("COM").to_string(),
+                    None => DEFUALT_COM_PORT.to_string(),
+                });
+                let emulation_config = self.emulation_config;
+                let recording = Recording::new(&qtp);
+
+                self.test_thread = Some(std::thread::spawn(move || {
+                    run_integration(
+                        &qtp,
+                        mode,
+                        &ports,
+                        emulation_config,
+                        Some(recording),
+                        &endpoints,
+                    );
+                }));
+            }
+
+            ui.add_space(MEDIUM_PADDING);
+
+            if ui
+                .button(format!("MARV Mode: {}", self.integration_mode))
+                .clicked()
+            {
+                self.integration_mode = match self.integration_mode {
+                    Mode::Emulate => Mode::Physical,
+                    Mode::Physical => Mode::Emulate,
+                }
+            }
+
+            ui.add_space(MEDIUM_PADDING);
+
+            if self.integration_mode == Mode::Physical {
+                for (subsystem, port) in [Subsystem::Snc, Subsystem::Ss, Subsystem::Mdps]
+                    .into_iter()
+                    .zip(&mut self.hub_ports)
+                {
+                    paint_port_menu(ui, &format!("{} Port", subsystem), port);
+                }
+            }
+        });
+
+        self.paint_bench_results(ui, qtp.name, running);
+    }
+
+    /// runs the kit as the hub between the physical subsystems, outside of a QTP, and shows
+    /// every packet passed on and every violation of the protocol as they happen
+    fn paint_hub_window(&mut self, ui: &mut Ui, ctx: &egui::Context) {
+        if ui.button("<").clicked() {
+            // the subsystems are disconnected once the window is left
+            self.bus_monitor = None;
+            self.state.pop();
+        }
+
+        ui.add_space(LARGE_PADDING);
+        ui.heading("Hub");
+        ui.label("The kit passes every packet between the subsystems on unchanged, and checks it against the protocol");
+        ui.add_space(MEDIUM_PADDING);
+        ui.separator();
+        ui.add_space(LARGE_PADDING);
+
+        let bus_monitor = match &mut self.bus_monitor {
+            Some(bus_monitor) => bus_monitor,
+            None => {
+                ui.horizontal(|ui| {
+                    if ui.button("Connect").clicked() && self.hub_ports.iter().all(Option::is_some)
+                    {
+                        // the ports are opened by their numbers
+                        let ports = self.hub_ports.clone().map(|port| {
+                            port.unwrap_or_default()
+                                .trim_start_matches("COM")
+                                .to_string()
+                        });
+
+                        self.bus_monitor = Some(BusMonitor::connect(&ports));
+                        self.packet_inspector = PacketInspector::new();
+                        self.capture_status = None;
+                    }
+
+                    ui.add_space(MEDIUM_PADDING);
+
+                    for (subsystem, port) in [Subsystem::Snc, Subsystem::Ss, Subsystem::Mdps]
+                        .into_iter()
+                        .zip(&mut self.hub_ports)
+                    {
+                        paint_port_menu(ui, &format!("{} Port", subsystem), port);
+                    }
                 });
+
+                return;
+            }
+        };
+
+        ctx.request_repaint();
+
+        while let Some(envelope) = bus_monitor.poll() {
+            self.packet_inspector.push(envelope, None);
+        }
+
+        let mut disconnect = false;
+        let mut export = None;
+
+        ui.horizontal(|ui| {
+            disconnect = ui.button("Disconnect").clicked();
+
+            ui.add_space(MEDIUM_PADDING);
+
+            if ui.button("Send Start").clicked() {
+                bus_monitor.inject(HUB_START);
             }
+
+            if ui.button("Send End of Maze").clicked() {
+                bus_monitor.inject(HUB_END_OF_MAZE);
+            }
+
+            ui.add_space(MEDIUM_PADDING);
+
+            if ui.button("Export CSV").clicked() {
+                export = Some(CaptureFormat::Csv);
+            }
+
+            if ui.button("Export JSONL").clicked() {
+                export = Some(CaptureFormat::Jsonl);
+            }
+
+            if let Some(status) = &self.capture_status {
+                ui.label(status);
+            }
+        });
+
+        if let Some(format) = export {
+            self.capture_status = Some(match bus_monitor.export(format) {
+                Ok(path) => format!("exported to {}", path.display()),
+                Err(err) => format!("could not export: {}", err),
+            });
+        }
+
+        let monitor = bus_monitor.monitor();
+
+        ui.add_space(LARGE_PADDING);
+        ui.label(format!("{}: {}", monitor.state(), monitor));
+
+        if !monitor.violations().is_empty() {
+            ui.add_space(MEDIUM_PADDING);
+            ui.group(|ui| {
+                ui.vertical(|ui| {
+                    ui.label("Violations");
+                    ui.separator();
+
+                    egui::ScrollArea::vertical()
+                        .id_source("hub_violations")
+                        .max_height(150.0)
+                        .stick_to_bottom(true)
+                        .show(ui, |ui| {
+                            for violation in monitor.violations() {
+                                ui.colored_label(Color32::RED, violation.to_string());
+                            }
+                        });
+                });
+            });
+        }
+
+        if disconnect {
+            self.bus_monitor = None;
+        }
+
+        if !self.packet_inspector.is_empty() {
+            ui.add_space(MEDIUM_PADDING);
+            self.packet_inspector.paint(ui);
+
+            ui.add_space(MEDIUM_PADDING);
+            self.paint_packet_sequence(ui);
         }
     }
+
+    /// passes on what has been sent to the GUI during a QTP run on the test bench (or
+    /// against the whole MARV): the packets, what the operator is asked to do, and the
+    /// steps carried out
+    fn read_bench_endpoints(&mut self) {
+        while let Some(envelope) = self.endpoints.packets.lock().unwrap().read() {
+            self.packet_inspector.push(envelope, None);
+        }
+
+        while let Some(prompt) = self.endpoints.prompts.lock().unwrap().read() {
+            self.bench_prompt = Some(prompt);
+        }
+
+        while let Some(step) = self.endpoints.steps.lock().unwrap().read() {
+            // the operator has done what they were asked to, or ran out of time
+            self.bench_prompt = None;
+
+            if let Some(report) = &mut self.bench_report {
+                report.steps.push(step);
+            }
+        }
+    }
+
+    /// shows what the operator is asked to do during the run of the QTP named `qtp`, its
+    /// report so far, and the packets sent
+    fn paint_bench_results(&mut self, ui: &mut Ui, qtp: &str, running: bool) {
+        if let Some(prompt) = self.bench_prompt.clone() {
+            ui.add_space(LARGE_PADDING);
+            ui.heading(RichText::new(prompt.text()).color(Color32::GOLD));
+
+            if let Prompt::Measure(_) = prompt {
+                self.paint_measurement_entry(ui);
+            }
+        }
+
+        // only the report of this QTP is shown, not of one run before it
+        let report = match &self.bench_report {
+            Some(report) if report.qtp == qtp => report,
+            _ => return,
+        };
+
+        if !report.steps.is_empty() {
+            ui.add_space(LARGE_PADDING);
+            paint_bench_report(ui, report, running);
+        }
+
+        if !self.packet_inspector.is_empty() {
+            ui.add_space(MEDIUM_PADDING);
+            self.packet_inspector.paint(ui);
+
+            ui.add_space(MEDIUM_PADDING);
+            self.paint_packet_sequence(ui);
+        }
+    }
+
+    /// lets the operator enter what they were asked to measure, or choose not to
+    fn paint_measurement_entry(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.bench_measurement);
+
+            let measurement = self.bench_measurement.trim().parse::<f32>().ok();
+            let entered = ui
+                .add_enabled(measurement.is_some(), egui::Button::new("Enter"))
+                .clicked();
+            let skipped = ui.button("Skip").clicked();
+
+            if entered || skipped {
+                self.endpoints
+                    .measurements
+                    .lock()
+                    .unwrap()
+                    .write(measurement.filter(|_| entered));
+                self.bench_prompt = None;
+                self.bench_measurement.clear();
+            }
+        });
+    }
+
+    /// loads the recorded run at `replay_path`, to be played back from its start
+    fn load_replay(&mut self) {
+        self.capture_status = None;
+
+        match Session::load(&self.replay_path) {
+            Ok(session) => {
+                self.replay = Some(SessionPlayer::new(session));
+                self.replay_error = None;
+            }
+            Err(err) => {
+                self.replay = None;
+                self.replay_error = Some(err.to_string());
+            }
+        }
+    }
+
+    /// exports the packets of the recording at `recording` to a file beside it
+    fn export_capture(&mut self, recording: &Path, format: CaptureFormat) {
+        let path = recording.with_extension(format!("capture.{}", format.extension()));
+
+        let exported = Session::load(recording)
+            .map_err(|err| err.to_string())
+            .and_then(|session| {
+                PacketCapture::from(&session)
+                    .export(&path, format)
+                    .map_err(|err| err.to_string())
+            });
+
+        self.capture_status = Some(match exported {
+            Ok(()) => format!("exported to {}", path.display()),
+            Err(err) => format!("could not export: {}", err),
+        });
+    }
+
+    /// paints the buttons that export the packets of the recording at `recording`
+    fn paint_capture_export(&mut self, ui: &mut Ui, recording: &Path) {
+        ui.horizontal(|ui| {
+            if ui.button("Export CSV").clicked() {
+                self.export_capture(recording, CaptureFormat::Csv);
+            }
+
+            if ui.button("Export JSONL").clicked() {
+                self.export_capture(recording, CaptureFormat::Jsonl);
+            }
+
+            if let Some(status) = &self.capture_status {
+                ui.label(status);
+            }
+        });
+    }
+
+    /// plays a recorded run back over its maze, without running any subsystems, and
+    /// lets the playback be scrubbed forward and backward
+    fn paint_replay_window(&mut self, ui: &mut Ui, ctx: &egui::Context) {
+        if ui.button("<").clicked() {
+            self.state.pop();
+        }
+
+        ui.add_space(LARGE_PADDING);
+        ui.heading("Replay");
+        ui.add_space(MEDIUM_PADDING);
+        ui.separator();
+        ui.add_space(LARGE_PADDING);
+
+        if let Some(player) = &mut self.replay {
+            player.advance(ui.input().stable_dt);
+
+            if player.playing {
+                ctx.request_repaint();
+            }
+
+            // a QTP run on the test bench has no maze to play the run back on
+            if let Some(maze) = player.session().qtp.maze() {
+                let positions = player.positions().unwrap_or([(0.1, 0.05); 5]);
+
+                paint_maze(ui, &maze, positions);
+
+                // the path of the middle sensor up to this point in the run
+                let points: Vec<Pos2> = player
+                    .events()
+                    .iter()
+                    .filter_map(|event| event.positions)
+                    .map(|positions| {
+                        let (x, y) = positions[2];
+                        Pos2::new(x + MAZE_LEFT_JUSTIFICATION, y + MAZE_TOP_JUSTIFICATION)
+                    })
+                    .collect();
+
+                ui.painter().add(Shape::line(
+                    points,
+                    Stroke::new(1.5, Color32::from_rgb(255, 140, 0)),
+                ));
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.add_space(300.0);
+
+            ui.vertical(|ui| {
+                ui.horizontal(|ui| {
+                    ui.label("File");
+                    ui.text_edit_singleline(&mut self.replay_path);
+
+                    if ui.button("Load").clicked() {
+                        self.load_replay();
+                    }
+                });
+
+                if let Some(err) = &self.replay_error {
+                    ui.colored_label(Color32::RED, err);
+                }
+
+                if self.replay.is_some() {
+                    let path = PathBuf::from(&self.replay_path);
+                    self.paint_capture_export(ui, &path);
+                }
+
+                let player = match &mut self.replay {
+                    Some(player) => player,
+                    None => return,
+                };
+
+                ui.add_space(MEDIUM_PADDING);
+                ui.label(format!(
+                    "{}, {} packets over {:.1} s",
+                    player.session().qtp.name(),
+                    player.session().events.len(),
+                    player.session().duration()
+                ));
+
+                ui.horizontal(|ui| {
+                    if ui.button("|<").clicked() {
+                        player.step_backward();
+                    }
+
+                    let play = match player.playing {
+                        true => "Pause",
+                        false => "Play",
+                    };
+                    if ui.button(play).clicked() {
+                        // playing from the end starts the recording over
+                        if player.time() >= player.session().duration() {
+                            player.seek(0.0);
+                        }
+                        player.playing = !player.playing;
+                    }
+
+                    if ui.button(">|").clicked() {
+                        player.step_forward();
+                    }
+
+                    ui.add_space(MEDIUM_PADDING);
+                    ui.label("Speed");
+                    ui.add(
+                        egui::DragValue::new(&mut player.speed)
+                            .clamp_range(0.1..=20.0)
+                            .speed(0.1)
+                            .suffix("x"),
+                    );
+                });
+
+                let mut time = player.time();
+                if ui
+                    .add(
+                        egui::Slider::new(&mut time, 0.0..=player.session().duration())
+                            .suffix(" s"),
+                    )
+                    .changed()
+                {
+                    player.seek(time);
+                }
+
+                ui.add_space(MEDIUM_PADDING);
+
+                // the latest packets at this point in the run, newest first
+                ui.group(|ui| {
+                    ui.vertical(|ui| {
+                        player.events().iter().rev().take(20).for_each(|event| {
+                            ui.label(format!(
+                                "{:.3} s {}: {} {:?}",
+                                event.time,
+                                event.sender,
+                                event.packet,
+                                event.packet.control_byte()
+                            ));
+                        });
+                    });
+                });
+
+                ui.add_space(MEDIUM_PADDING);
+
+                // and every packet up to this point in the run, as a sequence diagram
+                let envelopes: Vec<PacketEnvelope> = player
+                    .session()
+                    .envelopes()
+                    .take(player.events().len())
+                    .collect();
+                paint_sequence_diagram(ui, "replay_sequence_diagram", &envelopes);
+            });
+        });
+    }
+
+    /// paints the settings of the emulated MDPS's motor dynamics
+    fn paint_motor_model_settings(&mut self, ui: &mut Ui) {
+        egui::CollapsingHeader::new("MDPS motor model").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Ideal").clicked() {
+                    self.emulation_config.motor_model = MotorModel::ideal();
+                }
+                if ui.button("Realistic").clicked() {
+                    self.emulation_config.motor_model = MotorModel::realistic();
+                }
+            });
+
+            ui.add_space(SMALL_PADDING);
+
+            let model = &mut self.emulation_config.motor_model;
+
+            ui.horizontal(|ui| {
+                let mut limited = model.acceleration.is_some();
+
+                if ui.checkbox(&mut limited, "Acceleration limit").changed() {
+                    model.acceleration =
+                        limited.then_some(MotorModel::realistic().acceleration.unwrap());
+                }
+
+                if let Some(acceleration) = &mut model.acceleration {
+                    ui.add(
+                        egui::DragValue::new(acceleration)
+                            .clamp_range(1.0..=5000.0)
+                            .suffix(" mm/s²"),
+                    );
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Deadband");
+                ui.add(
+                    egui::DragValue::new(&mut model.deadband)
+                        .clamp_range(0.0..=100.0)
+                        .suffix(" mm/s"),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Motor gains (L/R)");
+                ui.add(
+                    egui::DragValue::new(&mut model.left_gain)
+                        .clamp_range(0.5..=1.5)
+                        .speed(0.01),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut model.right_gain)
+                        .clamp_range(0.5..=1.5)
+                        .speed(0.01),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Slip");
+                ui.add(
+                    egui::DragValue::new(&mut model.slip)
+                        .clamp_range(0.0..=0.5)
+                        .speed(0.01),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                let mut quantised = model.encoder_resolution.is_some();
+
+                if ui.checkbox(&mut quantised, "Encoder resolution").changed() {
+                    model.encoder_resolution =
+                        quantised.then_some(MotorModel::realistic().encoder_resolution.unwrap());
+                }
+
+                if let Some(resolution) = &mut model.encoder_resolution {
+                    ui.add(
+                        egui::DragValue::new(resolution)
+                            .clamp_range(0.1..=50.0)
+                            .speed(0.1)
+                            .suffix(" mm/tick"),
+                    );
+                }
+            });
+        });
+    }
+
+    /// paints the settings of how the emulated MDPS carries out rotate instructions
+    fn paint_rotation_control_settings(&mut self, ui: &mut Ui) {
+        egui::CollapsingHeader::new("MDPS rotation control").show(ui, |ui| {
+            if ui.button("Default").clicked() {
+                self.emulation_config.rotation_control = RotationControl::default();
+            }
+
+            ui.add_space(SMALL_PADDING);
+
+            let control = &mut self.emulation_config.rotation_control;
+
+            ui.horizontal(|ui| {
+                ui.label("Tolerance");
+                ui.add(
+                    egui::DragValue::new(&mut control.tolerance)
+                        .clamp_range(0.0..=10.0)
+                        .speed(0.1)
+                        .suffix("°"),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Slow down within");
+                ui.add(
+                    egui::DragValue::new(&mut control.slow_down_angle)
+                        .clamp_range(0.0..=90.0)
+                        .suffix("°"),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Minimum speed");
+                ui.add(
+                    egui::DragValue::new(&mut control.min_speed)
+                        .clamp_range(1.0..=100.0)
+                        .suffix(" mm/s"),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                let mut timeout = control.timeout.as_secs_f32();
+
+                ui.label("Timeout");
+                if ui
+                    .add(
+                        egui::DragValue::new(&mut timeout)
+                            .clamp_range(0.5..=60.0)
+                            .speed(0.1)
+                            .suffix(" s"),
+                    )
+                    .changed()
+                {
+                    control.timeout = Duration::from_secs_f32(timeout);
+                }
+            });
+        });
+    }
+
+    /// how long a run of `qtp` may go on for before it is stopped
+    fn run_limits(&self, qtp: NavconQtp) -> RunLimits {
+        self.run_limits
+            .get(&qtp.number)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// paints how long a run of the QTP may go on for before it is stopped
+    fn paint_run_limits_settings(&mut self, ui: &mut Ui, qtp: NavconQtp) {
+        egui::CollapsingHeader::new("Run limits").show(ui, |ui| {
+            let limits = self.run_limits.entry(qtp.number).or_default();
+
+            if ui.button("Default").clicked() {
+                *limits = RunLimits::default();
+            }
+
+            ui.add_space(SMALL_PADDING);
+
+            ui.horizontal(|ui| {
+                let mut timeout = limits.timeout.as_secs();
+
+                ui.label("Timeout");
+                if ui
+                    .add(
+                        egui::DragValue::new(&mut timeout)
+                            .clamp_range(5..=3600)
+                            .suffix(" s"),
+                    )
+                    .changed()
+                {
+                    limits.timeout = Duration::from_secs(timeout);
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("NAVCON steps");
+                ui.add(egui::DragValue::new(&mut limits.max_steps).clamp_range(10..=100_000));
+            });
+        });
+    }
+
+    /// paints the faults injected into the packets sent between the subsystems
+    fn paint_fault_settings(&mut self, ui: &mut Ui) {
+        egui::CollapsingHeader::new("SCS faults").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("None").clicked() {
+                    self.emulation_config.faults = FaultProfile::none();
+                }
+                if ui.button("Noisy").clicked() {
+                    self.emulation_config.faults = FaultProfile::noisy();
+                }
+            });
+
+            ui.add_space(SMALL_PADDING);
+
+            let faults = &mut self.emulation_config.faults;
+
+            ui.horizontal(|ui| {
+                ui.label("Packets from");
+                egui::ComboBox::from_id_source("fault_source")
+                    .selected_text(match faults.source {
+                        Some(source) => source.to_string(),
+                        None => "any source".to_string(),
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut faults.source, None, "any source");
+                        for source in SOURCES {
+                            ui.selectable_value(
+                                &mut faults.source,
+                                Some(source),
+                                source.to_string(),
+                            );
+                        }
+                    });
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Control byte");
+                egui::ComboBox::from_id_source("fault_control_byte")
+                    .selected_text(match faults.control_byte {
+                        Some(byte) => byte.to_string(),
+                        None => "any control byte".to_string(),
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut faults.control_byte, None, "any control byte");
+                        for byte in CONTROL_BYTES {
+                            ui.selectable_value(
+                                &mut faults.control_byte,
+                                Some(byte),
+                                control_byte_name(byte),
+                            );
+                        }
+                    });
+            });
+
+            for (fault, probability) in FAULTS.iter().zip(faults.probabilities.iter_mut()) {
+                ui.horizontal(|ui| {
+                    let mut percentage = *probability * 100.0;
+
+                    ui.label(fault.to_string());
+                    if ui
+                        .add(
+                            egui::DragValue::new(&mut percentage)
+                                .clamp_range(0.0..=100.0)
+                                .speed(0.1)
+                                .suffix("%"),
+                        )
+                        .changed()
+                    {
+                        *probability = percentage / 100.0;
+                    }
+                });
+            }
+
+            ui.horizontal(|ui| {
+                let mut delay = faults.delay_time * 1000.0;
+
+                ui.label("Delay");
+                if ui
+                    .add(
+                        egui::DragValue::new(&mut delay)
+                            .clamp_range(1.0..=5000.0)
+                            .suffix(" ms"),
+                    )
+                    .changed()
+                {
+                    faults.delay_time = delay / 1000.0;
+                }
+            });
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                let mut scripted = faults.script.is_some();
+
+                if ui.checkbox(&mut scripted, "Scripted").changed() {
+                    faults.script = scripted.then_some(ScriptedFault {
+                        packet: 1,
+                        fault: FAULTS[0],
+                    });
+                }
+
+                if let Some(script) = &mut faults.script {
+                    ui.add(
+                        egui::DragValue::new(&mut script.packet)
+                            .clamp_range(1..=10_000)
+                            .prefix("packet "),
+                    );
+
+                    egui::ComboBox::from_id_source("scripted_fault")
+                        .selected_text(script.fault.to_string())
+                        .show_ui(ui, |ui| {
+                            for fault in FAULTS {
+                                ui.selectable_value(&mut script.fault, fault, fault.to_string());
+                            }
+                        });
+                }
+            });
+        });
+    }
+
+    /// paints the settings of the emulated MDPS's battery
+    fn paint_battery_settings(&mut self, ui: &mut Ui) {
+        egui::CollapsingHeader::new("MDPS battery").show(ui, |ui| {
+            let mut emulated = self.emulation_config.battery.is_some();
+
+            if ui.checkbox(&mut emulated, "Emulate battery").changed() {
+                self.emulation_config.battery = emulated.then(Battery::new);
+            }
+
+            let battery = match &mut self.emulation_config.battery {
+                Some(battery) => battery,
+                None => {
+                    ui.label("Battery levels are sent as 0's");
+                    return;
+                }
+            };
+
+            ui.add_space(SMALL_PADDING);
+
+            ui.horizontal(|ui| {
+                let level = battery.level();
+
+                ui.label("Capacity");
+                if ui
+                    .add(
+                        egui::DragValue::new(&mut battery.capacity)
+                            .clamp_range(1.0..=10000.0)
+                            .suffix(" mAh"),
+                    )
+                    .changed()
+                {
+                    // keep the initial charge at the same level
+                    battery.charge = battery.capacity * level;
+                }
+            });
+
+            ui.horizontal(|ui| {
+                let mut level = battery.level() * 100.0;
+
+                ui.label("Initial charge");
+                if ui
+                    .add(egui::Slider::new(&mut level, 0.0..=100.0).suffix("%"))
+                    .changed()
+                {
+                    battery.charge = battery.capacity * level / 100.0;
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Voltage (empty/full/rated)");
+                ui.add(
+                    egui::DragValue::new(&mut battery.empty_voltage)
+                        .clamp_range(0.0..=20.0)
+                        .speed(0.1)
+                        .suffix(" V"),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut battery.full_voltage)
+                        .clamp_range(0.0..=20.0)
+                        .speed(0.1)
+                        .suffix(" V"),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut battery.rated_voltage)
+                        .clamp_range(0.1..=20.0)
+                        .speed(0.1)
+                        .suffix(" V"),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Internal resistance");
+                ui.add(
+                    egui::DragValue::new(&mut battery.internal_resistance)
+                        .clamp_range(0.0..=10.0)
+                        .speed(0.01)
+                        .suffix(" Ω"),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Current (idle/per mm/s)");
+                ui.add(
+                    egui::DragValue::new(&mut battery.idle_current)
+                        .clamp_range(0.0..=5000.0)
+                        .suffix(" mA"),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut battery.current_per_speed)
+                        .clamp_range(0.0..=100.0)
+                        .speed(0.1)
+                        .suffix(" mA"),
+                );
+            });
+        });
+    }
+
+    /// paints the settings of the emulated SS's colour sensors
+    /// paints the settings of the emulated MARV's chassis, which can be loaded from a file
+    fn paint_robot_config_settings(&mut self, ui: &mut Ui) {
+        egui::CollapsingHeader::new("MARV chassis").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("File");
+                ui.text_edit_singleline(&mut self.robot_config_path);
+
+                if ui.button("Load").clicked() {
+                    match RobotConfig::load(&self.robot_config_path) {
+                        Ok(robot) => {
+                            self.emulation_config.robot = robot;
+                            self.robot_config_error = None;
+                        }
+                        Err(err) => self.robot_config_error = Some(err.to_string()),
+                    }
+                }
+
+                if ui.button("Default").clicked() {
+                    self.emulation_config.robot = RobotConfig::default();
+                    self.robot_config_error = None;
+                }
+            });
+
+            if let Some(err) = &self.robot_config_error {
+                ui.colored_label(Color32::RED, err);
+            }
+
+            ui.add_space(SMALL_PADDING);
+
+            let robot = &mut self.emulation_config.robot;
+
+            ui.horizontal(|ui| {
+                ui.label("Axle length");
+                ui.add(
+                    egui::DragValue::new(&mut robot.axle_length)
+                        .clamp_range(10.0..=300.0)
+                        .suffix(" mm"),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Wheel radius");
+                ui.add(
+                    egui::DragValue::new(&mut robot.wheel_radius)
+                        .clamp_range(5.0..=100.0)
+                        .suffix(" mm"),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Max speed");
+                ui.add(
+                    egui::DragValue::new(&mut robot.max_speed)
+                        .clamp_range(10.0..=1000.0)
+                        .suffix(" mm/s"),
+                );
+            });
+
+            ui.label("Sensors (forward, left) in mm");
+            for (index, (forward, left)) in robot.sensors.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{}", index));
+                    ui.add(egui::DragValue::new(forward).clamp_range(-200.0..=200.0));
+                    ui.add(egui::DragValue::new(left).clamp_range(-200.0..=200.0));
+                });
+            }
+        });
+    }
+
+    fn paint_sensor_model_settings(&mut self, ui: &mut Ui) {
+        egui::CollapsingHeader::new("SS sensor model").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Ideal").clicked() {
+                    self.emulation_config.sensor_model = SensorModel::ideal();
+                }
+                if ui.button("Realistic").clicked() {
+                    self.emulation_config.sensor_model = SensorModel::realistic();
+                }
+            });
+
+            ui.add_space(SMALL_PADDING);
+
+            let model = &mut self.emulation_config.sensor_model;
+
+            ui.horizontal(|ui| {
+                let mut percentage = model.misclassification * 100.0;
+
+                ui.label("Misclassification");
+                if ui
+                    .add(
+                        egui::DragValue::new(&mut percentage)
+                            .clamp_range(0.0..=100.0)
+                            .speed(0.1)
+                            .suffix("%"),
+                    )
+                    .changed()
+                {
+                    model.misclassification = percentage / 100.0;
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Boundary blur");
+                ui.add(
+                    egui::DragValue::new(&mut model.boundary_blur)
+                        .clamp_range(0.0..=MAZE_LINE_WIDTH * 2.0)
+                        .speed(0.1),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Spot radius");
+                ui.add(
+                    egui::DragValue::new(&mut model.spot_radius)
+                        .clamp_range(0.0..=MAZE_LINE_WIDTH * 2.0)
+                        .speed(0.1),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Noise");
+                ui.add(
+                    egui::DragValue::new(&mut model.noise)
+                        .clamp_range(0.0..=0.5)
+                        .speed(0.01),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Bias");
+                for bias in model.bias.iter_mut() {
+                    ui.add(
+                        egui::DragValue::new(bias)
+                            .clamp_range(-0.5..=0.5)
+                            .speed(0.01),
+                    );
+                }
+            });
+
+            ui.checkbox(&mut model.calibrate, "Calibrate in CAL state");
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                let mode = &mut self.emulation_config.incidence_mode;
+
+                ui.label("Incidence");
+                ui.radio_value(mode, IncidenceMode::Geometric, "Geometric");
+                ui.radio_value(mode, IncidenceMode::StudentEstimate, "Student estimate");
+            });
+        });
+    }
+
+    /// paints the last battery level reported by the MDPS
+    fn paint_battery_gauge(&self, ui: &mut Ui) {
+        if let Some((percentage, decivolts)) = self.battery_level {
+            ui.group(|ui| {
+                ui.vertical(|ui| {
+                    ui.label("MDPS battery");
+                    ui.separator();
+
+                    ui.add(
+                        egui::ProgressBar::new(percentage as f32 / 100.0)
+                            .desired_width(150.0)
+                            .text(format!(
+                                "{}% ({:.1} V)",
+                                percentage,
+                                decivolts as f32 / 10.0
+                            )),
+                    );
+                });
+            });
+        }
+    }
+
+    /// paints the last incidence worked out by the emulated SS, and how far the
+    /// estimated incidence was from the true incidence over the run
+    fn paint_incidence(&self, ui: &mut Ui) {
+        ui.group(|ui| {
+            ui.vertical(|ui| {
+                ui.label("SS incidence");
+                ui.separator();
+
+                if let Some(report) = self.incidence_log.last() {
+                    ui.label(format!("mode: {}", report.mode));
+
+                    match report.geometric {
+                        Some((sensor, incidence)) => {
+                            ui.label(format!("true: {:.1}° (sensor {})", incidence, sensor))
+                        }
+                        None => ui.label("true: -"),
+                    };
+
+                    match report.estimated {
+                        Some(incidence) => ui.label(format!("estimated: {}°", incidence)),
+                        None => ui.label("estimated: -"),
+                    };
+
+                    ui.label(format!("reported: {}°", report.reported));
+                }
+
+                let errors: Vec<f32> = self
+                    .incidence_log
+                    .iter()
+                    .filter_map(|report| report.estimation_error())
+                    .map(f32::abs)
+                    .collect();
+
+                if !errors.is_empty() {
+                    ui.separator();
+                    ui.label(format!(
+                        "estimation error: mean {:.1}°, max {:.1}° ({} readings)",
+                        errors.iter().sum::<f32>() / errors.len() as f32,
+                        errors.iter().copied().fold(0.0, f32::max),
+                        errors.len()
+                    ));
+                }
+            });
+        });
+    }
+
+    /// paints the path that the emulated MARV's centre has followed over the maze
+    fn paint_trail(&self, ui: &Ui) {
+        let points: Vec<Pos2> = self
+            .trajectory
+            .samples()
+            .iter()
+            .chain(self.trajectory.latest().as_ref())
+            .map(|pose| {
+                let (x, y) = to_maze_coords(pose.position);
+                Pos2::new(x + MAZE_LEFT_JUSTIFICATION, y + MAZE_TOP_JUSTIFICATION)
+            })
+            .collect();
+
+        ui.painter().add(Shape::line(
+            points,
+            Stroke::new(1.5, Color32::from_rgb(255, 140, 0)),
+        ));
+    }
+
+    /// marks where the last run failed on the maze, if it did
+    fn paint_failure_location(&self, ui: &Ui) {
+        if let Some(position) = self.run_outcome.and_then(|outcome| outcome.location()) {
+            let (x, y) = to_maze_coords(position);
+            let centre = Pos2::new(x + MAZE_LEFT_JUSTIFICATION, y + MAZE_TOP_JUSTIFICATION);
+            let stroke = Stroke::new(2.0, Color32::RED);
+
+            ui.painter().circle_stroke(centre, 8.0, stroke);
+            ui.painter().line_segment(
+                [
+                    centre + egui::vec2(-5.0, -5.0),
+                    centre + egui::vec2(5.0, 5.0),
+                ],
+                stroke,
+            );
+            ui.painter().line_segment(
+                [
+                    centre + egui::vec2(-5.0, 5.0),
+                    centre + egui::vec2(5.0, -5.0),
+                ],
+                stroke,
+            );
+        }
+    }
+
+    /// paints the packets shown in the inspector, i.e. as they are filtered, as a sequence
+    /// diagram
+    fn paint_packet_sequence(&self, ui: &mut Ui) {
+        let envelopes: Vec<PacketEnvelope> = self
+            .packet_inspector
+            .visible()
+            .into_iter()
+            .map(|(_, inspected)| inspected.envelope)
+            .collect();
+
+        paint_sequence_diagram(ui, "sequence_diagram", &envelopes);
+    }
+
+    /// marks the pose of the MARV at the packet selected in the inspector, if there is one
+    fn paint_selected_pose(&self, ui: &Ui) {
+        let pose = match self
+            .packet_inspector
+            .selected()
+            .and_then(|inspected| inspected.pose)
+        {
+            Some(pose) => pose,
+            None => return,
+        };
+
+        let to_screen = |position: (f32, f32)| {
+            let (x, y) = to_maze_coords(position);
+            Pos2::new(x + MAZE_LEFT_JUSTIFICATION, y + MAZE_TOP_JUSTIFICATION)
+        };
+        let ahead = (
+            pose.position.0 + 0.05 * pose.heading.cos(),
+            pose.position.1 + 0.05 * pose.heading.sin(),
+        );
+        let stroke = Stroke::new(2.0, Color32::from_rgb(30, 110, 255));
+
+        ui.painter()
+            .circle_stroke(to_screen(pose.position), 8.0, stroke);
+        ui.painter()
+            .line_segment([to_screen(pose.position), to_screen(ahead)], stroke);
+    }
+
+    /// paints the axle and wheels of the emulated MARV at its latest pose
+    fn paint_chassis(&self, ui: &Ui) {
+        let pose = match self.trajectory.latest() {
+            Some(pose) => pose,
+            None => return,
+        };
+
+        let robot = &self.emulation_config.robot;
+        let to_screen = |(x, y): (f32, f32)| {
+            let (x, y) = to_maze_coords((x, y));
+            Pos2::new(x + MAZE_LEFT_JUSTIFICATION, y + MAZE_TOP_JUSTIFICATION)
+        };
+
+        // the maze is drawn with y pointing down, so the MARV's left is a quarter turn anticlockwise
+        let (forward_x, forward_y) = (pose.heading.cos(), pose.heading.sin());
+        let (left_x, left_y) = (forward_y, -forward_x);
+        let half_axle = robot.axle_length / 2_000.0;
+        let radius = robot.wheel_radius / 1_000.0;
+        let stroke = Stroke::new(1.0, Color32::from_rgb(60, 60, 60));
+
+        let wheel_centres = [1.0, -1.0].map(|side| {
+            (
+                pose.position.0 + side * half_axle * left_x,
+                pose.position.1 + side * half_axle * left_y,
+            )
+        });
+
+        ui.painter().line_segment(
+            [to_screen(wheel_centres[0]), to_screen(wheel_centres[1])],
+            stroke,
+        );
+
+        wheel_centres.into_iter().for_each(|(x, y)| {
+            ui.painter().line_segment(
+                [
+                    to_screen((x - radius * forward_x, y - radius * forward_y)),
+                    to_screen((x + radius * forward_x, y + radius * forward_y)),
+                ],
+                Stroke::new(3.0, Color32::from_rgb(60, 60, 60)),
+            );
+        });
+    }
+
+    /// paints the ground-truth pose of the emulated MARV, how accurately it turned when
+    /// NAVCON told it to rotate, and its heading over the run
+    fn paint_pose(&self, ui: &mut Ui) {
+        let pose = match self.trajectory.latest() {
+            Some(pose) => pose,
+            None => return,
+        };
+
+        ui.group(|ui| {
+            ui.vertical(|ui| {
+                ui.label("MARV pose (ground truth)");
+                ui.separator();
+
+                ui.label(format!(
+                    "position: ({:.0}, {:.0}) mm",
+                    pose.position.0 * 1_000.0,
+                    pose.position.1 * 1_000.0
+                ));
+                ui.label(format!("heading: {:.1}°", pose.heading.to_degrees()));
+                ui.label(format!(
+                    "v: {:.0} mm/s, ω: {:.1}°/s",
+                    pose.linear_velocity * 1_000.0,
+                    pose.angular_velocity.to_degrees()
+                ));
+                if let Some(error) = self.trajectory.heading_error() {
+                    ui.label(format!("heading error: {:.1}°", error));
+                }
+                ui.label(format!(
+                    "path length: {:.0} mm",
+                    self.trajectory.path_length()
+                ));
+
+                // pair every rotation NAVCON asked for with the turn that the MARV made
+                let rotations = self
+                    .decision_log
+                    .iter()
+                    .filter_map(|decision| match decision.to {
+                        NavConState::RotateLeft => Some(decision.output_rotation as f32),
+                        NavConState::RotateRight => Some(-(decision.output_rotation as f32)),
+                        _ => None,
+                    });
+
+                let turns: Vec<(f32, f32)> = rotations
+                    .zip(self.trajectory.turns().iter().map(|turn| turn.degrees()))
+                    .collect();
+
+                if !turns.is_empty() {
+                    ui.separator();
+                    ui.label("turns (+ left): commanded / achieved");
+
+                    turns.iter().for_each(|(commanded, achieved)| {
+                        ui.label(format!(
+                            "{:.0}° / {:.1}° (error {:.1}°)",
+                            commanded,
+                            achieved,
+                            achieved - commanded
+                        ));
+                    });
+                }
+
+                ui.separator();
+                ui.label("heading (°) over time (s)");
+
+                let points: Vec<[f64; 2]> = self
+                    .trajectory
+                    .samples()
+                    .iter()
+                    .map(|pose| [pose.time as f64, pose.heading.to_degrees() as f64])
+                    .collect();
+
+                Plot::new("heading_plot")
+                    .width(400.0)
+                    .height(150.0)
+                    .show(ui, |plot_ui| {
+                        plot_ui.line(Line::new(PlotPoints::new(points)))
+                    });
+            });
+        });
+    }
+
+    /// paints a scrollable list of every decision NAVCON has made during the run,
+    /// hovering over a decision shows the colours that NAVCON was given
+    fn paint_navcon_decisions(&self, ui: &mut Ui) {
+        ui.group(|ui| {
+            ui.vertical(|ui| {
+                ui.label("NAVCON decisions");
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .id_source("navcon_decisions")
+                    .max_height(400.0)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        self.decision_log.iter().for_each(|decision| {
+                            let colours = decision
+                                .colours
+                                .colours()
+                                .map(|colour| colour.to_string())
+                                .join(", ");
+
+                            ui.label(decision.to_string()).on_hover_text(format!(
+                                "colours: [{}]\npreviously encountered: {}",
+                                colours, decision.previously_encountered_colour
+                            ));
+                        });
+                    });
+            });
+        });
+    }
+}
+
+/// paints `maze`, with the sensors of the MARV at `sensor_pos` on it
+fn paint_maze(ui: &mut Ui, maze: &MazeLineMap, sensor_pos: [(f32, f32); 5]) {
+    maze.paint(ui);
+
+    sensor_pos.into_iter().for_each(|(x, y)| {
+        ui.painter().circle_filled(
+            Pos2::new(x + MAZE_LEFT_JUSTIFICATION, y + MAZE_TOP_JUSTIFICATION),
+            2.5,
+            Color32::from_rgb(100, 100, 100),
+        );
+    });
+}
+
+/// a menu (labelled `label`) to choose the serial port that a physical subsystem is
+/// connected to
+fn paint_port_menu(ui: &mut Ui, label: &str, port: &mut Option<String>) {
+    let button_name = match port {
+        Some(com_no) => com_no.clone(),
+        None => String::from("None"),
+    };
+
+    ui.menu_button(format!("{}: {}", label, button_name), |ui| {
+        for available in serialport::available_ports().unwrap() {
+            if ui
+                .button(format!(
+                    "{} ({:?})",
+                    available.port_name, available.port_type
+                ))
+                .clicked()
+            {
+                *port = Some(available.port_name);
+            }
+        }
+
+        if ui.button("None").clicked() {
+            *port = None;
+        }
+    });
+}
+
+/// paints each step of a QTP run on the test bench, and its verdict once it has ended
+fn paint_bench_report(ui: &mut Ui, report: &QtpReport, running: bool) {
+    let colour = |verdict: Verdict| match verdict {
+        Verdict::Pass => Color32::DARK_GREEN,
+        Verdict::Fail => Color32::RED,
+        Verdict::Skipped => Color32::GRAY,
+    };
+
+    ui.group(|ui| {
+        ui.vertical(|ui| {
+            if !running {
+                let verdict = report.verdict();
+                ui.heading(RichText::new(verdict.to_string()).color(colour(verdict)));
+                ui.separator();
+            }
+
+            egui::Grid::new("bench_steps").striped(true).show(ui, |ui| {
+                for step in &report.steps {
+                    ui.label(format!("{:.3} s", step.time));
+                    ui.colored_label(colour(step.verdict), step.verdict.to_string());
+                    ui.label(&step.name);
+                    ui.label(&step.detail);
+                    ui.end_row();
+                }
+            });
+        });
+    });
 }
 
 impl eframe::App for MARVApp {
@@ -323,7 +2015,11 @@ impl eframe::App for MARVApp {
             if let Some(window) = self.state.curr_window() {
                 match window {
                     Window::Main => self.paint_main_window(ui),
-                    Window::Navcon(qtp_no) => self.paint_navcon_qtp_window(ui, ctx, qtp_no),
+                    Window::Navcon(qtp) => self.paint_navcon_qtp_window(ui, ctx, qtp),
+                    Window::Replay => self.paint_replay_window(ui, ctx),
+                    Window::Bench(qtp) => self.paint_bench_window(ui, ctx, qtp),
+                    Window::Integration(qtp) => self.paint_integration_window(ui, ctx, qtp),
+                    Window::Hub => self.paint_hub_window(ui, ctx),
                 }
             } else {
                 self.state.push(Window::Main);
@@ -30,6 +30,24 @@ fn paint_rect(center_x: f32, center_y: f32, width: f32, height: f32, r: u8, g: u
     );
 }
 
+/// The direction in which a line of the maze runs on screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineOrientation {
+    /// a '--' line, i.e. along the x axis
+    Horizontal,
+    /// a '|' line, i.e. along the y axis
+    Vertical,
+}
+
+/// The part of the maze that a point is in, with the (column, row) or (row, column)
+/// indices of the line that it is on
+enum Region {
+    HorizontalLine(usize, usize),
+    VerticalLine(usize, usize),
+    Block,
+    Outside,
+}
+
 /// # MazeLineMap
 /// ## A map of the horizontal and vertical lines that make up a maze
 ///
@@ -45,6 +63,7 @@ fn paint_rect(center_x: f32, center_y: f32, width: f32, height: f32, r: u8, g: u
 /// Where the horizontal lines of the maze are represented by '--', and
 /// the vertical lines in the maze are represented by '|' in the figure
 /// above.
+#[derive(Clone)]
 pub struct MazeLineMap {
     columns: Vec<Column>,
     rows: Vec<Row>,
@@ -87,7 +106,8 @@ impl MazeLineMap {
         }
     }
 
-    pub fn get_colour_from_coord(&self, x: f32, y: f32) -> Option<Colour> {
+    /// finds the part of the maze that the point (x, y) is in
+    fn get_region_from_coord(&self, x: f32, y: f32) -> Region {
         //println!("accessing coord: ({}, {})", x, y);
         // x: <-->
         //
@@ -97,6 +117,11 @@ impl MazeLineMap {
         //     println!("beep");
         // }
 
+        // if we are outside the maze then there is nothing to see
+        if !self.contains(x, y) {
+            return Region::Outside;
+        }
+
         let col_index = x.floor() as usize / (MAZE_LINE_LENGTH + MAZE_LINE_WIDTH) as usize;
         let row_index = y.floor() as usize / (MAZE_LINE_LENGTH + MAZE_LINE_WIDTH) as usize;
 
@@ -118,29 +143,61 @@ impl MazeLineMap {
         // |                             085 pixels
         // |      : vertical line (|)     ↓
 
-        // if we are outside the maze then just return white
-        if col_index > self.columns.len() || row_index > self.rows.len() {
-            return Some(Colour::White);
-        }
-
         // get coords within block
         let x_in_block = x - (MAZE_LINE_LENGTH + MAZE_LINE_WIDTH) * col_index as f32;
         let y_in_block = y - (MAZE_LINE_LENGTH + MAZE_LINE_WIDTH) * row_index as f32;
 
         // if x_in_block > 5 && y_in_block <= 5 then the point is within the horizontal line, but
         // if y_in_block > 5 && x_in_block <= 5 then the point is within the vertical line
-        // otherwise it is within the block itself, and we can just return Colour::White.
+        // otherwise it is within the block itself.
         if x_in_block > MAZE_LINE_WIDTH && y_in_block <= MAZE_LINE_WIDTH {
             // point is in horizontal line (contained in columns)
-            self.columns[col_index].get(row_index)
+            Region::HorizontalLine(col_index, row_index)
         } else if y_in_block > MAZE_LINE_WIDTH && x_in_block <= MAZE_LINE_WIDTH {
             // point is in vertical line (contained in rows)
-            self.rows[row_index].get(col_index)
+            Region::VerticalLine(row_index, col_index)
         } else {
-            Some(Colour::White)
+            Region::Block
         }
     }
 
+    /// whether the point (x, y) is on the maze, i.e. within its outer lines
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= 0.0
+            && y >= 0.0
+            && x <= self.width as f32 * MAZE_COL_WIDTH + MAZE_LINE_WIDTH
+            && y <= self.height as f32 * MAZE_ROW_HEIGHT + MAZE_LINE_WIDTH
+    }
+
+    /// returns the colour at the point (x, y), which is white anywhere off the lines
+    /// (including off the maze), or `None` if the line there has not been added
+    pub fn get_colour_from_coord(&self, x: f32, y: f32) -> Option<Colour> {
+        match self.get_region_from_coord(x, y) {
+            Region::HorizontalLine(col_index, row_index) => self
+                .columns
+                .get(col_index)
+                .and_then(|column| column.get(row_index)),
+            Region::VerticalLine(row_index, col_index) => {
+                self.rows.get(row_index).and_then(|row| row.get(col_index))
+            }
+            Region::Block | Region::Outside => Some(Colour::White),
+        }
+    }
+
+    /// returns the colour and orientation of the line at the point (x, y), or `None`
+    /// if there is no (coloured) line there
+    pub fn get_line_from_coord(&self, x: f32, y: f32) -> Option<(Colour, LineOrientation)> {
+        let orientation = match self.get_region_from_coord(x, y) {
+            Region::HorizontalLine(..) => LineOrientation::Horizontal,
+            Region::VerticalLine(..) => LineOrientation::Vertical,
+            Region::Block | Region::Outside => return None,
+        };
+
+        self.get_colour_from_coord(x, y)
+            .filter(|colour| *colour != Colour::White)
+            .map(|colour| (colour, orientation))
+    }
+
     pub fn get_colours(&self, positions: [(f32, f32); 5]) -> Vec<Colour> {
         positions
             .iter()
@@ -210,6 +267,7 @@ impl MazeLineMap {
     }
 }
 
+#[derive(Clone)]
 pub struct Line(Colour);
 
 impl Line {
@@ -222,10 +280,10 @@ impl Line {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Column(Vec<Line>);
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Row(Vec<Line>);
 
 impl Column {
@@ -1,21 +1,276 @@
-use std::collections::VecDeque;
+//! # Packet inspector
+//!
+//! A table of every packet sent during a run, decoded, which can be filtered by source,
+//! control byte and state, searched, and paused while the run goes on. Clicking on a
+//! packet selects it, so that the pose of the MARV at that packet can be shown.
 
-pub struct LabelList(VecDeque<String>);
+use eframe::egui::{self, Ui};
 
-impl LabelList {
+use crate::{
+    components::{
+        comm_port::ControlByte, envelope::PacketEnvelope, state::SystemState, subsystem::Subsystem,
+    },
+    subsystems::sensor_positions::Pose,
+};
+
+pub const SOURCES: [Subsystem; 3] = [Subsystem::Snc, Subsystem::Ss, Subsystem::Mdps];
+const STATES: [SystemState; 4] = [
+    SystemState::Idle,
+    SystemState::Calibrate,
+    SystemState::Maze,
+    SystemState::Sos,
+];
+
+/// A packet in the inspector
+#[derive(Debug, Clone, Copy)]
+pub struct InspectedPacket {
+    pub envelope: PacketEnvelope,
+    /// the pose of the MARV when the packet was received, if it was known
+    pub pose: Option<Pose>,
+}
+
+impl InspectedPacket {
+    /// the packet's row in the table, after its number and time: source and destinations,
+    /// state, control byte and meaning
+    fn columns(&self) -> [String; 4] {
+        let envelope = self.envelope;
+        let packet = envelope.packet;
+
+        [
+            format!("{} -> {}", envelope.source, envelope.destination_names()),
+            packet.state().to_string(),
+            control_byte_name(self.control_byte()),
+            packet.meaning(),
+        ]
+    }
+
+    fn control_byte(&self) -> u8 {
+        <[u8; 4]>::from(self.envelope.packet)[0]
+    }
+}
+
+/// The packets sent during a run, and how they are shown
+#[derive(Default)]
+pub struct PacketInspector {
+    packets: Vec<InspectedPacket>,
+    /// the number of packets shown while the inspector is paused
+    paused_at: Option<usize>,
+    /// only packets from this source are shown, if it is set...
+    pub source: Option<Subsystem>,
+    /// ...with this control byte...
+    pub control_byte: Option<u8>,
+    /// ...in this state...
+    pub state: Option<SystemState>,
+    /// ...and that contain this text (in any case)
+    pub search: String,
+    /// the index of the packet that was clicked on
+    selected: Option<usize>,
+}
+
+impl PacketInspector {
     pub fn new() -> Self {
-        Self(VecDeque::new())
+        Self::default()
+    }
+
+    /// adds the next packet sent during the run, received with the MARV at `pose`
+    pub fn push(&mut self, envelope: PacketEnvelope, pose: Option<Pose>) {
+        self.packets.push(InspectedPacket { envelope, pose });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    /// stops new packets from being shown, or shows them all again
+    pub fn toggle_pause(&mut self) {
+        self.paused_at = match self.paused_at {
+            Some(_) => None,
+            None => Some(self.packets.len()),
+        };
+    }
+
+    /// the packets that are shown, with their indices, as filtered
+    pub fn visible(&self) -> Vec<(usize, &InspectedPacket)> {
+        let shown = self.paused_at.unwrap_or(self.packets.len());
+        let search = self.search.to_lowercase();
+
+        self.packets[..shown]
+            .iter()
+            .enumerate()
+            .filter(|(_, inspected)| {
+                let packet = inspected.envelope.packet;
+
+                allows(self.source, inspected.envelope.source)
+                    && allows(self.control_byte, inspected.control_byte())
+                    && allows(self.state, packet.state())
+                    && (search.is_empty()
+                        || inspected
+                            .columns()
+                            .iter()
+                            .chain([packet.to_string()].iter())
+                            .any(|column| column.to_lowercase().contains(&search)))
+            })
+            .collect()
     }
 
-    pub fn push(&mut self, string: &str) {
-        self.0.push_front(String::from(string));
+    /// the packet that was clicked on, if it is still shown
+    pub fn selected(&self) -> Option<&InspectedPacket> {
+        let index = self.selected?;
+
+        self.visible()
+            .into_iter()
+            .find_map(|(visible, inspected)| (visible == index).then_some(inspected))
+    }
 
-        if self.0.len() > 20 {
-            self.0.pop_back();
-        }
+    pub fn select(&mut self, index: Option<usize>) {
+        self.selected = index;
     }
 
-    pub fn to_vec(&self) -> Vec<String> {
-        self.0.clone().into()
+    /// paints the filters and the table of packets
+    pub fn paint(&mut self, ui: &mut Ui) {
+        ui.group(|ui| {
+            ui.vertical(|ui| {
+                ui.label("Packets");
+                ui.separator();
+
+                self.paint_filters(ui);
+
+                ui.add_space(4.0);
+
+                let visible: Vec<(usize, InspectedPacket)> = self
+                    .visible()
+                    .into_iter()
+                    .map(|(index, inspected)| (index, *inspected))
+                    .collect();
+                let mut clicked = None;
+
+                // only the rows that are scrolled to are painted, as a run sends thousands of packets
+                let row_height = ui.text_style_height(&egui::TextStyle::Body);
+
+                egui::ScrollArea::vertical()
+                    .id_source("packet_inspector")
+                    .max_height(400.0)
+                    .stick_to_bottom(!self.paused())
+                    .show_rows(ui, row_height, visible.len(), |ui, rows| {
+                        egui::Grid::new("packet_table")
+                            .striped(true)
+                            .start_row(rows.start)
+                            .show(ui, |ui| {
+                                for (index, inspected) in &visible[rows] {
+                                    let selected = self.selected == Some(*index);
+
+                                    if ui
+                                        .selectable_label(
+                                            selected,
+                                            format!(
+                                                "#{} {:.3} s",
+                                                inspected.envelope.sequence,
+                                                inspected.envelope.time
+                                            ),
+                                        )
+                                        .on_hover_text(inspected.envelope.packet.to_string())
+                                        .clicked()
+                                    {
+                                        clicked = Some(match selected {
+                                            true => None,
+                                            false => Some(*index),
+                                        });
+                                    }
+
+                                    inspected.columns().into_iter().for_each(|column| {
+                                        ui.label(column);
+                                    });
+                                    ui.end_row();
+                                }
+                            });
+                    });
+
+                if let Some(selection) = clicked {
+                    self.selected = selection;
+                }
+            });
+        });
     }
+
+    fn paint_filters(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            let pause = match self.paused() {
+                true => "Resume",
+                false => "Pause",
+            };
+            if ui.button(pause).clicked() {
+                self.toggle_pause();
+            }
+
+            egui::ComboBox::from_id_source("packet_source")
+                .selected_text(match self.source {
+                    Some(source) => source.to_string(),
+                    None => "any source".to_string(),
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.source, None, "any source");
+                    // the hub's own packets can be picked out as well
+                    for source in SOURCES.into_iter().chain([Subsystem::Hub]) {
+                        ui.selectable_value(&mut self.source, Some(source), source.to_string());
+                    }
+                });
+
+            // only the control bytes that have been sent can be chosen
+            let mut control_bytes: Vec<u8> = self
+                .packets
+                .iter()
+                .map(|inspected| inspected.control_byte())
+                .collect();
+            control_bytes.sort_unstable();
+            control_bytes.dedup();
+
+            egui::ComboBox::from_id_source("packet_control_byte")
+                .selected_text(match self.control_byte {
+                    Some(byte) => byte.to_string(),
+                    None => "any control byte".to_string(),
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.control_byte, None, "any control byte");
+                    for byte in control_bytes {
+                        ui.selectable_value(
+                            &mut self.control_byte,
+                            Some(byte),
+                            control_byte_name(byte),
+                        );
+                    }
+                });
+
+            egui::ComboBox::from_id_source("packet_state")
+                .selected_text(match self.state {
+                    Some(state) => state.to_string(),
+                    None => "any state".to_string(),
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.state, None, "any state");
+                    for state in STATES {
+                        ui.selectable_value(&mut self.state, Some(state), state.to_string());
+                    }
+                });
+
+            ui.label("Search");
+            ui.text_edit_singleline(&mut self.search);
+        });
+    }
+}
+
+/// whether a filter that is set to `filter`, if it is set, lets `value` through
+fn allows<T: PartialEq>(filter: Option<T>, value: T) -> bool {
+    match filter {
+        Some(filter) => filter == value,
+        None => true,
+    }
+}
+
+/// the name of the control byte, followed by its value
+pub fn control_byte_name(byte: u8) -> String {
+    format!("{:?} ({})", ControlByte::from(byte), byte)
 }
@@ -0,0 +1,224 @@
+//! # Sequence diagram
+//!
+//! The packets sent during a run drawn as a sequence diagram: a lifeline for each of the
+//! SNC, SS and MDPS, with an arrow from the sender of each packet to the two subsystems
+//! that receive it, labelled with its control byte. The packets are grouped into the
+//! phases (IDLE, CAL, MAZE and SOS) that they were sent in, so that the handshakes
+//! between the subsystems, and where a state machine strays from them, can be seen.
+
+use eframe::{
+    egui::{self, Align2, FontId, Sense, Ui},
+    epaint::{Color32, Pos2, Rect, Stroke, Vec2},
+};
+
+use crate::components::{envelope::PacketEnvelope, state::SystemState, subsystem::Subsystem};
+
+/// the lifelines, from left to right
+const LIFELINES: [Subsystem; 4] = [
+    Subsystem::Snc,
+    Subsystem::Ss,
+    Subsystem::Mdps,
+    Subsystem::Hub,
+];
+/// the width of the column with the number and time of each packet
+const GUTTER_WIDTH: f32 = 100.0;
+const LIFELINE_SPACING: f32 = 180.0;
+const ROW_HEIGHT: f32 = 26.0;
+const DIAGRAM_HEIGHT: f32 = 400.0;
+
+/// A run of packets that were all sent in the same state
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phase {
+    pub state: SystemState,
+    pub envelopes: Vec<PacketEnvelope>,
+}
+
+/// groups `envelopes`, in the order that they were sent, into phases, starting a new phase
+/// whenever a packet is sent in a different state to the packet before it
+pub fn phases<I: IntoIterator<Item = PacketEnvelope>>(envelopes: I) -> Vec<Phase> {
+    let mut phases: Vec<Phase> = Vec::new();
+
+    for envelope in envelopes {
+        let state = envelope.packet.state();
+
+        match phases.last_mut() {
+            Some(phase) if phase.state == state => phase.envelopes.push(envelope),
+            _ => phases.push(Phase {
+                state,
+                envelopes: vec![envelope],
+            }),
+        }
+    }
+
+    phases
+}
+
+/// A row of the diagram
+enum Row {
+    /// the start of a phase, with the number of packets in it
+    Phase(SystemState, usize),
+    Packet(PacketEnvelope),
+}
+
+/// paints `envelopes` as a sequence diagram, which keeps the latest packet in view while
+/// it is scrolled to the bottom
+pub fn paint_sequence_diagram(ui: &mut Ui, id: &str, envelopes: &[PacketEnvelope]) {
+    let rows: Vec<Row> = phases(envelopes.iter().copied())
+        .into_iter()
+        .flat_map(|phase| {
+            std::iter::once(Row::Phase(phase.state, phase.envelopes.len()))
+                .chain(phase.envelopes.into_iter().map(Row::Packet))
+        })
+        .collect();
+
+    ui.group(|ui| {
+        ui.vertical(|ui| {
+            ui.label("Sequence diagram");
+            ui.separator();
+
+            // the rows are painted against each other, so that the lifelines are unbroken
+            ui.spacing_mut().item_spacing.y = 0.0;
+
+            paint_lifeline_names(ui);
+
+            egui::ScrollArea::vertical()
+                .id_source(id)
+                .max_height(DIAGRAM_HEIGHT)
+                .stick_to_bottom(true)
+                .show_rows(ui, ROW_HEIGHT, rows.len(), |ui, visible| {
+                    for row in &rows[visible] {
+                        match row {
+                            Row::Phase(state, count) => paint_phase(ui, *state, *count),
+                            Row::Packet(envelope) => paint_packet(ui, envelope),
+                        }
+                    }
+                });
+        });
+    });
+}
+
+/// the x coordinate of the lifeline of `subsystem` in a row starting at `left`
+fn lifeline_x(left: f32, subsystem: Subsystem) -> f32 {
+    let index = LIFELINES
+        .iter()
+        .position(|lifeline| *lifeline == subsystem)
+        .unwrap_or_default();
+
+    left + GUTTER_WIDTH + (index as f32 + 0.5) * LIFELINE_SPACING
+}
+
+fn colour(subsystem: Subsystem) -> Color32 {
+    match subsystem {
+        Subsystem::Snc => Color32::from_rgb(40, 90, 200),
+        Subsystem::Ss => Color32::from_rgb(30, 140, 60),
+        Subsystem::Mdps => Color32::from_rgb(220, 120, 0),
+        Subsystem::Hub => Color32::from_rgb(110, 110, 110),
+    }
+}
+
+fn allocate_row(ui: &mut Ui, sense: Sense) -> (Rect, egui::Response) {
+    ui.allocate_exact_size(
+        Vec2::new(
+            GUTTER_WIDTH + LIFELINE_SPACING * LIFELINES.len() as f32,
+            ROW_HEIGHT,
+        ),
+        sense,
+    )
+}
+
+fn paint_lifelines(ui: &Ui, rect: Rect) {
+    for subsystem in LIFELINES {
+        let x = lifeline_x(rect.left(), subsystem);
+
+        ui.painter().line_segment(
+            [Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom())],
+            Stroke::new(1.0, Color32::GRAY),
+        );
+    }
+}
+
+fn paint_lifeline_names(ui: &mut Ui) {
+    let (rect, _) = allocate_row(ui, Sense::hover());
+
+    for subsystem in LIFELINES {
+        let centre = Pos2::new(lifeline_x(rect.left(), subsystem), rect.center().y);
+
+        ui.painter().rect_stroke(
+            Rect::from_center_size(centre, Vec2::new(70.0, ROW_HEIGHT - 6.0)),
+            2.0,
+            Stroke::new(1.5, colour(subsystem)),
+        );
+        ui.painter().text(
+            centre,
+            Align2::CENTER_CENTER,
+            subsystem.to_string(),
+            FontId::proportional(14.0),
+            colour(subsystem),
+        );
+    }
+}
+
+fn paint_phase(ui: &mut Ui, state: SystemState, count: usize) {
+    let (rect, _) = allocate_row(ui, Sense::hover());
+
+    ui.painter()
+        .rect_filled(rect.shrink(2.0), 2.0, ui.visuals().extreme_bg_color);
+    ui.painter().text(
+        rect.left_center() + Vec2::new(6.0, 0.0),
+        Align2::LEFT_CENTER,
+        format!("{} ({} packets)", state, count),
+        FontId::proportional(13.0),
+        ui.visuals().strong_text_color(),
+    );
+}
+
+fn paint_packet(ui: &mut Ui, envelope: &PacketEnvelope) {
+    let (rect, response) = allocate_row(ui, Sense::hover());
+
+    paint_lifelines(ui, rect);
+
+    ui.painter().text(
+        rect.left_center(),
+        Align2::LEFT_CENTER,
+        format!("#{} {:.3} s", envelope.sequence, envelope.time),
+        FontId::monospace(11.0),
+        ui.visuals().weak_text_color(),
+    );
+
+    // the arrows are below the middle of the row, with the label above them
+    let y = rect.top() + ROW_HEIGHT * 0.7;
+    let source = lifeline_x(rect.left(), envelope.source);
+    let stroke = Stroke::new(1.5, colour(envelope.source));
+
+    for destination in envelope.destinations {
+        let destination = lifeline_x(rect.left(), *destination);
+
+        ui.painter().arrow(
+            Pos2::new(source, y),
+            Vec2::new(destination - source, 0.0),
+            stroke,
+        );
+    }
+
+    // the label sits on the side of the sender that the packet goes to
+    let towards: Vec<bool> = envelope
+        .destinations
+        .iter()
+        .map(|destination| lifeline_x(rect.left(), *destination) > source)
+        .collect();
+    let (anchor, offset) = match (towards.iter().all(|right| *right), towards.contains(&true)) {
+        (true, _) => (Align2::LEFT_BOTTOM, 6.0),
+        (_, false) => (Align2::RIGHT_BOTTOM, -6.0),
+        _ => (Align2::CENTER_BOTTOM, 0.0),
+    };
+
+    ui.painter().text(
+        Pos2::new(source + offset, y - 2.0),
+        anchor,
+        format!("{:?}", envelope.packet.control_byte()),
+        FontId::proportional(12.0),
+        ui.visuals().text_color(),
+    );
+
+    response.on_hover_text(format!("{}\n{}", envelope, envelope.packet.meaning()));
+}
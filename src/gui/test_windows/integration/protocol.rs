@@ -0,0 +1,106 @@
+//! # Integration QTP protocol
+//!
+//! The parts of a run that every integration QTP goes through: starting the subsystems,
+//! taking the MARV from IDLE through CAL into MAZE, driving it to the end of the maze,
+//! and ending the run, after which every packet sent during it is checked against the
+//! protocol.
+
+use std::time::Duration;
+
+use crate::{
+    components::{
+        constants::{HUB_END_OF_MAZE, HUB_START},
+        state::SystemState,
+    },
+    gui::test_windows::snc::protocol::milliseconds,
+    subsystems::{
+        hub::Hub,
+        test_bench::{BenchError, OPERATOR_TIMEOUT},
+    },
+};
+
+/// the longest time that the MDPS may take to calibrate
+const CALIBRATION_TIMEOUT: Duration = Duration::from_secs(60);
+/// the longest time that the MARV may take to drive to the end of the maze
+const MAZE_TIMEOUT: Duration = Duration::from_secs(300);
+/// how long the kit carries on checking packets once the run has ended
+const SETTLE_PERIOD: Duration = Duration::from_millis(200);
+
+/// starts the subsystems, and waits for the touches (or prompts the operator for them)
+/// that take the MARV from IDLE through CAL into MAZE
+pub fn idle_and_calibrate(hub: &mut Hub) -> Result<(), BenchError> {
+    hub.broadcast(HUB_START);
+
+    if hub.has_operator() {
+        hub.prompt("Place the MARV at the start of the maze, and touch the SNC to leave IDLE");
+    }
+
+    hub.step("touch in IDLE", |hub| {
+        hub.until(OPERATOR_TIMEOUT, "touch in IDLE", |monitor| {
+            monitor.state() != SystemState::Idle
+        })?;
+
+        match hub.monitor().operational_velocity() {
+            Some(0) | None => Err(BenchError::Check(String::from("vop is 0 mm/s"))),
+            Some(vop) => Ok(format!("vop = {} mm/s at {:.3} s", vop, hub.time())),
+        }
+    })?;
+
+    hub.step("calibration", |hub| {
+        hub.until(CALIBRATION_TIMEOUT, "calibrated speeds (96)", |monitor| {
+            monitor.calibration().is_some()
+        })?;
+
+        match hub.monitor().calibration() {
+            Some(check) if check.passed() => Ok(check.to_string()),
+            Some(check) => Err(BenchError::Check(check.to_string())),
+            None => unreachable!("the MDPS has calibrated"),
+        }
+    })?;
+
+    if hub.has_operator() {
+        hub.prompt("Touch the SNC to start the maze");
+    }
+
+    hub.step("touch in CAL", |hub| {
+        hub.until(OPERATOR_TIMEOUT, "touch in CAL", |monitor| {
+            monitor.state() == SystemState::Maze
+        })?;
+
+        Ok(format!("MAZE at {:.3} s", hub.time()))
+    })
+}
+
+/// waits for the SS to report the end of the maze, with the MARV driving through it
+pub fn drive_maze(hub: &mut Hub) -> Result<(), BenchError> {
+    hub.step("reaches the end of the maze", |hub| {
+        let driven = hub.until(MAZE_TIMEOUT, "end of the maze (179)", |monitor| {
+            monitor.end_of_maze().is_some()
+        })?;
+
+        Ok(format!(
+            "179 after {:.1} s and {} cycles, the slowest took {}",
+            driven,
+            hub.monitor().cycles(),
+            milliseconds(hub.monitor().slowest_cycle())
+        ))
+    })
+}
+
+/// ends the run, and checks that every packet sent during it followed the protocol
+pub fn end_run(hub: &mut Hub) -> Result<(), BenchError> {
+    hub.broadcast(HUB_END_OF_MAZE);
+    hub.settle(SETTLE_PERIOD);
+
+    hub.step("follows the protocol", |hub| {
+        let monitor = hub.monitor();
+
+        match monitor.violations().first() {
+            Some(first) => Err(BenchError::Check(format!(
+                "{}, the first {}",
+                monitor, first
+            ))),
+            None => Ok(monitor.to_string()),
+        }
+    })
+}
@@ -0,0 +1,48 @@
+//! # Integration QTP 1
+//!
+//! Tests how the three subsystems start up together: the touch in IDLE with the vop that
+//! the MDPS must calibrate to, the MDPS's calibration, and the handshakes of the CAL state
+//! until the touch that starts the maze. The MARV is then given a few cycles of the MAZE
+//! state before the run is ended, and every packet sent is checked against the protocol.
+
+use std::time::Duration;
+
+use crate::{
+    gui::test_windows::{navcon::qtp1::navcon_qtp_1_maze, snc::protocol::milliseconds},
+    subsystems::{
+        hub::{Hub, IntegrationQtp},
+        test_bench::BenchError,
+    },
+};
+
+use super::protocol::{end_run, idle_and_calibrate};
+
+/// the number of cycles of the MAZE state that the MARV is given
+const CYCLES: u32 = 10;
+/// the longest time that those cycles may take
+const CYCLES_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub const INTEGRATION_QTP_1: IntegrationQtp = IntegrationQtp {
+    name: "Integration QTP 1",
+    description: "IDLE and CAL: starting up, calibrating and the CAL handshakes, into MAZE",
+    maze: navcon_qtp_1_maze,
+    script: run,
+};
+
+fn run(hub: &mut Hub) -> Result<(), BenchError> {
+    idle_and_calibrate(hub)?;
+
+    hub.step("cycles of MAZE", |hub| {
+        hub.until(CYCLES_TIMEOUT, "MAZE cycles", |monitor| {
+            monitor.cycles() >= CYCLES
+        })?;
+
+        Ok(format!(
+            "{} cycles, the slowest took {}",
+            hub.monitor().cycles(),
+            milliseconds(hub.monitor().slowest_cycle())
+        ))
+    })?;
+
+    end_run(hub)
+}
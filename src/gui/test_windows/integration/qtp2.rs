@@ -0,0 +1,28 @@
+//! # Integration QTP 2
+//!
+//! Tests a full run of the maze: the MARV is taken from IDLE through CAL into MAZE, and
+//! drives through the maze until the SS reports its end. Every packet sent during the run
+//! is checked against the protocol.
+
+use crate::{
+    gui::test_windows::navcon::qtp1::navcon_qtp_1_maze,
+    subsystems::{
+        hub::{Hub, IntegrationQtp},
+        test_bench::BenchError,
+    },
+};
+
+use super::protocol::{drive_maze, end_run, idle_and_calibrate};
+
+pub const INTEGRATION_QTP_2: IntegrationQtp = IntegrationQtp {
+    name: "Integration QTP 2",
+    description: "MAZE: a full run, from the start of the maze to its end",
+    maze: navcon_qtp_1_maze,
+    script: run,
+};
+
+fn run(hub: &mut Hub) -> Result<(), BenchError> {
+    idle_and_calibrate(hub)?;
+    drive_maze(hub)?;
+    end_run(hub)
+}
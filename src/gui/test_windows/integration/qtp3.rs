@@ -0,0 +1,70 @@
+//! # Integration QTP 3
+//!
+//! Tests how the three subsystems handle an operator in the MAZE state: a clap/snap must
+//! take the MARV into the SOS state, in which the MDPS stops, and another clap/snap must
+//! take it back to MAZE. Touching the SNC in MAZE must take it back to IDLE. Every packet
+//! sent during the run is checked against the protocol.
+//!
+//! These steps need an operator at a physical MARV. With emulated subsystems they are
+//! skipped, and the MARV drives to the end of the maze instead.
+
+use crate::{
+    components::state::SystemState,
+    gui::test_windows::navcon::qtp1::navcon_qtp_1_maze,
+    subsystems::{
+        hub::{Hub, IntegrationQtp},
+        test_bench::{BenchError, OPERATOR_TIMEOUT},
+    },
+};
+
+use super::protocol::{drive_maze, end_run, idle_and_calibrate};
+
+pub const INTEGRATION_QTP_3: IntegrationQtp = IntegrationQtp {
+    name: "Integration QTP 3",
+    description: "MAZE and SOS: clap/snap and touch, with an operator at a physical MARV",
+    maze: navcon_qtp_1_maze,
+    script: run,
+};
+
+/// each step, with what the operator is asked to do and the state that it must take the
+/// MARV into
+const STEPS: [(&str, &str, SystemState); 3] = [
+    (
+        "clap/snap in MAZE",
+        "Clap or snap to stop the MARV",
+        SystemState::Sos,
+    ),
+    (
+        "clap/snap in SOS",
+        "Clap or snap again to carry on",
+        SystemState::Maze,
+    ),
+    (
+        "touch in MAZE",
+        "Touch the SNC to end the run",
+        SystemState::Idle,
+    ),
+];
+
+fn run(hub: &mut Hub) -> Result<(), BenchError> {
+    idle_and_calibrate(hub)?;
+
+    if !hub.has_operator() {
+        for (step, _, _) in STEPS {
+            hub.skip(step, "needs an operator at a physical MARV");
+        }
+
+        drive_maze(hub)?;
+        return end_run(hub);
+    }
+
+    for (step, instruction, state) in STEPS {
+        hub.prompt(instruction);
+        hub.step(step, |hub| {
+            hub.until(OPERATOR_TIMEOUT, step, |monitor| monitor.state() == state)?;
+            Ok(format!("{} at {:.3} s", state, hub.time()))
+        })?;
+    }
+
+    end_run(hub)
+}
@@ -0,0 +1,191 @@
+//! # MDPS QTP protocol
+//!
+//! The parts of the SCS protocol that every MDPS QTP goes through, with the kit standing
+//! in for the SNC and the SS: the handshakes of the IDLE and CAL states, and the cycle
+//! of packets in the MAZE state, in which the MDPS answers each navigation instruction
+//! with its battery level, rotation, speeds and distance.
+
+use std::time::Duration;
+
+use crate::{
+    components::{
+        adjacent_bytes::AdjacentBytes,
+        calibration::CalibrationCheck,
+        comm_port::ControlByte,
+        constants::{
+            CAL_BUTTON_NOT_TOUCHED, CAL_BUTTON_TOUCHED, CAL_CALIBRATED, CAL_COLOURS,
+            IDLE_BUTTON_TOUCHED, MAZE_BUTTON_NOT_TOUCHED, MAZE_CLAPSNAP_NONE, MAZE_END_OF_MAZE,
+            MAZE_NAVCON_STOP,
+        },
+        packet::Packet,
+        subsystem::Subsystem,
+    },
+    subsystems::test_bench::{BenchError, TestBench, QUIET_PERIOD, RESPONSE_TIMEOUT},
+};
+
+/// the longest time that the MDPS may take to calibrate its wheel speeds
+const CALIBRATION_TIMEOUT: Duration = Duration::from_secs(60);
+/// the longest time that the MDPS may take to answer a rotate instruction, which it only
+/// does once it has turned
+pub const ROTATION_TIMEOUT: Duration = Duration::from_secs(15);
+/// the time between the cycles of the MAZE state while the MARV drives
+pub const CYCLE_PERIOD: Duration = Duration::from_millis(50);
+
+/// The MDPS's answer to a navigation instruction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Response {
+    /// the rotation (in degrees) achieved by the last rotate instruction, and the DEC of
+    /// that instruction (162 packet)
+    pub rotation: (u16, u8),
+    /// the (left, right) wheel speeds (in mm/s) (163 packet)
+    pub speeds: (u8, u8),
+    /// whether the MARV is reversing (163 packet)
+    pub reversing: bool,
+    /// the distance (in mm) that the MARV has driven since it last stopped (164 packet)
+    pub distance: u16,
+    /// when the answer arrived (in s), from the start of the QTP
+    pub time: f32,
+}
+
+/// touches the SNC in the IDLE state, and takes the MDPS through the handshakes of the
+/// CAL state into the MAZE state, returning the vop that it calibrated to
+pub fn idle_and_calibrate(bench: &mut TestBench) -> Result<u8, BenchError> {
+    let operational_velocity = IDLE_BUTTON_TOUCHED[2];
+
+    bench.step("calibrates to the operational velocity", |bench| {
+        let sent = bench.time();
+        bench.send(Subsystem::Snc, IDLE_BUTTON_TOUCHED);
+        bench.send(Subsystem::Ss, CAL_CALIBRATED);
+
+        let check = CalibrationCheck::new(
+            operational_velocity,
+            bench.expect(
+                ControlByte::CalibrateOperationalVelocity,
+                CALIBRATION_TIMEOUT,
+            )?,
+        );
+
+        match check.passed() {
+            true => Ok(format!("{} after {:.1} s", check, bench.time() - sent)),
+            false => Err(BenchError::Check(check.to_string())),
+        }
+    })?;
+
+    bench.step("reports its battery level in CAL", |bench| {
+        let battery = bench.expect(ControlByte::CalibrateBatteryLevel, RESPONSE_TIMEOUT)?;
+
+        Ok(battery_level(battery))
+    })?;
+
+    bench.step("waits for the touch in CAL", |bench| {
+        // the MDPS reports its battery level again for as long as the SNC is not touched...
+        bench.send(Subsystem::Ss, CAL_COLOURS);
+        bench.send(Subsystem::Snc, CAL_BUTTON_NOT_TOUCHED);
+        bench.expect(ControlByte::CalibrateBatteryLevel, RESPONSE_TIMEOUT)?;
+
+        // ...and sends nothing once it has been touched, until the MAZE state starts
+        bench.send(Subsystem::Ss, CAL_COLOURS);
+        bench.send(Subsystem::Snc, CAL_BUTTON_TOUCHED);
+        bench.quiet(QUIET_PERIOD)?;
+
+        Ok(format!(
+            "97 until the touch, nothing sent for {} ms after it",
+            QUIET_PERIOD.as_millis()
+        ))
+    })?;
+
+    Ok(operational_velocity)
+}
+
+/// sends the SNC's packets of a cycle of the MAZE state with `instruction` (a 147 packet),
+/// waits for the MDPS's answer, the first packet of which must arrive within `timeout`,
+/// and then sends the SS's packets of the cycle, with every sensor on white
+pub fn instruct(
+    bench: &mut TestBench,
+    instruction: [u8; 4],
+    timeout: Duration,
+) -> Result<Response, BenchError> {
+    bench.send(Subsystem::Snc, MAZE_CLAPSNAP_NONE);
+    bench.send(Subsystem::Snc, MAZE_BUTTON_NOT_TOUCHED);
+    bench.send(Subsystem::Snc, instruction);
+
+    bench.expect(ControlByte::MazeBatteryLevel, timeout)?;
+    let rotation = bench.expect(ControlByte::MazeRotation, RESPONSE_TIMEOUT)?;
+    let speeds = bench.expect(ControlByte::MazeSpeeds, RESPONSE_TIMEOUT)?;
+    let distance = bench.expect(ControlByte::MazeDistance, RESPONSE_TIMEOUT)?;
+
+    let response = Response {
+        rotation: (
+            AdjacentBytes::make(rotation.dat1(), rotation.dat0()).into(),
+            rotation.dec(),
+        ),
+        speeds: (speeds.dat1(), speeds.dat0()),
+        reversing: speeds.dec() == 1,
+        distance: AdjacentBytes::make(distance.dat1(), distance.dat0()).into(),
+        time: bench.time(),
+    };
+
+    bench.send(Subsystem::Ss, [177, 0, 0, 0]);
+    bench.send(Subsystem::Ss, [178, 0, 0, 0]);
+
+    Ok(response)
+}
+
+/// asks the operator to measure what the MDPS reported, and checks that the two are
+/// within `tolerance` of each other, which is skipped without an operator or if they
+/// choose not to measure it
+pub fn compare_measurement(
+    bench: &mut TestBench,
+    name: &str,
+    question: &str,
+    reported: f32,
+    unit: &str,
+    tolerance: f32,
+) -> Result<(), BenchError> {
+    if !bench.has_operator() {
+        bench.skip(name, "needs an operator at a physical MDPS");
+        return Ok(());
+    }
+
+    match bench.measure(question) {
+        Some(measured) => bench.step(name, |_| {
+            let difference = format!(
+                "reported {:.0} {}, measured {:.0} {}",
+                reported, unit, measured, unit
+            );
+
+            match (reported - measured).abs() <= tolerance {
+                true => Ok(difference),
+                false => Err(BenchError::Check(format!(
+                    "{}, more than {:.0} {} apart",
+                    difference, tolerance, unit
+                ))),
+            }
+        }),
+        None => {
+            bench.skip(name, "not measured");
+            Ok(())
+        }
+    }
+}
+
+/// stops the MARV, gives the MDPS the end of the maze, and checks that it then stops
+/// sending packets
+pub fn end_of_maze(bench: &mut TestBench) -> Result<(), BenchError> {
+    bench.end_of_maze("stops at the end of the maze", |bench| {
+        instruct(bench, MAZE_NAVCON_STOP, RESPONSE_TIMEOUT)?;
+        bench.send(Subsystem::Ss, MAZE_END_OF_MAZE);
+
+        Ok(String::from("179 sent"))
+    })
+}
+
+/// the battery level in a 97 or 161 packet, which is all zeros if it is not measured
+fn battery_level(packet: Packet) -> String {
+    match (packet.dat1(), packet.dat0()) {
+        (0, 0) => String::from("not measured (all zeros)"),
+        (percentage, decivolts) => {
+            format!("{}%, {:.1} V", percentage, decivolts as f32 / 10.0)
+        }
+    }
+}
@@ -0,0 +1,26 @@
+//! # MDPS QTP 1
+//!
+//! Tests whether the MDPS calibrates its wheel speeds to within 5% of the operational
+//! velocity once the SNC has been touched in the IDLE state, reports its battery level
+//! through the handshakes of the CAL state, and waits for the SNC to be touched again
+//! before it starts the maze. The MDPS is then given the end of the maze straight away,
+//! after which it must stop sending packets.
+
+use crate::{
+    components::subsystem::Subsystem,
+    subsystems::test_bench::{BenchError, BenchQtp, TestBench},
+};
+
+use super::protocol::{end_of_maze, idle_and_calibrate};
+
+pub const MDPS_QTP_1: BenchQtp = BenchQtp {
+    name: "MDPS QTP 1",
+    under_test: Subsystem::Mdps,
+    description: "IDLE and CAL: calibration to vop, the battery level and the CAL handshakes",
+    script: run,
+};
+
+fn run(bench: &mut TestBench) -> Result<(), BenchError> {
+    idle_and_calibrate(bench)?;
+    end_of_maze(bench)
+}
@@ -0,0 +1,194 @@
+//! # MDPS QTP 2
+//!
+//! Tests how the MDPS drives forward and in reverse: it must answer every navigation
+//! instruction in time, report wheel speeds (163) within a tolerance of the speeds it
+//! was asked for, in the right direction, and a distance (164) that matches the speeds
+//! it reported. It must then stop when it is told to.
+//!
+//! An operator at a physical MDPS can also measure how far the MARV drove, which is
+//! compared with the distance that the MDPS reported.
+
+use crate::{
+    components::{constants::MAZE_NAVCON_STOP, subsystem::Subsystem},
+    gui::test_windows::snc::protocol::milliseconds,
+    subsystems::test_bench::{BenchError, BenchQtp, TestBench, RESPONSE_TIMEOUT},
+};
+
+use super::protocol::{
+    compare_measurement, end_of_maze, idle_and_calibrate, instruct, Response, CYCLE_PERIOD,
+};
+
+/// how long (in s) the MARV drives in each direction
+const DRIVE_TIME: f32 = 2.0;
+/// the time (in s) that the wheels are given to reach their speed before it is checked
+const SETTLE_TIME: f32 = 0.5;
+/// the longest time (in s) that the MARV may take to stop
+const STOP_TIME: f32 = 1.0;
+/// the largest error (as a fraction of the speed asked for) allowed in a wheel speed
+const SPEED_TOLERANCE: f32 = 0.1;
+/// the largest error (as a fraction of the distance) allowed in a distance...
+const DISTANCE_TOLERANCE: f32 = 0.1;
+/// ...unless the distance is short, when this many mm are allowed
+const MIN_DISTANCE_TOLERANCE: f32 = 5.0;
+
+pub const MDPS_QTP_2: BenchQtp = BenchQtp {
+    name: "MDPS QTP 2",
+    under_test: Subsystem::Mdps,
+    description: "Forward and reverse: the speeds and distance that the MDPS reports",
+    script: run,
+};
+
+fn run(bench: &mut TestBench) -> Result<(), BenchError> {
+    let operational_velocity = idle_and_calibrate(bench)?;
+
+    drive(
+        bench,
+        "forward",
+        [147, operational_velocity, operational_velocity, 0],
+    )?;
+    drive(
+        bench,
+        "in reverse",
+        [147, operational_velocity, operational_velocity, 1],
+    )?;
+
+    end_of_maze(bench)
+}
+
+/// drives the MARV `direction` with `instruction` for `DRIVE_TIME`, checking what the
+/// MDPS reports, and then stops it
+fn drive(bench: &mut TestBench, direction: &str, instruction: [u8; 4]) -> Result<(), BenchError> {
+    // the left wheel's speed is in DAT1, the right wheel's in DAT0
+    let commanded = (instruction[1] as f32, instruction[2] as f32);
+    let reversing = instruction[3] == 1;
+    let mut responses: Vec<Response> = Vec::new();
+
+    bench.step(&format!("drives {}", direction), |bench| {
+        let start = bench.time();
+        let mut slowest: f32 = 0.0;
+
+        while bench.time() - start < DRIVE_TIME {
+            let sent = bench.time();
+            let response = instruct(bench, instruction, RESPONSE_TIMEOUT)?;
+            slowest = slowest.max(response.time - sent);
+
+            if response.reversing != reversing {
+                return Err(BenchError::Check(format!(
+                    "163 reports the wrong direction at {:.1} s",
+                    response.time - start
+                )));
+            }
+
+            let (left, right) = response.speeds;
+            let off = |speed: u8, commanded: f32| {
+                (speed as f32 - commanded).abs() > commanded * SPEED_TOLERANCE
+            };
+
+            if response.time - start > SETTLE_TIME
+                && (off(left, commanded.0) || off(right, commanded.1))
+            {
+                return Err(BenchError::Check(format!(
+                    "163 reports {}/{} mm/s at {:.1} s, more than {:.0}% off {}/{} mm/s",
+                    left,
+                    right,
+                    response.time - start,
+                    SPEED_TOLERANCE * 100.0,
+                    commanded.0,
+                    commanded.1
+                )));
+            }
+
+            responses.push(response);
+            std::thread::sleep(CYCLE_PERIOD);
+        }
+
+        let (left, right) = responses.last().map(|last| last.speeds).unwrap_or_default();
+
+        Ok(format!(
+            "{} cycles at {}/{} mm/s, the slowest answer took {}",
+            responses.len(),
+            left,
+            right,
+            milliseconds(slowest)
+        ))
+    })?;
+
+    bench.step(&format!("distance driven {}", direction), |_| {
+        if let Some(pair) = responses
+            .windows(2)
+            .find(|pair| pair[1].distance < pair[0].distance)
+        {
+            return Err(BenchError::Check(format!(
+                "164 went down from {} mm to {} mm",
+                pair[0].distance, pair[1].distance
+            )));
+        }
+
+        // the distance driven at the speeds that were reported, from the first answer on
+        let expected: f32 = responses
+            .windows(2)
+            .map(|pair| (pair[1].time - pair[0].time) * (mean(pair[0]) + mean(pair[1])) / 2.0)
+            .sum();
+        let reported = match (responses.first(), responses.last()) {
+            (Some(first), Some(last)) => (last.distance - first.distance) as f32,
+            _ => 0.0,
+        };
+
+        let detail = format!(
+            "{:.0} mm reported, {:.0} mm at the reported speeds",
+            reported, expected
+        );
+
+        match (reported - expected).abs() <= tolerance(expected) {
+            true => Ok(detail),
+            false => Err(BenchError::Check(detail)),
+        }
+    })?;
+
+    let mut driven = 0;
+
+    bench.step(&format!("stops after driving {}", direction), |bench| {
+        let start = bench.time();
+
+        loop {
+            let response = instruct(bench, MAZE_NAVCON_STOP, RESPONSE_TIMEOUT)?;
+            driven = response.distance;
+
+            if response.speeds == (0, 0) {
+                return Ok(format!(
+                    "stopped after {}, {} mm driven",
+                    milliseconds(response.time - start),
+                    driven
+                ));
+            }
+
+            if response.time - start > STOP_TIME {
+                return Err(BenchError::Check(format!(
+                    "still at {}/{} mm/s {:.1} s after the stop instruction",
+                    response.speeds.0, response.speeds.1, STOP_TIME
+                )));
+            }
+
+            std::thread::sleep(CYCLE_PERIOD);
+        }
+    })?;
+
+    compare_measurement(
+        bench,
+        &format!("measured distance {}", direction),
+        &format!("How far (in mm) did the MARV drive {}?", direction),
+        driven as f32,
+        "mm",
+        tolerance(driven as f32),
+    )
+}
+
+/// the speed (in mm/s) of the middle of the MARV, between its wheels
+fn mean(response: Response) -> f32 {
+    (response.speeds.0 as f32 + response.speeds.1 as f32) / 2.0
+}
+
+/// how far (in mm) a distance of `distance` mm may be off
+fn tolerance(distance: f32) -> f32 {
+    (distance * DISTANCE_TOLERANCE).max(MIN_DISTANCE_TOLERANCE)
+}
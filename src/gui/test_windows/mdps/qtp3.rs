@@ -0,0 +1,85 @@
+//! # MDPS QTP 3
+//!
+//! Tests how the MDPS rotates on the spot: it must answer a rotate instruction once it
+//! has turned, reporting a rotation (162) within a tolerance of the angle it was asked
+//! to rotate by, in the right direction, and be standing still again.
+//!
+//! An operator at a physical MDPS can also measure how far the MARV rotated, which is
+//! compared with the rotation that the MDPS reported.
+
+use crate::{
+    components::{adjacent_bytes::AdjacentBytes, subsystem::Subsystem},
+    subsystems::test_bench::{BenchError, BenchQtp, TestBench},
+};
+
+use super::protocol::{
+    compare_measurement, end_of_maze, idle_and_calibrate, instruct, ROTATION_TIMEOUT,
+};
+
+/// the rotations that the MARV is asked for, as the angle (in degrees) and the DEC of the
+/// instruction (2 for left, 3 for right)
+const ROTATIONS: [(u16, u8); 3] = [(90, 2), (90, 3), (45, 2)];
+/// how far (in degrees) a rotation may be from the angle asked for
+const ROTATION_TOLERANCE: f32 = 5.0;
+
+pub const MDPS_QTP_3: BenchQtp = BenchQtp {
+    name: "MDPS QTP 3",
+    under_test: Subsystem::Mdps,
+    description: "Rotations: 90° left, 90° right and 45° left on the spot",
+    script: run,
+};
+
+fn run(bench: &mut TestBench) -> Result<(), BenchError> {
+    idle_and_calibrate(bench)?;
+
+    for (angle, dec) in ROTATIONS {
+        let direction = match dec {
+            2 => "left",
+            _ => "right",
+        };
+        let name = format!("rotates {}° {}", angle, direction);
+        let mut reported = 0;
+
+        bench.step(&name, |bench| {
+            let sent = bench.time();
+            let bytes = AdjacentBytes::from(angle);
+
+            let response = instruct(
+                bench,
+                [147, bytes.msb(), bytes.lsb(), dec],
+                ROTATION_TIMEOUT,
+            )?;
+            let (rotation, rotation_dec) = response.rotation;
+            reported = rotation;
+
+            let detail = format!(
+                "162 reports {}° (DEC {}) after {:.1} s",
+                rotation,
+                rotation_dec,
+                response.time - sent
+            );
+
+            if rotation_dec != dec || (rotation as f32 - angle as f32).abs() > ROTATION_TOLERANCE {
+                Err(BenchError::Check(detail))
+            } else if response.speeds != (0, 0) {
+                Err(BenchError::Check(format!(
+                    "{}, but the wheels are still at {}/{} mm/s",
+                    detail, response.speeds.0, response.speeds.1
+                )))
+            } else {
+                Ok(detail)
+            }
+        })?;
+
+        compare_measurement(
+            bench,
+            &format!("measured rotation {}", direction),
+            &format!("How far (in degrees) did the MARV rotate {}?", direction),
+            reported as f32,
+            "°",
+            ROTATION_TOLERANCE,
+        )?;
+    }
+
+    end_of_maze(bench)
+}
@@ -5,22 +5,17 @@
 //! at an angle of incidence less than or equal to
 //! five degrees (<= 5)
 
-extern crate eframe;
+use crate::{components::colour::Colour, gui::maze::MazeLineMap, subsystems::system::NavconQtp};
 
-use eframe::{
-    egui::Ui,
-    epaint::{Color32, Pos2},
+pub const NAVCON_QTP_2: NavconQtp = NavconQtp {
+    number: 2,
+    name: "NAVCON QTP 2",
+    description: "Walls: a green line, a blue wall and the red end of the maze, in a single column",
+    maze: navcon_qtp_2_maze,
 };
 
-use crate::{
-    components::{
-        colour::Colour,
-        constants::{MAZE_LEFT_JUSTIFICATION, MAZE_TOP_JUSTIFICATION},
-    },
-    gui::maze::MazeLineMap,
-};
-
-pub fn generate_navcon_qtp_2_maze(ui: &mut Ui, sensor_pos: [(f32, f32); 5]) -> MazeLineMap {
+/// the maze of NAVCON QTP 2
+pub fn navcon_qtp_2_maze() -> MazeLineMap {
     // INITIALISE THE MAZE
     let mut maze_map = MazeLineMap::new(4, 1);
 
@@ -38,16 +33,5 @@ pub fn generate_navcon_qtp_2_maze(ui: &mut Ui, sensor_pos: [(f32, f32); 5]) -> M
         maze_map.add_row(vec![Colour::Black; 2]).unwrap();
     }
 
-    // PAINT THE MAZE ON UI
-    maze_map.paint(ui);
-
-    sensor_pos.into_iter().for_each(|(x, y)| {
-        ui.painter().circle_filled(
-            Pos2::new(x + MAZE_LEFT_JUSTIFICATION, y + MAZE_TOP_JUSTIFICATION),
-            2.5,
-            Color32::from_rgb(100, 100, 100),
-        );
-    });
-
     maze_map
 }
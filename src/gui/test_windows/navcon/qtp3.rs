@@ -5,22 +5,18 @@
 //! at an angle of incidence less than or equal to
 //! five degrees (<= 5)
 
-extern crate eframe;
-
-use eframe::{
-    egui::Ui,
-    epaint::{Color32, Pos2},
-};
-
-use crate::{
-    components::{
-        colour::Colour,
-        constants::{MAZE_LEFT_JUSTIFICATION, MAZE_TOP_JUSTIFICATION},
-    },
-    gui::maze::MazeLineMap,
+use crate::{components::colour::Colour, gui::maze::MazeLineMap, subsystems::system::NavconQtp};
+
+pub const NAVCON_QTP_3: NavconQtp = NavconQtp {
+    number: 3,
+    name: "NAVCON QTP 3",
+    description:
+        "Turns: a green line, and a blue wall to turn away from, towards the red end of the maze",
+    maze: navcon_qtp_3_maze,
 };
 
-pub fn generate_navcon_qtp_3_maze(ui: &mut Ui, sensor_pos: [(f32, f32); 5]) -> MazeLineMap {
+/// the maze of NAVCON QTP 3
+pub fn navcon_qtp_3_maze() -> MazeLineMap {
     // INITIALISE THE MAZE
     let mut maze_map = MazeLineMap::new(4, 2);
 
@@ -60,16 +56,5 @@ pub fn generate_navcon_qtp_3_maze(ui: &mut Ui, sensor_pos: [(f32, f32); 5]) -> M
         .add_row(vec![Colour::Black, Colour::White, Colour::Black])
         .unwrap();
 
-    // PAINT THE MAZE ON UI
-    maze_map.paint(ui);
-
-    sensor_pos.into_iter().for_each(|(x, y)| {
-        ui.painter().circle_filled(
-            Pos2::new(x + MAZE_LEFT_JUSTIFICATION, y + MAZE_TOP_JUSTIFICATION),
-            2.5,
-            Color32::from_rgb(100, 100, 100),
-        );
-    });
-
     maze_map
 }
@@ -0,0 +1,53 @@
+//! # QTP registry
+//!
+//! Every QTP that the kit can run, in the order that the GUI's main menu and the `qtp` CLI
+//! list them in. Each suite of QTPs lists its own with `qtps!`, next to the declarations of
+//! their modules, so that a new QTP is a module of its own, which defines it as a
+//! `NavconQtp`, `BenchQtp` or `IntegrationQtp`, declared and listed in its suite.
+
+use crate::subsystems::qtp::{Qtp, QtpKind};
+
+use super::{integration, mdps, navcon, snc, ss};
+
+/// lists the QTPs of a suite in its `QTPS`, in the order given, each by the module that
+/// defines it (e.g. `qtp1::SNC_QTP_1`)
+macro_rules! qtps {
+    ($($module:ident::$qtp:ident),* $(,)?) => {
+        /// every QTP of the suite, in order
+        pub const QTPS: &[&dyn $crate::subsystems::qtp::Qtp] = &[$(&$module::$qtp),*];
+    };
+}
+
+/// every suite, in the order that they are listed in
+const SUITES: &[&[&dyn Qtp]] = &[
+    snc::QTPS,
+    navcon::QTPS,
+    ss::QTPS,
+    mdps::QTPS,
+    integration::QTPS,
+];
+
+/// every QTP that the kit can run, suite by suite
+pub fn qtps() -> impl Iterator<Item = &'static dyn Qtp> {
+    SUITES.iter().flat_map(|suite| suite.iter().copied())
+}
+
+/// the QTP named `name`, ignoring case, spaces and punctuation (e.g. "snc-qtp-1")
+pub fn find(name: &str) -> Option<&'static dyn Qtp> {
+    let normalise = |name: &str| -> String {
+        name.chars()
+            .filter(char::is_ascii_alphanumeric)
+            .map(|c| c.to_ascii_lowercase())
+            .collect()
+    };
+
+    qtps().find(|qtp| normalise(qtp.name()) == normalise(name))
+}
+
+/// the NAVCON QTP numbered `number`, which older recorded runs refer to it by
+pub fn navcon_qtp(number: u8) -> Option<&'static dyn Qtp> {
+    qtps().find(|qtp| match qtp.kind() {
+        QtpKind::Navcon(navcon) => navcon.number == number,
+        _ => false,
+    })
+}
@@ -0,0 +1,224 @@
+//! # SNC QTP protocol
+//!
+//! The parts of the SCS protocol that every SNC QTP goes through, with the kit standing
+//! in for the SS and the MDPS: the handshakes of the IDLE and CAL states, and the cycle
+//! of packets in the MAZE state.
+
+use crate::{
+    components::{
+        adjacent_bytes::AdjacentBytes,
+        comm_port::ControlByte,
+        constants::{CAL_BATTERY_LEVEL, CAL_CALIBRATED, CAL_COLOURS, MAZE_END_OF_MAZE},
+        packet::Packet,
+        subsystem::Subsystem,
+    },
+    subsystems::test_bench::{
+        BenchError, TestBench, OPERATOR_TIMEOUT, QUIET_PERIOD, RESPONSE_TIMEOUT,
+    },
+};
+
+/// How a cycle of the MAZE state ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cycle {
+    /// the SNC sensed a clap/snap (in its 145 packet), and sent nothing further
+    ClapSnap,
+    /// the SNC sensed a touch (in its 146 packet), and sent nothing further
+    Touch,
+    /// the SNC sent this navigation instruction (147 packet), which the kit has answered
+    Instruction(Packet),
+}
+
+/// What the kit, as the SS and the MDPS, answers a navigation instruction with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Answer {
+    /// the colour word of the 177 packet
+    pub colours: u16,
+    /// the angle of incidence (in degrees) of the 178 packet
+    pub incidence: u8,
+    /// the distance (in mm) of the 164 packet
+    pub distance: u16,
+}
+
+impl Answer {
+    /// every sensor on white, at `distance` mm
+    pub fn white(distance: u16) -> Self {
+        Self {
+            colours: 0,
+            incidence: 0,
+            distance,
+        }
+    }
+}
+
+/// touches the SNC (or prompts the operator to) in the IDLE state, and takes it through
+/// the handshakes of the CAL state into the MAZE state, returning the vop that it sent
+pub fn idle_and_calibrate(bench: &mut TestBench) -> Result<u8, BenchError> {
+    let mut operational_velocity = 0;
+
+    if bench.has_operator() {
+        bench.prompt("Touch the SNC to leave IDLE");
+    }
+
+    bench.step("touch in IDLE", |bench| {
+        let touch = bench.wait_for(ControlByte::IdleButton, OPERATOR_TIMEOUT, |packet| {
+            packet.dat1() == 1
+        })?;
+        operational_velocity = touch.dat0();
+
+        Ok(format!("touched at {:.3} s", bench.time()))
+    })?;
+
+    bench.step("operational velocity", |_| match operational_velocity {
+        0 => Err(BenchError::Check(String::from("vop is 0 mm/s"))),
+        vop => Ok(format!("vop = {} mm/s", vop)),
+    })?;
+
+    bench.step("waits for the CAL handshake", |bench| {
+        // the SS has calibrated, then the MDPS reports its speeds and battery level...
+        bench.send(Subsystem::Ss, CAL_CALIBRATED);
+        bench.send(
+            Subsystem::Mdps,
+            [96, operational_velocity, operational_velocity, 0],
+        );
+        bench.send(Subsystem::Mdps, CAL_BATTERY_LEVEL);
+
+        // ...but the SNC may only answer once the SS has sent its colours
+        bench.quiet(QUIET_PERIOD)?;
+
+        Ok(format!("nothing sent for {} ms", QUIET_PERIOD.as_millis()))
+    })?;
+
+    let mut touched = false;
+
+    bench.step("answers the colours", |bench| {
+        let sent = bench.time();
+        bench.send(Subsystem::Ss, CAL_COLOURS);
+
+        touched = bench
+            .expect(ControlByte::CalibrateButton, RESPONSE_TIMEOUT)?
+            .dat1()
+            == 1;
+
+        Ok(format!("80 after {}", milliseconds(bench.time() - sent)))
+    })?;
+
+    if bench.has_operator() && !touched {
+        bench.prompt("Touch the SNC to start the maze");
+    }
+
+    bench.step("touch in CAL", |bench| {
+        let start = bench.time();
+
+        // the MDPS and SS keep reporting until the SNC is touched
+        while !touched {
+            if bench.time() - start > OPERATOR_TIMEOUT.as_secs_f32() {
+                return Err(BenchError::Timeout {
+                    control_byte: Some(ControlByte::CalibrateButton.into()),
+                    timeout: OPERATOR_TIMEOUT,
+                });
+            }
+
+            bench.send(Subsystem::Mdps, CAL_BATTERY_LEVEL);
+            bench.send(Subsystem::Ss, CAL_COLOURS);
+
+            touched = bench
+                .expect(ControlByte::CalibrateButton, RESPONSE_TIMEOUT)?
+                .dat1()
+                == 1;
+        }
+
+        Ok(format!("touched at {:.3} s", bench.time()))
+    })?;
+
+    Ok(operational_velocity)
+}
+
+/// waits for the SNC's packets of a cycle of the MAZE state, in order, and answers its
+/// navigation instruction with `answer`
+pub fn maze_cycle(bench: &mut TestBench, answer: Answer) -> Result<Cycle, BenchError> {
+    if bench
+        .expect(ControlByte::MazeClapSnap, RESPONSE_TIMEOUT)?
+        .dat1()
+        == 1
+    {
+        return Ok(Cycle::ClapSnap);
+    }
+
+    if bench
+        .expect(ControlByte::MazeButton, RESPONSE_TIMEOUT)?
+        .dat1()
+        == 1
+    {
+        return Ok(Cycle::Touch);
+    }
+
+    let instruction = bench.expect(ControlByte::MazeNavInstructions, RESPONSE_TIMEOUT)?;
+    check_instruction(instruction)?;
+
+    answer_instruction(bench, instruction, answer.distance);
+
+    let colours = AdjacentBytes::from(answer.colours);
+    bench.send(Subsystem::Ss, [177, colours.msb(), colours.lsb(), 0]);
+    bench.send(Subsystem::Ss, [178, answer.incidence, 0, 0]);
+
+    Ok(Cycle::Instruction(instruction))
+}
+
+/// waits for the SNC's next navigation instruction, answers it with the end of the maze,
+/// and checks that the SNC then stops sending packets
+pub fn end_of_maze(bench: &mut TestBench) -> Result<(), BenchError> {
+    bench.end_of_maze("stops at the end of the maze", |bench| {
+        bench.expect(ControlByte::MazeClapSnap, RESPONSE_TIMEOUT)?;
+        bench.expect(ControlByte::MazeButton, RESPONSE_TIMEOUT)?;
+
+        let instruction = bench.expect(ControlByte::MazeNavInstructions, RESPONSE_TIMEOUT)?;
+        answer_instruction(bench, instruction, 0);
+        bench.send(Subsystem::Ss, MAZE_END_OF_MAZE);
+
+        Ok(String::from("179 sent"))
+    })
+}
+
+/// the MDPS's answer to a navigation instruction: the battery level, and the rotation and
+/// speeds that it was asked for, at `distance` mm
+fn answer_instruction(bench: &mut TestBench, instruction: Packet, distance: u16) {
+    let (rotation, speeds) = match instruction.dec() {
+        dec @ (2 | 3) => (
+            [162, instruction.dat1(), instruction.dat0(), dec],
+            [163, 0, 0, 0],
+        ),
+        dec => (
+            [162, 0, 0, 2],
+            [163, instruction.dat1(), instruction.dat0(), dec],
+        ),
+    };
+    let distance = AdjacentBytes::from(distance);
+
+    bench.send(Subsystem::Mdps, [161, 0, 0, 0]);
+    bench.send(Subsystem::Mdps, rotation);
+    bench.send(Subsystem::Mdps, speeds);
+    bench.send(Subsystem::Mdps, [164, distance.msb(), distance.lsb(), 0]);
+}
+
+/// checks that a navigation instruction (147 packet) is one that the MDPS can carry out
+pub fn check_instruction(instruction: Packet) -> Result<(), BenchError> {
+    let rotation = u16::from(AdjacentBytes::make(instruction.dat1(), instruction.dat0()));
+
+    match instruction.dec() {
+        0 | 1 => Ok(()),
+        2 | 3 if rotation <= 360 => Ok(()),
+        2 | 3 => Err(BenchError::Check(format!(
+            "rotation of {}° in {}",
+            rotation, instruction
+        ))),
+        dec => Err(BenchError::Check(format!(
+            "unknown instruction {} in {}",
+            dec, instruction
+        ))),
+    }
+}
+
+/// `seconds` as a whole number of milliseconds, e.g. "12 ms"
+pub fn milliseconds(seconds: f32) -> String {
+    format!("{:.0} ms", seconds * 1000.0)
+}
@@ -0,0 +1,25 @@
+//! # SNC QTP 1
+//!
+//! Tests whether the SNC leaves the IDLE state when it is touched, asks for a sensible
+//! operational velocity, waits for the SS and the MDPS through the handshakes of the
+//! CAL state, and starts the maze when it is touched again. The SNC is then given the
+//! end of the maze straight away, after which it must stop sending packets.
+
+use crate::{
+    components::subsystem::Subsystem,
+    subsystems::test_bench::{BenchError, BenchQtp, TestBench},
+};
+
+use super::protocol::{end_of_maze, idle_and_calibrate};
+
+pub const SNC_QTP_1: BenchQtp = BenchQtp {
+    name: "SNC QTP 1",
+    under_test: Subsystem::Snc,
+    description: "IDLE and CAL: touch, operational velocity and the CAL handshakes",
+    script: run,
+};
+
+fn run(bench: &mut TestBench) -> Result<(), BenchError> {
+    idle_and_calibrate(bench)?;
+    end_of_maze(bench)
+}
@@ -0,0 +1,73 @@
+//! # SNC QTP 2
+//!
+//! Tests the SNC's side of the MAZE state: every cycle it must send its clap/snap (145),
+//! touch (146) and navigation instruction (147) packets, in that order and in time, and
+//! the instruction must be one that the MDPS can carry out. With every sensor on white
+//! the SNC must drive forward.
+
+use crate::{
+    components::subsystem::Subsystem,
+    subsystems::test_bench::{BenchError, BenchQtp, TestBench},
+};
+
+use super::protocol::{end_of_maze, idle_and_calibrate, maze_cycle, milliseconds, Answer, Cycle};
+
+/// the number of cycles of the MAZE state that the SNC is taken through
+const CYCLES: u16 = 20;
+/// how far (in mm) the MARV is said to move each cycle
+const DISTANCE_PER_CYCLE: u16 = 10;
+
+pub const SNC_QTP_2: BenchQtp = BenchQtp {
+    name: "SNC QTP 2",
+    under_test: Subsystem::Snc,
+    description: "MAZE: the order and timing of the SNC's packets, on white",
+    script: run,
+};
+
+fn run(bench: &mut TestBench) -> Result<(), BenchError> {
+    idle_and_calibrate(bench)?;
+
+    let mut instructions = Vec::new();
+
+    bench.step("packet order and timing", |bench| {
+        let mut slowest: f32 = 0.0;
+
+        for cycle in 0..CYCLES {
+            let start = bench.time();
+
+            match maze_cycle(bench, Answer::white(cycle * DISTANCE_PER_CYCLE))? {
+                Cycle::Instruction(instruction) => instructions.push(instruction),
+                Cycle::ClapSnap | Cycle::Touch => {
+                    return Err(BenchError::Check(format!(
+                        "a clap/snap or touch was sensed in cycle {}",
+                        cycle + 1
+                    )))
+                }
+            }
+
+            slowest = slowest.max(bench.time() - start);
+        }
+
+        Ok(format!(
+            "{} cycles, the slowest took {}",
+            CYCLES,
+            milliseconds(slowest)
+        ))
+    })?;
+
+    bench.step("drives forward on white", |_| {
+        match instructions
+            .iter()
+            .find(|instruction| instruction.dec() != 0)
+        {
+            Some(instruction) => Err(BenchError::Check(format!(
+                "{} ({}) on white",
+                instruction,
+                instruction.meaning()
+            ))),
+            None => Ok(format!("{} forward instructions", instructions.len())),
+        }
+    })?;
+
+    end_of_maze(bench)
+}
@@ -0,0 +1,96 @@
+//! # SNC QTP 3
+//!
+//! Tests how the SNC handles an operator in the MAZE state: a clap/snap must take it
+//! into the SOS state, in which the MDPS stops, and another clap/snap must take it back
+//! to MAZE. Touching it in MAZE must take it back to IDLE.
+//!
+//! These steps need an operator at a physical SNC, and are skipped on an emulated one.
+
+use crate::{
+    components::{comm_port::ControlByte, constants::SOS_SPEED, subsystem::Subsystem},
+    subsystems::test_bench::{BenchError, BenchQtp, TestBench, OPERATOR_TIMEOUT, RESPONSE_TIMEOUT},
+};
+
+use super::protocol::{end_of_maze, idle_and_calibrate, maze_cycle, Answer, Cycle};
+
+pub const SNC_QTP_3: BenchQtp = BenchQtp {
+    name: "SNC QTP 3",
+    under_test: Subsystem::Snc,
+    description: "MAZE and SOS: clap/snap and touch, with an operator at a physical SNC",
+    script: run,
+};
+
+const STEPS: [&str; 4] = [
+    "clap/snap in MAZE",
+    "clap/snap in SOS",
+    "touch in MAZE",
+    "back to IDLE",
+];
+
+fn run(bench: &mut TestBench) -> Result<(), BenchError> {
+    idle_and_calibrate(bench)?;
+
+    if !bench.has_operator() {
+        for step in STEPS {
+            bench.skip(step, "needs an operator at a physical SNC");
+        }
+
+        return end_of_maze(bench);
+    }
+
+    bench.prompt("Clap or snap to stop the MARV");
+    bench.step(STEPS[0], |bench| {
+        until_operator(bench, Cycle::ClapSnap)?;
+        Ok(format!("sensed at {:.3} s", bench.time()))
+    })?;
+
+    // the MDPS has stopped, and the SNC is in SOS until the next clap/snap
+    bench.send(Subsystem::Mdps, SOS_SPEED);
+
+    bench.prompt("Clap or snap again to carry on");
+    bench.step(STEPS[1], |bench| {
+        bench.wait_for(ControlByte::SosClapSnap, OPERATOR_TIMEOUT, |packet| {
+            packet.dat1() == 1
+        })?;
+
+        // back in MAZE, the SNC carries on with its cycles
+        maze_cycle(bench, Answer::white(0))?;
+
+        Ok(format!("back in MAZE at {:.3} s", bench.time()))
+    })?;
+
+    bench.prompt("Touch the SNC to end the run");
+    bench.step(STEPS[2], |bench| {
+        until_operator(bench, Cycle::Touch)?;
+        Ok(format!("sensed at {:.3} s", bench.time()))
+    })?;
+
+    bench.step(STEPS[3], |bench| {
+        bench.wait_for(ControlByte::IdleButton, RESPONSE_TIMEOUT, |_| true)?;
+        Ok(format!("IDLE at {:.3} s", bench.time()))
+    })
+}
+
+/// carries on with cycles of the MAZE state, on white, until the cycle ends with `action`
+fn until_operator(bench: &mut TestBench, action: Cycle) -> Result<(), BenchError> {
+    let start = bench.time();
+
+    while bench.time() - start < OPERATOR_TIMEOUT.as_secs_f32() {
+        match maze_cycle(bench, Answer::white(0))? {
+            cycle if cycle == action => return Ok(()),
+            Cycle::Instruction(_) => (),
+            cycle => {
+                return Err(BenchError::Check(format!(
+                    "expected {:?}, but the cycle ended with {:?}",
+                    action, cycle
+                )))
+            }
+        }
+    }
+
+    Err(BenchError::Check(format!(
+        "no {:?} within {:.1} s",
+        action,
+        OPERATOR_TIMEOUT.as_secs_f32()
+    )))
+}
@@ -0,0 +1,230 @@
+//! # SS QTP protocol
+//!
+//! The parts of the SCS protocol that every SS QTP goes through, with the kit standing
+//! in for the SNC and the MDPS: the handshakes of the IDLE and CAL states, and the cycle
+//! of packets in the MAZE state, at the end of which the SS reports what its sensors see.
+
+use std::time::Duration;
+
+use crate::{
+    components::{
+        adjacent_bytes::AdjacentBytes,
+        colour::{Colour, Colours},
+        comm_port::ControlByte,
+        constants::{
+            CAL_BATTERY_LEVEL, CAL_BUTTON_NOT_TOUCHED, CAL_BUTTON_TOUCHED, IDLE_BUTTON_TOUCHED,
+            MAZE_BATTERY_LEVEL, MAZE_BUTTON_NOT_TOUCHED, MAZE_CLAPSNAP_NONE, MAZE_NAVCON_FORWARD,
+        },
+        packet::Packet,
+        subsystem::Subsystem,
+    },
+    gui::test_windows::snc::protocol::milliseconds,
+    subsystems::{
+        sensor_subsystem::test_card::{test_card, Placement},
+        test_bench::{BenchError, TestBench, OPERATOR_TIMEOUT, QUIET_PERIOD, RESPONSE_TIMEOUT},
+    },
+};
+
+/// the pause between cycles of the MAZE state while the operator moves the MARV
+const CYCLE_PERIOD: Duration = Duration::from_millis(50);
+
+/// What the SS reported at the end of a cycle of the MAZE state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reading {
+    /// the colour under each sensor (177 packet) and the angle of incidence in degrees
+    /// (178 packet)
+    Colours { colours: [Colour; 5], incidence: u8 },
+    /// the SS sensed the end of the maze (179 packet)
+    EndOfMaze,
+}
+
+/// touches the SNC in the IDLE state, and takes the SS through the handshakes of the CAL
+/// state into the MAZE state
+pub fn idle_and_calibrate(bench: &mut TestBench) -> Result<(), BenchError> {
+    if bench.has_operator() {
+        bench.prompt("Calibrate the SS, if it needs you to");
+    }
+
+    bench.step("calibrates after the touch in IDLE", |bench| {
+        let sent = bench.time();
+        bench.send(Subsystem::Snc, IDLE_BUTTON_TOUCHED);
+
+        bench.expect(ControlByte::Calibrated, OPERATOR_TIMEOUT)?;
+
+        Ok(format!("112 after {}", milliseconds(bench.time() - sent)))
+    })?;
+
+    bench.step("reports the colours in CAL", |bench| {
+        let sent = bench.time();
+        bench.send(Subsystem::Mdps, CAL_BATTERY_LEVEL);
+
+        let colours =
+            decode_colours(bench.expect(ControlByte::CalibrateColours, RESPONSE_TIMEOUT)?)?;
+
+        Ok(format!(
+            "113 after {}, reading {:?}",
+            milliseconds(bench.time() - sent),
+            colours
+        ))
+    })?;
+
+    bench.step("waits for the touch in CAL", |bench| {
+        // the SS only reports its colours again once the MDPS has reported its battery level
+        bench.send(Subsystem::Snc, CAL_BUTTON_NOT_TOUCHED);
+        bench.quiet(QUIET_PERIOD)?;
+
+        bench.send(Subsystem::Mdps, CAL_BATTERY_LEVEL);
+        decode_colours(bench.expect(ControlByte::CalibrateColours, RESPONSE_TIMEOUT)?)?;
+
+        // ...and sends nothing once the SNC has been touched, until the MAZE state starts
+        bench.send(Subsystem::Snc, CAL_BUTTON_TOUCHED);
+        bench.quiet(QUIET_PERIOD)?;
+
+        Ok(format!(
+            "113 for each 97, nothing sent for {} ms after the touch",
+            QUIET_PERIOD.as_millis()
+        ))
+    })
+}
+
+/// sends the SNC's and the MDPS's packets of a cycle of the MAZE state, with the MARV
+/// driving forward and `distance` mm from where it last stopped, and waits for the SS's
+/// reading
+pub fn maze_cycle(bench: &mut TestBench, distance: u16) -> Result<Reading, BenchError> {
+    let distance = AdjacentBytes::from(distance);
+
+    bench.send(Subsystem::Snc, MAZE_CLAPSNAP_NONE);
+    bench.send(Subsystem::Snc, MAZE_BUTTON_NOT_TOUCHED);
+    bench.send(Subsystem::Snc, MAZE_NAVCON_FORWARD);
+
+    bench.send(Subsystem::Mdps, MAZE_BATTERY_LEVEL);
+    bench.send(Subsystem::Mdps, [162, 0, 0, 2]);
+    bench.send(
+        Subsystem::Mdps,
+        [163, MAZE_NAVCON_FORWARD[1], MAZE_NAVCON_FORWARD[2], 0],
+    );
+    bench.send(Subsystem::Mdps, [164, distance.msb(), distance.lsb(), 0]);
+
+    let colours_byte = u8::from(ControlByte::MazeColours);
+
+    match bench.receive(RESPONSE_TIMEOUT) {
+        Ok(packet) if packet.control_byte() == ControlByte::MazeEndOfMaze => Ok(Reading::EndOfMaze),
+        Ok(packet) if packet.control_byte() == ControlByte::MazeColours => {
+            let colours = decode_colours(packet)?;
+            let incidence = bench
+                .expect(ControlByte::MazeIncidence, RESPONSE_TIMEOUT)?
+                .dat1();
+
+            Ok(Reading::Colours { colours, incidence })
+        }
+        Ok(received) => Err(BenchError::Unexpected {
+            control_byte: Some(colours_byte),
+            received,
+        }),
+        Err(_) => Err(BenchError::Timeout {
+            control_byte: Some(colours_byte),
+            timeout: RESPONSE_TIMEOUT,
+        }),
+    }
+}
+
+/// carries on with cycles of the MAZE state, at `distance` mm, until the SS reports
+/// colours that `accept`s, e.g. once the operator has placed the MARV
+pub fn until_reading<F>(
+    bench: &mut TestBench,
+    distance: u16,
+    accept: F,
+) -> Result<(Reading, f32), BenchError>
+where
+    F: Fn(&Reading) -> bool,
+{
+    let start = bench.time();
+    let mut last = None;
+
+    while bench.time() - start < OPERATOR_TIMEOUT.as_secs_f32() {
+        let reading = maze_cycle(bench, distance)?;
+
+        if accept(&reading) {
+            return Ok((reading, bench.time() - start));
+        }
+
+        last = Some(reading);
+        std::thread::sleep(CYCLE_PERIOD);
+    }
+
+    Err(BenchError::Check(format!(
+        "not read within {:.1} s, the last reading was {}",
+        OPERATOR_TIMEOUT.as_secs_f32(),
+        match last {
+            Some(reading) => describe(&reading),
+            None => String::from("never sent"),
+        }
+    )))
+}
+
+/// places the MARV on the test card (or prompts the operator to), and waits until the SS
+/// reads the colours under its sensors there
+pub fn read_placement(
+    bench: &mut TestBench,
+    instruction: &str,
+    placement: Placement,
+    distance: u16,
+) -> Result<(Reading, f32), BenchError> {
+    let positions = placement.positions(bench.robot());
+    let expected = expected_colours(positions);
+
+    bench.place(instruction, positions);
+
+    until_reading(
+        bench,
+        distance,
+        |reading| matches!(reading, Reading::Colours { colours, .. } if *colours == expected),
+    )
+}
+
+/// places the MARV with every sensor on the red line (or prompts the operator to), and
+/// checks that the SS then reports the end of the maze and stops sending packets
+pub fn end_of_maze(bench: &mut TestBench) -> Result<(), BenchError> {
+    bench.place(
+        "Place the MARV with every sensor on the red line",
+        Placement::on(Colour::Red).positions(bench.robot()),
+    );
+
+    bench.end_of_maze("reports the end of the maze", |bench| {
+        let (_, waited) = until_reading(bench, 0, |reading| *reading == Reading::EndOfMaze)?;
+
+        Ok(format!("179 after {:.1} s", waited))
+    })
+}
+
+/// the colours that the sensors at `positions` are on, on the test card
+pub fn expected_colours(positions: [(f32, f32); 5]) -> [Colour; 5] {
+    let card = test_card();
+
+    positions.map(|(x, y)| card.get_colour_from_coord(x, y).unwrap_or(Colour::White))
+}
+
+/// the colour under each sensor in a colour word (177 or 113 packet), which must only
+/// hold known colours
+pub fn decode_colours(packet: Packet) -> Result<[Colour; 5], BenchError> {
+    let word = u16::from(AdjacentBytes::make(packet.dat1(), packet.dat0()));
+
+    if word >> 15 != 0 {
+        return Err(BenchError::Check(format!(
+            "the unused bit of the colour word is set in {}",
+            packet
+        )));
+    }
+
+    Colours::try_from(word)
+        .map(|colours| colours.colours())
+        .map_err(|err| BenchError::Check(format!("{} in {}", err, packet)))
+}
+
+/// a reading, e.g. "[Red, White, White, White, White] at 12°"
+pub fn describe(reading: &Reading) -> String {
+    match reading {
+        Reading::Colours { colours, incidence } => format!("{:?} at {}°", colours, incidence),
+        Reading::EndOfMaze => String::from("the end of the maze"),
+    }
+}
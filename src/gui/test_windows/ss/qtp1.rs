@@ -0,0 +1,25 @@
+//! # SS QTP 1
+//!
+//! Tests whether the SS calibrates once the SNC has been touched in the IDLE state,
+//! reports its colours through the handshakes of the CAL state, and waits for the SNC
+//! to be touched again before it starts the maze. The MARV is then placed on red, which
+//! the SS must report as the end of the maze.
+
+use crate::{
+    components::subsystem::Subsystem,
+    subsystems::test_bench::{BenchError, BenchQtp, TestBench},
+};
+
+use super::protocol::{end_of_maze, idle_and_calibrate};
+
+pub const SS_QTP_1: BenchQtp = BenchQtp {
+    name: "SS QTP 1",
+    under_test: Subsystem::Ss,
+    description: "IDLE and CAL: calibration and the CAL handshakes",
+    script: run,
+};
+
+fn run(bench: &mut TestBench) -> Result<(), BenchError> {
+    idle_and_calibrate(bench)?;
+    end_of_maze(bench)
+}
@@ -0,0 +1,91 @@
+//! # SS QTP 2
+//!
+//! Tests the SS's side of the MAZE state: every cycle it must answer the MDPS's distance
+//! (164) with its colours (177) and then the angle of incidence (178), in that order and
+//! in time, with every colour word well formed. With every sensor on white the SS must
+//! read white, and report an incidence of 0°.
+
+use crate::{
+    components::{colour::Colour, subsystem::Subsystem},
+    gui::test_windows::snc::protocol::milliseconds,
+    subsystems::{
+        sensor_subsystem::test_card::Placement,
+        test_bench::{BenchError, BenchQtp, TestBench},
+    },
+};
+
+use super::protocol::{
+    describe, end_of_maze, idle_and_calibrate, maze_cycle, read_placement, Reading,
+};
+
+/// the number of cycles of the MAZE state that the SS is taken through
+const CYCLES: u16 = 20;
+/// how far (in mm) the MARV is said to move each cycle
+const DISTANCE_PER_CYCLE: u16 = 10;
+
+pub const SS_QTP_2: BenchQtp = BenchQtp {
+    name: "SS QTP 2",
+    under_test: Subsystem::Ss,
+    description: "MAZE: the order and timing of the SS's packets, on white",
+    script: run,
+};
+
+fn run(bench: &mut TestBench) -> Result<(), BenchError> {
+    idle_and_calibrate(bench)?;
+
+    bench.step("placed on white", |bench| {
+        let (_, waited) = read_placement(
+            bench,
+            "Place the MARV with every sensor on white",
+            Placement::on(Colour::White),
+            0,
+        )?;
+
+        Ok(format!("read white after {:.1} s", waited))
+    })?;
+
+    let mut readings = Vec::new();
+
+    bench.step("packet order and timing", |bench| {
+        let mut slowest: f32 = 0.0;
+
+        for cycle in 0..CYCLES {
+            let start = bench.time();
+
+            match maze_cycle(bench, cycle * DISTANCE_PER_CYCLE)? {
+                Reading::EndOfMaze => {
+                    return Err(BenchError::Check(format!(
+                        "the end of the maze was sensed in cycle {}",
+                        cycle + 1
+                    )))
+                }
+                reading => readings.push(reading),
+            }
+
+            slowest = slowest.max(bench.time() - start);
+        }
+
+        Ok(format!(
+            "{} cycles, the slowest took {}",
+            CYCLES,
+            milliseconds(slowest)
+        ))
+    })?;
+
+    bench.step("reads white at 0°", |_| {
+        let white = Reading::Colours {
+            colours: [Colour::White; 5],
+            incidence: 0,
+        };
+
+        match readings.iter().find(|reading| **reading != white) {
+            Some(reading) => Err(BenchError::Check(format!(
+                "read {} on white",
+                describe(reading)
+            ))),
+            None => Ok(format!("{} readings of white at 0°", readings.len())),
+        }
+    })?;
+
+    end_of_maze(bench)
+}
@@ -0,0 +1,64 @@
+//! # SS QTP 3
+//!
+//! Tests the SS's colour sensors against the test card: the MARV is placed with every
+//! sensor on each of the white, green, blue and black lines in turn, and the SS must read
+//! that colour under every sensor. Every sensor on red is the end of the maze, so red is
+//! tested with only the leftmost sensor on the red line.
+//!
+//! On a physical SS the operator places the MARV, and the kit waits until the SS reads
+//! what is expected, or the operator runs out of time.
+
+use crate::{
+    components::{colour::Colour, subsystem::Subsystem},
+    subsystems::{
+        sensor_subsystem::test_card::Placement,
+        test_bench::{BenchError, BenchQtp, TestBench},
+    },
+};
+
+use super::protocol::{describe, end_of_maze, idle_and_calibrate, read_placement};
+
+/// the angle (in degrees) at which the MARV is placed across the red line, so that only
+/// its leftmost sensor is on it
+const RED_INCIDENCE: f32 = 30.0;
+
+pub const SS_QTP_3: BenchQtp = BenchQtp {
+    name: "SS QTP 3",
+    under_test: Subsystem::Ss,
+    description: "Colour test card: every sensor on each colour, read against the card",
+    script: run,
+};
+
+fn run(bench: &mut TestBench) -> Result<(), BenchError> {
+    idle_and_calibrate(bench)?;
+
+    for colour in [Colour::White, Colour::Green, Colour::Blue, Colour::Black] {
+        let instruction = format!("Place the MARV with every sensor on the {} line", colour);
+
+        bench.step(&format!("reads {}", colour), |bench| {
+            let (reading, waited) = read_placement(bench, &instruction, Placement::on(colour), 0)?;
+
+            Ok(format!("read {} after {:.1} s", describe(&reading), waited))
+        })?;
+    }
+
+    bench.step("reads Red", |bench| {
+        let (reading, waited) = read_placement(
+            bench,
+            &format!(
+                "Place the MARV at {}° to the red line, with only its leftmost sensor on it",
+                RED_INCIDENCE
+            ),
+            Placement {
+                colour: Colour::Red,
+                incidence: RED_INCIDENCE,
+                advance: 0.0,
+            },
+            0,
+        )?;
+
+        Ok(format!("read {} after {:.1} s", describe(&reading), waited))
+    })?;
+
+    end_of_maze(bench)
+}
@@ -0,0 +1,86 @@
+//! # SS QTP 4
+//!
+//! Tests the angle of incidence that the SS reports (178) as the MARV crosses a line.
+//! For each angle the MARV is placed across the green line with only its leftmost
+//! sensor on it, and then moved forward until the sensor next to it reaches the line,
+//! with the kit reporting the distance between the two as the MDPS would. The incidence
+//! that the SS then reports must be within a tolerance of the angle.
+
+use crate::{
+    components::{colour::Colour, subsystem::Subsystem},
+    subsystems::{
+        sensor_subsystem::test_card::Placement,
+        test_bench::{BenchError, BenchQtp, TestBench},
+    },
+};
+
+use super::protocol::{describe, end_of_maze, idle_and_calibrate, read_placement, Reading};
+
+/// the angles of incidence (in degrees) that the MARV crosses the line at
+const INCIDENCES: [f32; 4] = [10.0, 20.0, 30.0, 45.0];
+/// how far (in degrees) the reported incidence may be from the angle that the MARV
+/// crosses the line at
+const TOLERANCE: f32 = 5.0;
+
+pub const SS_QTP_4: BenchQtp = BenchQtp {
+    name: "SS QTP 4",
+    under_test: Subsystem::Ss,
+    description: "Angle of incidence: crossing the green line at 10°, 20°, 30° and 45°",
+    script: run,
+};
+
+fn run(bench: &mut TestBench) -> Result<(), BenchError> {
+    idle_and_calibrate(bench)?;
+
+    for incidence in INCIDENCES {
+        // how far the MARV moves from its leftmost sensor reaching the line to the
+        // sensor next to it reaching it
+        let advance = bench.robot().outer_sensor_spacing() * incidence.to_radians().tan();
+
+        bench.step(&format!("{}° incidence", incidence), |bench| {
+            read_placement(
+                bench,
+                &format!(
+                    "Place the MARV at {}° to the green line, with only its leftmost sensor on it",
+                    incidence
+                ),
+                Placement {
+                    colour: Colour::Green,
+                    incidence,
+                    advance: 0.0,
+                },
+                0,
+            )?;
+
+            let (reading, _) = read_placement(
+                bench,
+                &format!(
+                    "Push the MARV {:.0} mm forward, until the sensor next to the leftmost one is on the line",
+                    advance
+                ),
+                Placement {
+                    colour: Colour::Green,
+                    incidence,
+                    advance,
+                },
+                advance.round() as u16,
+            )?;
+
+            match reading {
+                Reading::Colours {
+                    incidence: reported,
+                    ..
+                } if (reported as f32 - incidence).abs() <= TOLERANCE => {
+                    Ok(format!("read {}", describe(&reading)))
+                }
+                reading => Err(BenchError::Check(format!(
+                    "read {}, more than {}° off",
+                    describe(&reading),
+                    TOLERANCE
+                ))),
+            }
+        })?;
+    }
+
+    end_of_maze(bench)
+}
@@ -1,18 +1,32 @@
 use std::collections::VecDeque;
 
+use crate::subsystems::{
+    hub::IntegrationQtp, qtp::QtpKind, system::NavconQtp, test_bench::BenchQtp,
+};
+
 #[derive(Debug, Clone, Copy)]
 pub enum Window {
     Main,
-    Navcon(QtpNo),
+    /// a QTP run against NAVCON, in an emulated maze
+    Navcon(NavconQtp),
+    Replay,
+    /// a QTP run against a single subsystem
+    Bench(BenchQtp),
+    /// a QTP run against the whole MARV
+    Integration(IntegrationQtp),
+    /// the kit as the hub between the physical subsystems, outside of a QTP
+    Hub,
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum QtpNo {
-    Qtp1,
-    Qtp2,
-    Qtp3,
-    Qtp4,
-    Qtp5,
+impl From<QtpKind> for Window {
+    /// the window that a QTP of `kind` is run from
+    fn from(kind: QtpKind) -> Self {
+        match kind {
+            QtpKind::Navcon(qtp) => Window::Navcon(qtp),
+            QtpKind::Bench(qtp) => Window::Bench(qtp),
+            QtpKind::Integration(qtp) => Window::Integration(qtp),
+        }
+    }
 }
 
 #[derive(Default)]
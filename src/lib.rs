@@ -1,11 +1,15 @@
 pub mod components {
     pub mod adjacent_bytes;
     pub mod buffer;
+    pub mod calibration;
     pub mod colour;
     pub mod comm_port;
     pub mod constants;
+    pub mod envelope;
     pub mod packet;
+    pub mod robot_config;
     pub mod state;
+    pub mod subsystem;
 }
 
 pub mod subsystems {
@@ -15,17 +19,34 @@ pub mod subsystems {
     }
 
     pub mod motor_subsystem {
+        pub mod battery;
         pub mod mdps;
+        pub mod motor_model;
+        pub mod rotation_control;
         pub mod wheel;
     }
 
     pub mod sensor_subsystem {
+        pub mod incidence;
+        pub mod sensor_model;
         pub mod ss;
+        pub mod test_card;
     }
 
+    pub mod fault_injection;
+    pub mod hub;
+    pub mod kinematics;
+    pub mod packet_capture;
+    pub mod protocol_monitor;
+    pub mod qtp;
+    pub mod qtp_report;
+    pub mod run_monitor;
     pub mod sensor_positions;
     pub mod serial_relay;
+    pub mod session;
     pub mod system;
+    pub mod test_bench;
+    pub mod trajectory;
 }
 
 pub mod asynchronous {
@@ -39,19 +60,74 @@ pub mod gui {
     pub mod gui;
     pub mod maze;
     pub mod packet_display;
+    pub mod sequence_diagram;
     pub mod window_stack;
 
     pub mod test_windows {
+        // declares `qtps!` for the suites below
+        #[macro_use]
+        pub mod registry;
+
+        pub mod integration {
+            pub mod protocol;
+            pub mod qtp1;
+            pub mod qtp2;
+            pub mod qtp3;
+
+            qtps!(
+                qtp1::INTEGRATION_QTP_1,
+                qtp2::INTEGRATION_QTP_2,
+                qtp3::INTEGRATION_QTP_3,
+            );
+        }
+
+        pub mod mdps {
+            pub mod protocol;
+            pub mod qtp1;
+            pub mod qtp2;
+            pub mod qtp3;
+
+            qtps!(qtp1::MDPS_QTP_1, qtp2::MDPS_QTP_2, qtp3::MDPS_QTP_3);
+        }
+
         pub mod navcon {
             pub mod qtp1;
             pub mod qtp2;
             pub mod qtp3;
             pub mod qtp4;
             pub mod qtp5;
+
+            qtps!(
+                qtp1::NAVCON_QTP_1,
+                qtp2::NAVCON_QTP_2,
+                qtp3::NAVCON_QTP_3,
+                qtp4::NAVCON_QTP_4,
+                qtp5::NAVCON_QTP_5,
+            );
         }
 
         pub mod snc {
+            pub mod protocol;
+            pub mod qtp1;
+            pub mod qtp2;
+            pub mod qtp3;
+
+            qtps!(qtp1::SNC_QTP_1, qtp2::SNC_QTP_2, qtp3::SNC_QTP_3);
+        }
+
+        pub mod ss {
+            pub mod protocol;
             pub mod qtp1;
+            pub mod qtp2;
+            pub mod qtp3;
+            pub mod qtp4;
+
+            qtps!(
+                qtp1::SS_QTP_1,
+                qtp2::SS_QTP_2,
+                qtp3::SS_QTP_3,
+                qtp4::SS_QTP_4,
+            );
         }
     }
 }
@@ -0,0 +1,338 @@
+//! # Fault injection
+//!
+//! Stands in for a noisy UART link between the subsystems of a run: the packets that a
+//! subsystem sends go through a `FaultInjector` on their way to the rest of the SCS (and
+//! the session recorder), which can drop, delay, duplicate, reorder or flip a bit of each
+//! of them. This tests how robust the state machines of the SNC, SS and MDPS are to what
+//! a real link does now and then.
+//!
+//! A `FaultProfile` picks the packets that are affected, by their sender and control
+//! byte, and injects faults into them at random, with a probability for each fault, or as
+//! scripted, into a chosen packet. With `FaultProfile::none()` every packet goes straight
+//! through, as it always has.
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, SystemTime},
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    asynchronous::one_to_many_channel::{Bound, OTMChannel},
+    components::{buffer::Buffer, packet::Packet, subsystem::Subsystem},
+};
+
+/// every control byte of the protocol, which a profile can single out
+pub const CONTROL_BYTES: [u8; 18] = [
+    16, 112, 96, 97, 113, 80, 145, 146, 147, 161, 162, 163, 164, 177, 178, 179, 228, 208,
+];
+
+/// how long an injector waits for the next packet when there is nothing to deliver
+const IDLE_PERIOD: Duration = Duration::from_micros(100);
+
+/// What can happen to a packet on its way over the link
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// the packet never arrives
+    Drop,
+    /// the packet arrives `FaultProfile::delay_time` late
+    Delay,
+    /// the packet arrives twice
+    Duplicate,
+    /// the packet arrives after the next packet from the same sender, or
+    /// `FaultProfile::delay_time` late if there is none by then
+    Reorder,
+    /// one bit of the packet, chosen at random, is flipped
+    BitFlip,
+}
+
+/// every fault, in the order that their probabilities are drawn in
+pub const FAULTS: [Fault; 5] = [
+    Fault::Drop,
+    Fault::Delay,
+    Fault::Duplicate,
+    Fault::Reorder,
+    Fault::BitFlip,
+];
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fault::Drop => write!(f, "Drop"),
+            Fault::Delay => write!(f, "Delay"),
+            Fault::Duplicate => write!(f, "Duplicate"),
+            Fault::Reorder => write!(f, "Reorder"),
+            Fault::BitFlip => write!(f, "Bit flip"),
+        }
+    }
+}
+
+/// A fault injected into a chosen packet, whatever the probabilities of the profile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScriptedFault {
+    /// the packet that the fault is injected into, counted from 1 among the packets that
+    /// the profile affects on each link
+    pub packet: u32,
+    pub fault: Fault,
+}
+
+/// The faults injected into the packets sent between the subsystems of a run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FaultProfile {
+    /// the subsystem whose packets are affected, or every subsystem's if `None`
+    pub source: Option<Subsystem>,
+    /// the control byte of the packets affected, or every packet if `None`
+    pub control_byte: Option<u8>,
+    /// the probability of each fault for each packet affected, in the order of `FAULTS`
+    pub probabilities: [f32; 5],
+    /// how long (in s) a delayed packet is held back
+    pub delay_time: f32,
+    /// a fault injected into one packet, on top of the random ones
+    pub script: Option<ScriptedFault>,
+}
+
+impl FaultProfile {
+    /// a perfect link
+    pub fn none() -> Self {
+        Self {
+            source: None,
+            control_byte: None,
+            probabilities: [0.0; 5],
+            delay_time: 0.05,
+            script: None,
+        }
+    }
+
+    /// a link that gets roughly one packet in a hundred wrong
+    pub fn noisy() -> Self {
+        Self {
+            probabilities: [0.002, 0.004, 0.002, 0.001, 0.001],
+            ..Self::none()
+        }
+    }
+
+    /// whether any fault is ever injected into the packets of `source`
+    pub fn affects(&self, source: Subsystem) -> bool {
+        let faults = self
+            .probabilities
+            .iter()
+            .any(|probability| *probability > 0.0)
+            || self.script.is_some();
+
+        faults && (self.source.is_none() || self.source == Some(source))
+    }
+
+    /// whether faults are injected into `packet`, if its sender is affected
+    fn affects_packet(&self, packet: Packet) -> bool {
+        match self.control_byte {
+            Some(byte) => <[u8; 4]>::from(packet)[0] == byte,
+            None => true,
+        }
+    }
+}
+
+impl Default for FaultProfile {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+/// The faults of a profile, injected into the packets that one subsystem sends, in the
+/// order that they are sent
+#[derive(Debug)]
+pub struct FaultyLink {
+    profile: FaultProfile,
+    rng: StdRng,
+    /// the number of packets affected by the profile so far
+    affected: u32,
+    /// a reordered packet, until the next one is delivered or (in s) it is due
+    held: Option<(f32, Packet)>,
+    /// delayed packets, until (in s) they are due
+    delayed: VecDeque<(f32, Packet)>,
+}
+
+impl FaultyLink {
+    pub fn new(profile: FaultProfile) -> Self {
+        Self {
+            profile,
+            rng: StdRng::from_entropy(),
+            affected: 0,
+            held: None,
+            delayed: VecDeque::new(),
+        }
+    }
+
+    /// passes `packet`, sent at `time` (in s), over the link, and returns the packets that
+    /// arrive at once, with the fault injected into it (if one was)
+    pub fn send(&mut self, packet: Packet, time: f32) -> (Vec<Packet>, Option<Fault>) {
+        let fault = self.fault(packet);
+        let due = time + self.profile.delay_time;
+
+        let mut arrived = match fault {
+            None => vec![packet],
+            Some(Fault::Drop) => Vec::new(),
+            Some(Fault::Delay) => {
+                self.delayed.push_back((due, packet));
+                Vec::new()
+            }
+            Some(Fault::Duplicate) => vec![packet, packet],
+            Some(Fault::Reorder) => {
+                // a packet that was already held back arrives in its place
+                return (
+                    self.held
+                        .replace((due, packet))
+                        .map(|(_, held)| held)
+                        .into_iter()
+                        .collect(),
+                    fault,
+                );
+            }
+            Some(Fault::BitFlip) => {
+                let mut bytes = <[u8; 4]>::from(packet);
+                let bit = self.rng.gen_range(0..32);
+                bytes[bit / 8] ^= 1 << (bit % 8);
+                vec![bytes.into()]
+            }
+        };
+
+        // the packet held back arrives after the one that overtook it
+        arrived.extend(self.held.take().map(|(_, held)| held));
+
+        (arrived, fault)
+    }
+
+    /// the packets held back that are due by `time` (in s), in the order that they arrive
+    pub fn due(&mut self, time: f32) -> Vec<Packet> {
+        let mut arrived = Vec::new();
+
+        while matches!(self.delayed.front(), Some((due, _)) if *due <= time) {
+            arrived.extend(self.delayed.pop_front().map(|(_, packet)| packet));
+        }
+
+        if matches!(self.held, Some((due, _)) if due <= time) {
+            arrived.extend(self.held.take().map(|(_, held)| held));
+        }
+
+        arrived
+    }
+
+    /// the fault injected into `packet`, if any: the scripted one if it is the scripted
+    /// packet, or one drawn at random otherwise
+    fn fault(&mut self, packet: Packet) -> Option<Fault> {
+        if !self.profile.affects_packet(packet) {
+            return None;
+        }
+
+        self.affected += 1;
+
+        if let Some(script) = self.profile.script {
+            if script.packet == self.affected {
+                return Some(script.fault);
+            }
+        }
+
+        let mut draw: f32 = self.rng.gen();
+
+        for (fault, probability) in FAULTS.iter().zip(self.profile.probabilities) {
+            if draw < probability {
+                return Some(*fault);
+            }
+
+            draw -= probability;
+        }
+
+        None
+    }
+}
+
+/// Passes the packets that a subsystem sends on to the rest of the SCS over a `FaultyLink`
+pub struct FaultInjector {
+    link: FaultyLink,
+    /// the packets are received from the subsystem, and passed on, on this channel
+    channel: OTMChannel<Packet>,
+    start: SystemTime,
+    /// cleared once the run has ended
+    running: Arc<AtomicBool>,
+}
+
+impl FaultInjector {
+    pub fn new(
+        profile: FaultProfile,
+        channel: OTMChannel<Packet>,
+        running: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            link: FaultyLink::new(profile),
+            channel,
+            start: SystemTime::now(),
+            running,
+        }
+    }
+
+    /// passes packets on until the run ends
+    pub fn run(&mut self) {
+        while self.running.load(Ordering::Relaxed) {
+            let time = self.start.elapsed().unwrap_or_default().as_secs_f32();
+            let mut arrived = self.link.due(time);
+
+            if let Ok(packet) = self.channel.try_receive() {
+                let (sent, fault) = self.link.send(packet, time);
+
+                if let Some(fault) = fault {
+                    println!(
+                        "{}: {} injected into {}",
+                        self.channel.name(),
+                        fault,
+                        packet
+                    );
+                }
+
+                arrived.extend(sent);
+            }
+
+            if arrived.is_empty() {
+                std::thread::sleep(IDLE_PERIOD);
+            }
+
+            for packet in arrived {
+                self.channel.send(packet);
+            }
+        }
+    }
+}
+
+/// the channel on which `source` sends its packets to `endpoints`, through a
+/// `FaultInjector` if `profile` affects its packets, or straight to them if not
+///
+/// `origin` is where `source` receives the packets sent to it.
+pub fn link(
+    name: &str,
+    source: Subsystem,
+    origin: &Arc<Mutex<Buffer<Packet>>>,
+    endpoints: Vec<&Arc<Mutex<Buffer<Packet>>>>,
+    profile: FaultProfile,
+    running: &Arc<AtomicBool>,
+) -> OTMChannel<Packet> {
+    if !profile.affects(source) {
+        return OTMChannel::with_endpoints(name, origin, endpoints, Bound::Inifinity);
+    }
+
+    let to_injector = Arc::new(Mutex::new(Buffer::new()));
+    let injector_channel = OTMChannel::with_endpoints(
+        &format!("{} (Faults)", name),
+        &to_injector,
+        endpoints,
+        Bound::Inifinity,
+    );
+
+    let mut injector = FaultInjector::new(profile, injector_channel, Arc::clone(running));
+    std::thread::spawn(move || injector.run());
+
+    OTMChannel::with_endpoints(name, origin, vec![&to_injector], Bound::Inifinity)
+}
@@ -0,0 +1,567 @@
+//! # Hub
+//!
+//! Runs an integration QTP against the whole MARV: the three subsystems talk to each other
+//! through the kit, which passes every packet on unchanged, as the hub of the SCS would.
+//! The kit only listens in, following the protocol with a `ProtocolMonitor`, apart from
+//! sending the hub's own packets: `HUB_START` to start the subsystems, and
+//! `HUB_END_OF_MAZE` to end the run.
+//!
+//! The subsystems are either all physical, each connected to the kit over its own serial
+//! port, with an operator at the MARV, or all emulated, in which case the MARV drives
+//! through a maze on its own and there is no hub to start or end it.
+//!
+//! Outside of a QTP, a `BusMonitor` runs the kit as the hub between the physical
+//! subsystems for as long as it is connected, so that a fully-built MARV can be diagnosed
+//! on the bench: every packet is passed on, checked against the protocol, and captured to
+//! be exported. The hub's own packets are captured too, with the hub as their source.
+
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    asynchronous::{
+        async_type::{GuiEndpoints, PacketsEndpoint, PromptsEndpoint, StepsEndpoint},
+        one_to_many_channel::{Bound, OTMChannel},
+    },
+    components::{
+        buffer::Buffer,
+        constants::{DEFUALT_COM_PORT, DEFUALT_STARTING_POSITION, NINETY_DEGREES},
+        envelope::PacketEnvelope,
+        packet::Packet,
+        subsystem::Subsystem,
+    },
+    gui::maze::MazeLineMap,
+};
+
+use super::{
+    packet_capture::{CaptureFormat, PacketCapture},
+    protocol_monitor::ProtocolMonitor,
+    qtp_report::{QtpReport, QtpStep, Verdict},
+    serial_relay::SerialRelay,
+    session::{Recording, SessionRecorder, SESSIONS_DIRECTORY},
+    system::{run_system, EmulationConfig, Mode},
+    test_bench::{BenchError, Prompt},
+};
+
+/// how long the kit waits for more packets before checking those captured so far, as
+/// long as `REORDER_WINDOW`
+const REORDER_PERIOD: Duration = Duration::from_millis(10);
+
+/// A QTP that the kit runs against the whole MARV
+#[derive(Debug, Clone, Copy)]
+pub struct IntegrationQtp {
+    /// e.g. "Integration QTP 1"
+    pub name: &'static str,
+    /// what the QTP tests, shown before it is run
+    pub description: &'static str,
+    /// the maze that the MARV drives through when its subsystems are emulated
+    pub maze: fn() -> MazeLineMap,
+    /// the steps of the QTP, which stop at the first step that fails
+    pub script: fn(&mut Hub) -> Result<(), BenchError>,
+}
+
+/// The kit's side of an integration QTP: every packet sent between the subsystems, and
+/// the hub's own packets to them
+pub struct Hub {
+    /// every packet sent between the subsystems, as it was captured
+    envelopes: OTMChannel<PacketEnvelope>,
+    /// the hub's packets go to every subsystem on this channel, if they are physical
+    subsystems: Option<OTMChannel<Packet>>,
+    monitor: ProtocolMonitor,
+    start: SystemTime,
+    report: QtpReport,
+    /// every packet is passed on to the GUI once the monitor has seen it...
+    packets: PacketsEndpoint,
+    /// ...as is each step, as soon as it has been carried out...
+    steps: StepsEndpoint,
+    /// ...and what the operator is asked to do
+    prompts: PromptsEndpoint,
+}
+
+impl Hub {
+    pub fn new(
+        qtp: &str,
+        envelopes: OTMChannel<PacketEnvelope>,
+        subsystems: Option<OTMChannel<Packet>>,
+        gui: &GuiEndpoints,
+    ) -> Self {
+        Self {
+            envelopes,
+            subsystems,
+            monitor: ProtocolMonitor::new(),
+            start: SystemTime::now(),
+            report: QtpReport::new(qtp),
+            packets: Arc::clone(&gui.packets),
+            steps: Arc::clone(&gui.steps),
+            prompts: Arc::clone(&gui.prompts),
+        }
+    }
+
+    /// whether the subsystems are physical, with an operator at the MARV
+    pub fn has_operator(&self) -> bool {
+        self.subsystems.is_some()
+    }
+
+    /// how long (in s) the QTP has been running for
+    pub fn time(&self) -> f32 {
+        self.start.elapsed().unwrap_or_default().as_secs_f32()
+    }
+
+    pub fn monitor(&self) -> &ProtocolMonitor {
+        &self.monitor
+    }
+
+    /// sends one of the hub's own packets (e.g. `HUB_START`) to every subsystem, if they
+    /// are physical
+    pub fn broadcast(&self, data: [u8; 4]) {
+        if let Some(subsystems) = &self.subsystems {
+            subsystems.send(data.into());
+        }
+    }
+
+    /// passes on the next packet captured, if there is one, and checks it against the
+    /// protocol, or checks the packets captured so far if none has been for a while
+    fn poll(&mut self) {
+        if !self.captured() {
+            std::thread::sleep(REORDER_PERIOD);
+
+            // nothing more was captured alongside the last packets
+            if !self.captured() {
+                self.monitor.flush();
+            }
+        }
+    }
+
+    /// passes on the next packet captured, and checks it against the protocol, or returns
+    /// `false` if none has been captured
+    fn captured(&mut self) -> bool {
+        match self.envelopes.try_receive() {
+            Ok(envelope) => {
+                self.packets.lock().unwrap().write(envelope);
+                self.monitor.check(envelope);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// checks the packets as they are captured until the monitor shows that `done`, and
+    /// returns how long (in s) that took, or fails with `what` if it takes longer than
+    /// `timeout`
+    pub fn until<F>(&mut self, timeout: Duration, what: &str, done: F) -> Result<f32, BenchError>
+    where
+        F: Fn(&ProtocolMonitor) -> bool,
+    {
+        let start = self.time();
+
+        while !done(&self.monitor) {
+            if self.time() - start > timeout.as_secs_f32() {
+                return Err(BenchError::Check(format!(
+                    "no {} within {:.1} s",
+                    what,
+                    timeout.as_secs_f32()
+                )));
+            }
+
+            self.poll();
+        }
+
+        Ok(self.time() - start)
+    }
+
+    /// checks the packets as they are captured for `duration`, e.g. to catch any sent
+    /// after the end of the run
+    pub fn settle(&mut self, duration: Duration) {
+        let start = self.time();
+
+        while self.time() - start < duration.as_secs_f32() {
+            self.poll();
+        }
+
+        self.monitor.flush();
+    }
+
+    /// asks the operator to do something, e.g. to touch the SNC
+    pub fn prompt(&self, instruction: &str) {
+        self.prompts
+            .lock()
+            .unwrap()
+            .write(Prompt::Act(String::from(instruction)));
+    }
+
+    /// carries out a step, which passes with what `step` returns, or fails with its error,
+    /// which is returned so that the QTP stops
+    pub fn step<F>(&mut self, name: &str, step: F) -> Result<(), BenchError>
+    where
+        F: FnOnce(&mut Self) -> Result<String, BenchError>,
+    {
+        match step(self) {
+            Ok(detail) => {
+                self.record(name, Verdict::Pass, detail);
+                Ok(())
+            }
+            Err(err) => {
+                self.record(name, Verdict::Fail, err.to_string());
+                Err(err)
+            }
+        }
+    }
+
+    /// records a step that could not be carried out, and why
+    pub fn skip(&mut self, name: &str, reason: &str) {
+        self.record(name, Verdict::Skipped, String::from(reason));
+    }
+
+    fn record(&mut self, name: &str, verdict: Verdict, detail: String) {
+        let step = QtpStep {
+            time: self.time(),
+            name: String::from(name),
+            verdict,
+            detail,
+        };
+
+        self.steps.lock().unwrap().write(step.clone());
+        self.report.steps.push(step);
+    }
+
+    pub fn report(&self) -> &QtpReport {
+        &self.report
+    }
+}
+
+/// The kit as the hub between the physical subsystems, outside of a QTP: every packet is
+/// passed on unchanged, checked against the protocol and captured, until the monitor is
+/// dropped
+pub struct BusMonitor {
+    /// the hub's packets go to every subsystem on this channel
+    subsystems: OTMChannel<Packet>,
+    /// every packet sent between the subsystems, as it was captured
+    envelopes: OTMChannel<PacketEnvelope>,
+    monitor: ProtocolMonitor,
+    capture: PacketCapture,
+    /// when the monitor was connected, which names its exported captures
+    connected: SystemTime,
+    /// when the last packet was captured, once one has been
+    last_captured: Option<SystemTime>,
+    /// cleared to disconnect the subsystems
+    running: Arc<AtomicBool>,
+}
+
+impl BusMonitor {
+    /// connects the physical subsystems on the COM ports in `ports` (in the order SNC, SS,
+    /// MDPS) to each other through the kit
+    pub fn connect(ports: &[String; 3]) -> Self {
+        let running = Arc::new(AtomicBool::new(true));
+        let to_hub_captured = Arc::new(Mutex::new(Buffer::new()));
+        let subsystems = connect(ports, None, &to_hub_captured, &running);
+
+        Self::new(subsystems, &to_hub_captured, running)
+    }
+
+    /// connects three subsystems to each other through the kit in memory instead of over
+    /// serial ports, e.g. to test the monitor, and returns the channel that each of them
+    /// (in the order SNC, SS, MDPS) sends its packets on and receives the others' packets on
+    pub fn in_memory() -> (Self, [OTMChannel<Packet>; 3]) {
+        let running = Arc::new(AtomicBool::new(true));
+        let to_hub_captured = Arc::new(Mutex::new(Buffer::new()));
+        let (subsystems, channels) = wire(None, &to_hub_captured, &running);
+
+        (Self::new(subsystems, &to_hub_captured, running), channels)
+    }
+
+    fn new(
+        subsystems: OTMChannel<Packet>,
+        to_hub_captured: &Arc<Mutex<Buffer<PacketEnvelope>>>,
+        running: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            subsystems,
+            envelopes: OTMChannel::new("Hub (Captured)", to_hub_captured, Bound::Inifinity),
+            monitor: ProtocolMonitor::new(),
+            capture: PacketCapture::new(),
+            connected: SystemTime::now(),
+            last_captured: None,
+            running,
+        }
+    }
+
+    /// sends one of the hub's own packets (e.g. `HUB_START`) to every subsystem
+    pub fn inject(&self, data: [u8; 4]) {
+        self.subsystems.send(data.into());
+    }
+
+    /// captures the next packet sent between the subsystems, if there is one, and checks
+    /// it against the protocol, or checks the packets captured so far if none has been for
+    /// a while
+    pub fn poll(&mut self) -> Option<PacketEnvelope> {
+        match self.envelopes.try_receive() {
+            Ok(envelope) => {
+                self.monitor.check(envelope);
+                self.capture.push(envelope);
+                self.last_captured = Some(SystemTime::now());
+                Some(envelope)
+            }
+            Err(_) => {
+                let idle = self
+                    .last_captured
+                    .map(|captured| captured.elapsed().unwrap_or_default());
+
+                if matches!(idle, Some(idle) if idle >= REORDER_PERIOD) {
+                    self.monitor.flush();
+                }
+
+                None
+            }
+        }
+    }
+
+    /// checks every packet captured so far, e.g. before the subsystems are disconnected
+    pub fn flush(&mut self) {
+        self.monitor.flush();
+    }
+
+    pub fn monitor(&self) -> &ProtocolMonitor {
+        &self.monitor
+    }
+
+    /// every packet captured since the monitor was connected
+    pub fn capture(&self) -> &PacketCapture {
+        &self.capture
+    }
+
+    /// exports every packet captured so far to `SESSIONS_DIRECTORY` in `format`, and
+    /// returns where to
+    pub fn export(&self, format: CaptureFormat) -> io::Result<PathBuf> {
+        let connected = self
+            .connected
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = PathBuf::from(SESSIONS_DIRECTORY).join(format!(
+            "hub_{}.capture.{}",
+            connected,
+            format.extension()
+        ));
+
+        fs::create_dir_all(SESSIONS_DIRECTORY)?;
+        self.capture.export(&path, format)?;
+
+        Ok(path)
+    }
+}
+
+impl Drop for BusMonitor {
+    fn drop(&mut self) {
+        // stop the serial port relays and the recorder
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Runs `qtp` against the whole MARV, with its subsystems emulated or physical according
+/// to `mode` (on the COM ports in `ports`, in the order SNC, SS, MDPS), and returns its
+/// report
+///
+/// Every packet sent is passed on to the GUI, as during a run of the whole MARV.
+pub fn run_integration(
+    qtp: &IntegrationQtp,
+    mode: Mode,
+    ports: &[String; 3],
+    config: EmulationConfig,
+    recording: Option<Recording>,
+    gui: &GuiEndpoints,
+) -> QtpReport {
+    // cleared once the QTP has ended
+    let running = Arc::new(AtomicBool::new(true));
+
+    // endpoint for the packets captured during the run, which only the kit reads
+    let to_hub_captured = Arc::new(Mutex::new(Buffer::new()));
+
+    let hub_captured = OTMChannel::new("Hub (Captured)", &to_hub_captured, Bound::Inifinity);
+
+    // the thread of the emulated run, if the subsystems are emulated
+    let mut emulation = None;
+
+    let mut hub = match mode {
+        Mode::Physical => {
+            let subsystems = connect(ports, recording.clone(), &to_hub_captured, &running);
+            Hub::new(qtp.name, hub_captured, Some(subsystems), gui)
+        }
+        Mode::Emulate => {
+            // the emulated run passes its packets to the kit instead of to the GUI, and ends
+            // at the end of the maze, or once the QTP has ended
+            let endpoints = GuiEndpoints {
+                packets: Arc::clone(&to_hub_captured),
+                ..gui.clone()
+            };
+            let maze = (qtp.maze)();
+            let recording = recording.clone();
+            let running = Arc::clone(&running);
+
+            emulation = Some(std::thread::spawn(move || {
+                run_system(
+                    Mode::Emulate,
+                    Mode::Emulate,
+                    Mode::Emulate,
+                    DEFUALT_COM_PORT,
+                    DEFUALT_COM_PORT,
+                    DEFUALT_COM_PORT,
+                    maze,
+                    DEFUALT_STARTING_POSITION,
+                    NINETY_DEGREES,
+                    config,
+                    recording,
+                    running,
+                    &endpoints,
+                );
+            }));
+
+            Hub::new(qtp.name, hub_captured, None, gui)
+        }
+    };
+
+    // the step that failed, if one did, has already been recorded
+    let _ = (qtp.script)(&mut hub);
+
+    // stop the threads that only end with the QTP
+    running.store(false, Ordering::Relaxed);
+
+    if let Some(emulation) = emulation {
+        emulation
+            .join()
+            .expect("could not join the emulated run's thread");
+    }
+
+    if let Some(recording) = &recording {
+        recording.save_report(hub.report());
+    }
+
+    hub.report().clone()
+}
+
+/// connects the physical subsystems on `ports` to each other through the kit, which
+/// captures every packet sent to `to_hub_captured` (and records it to `recording`, if
+/// there is one), and returns the channel on which the hub's own packets go to every
+/// subsystem
+fn connect(
+    ports: &[String; 3],
+    recording: Option<Recording>,
+    to_hub_captured: &Arc<Mutex<Buffer<PacketEnvelope>>>,
+    running: &Arc<AtomicBool>,
+) -> OTMChannel<Packet> {
+    let (hub_channel, channels) = wire(recording, to_hub_captured, running);
+
+    for (channel, port) in channels.into_iter().zip(ports.clone()) {
+        let running = Arc::clone(running);
+
+        // the port is opened on the relay's own thread, which is all that stops if it
+        // cannot be
+        std::thread::spawn(move || SerialRelay::new(channel, &port, running).run());
+    }
+
+    hub_channel
+}
+
+/// connects the subsystems' channels to each other and to a session recorder, which
+/// captures every packet sent (including the hub's own) to `to_hub_captured` and records
+/// it to `recording` (if there is one), and returns the hub's channel to every subsystem,
+/// along with each subsystem's channel (in the order SNC, SS, MDPS)
+fn wire(
+    recording: Option<Recording>,
+    to_hub_captured: &Arc<Mutex<Buffer<PacketEnvelope>>>,
+    running: &Arc<AtomicBool>,
+) -> (OTMChannel<Packet>, [OTMChannel<Packet>; 3]) {
+    // ENDPOINT variables:
+
+    // endpoints for packets between the subsystems' serial port relays
+    let to_snc = Arc::new(Mutex::new(Buffer::new()));
+    let to_ss = Arc::new(Mutex::new(Buffer::new()));
+    let to_mdps = Arc::new(Mutex::new(Buffer::new()));
+
+    // endpoints for packets going to the session recorder, one per sender (which passes
+    // the packets on to the kit)
+    let to_recorder_snc = Arc::new(Mutex::new(Buffer::new()));
+    let to_recorder_ss = Arc::new(Mutex::new(Buffer::new()));
+    let to_recorder_mdps = Arc::new(Mutex::new(Buffer::new()));
+    let to_recorder_hub = Arc::new(Mutex::new(Buffer::new()));
+    let to_recorder_positions = Arc::new(Mutex::new(Buffer::new()));
+    let to_recorder_captured = Arc::new(Mutex::new(Buffer::new()));
+
+    // endpoint for the hub's own packets (nothing is ever sent back to the hub on this channel)
+    let to_hub = Arc::new(Mutex::new(Buffer::new()));
+
+    // CHANNEL variables:
+
+    // packet channels (each subsystem to the recorder first, and to the other two):
+    let snc_channel = OTMChannel::with_endpoints(
+        "SNC",
+        &to_snc,
+        vec![&to_recorder_snc, &to_ss, &to_mdps],
+        Bound::Inifinity,
+    );
+    let ss_channel = OTMChannel::with_endpoints(
+        "SS",
+        &to_ss,
+        vec![&to_recorder_ss, &to_snc, &to_mdps],
+        Bound::Inifinity,
+    );
+    let mdps_channel = OTMChannel::with_endpoints(
+        "MDPS",
+        &to_mdps,
+        vec![&to_recorder_mdps, &to_snc, &to_ss],
+        Bound::Inifinity,
+    );
+
+    // the hub's channel, to the recorder first, and to every subsystem:
+    let hub_channel = OTMChannel::with_endpoints(
+        "Hub",
+        &to_hub,
+        vec![&to_recorder_hub, &to_snc, &to_ss, &to_mdps],
+        Bound::Inifinity,
+    );
+
+    // the positions of the sensors of a physical MARV are not known, so none are recorded
+    let mut session_recorder = SessionRecorder::new(
+        recording,
+        vec![
+            (
+                Subsystem::Snc,
+                OTMChannel::new("Recorder (SNC)", &to_recorder_snc, Bound::Inifinity),
+            ),
+            (
+                Subsystem::Ss,
+                OTMChannel::new("Recorder (SS)", &to_recorder_ss, Bound::Inifinity),
+            ),
+            (
+                Subsystem::Mdps,
+                OTMChannel::new("Recorder (MDPS)", &to_recorder_mdps, Bound::Inifinity),
+            ),
+            (
+                Subsystem::Hub,
+                OTMChannel::new("Recorder (Hub)", &to_recorder_hub, Bound::Inifinity),
+            ),
+        ],
+        OTMChannel::new(
+            "Recorder (Positions)",
+            &to_recorder_positions,
+            Bound::Finite(1),
+        ),
+        OTMChannel::with_endpoints(
+            "Recorder (Captured)",
+            &to_recorder_captured,
+            vec![to_hub_captured],
+            Bound::Inifinity,
+        ),
+        Arc::clone(running),
+    );
+
+    std::thread::spawn(move || session_recorder.run());
+
+    (hub_channel, [snc_channel, ss_channel, mdps_channel])
+}
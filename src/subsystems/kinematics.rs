@@ -0,0 +1,73 @@
+//! # Kinematics
+//!
+//! The motion of the MARV as a differential drive: two wheels on a common axle, each
+//! turning at its own speed. Both the MDPS (measuring its rotation and distance from
+//! its wheels) and the sensor positions computer (moving the MARV through the maze)
+//! use these functions, so that they agree on how far the MARV moved and turned.
+//!
+//! Everything is in the maze's frame, which is drawn with y pointing down. A heading
+//! of 0 faces along x, and the heading increases as the MARV turns clockwise on the
+//! screen, i.e. to its right. Any unit of length can be used, as long as the speeds,
+//! distances and axle length all use the same one.
+
+/// headings that change by less than this (in rad) over a step are treated as a straight line
+const STRAIGHT_LINE_ROTATION: f32 = 1e-6;
+
+/// the speed of the MARV's centre, given the speeds of its left and right wheels
+pub fn linear_velocity(left_speed: f32, right_speed: f32) -> f32 {
+    (left_speed + right_speed) / 2.0
+}
+
+/// the rate (in rad per unit of time) at which the MARV's heading changes, given the
+/// speeds of its left and right wheels, positive when it turns right
+pub fn angular_velocity(left_speed: f32, right_speed: f32, axle_length: f32) -> f32 {
+    (left_speed - right_speed) / axle_length
+}
+
+/// the distance that the MARV's centre travelled, given the distances that its left and
+/// right wheels travelled
+pub fn distance(left_distance: f32, right_distance: f32) -> f32 {
+    linear_velocity(left_distance, right_distance)
+}
+
+/// the angle (in rad) that the MARV turned, given the distances that its left and right
+/// wheels travelled, positive when it turned right
+pub fn rotation(left_distance: f32, right_distance: f32, axle_length: f32) -> f32 {
+    angular_velocity(left_distance, right_distance, axle_length)
+}
+
+/// returns the position and heading of the MARV after its wheels have turned at
+/// `left_speed` and `right_speed` for `time`, starting at `position` facing `heading`
+///
+/// The speeds are constant over the step, so the MARV's centre follows an arc of a
+/// circle (or a straight line) exactly, however long the step is.
+pub fn integrate(
+    position: (f32, f32),
+    heading: f32,
+    left_speed: f32,
+    right_speed: f32,
+    axle_length: f32,
+    time: f32,
+) -> ((f32, f32), f32) {
+    let linear_velocity = linear_velocity(left_speed, right_speed);
+    let angular_velocity = angular_velocity(left_speed, right_speed, axle_length);
+    let new_heading = heading + angular_velocity * time;
+
+    let (dx, dy) = if (angular_velocity * time).abs() < STRAIGHT_LINE_ROTATION {
+        // the midpoint heading keeps this accurate for the tiniest of turns
+        let midpoint = (heading + new_heading) / 2.0;
+        let distance = linear_velocity * time;
+
+        (distance * midpoint.cos(), distance * midpoint.sin())
+    } else {
+        // the centre moves along an arc around the instantaneous centre of rotation
+        let radius = linear_velocity / angular_velocity;
+
+        (
+            radius * (new_heading.sin() - heading.sin()),
+            radius * (heading.cos() - new_heading.cos()),
+        )
+    };
+
+    ((position.0 + dx, position.1 + dy), new_heading)
+}
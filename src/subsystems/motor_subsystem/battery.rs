@@ -0,0 +1,103 @@
+//! # Battery model
+//!
+//! Battery voltage sensing was removed from the MDPS in 2022, so by default the
+//! emulated MDPS sends zeros in its battery level packets (97 and 161). When a
+//! `Battery` is given to the emulation, the MDPS reports the level of this model
+//! instead, as the older protocol revisions required. The battery drains as the
+//! wheels turn, and its voltage sags under load, which limits the speed that the
+//! motors can reach.
+
+/// A battery powering the emulated MARV
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Battery {
+    /// the charge (in mAh) of a full battery
+    pub capacity: f32,
+    /// the charge (in mAh) left in the battery
+    pub charge: f32,
+    /// the open-circuit voltage (in V) of a full battery
+    pub full_voltage: f32,
+    /// the open-circuit voltage (in V) of an empty battery
+    pub empty_voltage: f32,
+    /// the voltage (in V) at which the motors reach their commanded speed
+    pub rated_voltage: f32,
+    /// the internal resistance (in Ω) of the battery, which makes its voltage sag under load
+    pub internal_resistance: f32,
+    /// the current (in mA) drawn by the MARV while standing still
+    pub idle_current: f32,
+    /// the current (in mA) drawn by a motor per mm/s of wheel speed
+    pub current_per_speed: f32,
+    /// the current (in mA) that was last drawn from the battery
+    load: f32,
+}
+
+impl Battery {
+    /// a full two cell lithium battery
+    pub fn new() -> Self {
+        Self {
+            capacity: 1000.0,
+            charge: 1000.0,
+            full_voltage: 8.4,
+            empty_voltage: 6.0,
+            rated_voltage: 7.4,
+            internal_resistance: 0.3,
+            idle_current: 150.0,
+            current_per_speed: 5.0,
+            load: 0.0,
+        }
+    }
+
+    /// the fraction of the battery's charge that is left
+    pub fn level(&self) -> f32 {
+        if self.capacity > 0.0 {
+            (self.charge / self.capacity).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// the battery level as a percentage, as sent in DAT1 of the battery level packets
+    pub fn percentage(&self) -> u8 {
+        (self.level() * 100.0).round() as u8
+    }
+
+    /// the voltage (in V) at the battery's terminals under the last load
+    pub fn voltage(&self) -> f32 {
+        if self.charge <= 0.0 {
+            return 0.0;
+        }
+
+        let open_circuit =
+            self.empty_voltage + (self.full_voltage - self.empty_voltage) * self.level();
+
+        (open_circuit - self.load / 1000.0 * self.internal_resistance).max(0.0)
+    }
+
+    /// the terminal voltage in tenths of a volt, as sent in DAT0 of the battery level packets
+    pub fn decivolts(&self) -> u8 {
+        (self.voltage() * 10.0).round().min(u8::MAX as f32) as u8
+    }
+
+    /// the fraction of their commanded speed that the motors can reach at the present voltage
+    pub fn speed_factor(&self) -> f32 {
+        if self.rated_voltage > 0.0 {
+            (self.voltage() / self.rated_voltage).clamp(0.0, 1.0)
+        } else {
+            1.0
+        }
+    }
+
+    /// drains the battery for `time` seconds with the wheels turning at `left_speed` and
+    /// `right_speed` (in mm/s)
+    pub fn drain(&mut self, left_speed: f32, right_speed: f32, time: f32) {
+        self.load =
+            self.idle_current + self.current_per_speed * (left_speed.abs() + right_speed.abs());
+
+        self.charge = (self.charge - self.load * time / 3600.0).max(0.0);
+    }
+}
+
+impl Default for Battery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,16 +1,40 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+
 use crate::{
     asynchronous::{one_to_many_channel::OTMChannel, one_to_one_channel::OTOChannel},
     components::{
         adjacent_bytes::AdjacentBytes,
         buffer::BufferUser,
         comm_port::ControlByte,
-        constants::{CAL_BATTERY_LEVEL, CAL_OPERATIONAL_VELOCITY, MAZE_BATTERY_LEVEL, SOS_SPEED},
+        constants::{CAL_BATTERY_LEVEL, MAZE_BATTERY_LEVEL, MAZE_END_OF_MAZE, SOS_SPEED},
         packet::Packet,
         state::SystemState,
     },
-    subsystems::{motor_subsystem::wheel::Wheels, sensor_positions::Speeds},
+    subsystems::{
+        motor_subsystem::{
+            rotation_control::{Rotation, RotationControl},
+            wheel::Wheels,
+        },
+        sensor_positions::Speeds,
+    },
 };
 
+/// the longest time that the MDPS may take to calibrate its wheel speeds
+const CALIBRATION_TIMEOUT: Duration = Duration::from_secs(60);
+/// the time that the wheels are given to settle at a speed before it is measured
+const CALIBRATION_SETTLE_TIME: Duration = Duration::from_millis(200);
+/// calibration is complete once both wheel speeds are within this fraction of vop
+const CALIBRATION_ACCURACY: f32 = 0.01;
+/// the smallest and largest trims of a motor's commanded speed
+const MIN_TRIM: f32 = 0.1;
+const MAX_TRIM: f32 = 10.0;
+
 /**
     # Motor-driver and Power Subsystem (MDPS) struct
     Provides a way to emulate the MDPS
@@ -26,6 +50,13 @@ pub struct Mdps {
     /// The desired operating velocity during maze navigation
     operational_velocity: u8,
     speed_comms: OTOChannel<Speeds>,
+    /// How rotate instructions are carried out
+    rotation_control: RotationControl,
+    /// The rotation (in degrees) achieved by the last rotate instruction, and the
+    /// DEC of that instruction
+    last_rotation: (u16, u8),
+    /// Cleared once the run has ended, after which the MDPS stops waiting for packets
+    running: Arc<AtomicBool>,
 }
 
 impl Mdps {
@@ -33,20 +64,137 @@ impl Mdps {
     ///
     /// If the test kit is running with one or more real subsystems, commport will be Some(..), otherwise it
     /// must be None
-    pub fn new(comms: OTMChannel<Packet>, speed_comms: OTOChannel<Speeds>, wheels: Wheels) -> Self {
+    pub fn new(
+        comms: OTMChannel<Packet>,
+        speed_comms: OTOChannel<Speeds>,
+        wheels: Wheels,
+        rotation_control: RotationControl,
+        running: Arc<AtomicBool>,
+    ) -> Self {
         Self {
             wheels,
             state: SystemState::Idle,
             operational_velocity: 0,
             comms,
             speed_comms,
+            rotation_control,
+            last_rotation: (0, 2),
+            running,
+        }
+    }
+
+    /// Whether the run has ended without the MDPS reaching the end of the maze
+    fn stopped(&self) -> bool {
+        !self.running.load(Ordering::Relaxed)
+    }
+
+    /// Calibrates the tangential wheel speeds to the operational velocity and returns the
+    /// measured (left, right) wheel speeds (in mm/s)
+    ///
+    /// Each motor's commanded speed is trimmed until its wheel turns at vop, the trims are then
+    /// kept for the rest of the run. The MARV is not moved in the maze while calibrating.
+    fn calibrate(&mut self) -> (f32, f32) {
+        let target = self.operational_velocity as f32;
+        let start = SystemTime::now();
+        let (mut left_trim, mut right_trim) = (1.0, 1.0);
+
+        self.wheels
+            .set_left_wheel_speed(self.operational_velocity as i16);
+        self.wheels
+            .set_right_wheel_speed(self.operational_velocity as i16);
+
+        let speeds = loop {
+            self.wheels.set_trims(left_trim, right_trim);
+            self.turn_wheels_for(CALIBRATION_SETTLE_TIME);
+
+            let (left, right) = self.wheels.actual_speeds();
+            let accurate = |speed: f32| (speed - target).abs() <= target * CALIBRATION_ACCURACY;
+            // a wheel that is not at vop at the smallest or largest trim never will be (e.g.
+            // if vop is beyond the motor's maximum speed)
+            let unreachable =
+                |trim: f32, speed: f32| !accurate(speed) && (trim == MIN_TRIM || trim == MAX_TRIM);
+
+            if (accurate(left) && accurate(right))
+                || unreachable(left_trim, left)
+                || unreachable(right_trim, right)
+                || start.elapsed().unwrap_or_default() > CALIBRATION_TIMEOUT
+                || self.stopped()
+            {
+                break (left, right);
+            }
+
+            left_trim = retrim(left_trim, target, left);
+            right_trim = retrim(right_trim, target, right);
+        };
+
+        self.wheels.set_left_wheel_speed(0);
+        self.wheels.set_right_wheel_speed(0);
+
+        while !self.wheels.is_stationary()
+            && start.elapsed().unwrap_or_default() < CALIBRATION_TIMEOUT
+        {
+            self.turn_wheels_for(Duration::from_millis(10));
+        }
+
+        self.wheels.reset_measurements();
+
+        speeds
+    }
+
+    /// Keeps updating the wheels for `duration` without moving the MARV in the maze
+    fn turn_wheels_for(&mut self, duration: Duration) {
+        let start = SystemTime::now();
+
+        while start.elapsed().unwrap_or_default() < duration {
+            self.wheels.update_distance();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Returns the battery level packet with the control byte of `no_battery`
+    ///
+    /// If a battery is emulated, DAT1 holds its level as a percentage and DAT0 its voltage
+    /// in tenths of a volt, otherwise `no_battery` (all zeros as required since 2022) is returned
+    fn battery_level(&self, no_battery: [u8; 4]) -> [u8; 4] {
+        match self.wheels.battery() {
+            Some(battery) => [no_battery[0], battery.percentage(), battery.decivolts(), 0],
+            None => no_battery,
+        }
+    }
+
+    /// Turns the MARV on the spot by `target_rotation` degrees, left if `dec` is 2 and
+    /// right if it is 3, and leaves it standing still
+    ///
+    /// The wheels slow down as the rotation nears its target, and the rotation is abandoned
+    /// if it takes longer than the timeout (e.g. when the motors cannot overcome their
+    /// deadband). The rotation that was actually achieved is kept for the 162 packet.
+    fn rotate(&mut self, target_rotation: u16, dec: u8) {
+        let control = self.rotation_control;
+        let speed_comms = &self.speed_comms;
+
+        let rotation = control.turn(
+            &mut self.wheels,
+            self.operational_velocity,
+            target_rotation as f32,
+            dec == 2,
+            |wheels| speed_comms.send(wheels.ground_speeds()),
+        );
+
+        // the 162 packet reports how far the MARV got before it gave up
+        if let Rotation::TimedOut(achieved) = rotation {
+            println!(
+                "MDPS rotation timed out at {:.1} of {} degrees",
+                achieved, target_rotation
+            );
         }
+
+        self.last_rotation = (rotation.degrees().round() as u16, dec);
     }
 
     pub fn run(&mut self) {
         let mut end_of_maze = false;
 
-        while !end_of_maze {
+        while !end_of_maze && !self.stopped() {
             match self.state {
                 SystemState::Idle => {
                     /* Idle things */
@@ -64,15 +212,20 @@ impl Mdps {
                     /* Calibration things */
                     self.wait_for_packet(112.into());
 
-                    self.write(CAL_OPERATIONAL_VELOCITY);
-                    self.write(CAL_BATTERY_LEVEL);
+                    if self.stopped() {
+                        break;
+                    }
 
-                    self.wait_for_packet(113.into());
+                    // calibrate the wheel speeds and report the measured speeds
+                    let (left, right) = self.calibrate();
+                    self.write([96, to_speed_byte(right), to_speed_byte(left), 0]);
+                    self.write(self.battery_level(CAL_BATTERY_LEVEL));
 
-                    while self.wait_for_packet(80.into()).dat1() != 1 {
+                    // the SS's colours (113) and the SNC's touch (80) may arrive in either
+                    // order, so only the touch is waited for
+                    while self.wait_for_packet(80.into()).dat1() != 1 && !self.stopped() {
                         /* wait for go to Maze state */
-                        self.write(CAL_BATTERY_LEVEL);
-                        self.wait_for_packet(113.into());
+                        self.write(self.battery_level(CAL_BATTERY_LEVEL));
                     }
 
                     self.state = SystemState::Maze;
@@ -94,8 +247,6 @@ impl Mdps {
                         }
                         ControlByte::MazeNavInstructions => {
                             // println!("{:?}", packet);
-                            let mut rotating = false;
-
                             let (left, right) = (packet.dat1(), packet.dat0());
 
                             match packet.dec() {
@@ -107,78 +258,34 @@ impl Mdps {
                                     self.wheels.set_left_wheel_speed(-(left as i16));
                                     self.wheels.set_right_wheel_speed(-(right as i16));
                                 }
-                                2 => {
-                                    self.wheels
-                                        .set_left_wheel_speed(self.operational_velocity as i16);
-                                    self.wheels
-                                        .set_right_wheel_speed(-(self.operational_velocity as i16));
+                                2 | 3 => {
+                                    let target_rotation: u16 =
+                                        AdjacentBytes::make(packet.dat1(), packet.dat0()).into();
 
-                                    rotating = true;
-                                }
-                                3 => {
-                                    self.wheels
-                                        .set_left_wheel_speed(-(self.operational_velocity as i16));
-                                    self.wheels
-                                        .set_right_wheel_speed(self.operational_velocity as i16);
-
-                                    rotating = true;
+                                    self.rotate(target_rotation, packet.dec());
                                 }
                                 _ => (),
                             };
 
                             self.wheels.update_distance();
 
-                            if rotating {
-                                let target_rotation: u16 =
-                                    AdjacentBytes::make(packet.dat1(), packet.dat0()).into();
-
-                                while self.wheels.get_rotation() < target_rotation {
-                                    self.wheels.update_distance();
-
-                                    let left_speed = self.wheels.get_left();
-                                    let right_speed = self.wheels.get_right();
-
-                                    self.speed_comms
-                                        .send(Speeds::new(left_speed as f32, right_speed as f32));
-
-                                    // match wheel_speeds
-                                    //     .try_send((self.wheels.get_left(), self.wheels.get_right()))
-                                    // {
-                                    //     Ok(_) => println!(
-                                    //         "successfully sent wheel speeds to calculator thread"
-                                    //     ),
-                                    //     Err(e) => {
-                                    //         println!("INFO: mdps run thread could not send data to sensor positions calculator thread, {}", e);
-                                    //         std::thread::sleep(Duration::from_millis(10));
-                                    //     }
-                                    // }
-                                }
-                            }
-
-                            let left_speed = self.wheels.get_left();
-                            let right_speed = self.wheels.get_right();
-
-                            self.speed_comms
-                                .send(Speeds::new(left_speed as f32, right_speed as f32));
+                            self.speed_comms.send(self.wheels.ground_speeds());
 
                             // wheel_speeds.send((self.wheels.get_left(), self.wheels.get_right())).expect("FATAL: mdps run thread could not send data to sensor positions calculator thread");
 
-                            // write battery level (no longer required as of 2022, so just send 0's)
-                            self.write(MAZE_BATTERY_LEVEL);
+                            // write battery level (no longer required as of 2022, so 0's unless a battery is emulated)
+                            self.write(self.battery_level(MAZE_BATTERY_LEVEL));
 
-                            // get final rotation
-                            let curr_rotation = self.wheels.get_rotation();
+                            // write the rotation achieved by the last rotate instruction
+                            let (last_rotation, last_rotation_dec) = self.last_rotation;
 
-                            let rotation_bytes = AdjacentBytes::from(curr_rotation);
+                            let rotation_bytes = AdjacentBytes::from(last_rotation);
 
                             self.write([
                                 162,
-                                rotation_bytes.lsb(),
                                 rotation_bytes.msb(),
-                                match self.wheels.left_rotation() {
-                                    true => 2,
-                                    false => 3,
-                                },
+                                rotation_bytes.lsb(),
+                                last_rotation_dec,
                             ]);
 
                             // write speed
@@ -214,7 +321,7 @@ impl Mdps {
                     self.write(SOS_SPEED);
 
                     // wait for clap/snap
-                    while self.wait_for_packet(208.into()).dat1() != 1 {
+                    while self.wait_for_packet(208.into()).dat1() != 1 && !self.stopped() {
                         // (do nothing / wait for end of SOS)
                     }
 
@@ -227,22 +334,47 @@ impl Mdps {
     }
 }
 
+/// Returns the trim that should bring a wheel measured at `measured` mm/s to `target` mm/s
+fn retrim(trim: f32, target: f32, measured: f32) -> f32 {
+    let trim = if measured > 0.0 {
+        trim * target / measured
+    } else {
+        // the wheel is not turning at all (e.g. stuck in the motor's deadband)
+        trim * 1.5
+    };
+
+    trim.clamp(MIN_TRIM, MAX_TRIM)
+}
+
+/// Rounds a wheel speed to the byte sent in a packet
+fn to_speed_byte(speed: f32) -> u8 {
+    speed.abs().round().min(u8::MAX as f32) as u8
+}
+
 impl BufferUser for Mdps {
     /// writes to the output buffer
     fn write(&mut self, data: [u8; 4]) {
         self.comms.send(data.into());
     }
 
-    /// reads from the input buffer
+    /// reads from the input buffer, or returns the end of the maze once the run has ended
     fn read(&mut self) -> Packet {
-        self.comms.receive()
+        loop {
+            if let Ok(packet) = self.comms.try_receive() {
+                return packet;
+            }
+
+            if self.stopped() {
+                return MAZE_END_OF_MAZE.into();
+            }
+        }
     }
 
     fn wait_for_packet(&mut self, control_byte: ControlByte) -> Packet {
         loop {
             let packet = self.read();
 
-            if packet.control_byte() == control_byte {
+            if packet.control_byte() == control_byte || self.stopped() {
                 return packet;
             }
         }
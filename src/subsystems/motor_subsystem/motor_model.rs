@@ -0,0 +1,92 @@
+//! # Motor model
+//!
+//! Describes how far the emulated motors are from a perfect robot. With
+//! `MotorModel::ideal()` the wheels reach their commanded speed instantly and
+//! the encoders measure exactly what the wheels did, which is how the emulated
+//! MDPS has always behaved.
+
+/// The parameters of the motor dynamics used by `Wheels`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotorModel {
+    /// the largest change in wheel speed per second (in mm/s²), or `None` if a
+    /// wheel reaches its commanded speed instantly
+    pub acceleration: Option<f32>,
+    /// commanded speeds (in mm/s) with a smaller magnitude than this do not
+    /// turn the motor at all
+    pub deadband: f32,
+    /// the speed that the left motor achieves relative to the commanded speed
+    pub left_gain: f32,
+    /// the speed that the right motor achieves relative to the commanded speed
+    pub right_gain: f32,
+    /// the fraction of a wheel's speed that is lost to slip on the maze surface,
+    /// the encoders still measure the full speed of the wheel
+    pub slip: f32,
+    /// the distance (in mm) that a wheel travels per encoder tick, or `None` for
+    /// perfect encoders
+    pub encoder_resolution: Option<f32>,
+}
+
+impl MotorModel {
+    /// a perfect robot
+    pub fn ideal() -> Self {
+        Self {
+            acceleration: None,
+            deadband: 0.0,
+            left_gain: 1.0,
+            right_gain: 1.0,
+            slip: 0.0,
+            encoder_resolution: None,
+        }
+    }
+
+    /// roughly what a robot built from the EPR 320 kit does
+    pub fn realistic() -> Self {
+        Self {
+            acceleration: Some(300.0),
+            deadband: 8.0,
+            left_gain: 0.97,
+            right_gain: 1.0,
+            slip: 0.03,
+            encoder_resolution: Some(5.0),
+        }
+    }
+
+    /// returns the new speed of a wheel (in mm/s) after `time` seconds, when it was
+    /// turning at `speed` and is being driven at `commanded` by a motor with `gain`
+    pub fn step_speed(&self, speed: f32, commanded: f32, gain: f32, time: f32) -> f32 {
+        let target = if commanded.abs() < self.deadband {
+            0.0
+        } else {
+            commanded * gain
+        };
+
+        match self.acceleration {
+            Some(acceleration) => {
+                let max_change = acceleration * time;
+                speed + (target - speed).clamp(-max_change, max_change)
+            }
+            None => target,
+        }
+    }
+
+    /// the speed (in mm/s) at which the robot moves over the ground when its wheel
+    /// turns at `wheel_speed`
+    pub fn ground_speed(&self, wheel_speed: f32) -> f32 {
+        wheel_speed * (1.0 - self.slip)
+    }
+
+    /// the distance (in mm) that an encoder reports for a wheel that has travelled
+    /// `distance`
+    pub fn measured_distance(&self, distance: f32) -> f32 {
+        match self.encoder_resolution {
+            Some(resolution) if resolution > 0.0 => (distance / resolution).trunc() * resolution,
+            _ => distance,
+        }
+    }
+}
+
+impl Default for MotorModel {
+    fn default() -> Self {
+        Self::ideal()
+    }
+}
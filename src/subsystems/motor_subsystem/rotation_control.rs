@@ -0,0 +1,148 @@
+//! # Rotation control
+//!
+//! The emulated MDPS executes a rotate instruction as a controlled turn: the
+//! wheels turn at the operational velocity until the robot nears the target, then
+//! slow down so that the turn ends within a tolerance of the target instead of
+//! overshooting it.
+
+use std::time::{Duration, SystemTime};
+
+use super::wheel::Wheels;
+
+/// The parameters of the closed-loop turn used by `Mdps`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RotationControl {
+    /// a rotation is complete once it is within this many degrees of the target
+    pub tolerance: f32,
+    /// the number of degrees before the target at which the wheels start slowing down
+    pub slow_down_angle: f32,
+    /// the slowest speed (in mm/s) that the wheels are driven at near the target
+    pub min_speed: f32,
+    /// a rotation that takes longer than this is abandoned
+    pub timeout: Duration,
+}
+
+/// How a rotation ended, with the rotation (in degrees) that was actually achieved
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rotation {
+    /// the rotation reached its target, within the tolerance
+    Complete(f32),
+    /// the rotation was abandoned at the timeout, e.g. because the motors could not
+    /// overcome their deadband near the target
+    TimedOut(f32),
+}
+
+impl Rotation {
+    /// the rotation (in degrees) that was actually achieved
+    pub fn degrees(&self) -> f32 {
+        match self {
+            Rotation::Complete(degrees) | Rotation::TimedOut(degrees) => *degrees,
+        }
+    }
+}
+
+impl RotationControl {
+    /// returns the speed (in mm/s) to drive the wheels at when `remaining` degrees
+    /// of the rotation are left
+    pub fn wheel_speed(&self, operational_velocity: f32, remaining: f32) -> f32 {
+        let fraction = if self.slow_down_angle > 0.0 {
+            (remaining / self.slow_down_angle).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+
+        (operational_velocity * fraction)
+            .max(self.min_speed)
+            .min(operational_velocity)
+    }
+
+    /// whether a rotation of `achieved` degrees is close enough to `target`
+    pub fn is_complete(&self, target: f32, achieved: f32) -> bool {
+        target - achieved <= self.tolerance
+    }
+
+    /// turns `wheels` on the spot by `target` degrees (left if `left`, otherwise right) at
+    /// up to `operational_velocity`, and leaves them standing still
+    ///
+    /// `moved` is called with the wheels every time that they have turned, and the
+    /// rotation is measured from where they were when the turn started.
+    pub fn turn<F>(
+        &self,
+        wheels: &mut Wheels,
+        operational_velocity: u8,
+        target: f32,
+        left: bool,
+        mut moved: F,
+    ) -> Rotation
+    where
+        F: FnMut(&Wheels),
+    {
+        let direction = match left {
+            true => 1,
+            false => -1,
+        };
+        let start = SystemTime::now();
+        let timed_out = || start.elapsed().unwrap_or_default() > self.timeout;
+
+        wheels.set_left_wheel_speed(0);
+        wheels.set_right_wheel_speed(0);
+        wheels.update_distance();
+        wheels.reset_measurements();
+
+        let complete = loop {
+            let achieved = wheels.rotation_degrees();
+
+            if self.is_complete(target, achieved) {
+                break true;
+            }
+
+            if timed_out() {
+                break false;
+            }
+
+            let speed = self
+                .wheel_speed(operational_velocity as f32, target - achieved)
+                .round() as i16;
+
+            // turning left drives the right wheel forward and the left wheel backward
+            wheels.set_left_wheel_speed(-direction * speed);
+            wheels.set_right_wheel_speed(direction * speed);
+
+            std::thread::sleep(Duration::from_millis(1));
+
+            wheels.update_distance();
+            moved(wheels);
+        };
+
+        // come to a standstill, the rotation keeps being measured while the wheels slow down
+        wheels.set_left_wheel_speed(0);
+        wheels.set_right_wheel_speed(0);
+
+        loop {
+            wheels.update_distance();
+            moved(wheels);
+
+            if wheels.is_stationary() || timed_out() {
+                break;
+            }
+
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        match complete {
+            true => Rotation::Complete(wheels.rotation_degrees()),
+            false => Rotation::TimedOut(wheels.rotation_degrees()),
+        }
+    }
+}
+
+impl Default for RotationControl {
+    fn default() -> Self {
+        Self {
+            tolerance: 1.0,
+            slow_down_angle: 20.0,
+            min_speed: 10.0,
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
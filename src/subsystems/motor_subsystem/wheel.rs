@@ -1,29 +1,59 @@
 use std::{f32::consts::PI, time::SystemTime};
 
-use crate::components::constants;
+use crate::{
+    components::robot_config::RobotConfig,
+    subsystems::{kinematics, sensor_positions::Speeds},
+};
+
+use super::{battery::Battery, motor_model::MotorModel};
 
 #[derive(Debug)]
 pub struct Wheels {
+    /// commanded speeds (mm/s)
     left_speed: i16,
     right_speed: i16,
+    /// factors that the commanded speeds are multiplied by, set during calibration
+    left_trim: f32,
+    right_trim: f32,
+    /// speeds (mm/s) that the wheels are actually turning at
+    left_actual: f32,
+    right_actual: f32,
+    /// distances (mm) that each wheel has turned since the last stop
     left_distance: f32,
     right_distance: f32,
+    /// rotation (rad) and distance (mm) since the last stop, as measured by the encoders
     rotation: f32,
     total_distance: f32,
-    _axle_dist: f32,
+    /// whether both wheels have been commanded to stop
+    stopped: bool,
+    /// the distance between the wheels (mm)
+    axle_length: f32,
+    /// the fastest that a wheel can turn (mm/s)
+    max_speed: f32,
+    motor_model: MotorModel,
+    /// the battery powering the motors, `None` if it is not emulated
+    battery: Option<Battery>,
     time: SystemTime,
 }
 
 impl Wheels {
-    pub fn new(axle_distance: f32) -> Self {
+    pub fn new(robot: &RobotConfig, motor_model: MotorModel, battery: Option<Battery>) -> Self {
         Self {
             left_speed: 0,
             right_speed: 0,
+            left_trim: 1.0,
+            right_trim: 1.0,
+            left_actual: 0.0,
+            right_actual: 0.0,
             left_distance: 0.0,
             right_distance: 0.0,
             rotation: 0.0,
             total_distance: 0.0,
-            _axle_dist: axle_distance,
+            stopped: true,
+            axle_length: robot.axle_length,
+            max_speed: robot.max_speed,
+            motor_model,
+            battery,
             time: SystemTime::now(),
         }
     }
@@ -36,12 +66,25 @@ impl Wheels {
         self.right_speed = speed;
     }
 
+    /// sets the factors that the commanded speeds of the (left, right) motors are multiplied by
+    pub fn set_trims(&mut self, left: f32, right: f32) {
+        self.left_trim = left;
+        self.right_trim = right;
+    }
+
+    /// the measured (left, right) wheel speeds (mm/s)
+    pub fn actual_speeds(&self) -> (f32, f32) {
+        (self.left_actual, self.right_actual)
+    }
+
+    /// the measured speed of the left wheel (mm/s)
     pub fn get_left_wheel_speed(&self) -> u8 {
-        self.left_speed.unsigned_abs() as u8
+        self.left_actual.abs().round().min(u8::MAX as f32) as u8
     }
 
+    /// the measured speed of the right wheel (mm/s)
     pub fn get_right_wheel_speed(&self) -> u8 {
-        self.right_speed.unsigned_abs() as u8
+        self.right_actual.abs().round().min(u8::MAX as f32) as u8
     }
 
     pub fn going_forward(&self) -> bool {
@@ -57,7 +100,17 @@ impl Wheels {
     }
 
     pub fn get_rotation(&self) -> u16 {
-        (self.rotation.abs() * (180.0 / PI)).floor() as u16
+        self.rotation_degrees().floor() as u16
+    }
+
+    /// the magnitude of the rotation (in degrees) since the last stop
+    pub fn rotation_degrees(&self) -> f32 {
+        self.rotation.abs() * (180.0 / PI)
+    }
+
+    /// whether both wheels have actually come to a standstill
+    pub fn is_stationary(&self) -> bool {
+        self.left_actual == 0.0 && self.right_actual == 0.0
     }
 
     pub fn get_left(&self) -> i16 {
@@ -68,33 +121,75 @@ impl Wheels {
         self.right_speed
     }
 
+    /// the speeds at which the robot is actually moving over the ground, i.e. what
+    /// determines where the robot ends up
+    pub fn ground_speeds(&self) -> Speeds {
+        Speeds::new(
+            self.motor_model.ground_speed(self.left_actual),
+            self.motor_model.ground_speed(self.right_actual),
+        )
+    }
+
+    /// the battery powering the motors, if it is emulated
+    pub fn battery(&self) -> Option<&Battery> {
+        self.battery.as_ref()
+    }
+
     pub fn update_distance(&mut self) {
+        // get the elapsed time and reset it
+        let time = self.time.elapsed().unwrap().as_secs_f32();
+        self.time = SystemTime::now();
+
+        // a sagging battery cannot drive the motors at their full commanded speed
+        let supply = match &mut self.battery {
+            Some(battery) => {
+                battery.drain(self.left_actual, self.right_actual, time);
+                battery.speed_factor()
+            }
+            None => 1.0,
+        };
+
+        // move the wheels' speeds toward the commanded speeds, which the motors can only reach
+        // up to their maximum speed
+        let max_speed = self.max_speed;
+        let limit = |speed: f32| speed.clamp(-max_speed, max_speed);
+
+        self.left_actual = self.motor_model.step_speed(
+            self.left_actual,
+            limit(self.left_speed as f32 * self.left_trim * supply),
+            self.motor_model.left_gain,
+            time,
+        );
+        self.right_actual = self.motor_model.step_speed(
+            self.right_actual,
+            limit(self.right_speed as f32 * self.right_trim * supply),
+            self.motor_model.right_gain,
+            time,
+        );
+
         if self.left_speed == 0 && self.right_speed == 0 {
-            self.reset_fields();
-        } else {
-            // get the elapsed time and reset it
-            let time = self.time.elapsed().unwrap().as_secs_f32();
-            self.time = SystemTime::now();
-
-            // store speeds as f32 so that the conversion from i16 only has to be done once
-            let left_speed = self.left_speed as f32;
-            let right_speed = self.right_speed as f32;
-
-            // calculate the linear and angular velocity of the robot
-            let linear_speed = (left_speed + right_speed) / 2.0;
-            let angular_velocity = (right_speed - left_speed) / (constants::AXLE_DIST as f32);
-
-            // update the distances that each wheel has travelled respectively (first order numerical integration / rectangle rule)
-            self.left_distance += time * left_speed;
-            self.right_distance += time * right_speed;
-
-            // update the total linear distance travelled by the robot and its angle/rotation (first order numerical integration / rectangle rule)
-            self.total_distance += time * linear_speed;
-            self.rotation += time * angular_velocity;
+            self.stopped = true;
+        } else if self.stopped {
+            // the distance and rotation are reported since the last stop, so they
+            // start over once the robot sets off again
+            self.reset_measurements();
+            self.stopped = false;
         }
+
+        // update the distances that each wheel has travelled respectively (first order numerical integration / rectangle rule)
+        self.left_distance += time * self.left_actual;
+        self.right_distance += time * self.right_actual;
+
+        // the MDPS only knows what its encoders measured
+        let left_measured = self.motor_model.measured_distance(self.left_distance);
+        let right_measured = self.motor_model.measured_distance(self.right_distance);
+
+        self.total_distance = kinematics::distance(left_measured, right_measured);
+        self.rotation = kinematics::rotation(left_measured, right_measured, self.axle_length);
     }
 
-    fn reset_fields(&mut self) {
+    /// starts measuring the distance and rotation over from zero
+    pub fn reset_measurements(&mut self) {
         self.left_distance = 0.0;
         self.rotation = 0.0;
         self.right_distance = 0.0;
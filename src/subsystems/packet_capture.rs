@@ -0,0 +1,199 @@
+//! # Packet capture
+//!
+//! A complete capture of the packets sent during a run, with when each packet was sent,
+//! who sent it, who it was delivered to and what it means, which can be exported for
+//! teams to analyse their traffic in a spreadsheet or to attach to their lab reports.
+//! Two formats are supported:
+//!
+//! - CSV, with a header row and one packet per row
+//! - JSONL, with one JSON object per packet, one per line
+
+use std::{fmt::Write, fs, io, path::Path};
+
+use crate::components::envelope::PacketEnvelope;
+
+use super::session::Session;
+
+/// The kinds of values in a capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Number,
+    Text,
+    /// a comma separated list of text
+    List,
+}
+
+/// the columns of a CSV capture, which are also the keys of a JSONL capture
+const FIELDS: [(&str, Kind); 11] = [
+    ("sequence", Kind::Number),
+    ("time", Kind::Number),
+    ("source", Kind::Text),
+    ("destinations", Kind::List),
+    ("state", Kind::Text),
+    ("control", Kind::Number),
+    ("control_byte", Kind::Text),
+    ("dat1", Kind::Number),
+    ("dat0", Kind::Number),
+    ("dec", Kind::Number),
+    ("meaning", Kind::Text),
+];
+
+/// The formats that a capture can be exported in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureFormat {
+    Csv,
+    Jsonl,
+}
+
+impl CaptureFormat {
+    /// the format of a file, from the extension of its path
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            "csv" => Some(CaptureFormat::Csv),
+            "jsonl" => Some(CaptureFormat::Jsonl),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CaptureFormat::Csv => "csv",
+            CaptureFormat::Jsonl => "jsonl",
+        }
+    }
+}
+
+/// the values of each of `FIELDS` for `envelope`, with the numbers already formatted
+fn values(envelope: &PacketEnvelope) -> [String; 11] {
+    let bytes: [u8; 4] = envelope.packet.into();
+
+    [
+        envelope.sequence.to_string(),
+        format!("{:.3}", envelope.time),
+        envelope.source.to_string(),
+        envelope.destination_names(),
+        envelope.packet.state().to_string(),
+        bytes[0].to_string(),
+        format!("{:?}", envelope.packet.control_byte()),
+        bytes[1].to_string(),
+        bytes[2].to_string(),
+        bytes[3].to_string(),
+        envelope.packet.meaning(),
+    ]
+}
+
+/// Every packet sent during a run, in the order that they were sent
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PacketCapture {
+    packets: Vec<PacketEnvelope>,
+}
+
+impl PacketCapture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// adds the next packet sent during the run to the capture
+    pub fn push(&mut self, envelope: PacketEnvelope) {
+        self.packets.push(envelope);
+    }
+
+    pub fn packets(&self) -> &[PacketEnvelope] {
+        &self.packets
+    }
+
+    /// the capture as CSV, with a header row
+    pub fn to_csv(&self) -> String {
+        let header: Vec<&str> = FIELDS.iter().map(|(field, _)| *field).collect();
+        let mut csv = header.join(",");
+        csv.push('\n');
+
+        for packet in &self.packets {
+            let row: Vec<String> = values(packet)
+                .iter()
+                .map(|value| csv_field(value))
+                .collect();
+
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+
+        csv
+    }
+
+    /// the capture as JSONL, with one object per packet
+    pub fn to_jsonl(&self) -> String {
+        let mut jsonl = String::new();
+
+        for packet in &self.packets {
+            let members: Vec<String> = FIELDS
+                .iter()
+                .zip(values(packet).iter())
+                .map(|((field, kind), value)| {
+                    let value = match kind {
+                        Kind::Number => value.to_string(),
+                        Kind::Text => json_string(value),
+                        Kind::List => {
+                            let items: Vec<String> = value.split(", ").map(json_string).collect();
+                            format!("[{}]", items.join(","))
+                        }
+                    };
+
+                    format!("{}:{}", json_string(field), value)
+                })
+                .collect();
+
+            let _ = writeln!(jsonl, "{{{}}}", members.join(","));
+        }
+
+        jsonl
+    }
+
+    /// writes the capture to the file at `path` in `format`
+    pub fn export<P: AsRef<Path>>(&self, path: P, format: CaptureFormat) -> io::Result<()> {
+        match format {
+            CaptureFormat::Csv => fs::write(path, self.to_csv()),
+            CaptureFormat::Jsonl => fs::write(path, self.to_jsonl()),
+        }
+    }
+}
+
+impl From<&Session> for PacketCapture {
+    fn from(session: &Session) -> Self {
+        let mut capture = PacketCapture::new();
+
+        for envelope in session.envelopes() {
+            capture.push(envelope);
+        }
+
+        capture
+    }
+}
+
+/// quotes `value` if it would otherwise not be a single CSV field
+fn csv_field(value: &str) -> String {
+    match value.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
+
+/// `value` as a JSON string, with any characters that JSON does not allow escaped
+fn json_string(value: &str) -> String {
+    let mut json = String::from('"');
+
+    for char in value.chars() {
+        match char {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            char if char.is_control() => {
+                let _ = write!(json, "\\u{:04x}", char as u32);
+            }
+            char => json.push(char),
+        }
+    }
+
+    json.push('"');
+    json
+}
@@ -0,0 +1,391 @@
+//! # Protocol monitor
+//!
+//! Follows the SCS protocol as the packets of a run go by, and flags every packet that
+//! breaks it: one sent by the wrong subsystem, out of turn, or with data that makes no
+//! sense (e.g. an unknown colour). The monitor only listens in, so it can follow a run of
+//! the whole MARV, whether its subsystems are physical or emulated.
+//!
+//! In each state, the subsystems take turns to send their packets in a fixed order:
+//!
+//! - IDLE: the SNC's touch (16), until it is touched
+//! - CAL: the SS has calibrated (112), the MDPS's calibrated speeds (96) and battery level
+//!   (97), the SS's colours (113) and the SNC's touch (80), after which the MDPS, SS and
+//!   SNC carry on with 97, 113 and 80 until the SNC is touched
+//! - MAZE: the SNC's clap/snap (145), touch (146) and navigation instruction (147), the
+//!   MDPS's battery level (161), rotation (162), speeds (163) and distance (164), and the
+//!   SS's colours (177) and incidence (178), or the end of the maze (179) instead, after
+//!   which the SNC is back in IDLE
+//! - SOS: the MDPS has stopped (228), then the SNC's clap/snap (208) until it senses one
+//!
+//! The hub's own packets (e.g. `HUB_START`) are not part of any turn, so they are not
+//! checked.
+//!
+//! Packets that different subsystems send at almost the same time may be captured in
+//! either order, so a packet is only checked once it is `REORDER_WINDOW` older than the
+//! latest packet captured, and the packets of different subsystems captured within that
+//! window are taken in whichever order the protocol expects them. The packets of any one
+//! subsystem are always taken in the order that they were captured.
+
+use std::fmt;
+
+use crate::{
+    components::{
+        calibration::CalibrationCheck, comm_port::ControlByte, envelope::PacketEnvelope,
+        state::SystemState, subsystem::Subsystem,
+    },
+    gui::test_windows::{snc::protocol::check_instruction, ss::protocol::decode_colours},
+};
+
+/// packets of different subsystems captured within this long (in s) of each other may
+/// be in either order, as each subsystem's packets are captured by a thread of its own
+pub const REORDER_WINDOW: f32 = 0.01;
+
+/// a packet that the protocol expects: who sends it, and its control byte
+type Turn = (Subsystem, u8);
+
+const IDLE: [Turn; 1] = [(Subsystem::Snc, 16)];
+const CAL: [Turn; 5] = [
+    (Subsystem::Ss, 112),
+    (Subsystem::Mdps, 96),
+    (Subsystem::Mdps, 97),
+    (Subsystem::Ss, 113),
+    (Subsystem::Snc, 80),
+];
+/// the turns of CAL after the first, while the SNC has not been touched
+const CAL_WAITING: [Turn; 3] = [
+    (Subsystem::Mdps, 97),
+    (Subsystem::Ss, 113),
+    (Subsystem::Snc, 80),
+];
+const MAZE: [Turn; 9] = [
+    (Subsystem::Snc, 145),
+    (Subsystem::Snc, 146),
+    (Subsystem::Snc, 147),
+    (Subsystem::Mdps, 161),
+    (Subsystem::Mdps, 162),
+    (Subsystem::Mdps, 163),
+    (Subsystem::Mdps, 164),
+    (Subsystem::Ss, 177),
+    (Subsystem::Ss, 178),
+];
+const SOS: [Turn; 2] = [(Subsystem::Mdps, 228), (Subsystem::Snc, 208)];
+
+/// the turns of `state`, from its start
+fn turns(state: SystemState) -> &'static [Turn] {
+    match state {
+        SystemState::Idle => &IDLE,
+        SystemState::Calibrate => &CAL,
+        SystemState::Maze => &MAZE,
+        SystemState::Sos => &SOS,
+    }
+}
+
+/// A packet that broke the protocol
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub envelope: PacketEnvelope,
+    /// the state that the MARV was in when the packet was sent
+    pub state: SystemState,
+    pub reason: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "#{} at {:.3} s in {}: {}",
+            self.envelope.sequence, self.envelope.time, self.state, self.reason
+        )
+    }
+}
+
+/// Follows the protocol through the packets of a run, and keeps every violation of it
+#[derive(Debug, Clone)]
+pub struct ProtocolMonitor {
+    state: SystemState,
+    /// the turns of the current state, and whose turn it is next
+    turns: &'static [Turn],
+    next: usize,
+    /// the packets captured too recently to be sure of their order, in the order captured
+    pending: Vec<PacketEnvelope>,
+    packets: u32,
+    operational_velocity: Option<u8>,
+    calibration: Option<CalibrationCheck>,
+    /// the number of cycles of the MAZE state completed, when the current one started,
+    /// and how long (in s) the slowest took
+    cycles: u32,
+    cycle_start: Option<f32>,
+    slowest_cycle: f32,
+    /// when the SS reported the end of the maze, if it has
+    end_of_maze: Option<f32>,
+    violations: Vec<Violation>,
+}
+
+impl Default for ProtocolMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProtocolMonitor {
+    pub fn new() -> Self {
+        Self {
+            state: SystemState::Idle,
+            turns: &IDLE,
+            next: 0,
+            pending: Vec::new(),
+            packets: 0,
+            operational_velocity: None,
+            calibration: None,
+            cycles: 0,
+            cycle_start: None,
+            slowest_cycle: 0.0,
+            end_of_maze: None,
+            violations: Vec::new(),
+        }
+    }
+
+    /// the state that the MARV is in, as far as the packets checked so far show
+    pub fn state(&self) -> SystemState {
+        self.state
+    }
+
+    /// the number of packets checked so far
+    pub fn packets(&self) -> u32 {
+        self.packets
+    }
+
+    /// the vop (in mm/s) that the SNC sent with its touch in IDLE, once it has
+    pub fn operational_velocity(&self) -> Option<u8> {
+        self.operational_velocity
+    }
+
+    /// the MDPS's calibrated wheel speeds, checked against vop, once it has sent them
+    pub fn calibration(&self) -> Option<CalibrationCheck> {
+        self.calibration
+    }
+
+    /// the number of cycles of the MAZE state completed so far
+    pub fn cycles(&self) -> u32 {
+        self.cycles
+    }
+
+    /// how long (in s) the slowest cycle of the MAZE state took, from 145 to 178
+    pub fn slowest_cycle(&self) -> f32 {
+        self.slowest_cycle
+    }
+
+    /// when (in s) the SS reported the end of the maze, if it has
+    pub fn end_of_maze(&self) -> Option<f32> {
+        self.end_of_maze
+    }
+
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+
+    /// checks the packets captured more than `REORDER_WINDOW` before `envelope`, which is
+    /// checked once the next packets are captured (or the monitor is `flush`ed), and returns
+    /// the violations found
+    pub fn check(&mut self, envelope: PacketEnvelope) -> Vec<Violation> {
+        if envelope.source == Subsystem::Hub {
+            return Vec::new();
+        }
+
+        self.pending.push(envelope);
+        self.settle(envelope.time - REORDER_WINDOW)
+    }
+
+    /// checks every packet captured so far, e.g. once no more have been captured for a
+    /// while, and returns the violations found
+    pub fn flush(&mut self) -> Vec<Violation> {
+        self.settle(f32::INFINITY)
+    }
+
+    /// checks the packets captured before `time` (in s), taking the packets captured
+    /// alongside them in whichever order the protocol expects
+    fn settle(&mut self, time: f32) -> Vec<Violation> {
+        let first = self.violations.len();
+
+        while matches!(self.pending.first(), Some(oldest) if oldest.time <= time) {
+            let expected = (0..self.pending.len()).find(|&index| {
+                let envelope = &self.pending[index];
+
+                // no packet of a subsystem is taken before one that it sent earlier
+                self.expects(envelope)
+                    && !self.pending[..index]
+                        .iter()
+                        .any(|earlier| earlier.source == envelope.source)
+            });
+
+            let envelope = match expected {
+                Some(index) => self.pending.remove(index),
+                None => {
+                    let envelope = self.pending.remove(0);
+                    self.unexpected(&envelope);
+                    envelope
+                }
+            };
+
+            self.packets += 1;
+            self.accept(&envelope);
+        }
+
+        self.violations[first..].to_vec()
+    }
+
+    /// whether `envelope` is the packet whose turn it is
+    fn expects(&self, envelope: &PacketEnvelope) -> bool {
+        let turn = (envelope.source, u8::from(envelope.packet.control_byte()));
+
+        match self.turns.get(self.next) {
+            // the SS reports the end of the maze instead of its colours
+            Some(&(Subsystem::Ss, 177)) => {
+                turn == (Subsystem::Ss, 177) || turn == (Subsystem::Ss, 179)
+            }
+            Some(expected) => turn == *expected,
+            None => false,
+        }
+    }
+
+    /// records a packet sent out of turn, and finds where it fits in the protocol: later
+    /// in the current state, or in the state that its control byte is from
+    fn unexpected(&mut self, envelope: &PacketEnvelope) {
+        let turn = (envelope.source, u8::from(envelope.packet.control_byte()));
+
+        let reason = match self.turns.get(self.next) {
+            Some((subsystem, byte)) => format!(
+                "the {} sent {} ({:?}), but it was the {}'s turn to send {} ({:?})",
+                envelope.source,
+                envelope.packet,
+                envelope.packet.control_byte(),
+                subsystem,
+                byte,
+                ControlByte::from(*byte)
+            ),
+            None => format!(
+                "the {} sent {} ({:?}), but no packet was expected",
+                envelope.source,
+                envelope.packet,
+                envelope.packet.control_byte()
+            ),
+        };
+        self.violate(envelope, reason);
+
+        // the end of the maze takes the place of the SS's colours
+        let fits = |expected: &Turn| {
+            *expected == turn || (turn == (Subsystem::Ss, 179) && *expected == (Subsystem::Ss, 177))
+        };
+
+        if let Some(next) = self.turns.iter().position(fits) {
+            self.next = next;
+            return;
+        }
+
+        let state = envelope.packet.state();
+
+        if let Some(next) = turns(state).iter().position(fits) {
+            self.state = state;
+            self.turns = turns(state);
+            self.next = next;
+        }
+    }
+
+    /// takes the MARV through the packet whose turn it is (if it is), and checks its data
+    fn accept(&mut self, envelope: &PacketEnvelope) {
+        let packet = envelope.packet;
+        let turn = (envelope.source, u8::from(packet.control_byte()));
+
+        if !self.expects(envelope) {
+            // the packet did not fit anywhere in the protocol
+            return;
+        }
+
+        self.next += 1;
+
+        match turn {
+            (Subsystem::Snc, 16) if packet.dat1() == 1 => {
+                if packet.dat0() == 0 {
+                    self.violate(envelope, String::from("the SNC sent a vop of 0 mm/s"));
+                }
+
+                self.operational_velocity = Some(packet.dat0());
+                self.enter(SystemState::Calibrate);
+            }
+            (Subsystem::Snc, 16) => self.next = 0,
+            (Subsystem::Mdps, 96) => {
+                let check =
+                    CalibrationCheck::new(self.operational_velocity.unwrap_or_default(), packet);
+
+                if !check.passed() {
+                    self.violate(envelope, format!("the MDPS calibrated to {}", check));
+                }
+
+                self.calibration = Some(check);
+            }
+            (Subsystem::Ss, 113 | 177) => {
+                if let Err(err) = decode_colours(packet) {
+                    self.violate(envelope, err.to_string());
+                }
+            }
+            (Subsystem::Snc, 80) if packet.dat1() == 1 => self.enter(SystemState::Maze),
+            (Subsystem::Snc, 80) => {
+                self.turns = &CAL_WAITING;
+                self.next = 0;
+            }
+            (Subsystem::Snc, 145) if packet.dat1() == 1 => self.enter(SystemState::Sos),
+            (Subsystem::Snc, 145) => self.cycle_start = Some(envelope.time),
+            (Subsystem::Snc, 146) if packet.dat1() == 1 => self.enter(SystemState::Idle),
+            (Subsystem::Snc, 147) => {
+                if let Err(err) = check_instruction(packet) {
+                    self.violate(envelope, err.to_string());
+                }
+            }
+            (Subsystem::Ss, 178) => {
+                self.cycles += 1;
+
+                if let Some(start) = self.cycle_start.take() {
+                    self.slowest_cycle = self.slowest_cycle.max(envelope.time - start);
+                }
+
+                self.next = 0;
+            }
+            (Subsystem::Ss, 179) => {
+                self.end_of_maze = Some(envelope.time);
+                self.enter(SystemState::Idle);
+            }
+            // the SNC carries on sending 208 until it senses a clap/snap
+            (Subsystem::Snc, 208) if packet.dat1() == 1 => self.enter(SystemState::Maze),
+            (Subsystem::Snc, 208) => self.next = 1,
+            _ => (),
+        }
+    }
+
+    fn enter(&mut self, state: SystemState) {
+        self.state = state;
+        self.turns = turns(state);
+        self.next = 0;
+        self.cycle_start = None;
+    }
+
+    fn violate(&mut self, envelope: &PacketEnvelope, reason: String) {
+        self.violations.push(Violation {
+            envelope: *envelope,
+            state: self.state,
+            reason,
+        });
+    }
+}
+
+impl fmt::Display for ProtocolMonitor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} packets, {} MAZE cycles (the slowest took {:.0} ms), {} violations",
+            self.packets,
+            self.cycles,
+            self.slowest_cycle * 1000.0,
+            self.violations.len()
+        )
+    }
+}
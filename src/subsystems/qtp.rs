@@ -0,0 +1,233 @@
+//! # QTP
+//!
+//! What every QTP that the kit runs has in common, whichever subsystems it is run against
+//! and however the kit runs it: against NAVCON in an emulated maze (`NavconQtp`), against
+//! a single subsystem on the test bench (`BenchQtp`), or against the whole MARV with the
+//! kit as the hub (`IntegrationQtp`).
+//!
+//! Each QTP is a module of its own under `gui::test_windows`, listed in its suite's
+//! `qtps!`, and so in `gui::test_windows::registry::qtps`, from which the GUI's main menu
+//! and the `qtp` CLI are built.
+
+use std::fmt;
+
+use crate::{
+    asynchronous::async_type::GuiEndpoints, components::subsystem::Subsystem,
+    gui::maze::MazeLineMap,
+};
+
+use super::{
+    hub::{run_integration, IntegrationQtp},
+    qtp_report::{QtpReport, QtpStep, Verdict},
+    session::Recording,
+    system::{run_navcon, EmulationConfig, Mode, NavconQtp},
+    test_bench::{run_bench, BenchQtp},
+};
+
+/// every subsystem, in the order that their ports are given in
+const SUBSYSTEMS: [Subsystem; 3] = [Subsystem::Snc, Subsystem::Ss, Subsystem::Mdps];
+
+/// How the kit runs a QTP, which decides the window that it is run from
+#[derive(Debug, Clone, Copy)]
+pub enum QtpKind {
+    Navcon(NavconQtp),
+    Bench(BenchQtp),
+    Integration(IntegrationQtp),
+}
+
+/// A QTP that the kit can run
+pub trait Qtp: fmt::Debug + Sync {
+    /// e.g. "SNC QTP 1"
+    fn name(&self) -> &'static str;
+
+    /// the suite that the QTP belongs to, e.g. "SNC" or "NAVCON"
+    fn suite(&self) -> &'static str;
+
+    /// the name of the QTP within its suite, e.g. "QTP 1"
+    fn short_name(&self) -> &'static str {
+        self.name().trim_start_matches(self.suite()).trim_start()
+    }
+
+    /// the subsystems under test, in the order that their ports are given in
+    fn under_test(&self) -> &'static [Subsystem];
+
+    /// what the QTP tests, shown before it is run
+    fn description(&self) -> &'static str;
+
+    /// the maze that the MARV drives through when its subsystems are emulated, if it
+    /// drives through one
+    fn maze(&self) -> Option<MazeLineMap>;
+
+    /// the modes that the subsystems under test can be run in
+    fn modes(&self) -> &'static [Mode];
+
+    fn kind(&self) -> QtpKind;
+
+    /// runs the QTP with the subsystems under test in `mode`, connected on `ports` (one
+    /// for each subsystem under test) if they are physical, and returns its report, whose
+    /// verdict is that of the QTP
+    ///
+    /// Everything that happens during the run is passed on to `gui`.
+    fn run(
+        &self,
+        mode: Mode,
+        ports: &[String],
+        config: EmulationConfig,
+        gui: &GuiEndpoints,
+    ) -> QtpReport;
+}
+
+impl Qtp for NavconQtp {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn suite(&self) -> &'static str {
+        "NAVCON"
+    }
+
+    fn under_test(&self) -> &'static [Subsystem] {
+        &SUBSYSTEMS[..1]
+    }
+
+    fn description(&self) -> &'static str {
+        self.description
+    }
+
+    fn maze(&self) -> Option<MazeLineMap> {
+        Some((self.maze)())
+    }
+
+    fn modes(&self) -> &'static [Mode] {
+        &[Mode::Emulate]
+    }
+
+    fn kind(&self) -> QtpKind {
+        QtpKind::Navcon(*self)
+    }
+
+    fn run(
+        &self,
+        _mode: Mode,
+        _ports: &[String],
+        config: EmulationConfig,
+        gui: &GuiEndpoints,
+    ) -> QtpReport {
+        run_navcon(self, config, Some(Recording::new(self)), gui)
+    }
+}
+
+impl Qtp for BenchQtp {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn suite(&self) -> &'static str {
+        match self.under_test {
+            Subsystem::Snc => "SNC",
+            Subsystem::Ss => "SS",
+            Subsystem::Mdps => "MDPS",
+            Subsystem::Hub => "Hub",
+        }
+    }
+
+    fn under_test(&self) -> &'static [Subsystem] {
+        match self.under_test {
+            Subsystem::Snc => &SUBSYSTEMS[0..1],
+            Subsystem::Ss => &SUBSYSTEMS[1..2],
+            Subsystem::Mdps => &SUBSYSTEMS[2..3],
+            Subsystem::Hub => &[Subsystem::Hub],
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        self.description
+    }
+
+    fn maze(&self) -> Option<MazeLineMap> {
+        None
+    }
+
+    fn modes(&self) -> &'static [Mode] {
+        &[Mode::Emulate, Mode::Physical]
+    }
+
+    fn kind(&self) -> QtpKind {
+        QtpKind::Bench(*self)
+    }
+
+    fn run(
+        &self,
+        mode: Mode,
+        ports: &[String],
+        config: EmulationConfig,
+        gui: &GuiEndpoints,
+    ) -> QtpReport {
+        let com = ports.first().map_or("", String::as_str);
+
+        run_bench(self, mode, com, config, Some(Recording::new(self)), gui)
+    }
+}
+
+impl Qtp for IntegrationQtp {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn suite(&self) -> &'static str {
+        "Integration"
+    }
+
+    fn under_test(&self) -> &'static [Subsystem] {
+        &SUBSYSTEMS
+    }
+
+    fn description(&self) -> &'static str {
+        self.description
+    }
+
+    fn maze(&self) -> Option<MazeLineMap> {
+        Some((self.maze)())
+    }
+
+    fn modes(&self) -> &'static [Mode] {
+        &[Mode::Emulate, Mode::Physical]
+    }
+
+    fn kind(&self) -> QtpKind {
+        QtpKind::Integration(*self)
+    }
+
+    fn run(
+        &self,
+        mode: Mode,
+        ports: &[String],
+        config: EmulationConfig,
+        gui: &GuiEndpoints,
+    ) -> QtpReport {
+        let ports = match (mode, ports) {
+            (_, [snc, ss, mdps]) => [snc.clone(), ss.clone(), mdps.clone()],
+            // emulated subsystems are not connected to any port
+            (Mode::Emulate, []) => Default::default(),
+            _ => {
+                let step = QtpStep {
+                    time: 0.0,
+                    name: String::from("connect the subsystems"),
+                    verdict: Verdict::Fail,
+                    detail: format!(
+                        "needs a port for each of the SNC, SS and MDPS, but was given {}",
+                        ports.len()
+                    ),
+                };
+
+                let mut report = QtpReport::new(self.name);
+                gui.steps.lock().unwrap().write(step.clone());
+                report.steps.push(step);
+
+                return report;
+            }
+        };
+
+        run_integration(self, mode, &ports, config, Some(Recording::new(self)), gui)
+    }
+}
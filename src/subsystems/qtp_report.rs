@@ -0,0 +1,91 @@
+//! # QTP report
+//!
+//! The result of each step of a QTP that the kit runs against a subsystem, and the
+//! verdict of the QTP as a whole.
+
+use std::fmt;
+
+/// The result of a single step of a QTP
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    Fail,
+    /// the step could not be carried out, e.g. because it needs an operator at a physical
+    /// subsystem
+    Skipped,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // padded, so that steps line up in a report
+        f.pad(match self {
+            Verdict::Pass => "PASS",
+            Verdict::Fail => "FAIL",
+            Verdict::Skipped => "SKIPPED",
+        })
+    }
+}
+
+/// A step of a QTP, once it has been carried out
+#[derive(Debug, Clone, PartialEq)]
+pub struct QtpStep {
+    /// when the step ended (in s), from the start of the QTP
+    pub time: f32,
+    pub name: String,
+    pub verdict: Verdict,
+    /// what was measured, or why the step failed or was skipped
+    pub detail: String,
+}
+
+impl fmt::Display for QtpStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:7.3} s  {:<7}  {}: {}",
+            self.time, self.verdict, self.name, self.detail
+        )
+    }
+}
+
+/// Every step of a QTP, in the order that they were carried out
+#[derive(Debug, Clone, PartialEq)]
+pub struct QtpReport {
+    /// the name of the QTP, e.g. "SNC QTP 1"
+    pub qtp: String,
+    pub steps: Vec<QtpStep>,
+}
+
+impl QtpReport {
+    pub fn new(qtp: &str) -> Self {
+        Self {
+            qtp: String::from(qtp),
+            steps: Vec::new(),
+        }
+    }
+
+    /// the verdict of the whole QTP: it fails if any step failed, and only passes if
+    /// at least one step passed and the rest were not skipped
+    pub fn verdict(&self) -> Verdict {
+        let steps = &self.steps;
+
+        if steps.iter().any(|step| step.verdict == Verdict::Fail) {
+            Verdict::Fail
+        } else if !steps.is_empty() && steps.iter().all(|step| step.verdict == Verdict::Pass) {
+            Verdict::Pass
+        } else {
+            Verdict::Skipped
+        }
+    }
+}
+
+impl fmt::Display for QtpReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}: {}", self.qtp, self.verdict())?;
+
+        for step in &self.steps {
+            writeln!(f, "{}", step)?;
+        }
+
+        Ok(())
+    }
+}
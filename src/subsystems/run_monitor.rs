@@ -0,0 +1,338 @@
+//! # Run monitor
+//!
+//! Watches the ground-truth poses of the emulated MARV during a run. Nothing in the
+//! emulation itself stops the MARV from driving through a wall (a blue or black line),
+//! off the edge of the maze, or from never getting anywhere, so the monitor ends the run
+//! as soon as one of these happens and reports why and where.
+//!
+//! A NAVCON that never reaches the end of the maze would keep a run going forever,
+//! so a run is also stopped once it exceeds the time or the number of NAVCON steps
+//! allowed by its `RunLimits`.
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    asynchronous::one_to_many_channel::OTMChannel,
+    components::{calibration::CalibrationCheck, colour::Colour, constants::MAZE_LINE_WIDTH},
+    gui::maze::MazeLineMap,
+};
+
+use super::{
+    sensor_positions::{to_maze_coords, Pose},
+    state_navigation::navcon::{NavConDecision, NavConState},
+};
+
+/// the MARV has made progress once its centre has moved this far (in m)...
+const PROGRESS_DISTANCE: f32 = 0.01;
+/// ...or it has turned this far (in rad)
+const PROGRESS_ROTATION: f32 = 0.1;
+/// the MARV is stuck once it has made no progress for this long (in s)
+const STUCK_TIME: f32 = 15.0;
+
+/// How long a run may go on for before it is stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunLimits {
+    /// the longest that a run may take, from the start of the emulation
+    pub timeout: Duration,
+    /// the most decisions that NAVCON may make during a run
+    pub max_steps: u32,
+}
+
+impl Default for RunLimits {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(120),
+            max_steps: 3_000,
+        }
+    }
+}
+
+/// Where the MARV was and what NAVCON was doing when a run was stopped
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FinalState {
+    /// the last pose of the MARV, if it had moved at all
+    pub pose: Option<Pose>,
+    /// the state that NAVCON was in, if it had made any decisions
+    pub navcon_state: Option<NavConState>,
+    /// the number of decisions that NAVCON had made
+    pub steps: u32,
+}
+
+impl fmt::Display for FinalState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.pose {
+            Some(pose) => write!(
+                f,
+                "at ({:.0}, {:.0}) mm facing {:.0}°",
+                pose.position.0 * 1_000.0,
+                pose.position.1 * 1_000.0,
+                pose.heading.to_degrees()
+            )?,
+            None => write!(f, "before moving")?,
+        }
+
+        match self.navcon_state {
+            Some(state) => write!(f, ", NAVCON in {:?} after {} steps", state, self.steps),
+            None => write!(f, ", before NAVCON made any decisions"),
+        }
+    }
+}
+
+/// How a run of the emulation ended
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RunOutcome {
+    /// the run ended without the monitor stepping in
+    Completed,
+    /// the MARV's centre crossed a wall (a blue or black line) at the position (in m)
+    CrossedWall((f32, f32)),
+    /// the MARV's centre left the maze at the position (in m)
+    LeftMaze((f32, f32)),
+    /// the MARV made no progress for this long (in s) at the position (in m)
+    Stuck((f32, f32), f32),
+    /// the run took longer than its time limit (in s)
+    TimedOut(f32, FinalState),
+    /// NAVCON made more decisions than the run's step limit
+    OutOfSteps(u32, FinalState),
+    /// the MDPS's calibrated wheel speeds were not within 5% of vop, so the SNC never let
+    /// the MARV into the maze
+    CalibrationFailed(CalibrationCheck),
+}
+
+impl RunOutcome {
+    /// whether the run failed
+    pub fn failed(&self) -> bool {
+        !matches!(self, RunOutcome::Completed)
+    }
+
+    /// where the run failed (in m), if it did
+    pub fn location(&self) -> Option<(f32, f32)> {
+        match self {
+            RunOutcome::Completed | RunOutcome::CalibrationFailed(_) => None,
+            RunOutcome::CrossedWall(position)
+            | RunOutcome::LeftMaze(position)
+            | RunOutcome::Stuck(position, _) => Some(*position),
+            RunOutcome::TimedOut(_, end) | RunOutcome::OutOfSteps(_, end) => {
+                end.pose.map(|pose| pose.position)
+            }
+        }
+    }
+}
+
+impl fmt::Display for RunOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mm = |(x, y): (f32, f32)| (x * 1_000.0, y * 1_000.0);
+
+        match self {
+            RunOutcome::Completed => write!(f, "run completed"),
+            RunOutcome::CrossedWall(position) => {
+                let (x, y) = mm(*position);
+                write!(f, "FAIL: crossed a wall at ({:.0}, {:.0}) mm", x, y)
+            }
+            RunOutcome::LeftMaze(position) => {
+                let (x, y) = mm(*position);
+                write!(f, "FAIL: left the maze at ({:.0}, {:.0}) mm", x, y)
+            }
+            RunOutcome::Stuck(position, time) => {
+                let (x, y) = mm(*position);
+                write!(
+                    f,
+                    "FAIL: stuck for {:.0} s at ({:.0}, {:.0}) mm",
+                    time, x, y
+                )
+            }
+            RunOutcome::TimedOut(limit, end) => {
+                write!(f, "FAIL: timed out after {:.0} s {}", limit, end)
+            }
+            RunOutcome::OutOfSteps(limit, end) => {
+                write!(f, "FAIL: exceeded {} NAVCON steps {}", limit, end)
+            }
+            RunOutcome::CalibrationFailed(check) => {
+                write!(f, "FAIL: the MDPS did not calibrate, {}", check)
+            }
+        }
+    }
+}
+
+/// Ends a run when the emulated MARV crosses a wall, leaves the maze, gets stuck, or
+/// exceeds the run's limits, or when the SNC finds that the MDPS did not calibrate
+pub struct RunMonitor {
+    maze: MazeLineMap,
+    limits: RunLimits,
+    /// the poses of the MARV, NAVCON's decisions and the SNC's checks of the MDPS's
+    /// calibration are received on these channels...
+    poses: OTMChannel<Pose>,
+    decisions: OTMChannel<NavConDecision>,
+    calibrations: OTMChannel<CalibrationCheck>,
+    /// ...and the outcome of the run is sent out on this one
+    outcomes: OTMChannel<RunOutcome>,
+    /// cleared by the monitor to end the run, or by the system once the run has ended
+    running: Arc<AtomicBool>,
+    latest: Option<Pose>,
+    /// the pose at which the MARV last made progress
+    progress: Option<Pose>,
+    latest_decision: Option<NavConDecision>,
+}
+
+impl RunMonitor {
+    pub fn new(
+        maze: MazeLineMap,
+        limits: RunLimits,
+        poses: OTMChannel<Pose>,
+        decisions: OTMChannel<NavConDecision>,
+        calibrations: OTMChannel<CalibrationCheck>,
+        outcomes: OTMChannel<RunOutcome>,
+        running: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            maze,
+            limits,
+            poses,
+            decisions,
+            calibrations,
+            outcomes,
+            running,
+            latest: None,
+            progress: None,
+            latest_decision: None,
+        }
+    }
+
+    /// where the MARV is and what NAVCON is doing
+    pub fn final_state(&self) -> FinalState {
+        FinalState {
+            pose: self.latest,
+            navcon_state: self.latest_decision.map(|decision| decision.to),
+            steps: self.latest_decision.map_or(0, |decision| decision.step),
+        }
+    }
+
+    /// checks every pose until the run ends, and then sends out its outcome
+    pub fn run(&mut self) {
+        let start = SystemTime::now();
+        let mut outcome = RunOutcome::Completed;
+
+        while self.running.load(Ordering::Relaxed) {
+            let mut failure = self
+                .check_elapsed(start.elapsed().unwrap_or_default())
+                .or_else(|| self.check_calibrations());
+
+            while failure.is_none() {
+                match self.decisions.try_receive() {
+                    Ok(decision) => failure = self.check_decision(decision),
+                    Err(_) => break,
+                }
+            }
+
+            if failure.is_none() {
+                match self.poses.try_receive() {
+                    Ok(pose) => failure = self.check(pose),
+                    Err(_) => std::thread::sleep(Duration::from_millis(1)),
+                }
+            }
+
+            if let Some(failure) = failure {
+                outcome = failure;
+                self.running.store(false, Ordering::Relaxed);
+            }
+        }
+
+        // the SNC ends the run as soon as it has sent a failed check of the calibration
+        if !outcome.failed() {
+            outcome = self.check_calibrations().unwrap_or(outcome);
+        }
+
+        self.outcomes.send(outcome);
+    }
+
+    /// checks the SNC's checks of the MDPS's calibration, and returns a failure if one failed
+    fn check_calibrations(&mut self) -> Option<RunOutcome> {
+        while let Ok(check) = self.calibrations.try_receive() {
+            if !check.passed() {
+                return Some(RunOutcome::CalibrationFailed(check));
+            }
+        }
+
+        None
+    }
+
+    /// checks how long the run has been going on for, and returns a failure if it is too long
+    pub fn check_elapsed(&self, elapsed: Duration) -> Option<RunOutcome> {
+        (elapsed > self.limits.timeout)
+            .then(|| RunOutcome::TimedOut(self.limits.timeout.as_secs_f32(), self.final_state()))
+    }
+
+    /// checks the next decision made by NAVCON, and returns a failure if it has made too many
+    pub fn check_decision(&mut self, decision: NavConDecision) -> Option<RunOutcome> {
+        self.latest_decision = Some(decision);
+
+        (decision.step > self.limits.max_steps)
+            .then(|| RunOutcome::OutOfSteps(self.limits.max_steps, self.final_state()))
+    }
+
+    /// checks the next pose of the MARV, and returns why the run has failed if it has
+    pub fn check(&mut self, pose: Pose) -> Option<RunOutcome> {
+        let previous = self.latest.replace(pose).unwrap_or(pose);
+
+        // a MARV that drives off the maze through a wall crossed the wall first
+        if let Some(position) = self.wall_crossed(previous.position, pose.position) {
+            return Some(RunOutcome::CrossedWall(position));
+        }
+
+        if !self.on_maze(pose.position) {
+            return Some(RunOutcome::LeftMaze(pose.position));
+        }
+
+        let progress = *self.progress.get_or_insert(pose);
+        let (dx, dy) = (
+            pose.position.0 - progress.position.0,
+            pose.position.1 - progress.position.1,
+        );
+
+        if (dx * dx + dy * dy).sqrt() > PROGRESS_DISTANCE
+            || (pose.heading - progress.heading).abs() > PROGRESS_ROTATION
+        {
+            self.progress = Some(pose);
+        } else if pose.time - progress.time > STUCK_TIME {
+            return Some(RunOutcome::Stuck(pose.position, pose.time - progress.time));
+        }
+
+        None
+    }
+
+    fn on_maze(&self, position: (f32, f32)) -> bool {
+        let (x, y) = to_maze_coords(position);
+        self.maze.contains(x, y)
+    }
+
+    /// returns the first point (in m) on the way from `from` to `to` that is on a wall, i.e.
+    /// a blue or black line
+    fn wall_crossed(&self, from: (f32, f32), to: (f32, f32)) -> Option<(f32, f32)> {
+        let (start, end) = (to_maze_coords(from), to_maze_coords(to));
+        let length = ((end.0 - start.0).powi(2) + (end.1 - start.1).powi(2)).sqrt();
+
+        // sample finely enough that a line cannot be stepped over
+        let steps = (length / (MAZE_LINE_WIDTH / 2.0)).ceil().max(1.0) as usize;
+
+        (1..=steps)
+            .map(|step| step as f32 / steps as f32)
+            .find_map(|t| {
+                let point = (
+                    start.0 + (end.0 - start.0) * t,
+                    start.1 + (end.1 - start.1) * t,
+                );
+
+                matches!(
+                    self.maze.get_colour_from_coord(point.0, point.1),
+                    Some(Colour::Blue | Colour::Black)
+                )
+                .then_some((from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t))
+            })
+    }
+}
@@ -1,10 +1,18 @@
-use std::time::SystemTime;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::SystemTime,
+};
 
 use crate::{
     asynchronous::{one_to_many_channel::OTMChannel, one_to_one_channel::OTOChannel},
-    components::constants::{AXLE_DIST, B_ISD, MAZE_COL_WIDTH, S_ISD},
+    components::{constants::MAZE_COL_WIDTH, robot_config::RobotConfig},
 };
 
+use super::kinematics;
+
 #[derive(Debug, Clone, Copy)]
 pub struct Speeds(f32, f32);
 
@@ -22,6 +30,64 @@ impl Speeds {
     }
 }
 
+/// The ground truth of where the emulated MARV is and how it is moving
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pose {
+    /// the time (in s) since the emulation started
+    pub time: f32,
+    /// the centre of the MARV, between its wheels (in m)
+    pub position: (f32, f32),
+    /// the direction that the MARV is facing (in rad)
+    pub heading: f32,
+    /// the speed at which the MARV's centre is moving forward (in m/s)
+    pub linear_velocity: f32,
+    /// the rate at which the MARV's heading is changing (in rad/s)
+    pub angular_velocity: f32,
+    /// the positions of the sensors, in the same coordinates as the maze
+    pub sensors: [(f32, f32); 5],
+}
+
+/// converts a position (in m) to the coordinates of the maze
+pub fn to_maze_coords((x, y): (f32, f32)) -> (f32, f32) {
+    (x * (MAZE_COL_WIDTH / 0.2), y * (MAZE_COL_WIDTH / 0.2))
+}
+
+/// the position (in m) and angle (in rad) from the middle of the axle to each sensor of `robot`
+fn sensor_rads(robot: &RobotConfig) -> [(f32, f32); 5] {
+    // the maze is drawn with y pointing down, so the sensors on the left are at a negative angle
+    robot.sensors.map(|(forward, left)| {
+        (
+            (forward.powi(2) + left.powi(2)).sqrt() / 1_000.0,
+            (-left).atan2(forward),
+        )
+    })
+}
+
+/// the positions, in the coordinates of the maze, of sensors at `sensor_rads` from a MARV
+/// whose centre is at (x, y) (in m) and which faces `heading` (in rad)
+fn place_sensors(
+    sensor_rads: &[(f32, f32); 5],
+    (x, y): (f32, f32),
+    heading: f32,
+) -> [(f32, f32); 5] {
+    sensor_rads.map(|(radius, angle)| {
+        to_maze_coords((
+            x + radius * (heading + angle).cos(),
+            y + radius * (heading + angle).sin(),
+        ))
+    })
+}
+
+/// the positions, in the coordinates of the maze, of the sensors of `robot` when its centre
+/// is at `position` (in m) and it faces `heading` (in rad)
+pub fn sensor_positions(
+    robot: &RobotConfig,
+    position: (f32, f32),
+    heading: f32,
+) -> [(f32, f32); 5] {
+    place_sensors(&sensor_rads(robot), position, heading)
+}
+
 pub struct RobotParams {
     pub x: f32,
     pub y: f32,
@@ -30,8 +96,11 @@ pub struct RobotParams {
 
 pub struct CalcParams {
     pub time: SystemTime,
-    pub prev_angular_velocity: f32,
     pub sensor_rads: [(f32, f32); 5],
+    /// the distance between the wheels (in m)
+    pub axle_length: f32,
+    /// the wheel speeds (in mm/s) that the MARV has been driving at since `time`
+    pub speeds: Speeds,
 }
 
 pub struct SensorPosComputer {
@@ -39,37 +108,29 @@ pub struct SensorPosComputer {
     calculation_parameters: CalcParams,
     in_channel: OTOChannel<Speeds>,
     out_channel: OTMChannel<[(f32, f32); 5]>,
+    /// the full pose is sent out on this channel after every update
+    pose_channel: OTMChannel<Pose>,
+    start_time: SystemTime,
+    /// the positions are computed for as long as this is set
+    running: Arc<AtomicBool>,
 }
 
 impl SensorPosComputer {
     pub fn new(
-        init_x: f32,
-        init_y: f32,
+        (init_x, init_y): (f32, f32),
+        start_angle: f32,
+        robot: &RobotConfig,
         in_channel: OTOChannel<Speeds>,
         out_channel: OTMChannel<[(f32, f32); 5]>,
-        start_angle: f32,
+        pose_channel: OTMChannel<Pose>,
+        running: Arc<AtomicBool>,
     ) -> Self {
-        let inside_rad: f32 =
-            ((AXLE_DIST as f32).powi(2) + (S_ISD as f32).powi(2)).sqrt() / 1_000.0;
-        let outside_rad: f32 =
-            ((AXLE_DIST as f32).powi(2) + (S_ISD as f32 + B_ISD as f32).powi(2)).sqrt() / 1_000.0;
-
-        let inside_angle: f32 = ((S_ISD as f32 / 1_000.0) / inside_rad).asin();
-        let outside_angle: f32 = (((S_ISD as f32 + B_ISD as f32) / 1_000.0) / outside_rad).asin();
-
-        let sensor_rads: [(f32, f32); 5] = [
-            (outside_rad, -outside_angle),
-            (inside_rad, -inside_angle),
-            (AXLE_DIST as f32 / 1_000.0, 0.0),
-            (inside_rad, inside_angle),
-            (outside_rad, outside_angle),
-        ];
-
         Self {
             calculation_parameters: CalcParams {
                 time: SystemTime::now(),
-                prev_angular_velocity: 0.,
-                sensor_rads,
+                sensor_rads: sensor_rads(robot),
+                axle_length: robot.axle_length / 1_000.0,
+                speeds: Speeds::new(0.0, 0.0),
             },
             robot_parameters: RobotParams {
                 x: init_x,
@@ -78,67 +139,72 @@ impl SensorPosComputer {
             },
             in_channel,
             out_channel,
+            pose_channel,
+            start_time: SystemTime::now(),
+            running,
         }
     }
 
     pub fn compute_pos(&mut self) {
-        loop {
+        while self.running.load(Ordering::Relaxed) {
             // receive wheel speeds and compute the sensor positions
-            let speeds = self.in_channel.receive();
-            let sensor_positions = self.compute(speeds);
+            if let Ok(speeds) = self.in_channel.try_receive() {
+                let pose = self.compute(speeds);
 
-            // send them to the GUI
-            self.out_channel.send(sensor_positions);
+                // send them to the GUI
+                self.out_channel.send(pose.sensors);
+                self.pose_channel.send(pose);
+            }
         }
     }
 
-    fn compute(&mut self, speeds: Speeds) -> [(f32, f32); 5] {
+    fn compute(&mut self, speeds: Speeds) -> Pose {
         let elapsed_time = self
             .calculation_parameters
             .time
             .elapsed()
-            .unwrap()
-            .as_millis() as f32
-            / 1_000.0; // s
-
-        let right_speed = speeds.right_speed();
-        let left_speed = speeds.left_speed();
+            .unwrap_or_default()
+            .as_secs_f32();
 
         self.calculation_parameters.time = SystemTime::now();
 
-        let angular_velocity =
-            ((right_speed - left_speed) / 1_000.0) / (AXLE_DIST as f32 / 1_000.0) * 1.00;
+        // the MARV drove at the previous speeds until the new ones were received
+        let previous = self.calculation_parameters.speeds;
+        self.calculation_parameters.speeds = speeds;
+
+        // the wheel speeds are in mm/s, the MARV's position in m
+        let axle_length = self.calculation_parameters.axle_length;
+
+        let ((x, y), angle) = kinematics::integrate(
+            (self.robot_parameters.x, self.robot_parameters.y),
+            self.robot_parameters.angle,
+            previous.left_speed() / 1_000.0,
+            previous.right_speed() / 1_000.0,
+            axle_length,
+            elapsed_time,
+        );
 
-        let linear_velocity = ((right_speed + left_speed) / 1_000.0) / 2.0;
+        let right_speed = speeds.right_speed() / 1_000.0;
+        let left_speed = speeds.left_speed() / 1_000.0;
 
-        // trapezoidal rule for integrals
-        self.robot_parameters.angle += elapsed_time
-            * ((self.calculation_parameters.prev_angular_velocity + angular_velocity) / 1.960);
-        self.robot_parameters.x +=
-            elapsed_time * linear_velocity * self.robot_parameters.angle.cos();
-        self.robot_parameters.y +=
-            elapsed_time * linear_velocity * self.robot_parameters.angle.sin();
+        let linear_velocity = kinematics::linear_velocity(left_speed, right_speed);
+        let angular_velocity = kinematics::angular_velocity(left_speed, right_speed, axle_length);
+
+        self.robot_parameters.x = x;
+        self.robot_parameters.y = y;
+        self.robot_parameters.angle = angle;
 
         // update sensor_positions
-        let mut sensor_positions = [(0.0, 0.0); 5];
-
-        self.calculation_parameters
-            .sensor_rads
-            .iter()
-            .enumerate()
-            .for_each(|(index, (radius, angle))| {
-                sensor_positions[index] = (
-                    (self.robot_parameters.x
-                        + ((*radius) * (self.robot_parameters.angle + angle).cos()) as f32)
-                        * (MAZE_COL_WIDTH / 0.2),
-                    (self.robot_parameters.y
-                        + ((*radius) * (self.robot_parameters.angle + angle).sin()) as f32)
-                        * (MAZE_COL_WIDTH / 0.2),
-                )
-            });
-
-        self.calculation_parameters.prev_angular_velocity = angular_velocity;
-
-        sensor_positions
+        let sensor_positions =
+            place_sensors(&self.calculation_parameters.sensor_rads, (x, y), angle);
+
+        Pose {
+            time: self.start_time.elapsed().unwrap_or_default().as_secs_f32(),
+            position: (self.robot_parameters.x, self.robot_parameters.y),
+            heading: self.robot_parameters.angle,
+            linear_velocity,
+            angular_velocity,
+            sensors: sensor_positions,
+        }
     }
 }
@@ -0,0 +1,155 @@
+//! # Angle of incidence
+//!
+//! The angle of incidence is the angle between the MARV's heading and the normal
+//! of the line that it is crossing, i.e. 0° when it drives straight across a line.
+//!
+//! The emulated SS knows exactly where its sensors are, so it can work out the
+//! true incidence from the MARV's heading and the orientation of the line under a
+//! sensor. A real SS has to estimate it instead, typically from the distance that
+//! the MARV travels between an outer and an inner sensor reaching the line. Both are
+//! computed on every reading so that the GUI can show how far off the estimate is.
+
+use std::{f32::consts::PI, fmt};
+
+use crate::{
+    components::{colour::Colour, robot_config::RobotConfig},
+    gui::maze::{LineOrientation, MazeLineMap},
+};
+
+/// the order in which the sensors are checked for a line, the inner sensors are the
+/// ones that NAVCON reacts to
+const SENSOR_PRIORITY: [usize; 5] = [1, 3, 2, 0, 4];
+
+/// How the emulated SS determines the incidence that it reports in packet 178
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IncidenceMode {
+    /// the true incidence, from the MARV's heading and the line under its sensors
+    #[default]
+    Geometric,
+    /// the estimate that a typical SS makes from the distance travelled between
+    /// an outer and an inner sensor seeing the line
+    StudentEstimate,
+}
+
+impl fmt::Display for IncidenceMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IncidenceMode::Geometric => write!(f, "Geometric"),
+            IncidenceMode::StudentEstimate => write!(f, "Student estimate"),
+        }
+    }
+}
+
+/// Both incidences that the emulated SS worked out for one reading, and what it reported
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IncidenceReport {
+    /// the true incidence (in degrees) and the sensor whose line it was measured against,
+    /// if any sensor is on a line
+    pub geometric: Option<(usize, f32)>,
+    /// the estimated incidence (in degrees), if the sensors were in a pattern that
+    /// allows it to be estimated
+    pub estimated: Option<u8>,
+    /// the incidence (in degrees) sent in packet 178
+    pub reported: u8,
+    /// which of the two was reported
+    pub mode: IncidenceMode,
+}
+
+impl IncidenceReport {
+    /// the difference (in degrees) between the estimated and the true incidence, if both are known
+    pub fn estimation_error(&self) -> Option<f32> {
+        match (self.geometric, self.estimated) {
+            (Some((_, geometric)), Some(estimated)) => Some(estimated as f32 - geometric),
+            _ => None,
+        }
+    }
+}
+
+/// returns the unit vector in which the MARV is heading, worked out from its sensor positions
+///
+/// The line from sensor 0 to sensor 4 is at a fixed angle to the MARV's heading, which
+/// follows from where `robot` places those sensors (90° when they are level with each other).
+pub fn heading(positions: [(f32, f32); 5], robot: &RobotConfig) -> (f32, f32) {
+    let (dx, dy) = (
+        positions[4].0 - positions[0].0,
+        positions[4].1 - positions[0].1,
+    );
+
+    // the maze is drawn with y pointing down, so the sensors on the left are at a negative angle
+    let (left, right) = (robot.sensors[0], robot.sensors[4]);
+    let array_angle = (left.1 - right.1).atan2(right.0 - left.0);
+
+    if dx == 0.0 && dy == 0.0 {
+        (0.0, 0.0)
+    } else {
+        let angle = dy.atan2(dx) - array_angle;
+        (angle.cos(), angle.sin())
+    }
+}
+
+/// returns the true incidence (in degrees, between 0° and 90°) of the MARV on the line under
+/// the first of its sensors that sees one, along with that sensor's index
+pub fn geometric_incidence(
+    maze: &MazeLineMap,
+    positions: [(f32, f32); 5],
+    robot: &RobotConfig,
+) -> Option<(usize, f32)> {
+    let (x, y) = heading(positions, robot);
+
+    SENSOR_PRIORITY.into_iter().find_map(|sensor| {
+        let (px, py) = positions[sensor];
+
+        maze.get_line_from_coord(px, py)
+            .map(|(_, orientation)| {
+                // the component of the heading along the line's normal
+                let normal_component = match orientation {
+                    LineOrientation::Horizontal => y.abs(),
+                    LineOrientation::Vertical => x.abs(),
+                };
+
+                normal_component.clamp(0.0, 1.0).acos() * (180.0 / PI)
+            })
+            .map(|incidence| (sensor, incidence))
+    })
+}
+
+/// rounds an incidence to the whole degree sent in packet 178
+pub fn to_incidence_byte(incidence: f32) -> u8 {
+    incidence.round().clamp(0.0, 90.0) as u8
+}
+
+/// Estimates the incidence the way a typical SS does: the distance at which an outer sensor
+/// sees a line is stored, and once an inner sensor sees the line the incidence follows from
+/// the distance travelled since, over the distance between the two sensors
+#[derive(Debug)]
+pub struct IncidenceEstimator {
+    reference_distance: u16,
+    /// the sideways distance (in mm) between an outer and an inner sensor
+    sensor_spacing: f32,
+}
+
+impl IncidenceEstimator {
+    pub fn new(sensor_spacing: f32) -> Self {
+        Self {
+            reference_distance: 0,
+            sensor_spacing,
+        }
+    }
+
+    /// returns the estimated incidence (in degrees) for `colours` seen at `distance` (in mm
+    /// since the last stop), if the sensors are in a pattern that allows it to be estimated
+    pub fn estimate(&mut self, colours: &[Colour; 5], distance: u16) -> Option<u8> {
+        if colours[0] != Colour::White || colours[4] != Colour::White {
+            self.reference_distance = distance;
+            None
+        } else if colours[1] != Colour::White || colours[3] != Colour::White {
+            let travelled = distance.saturating_sub(self.reference_distance);
+
+            Some(to_incidence_byte(
+                (travelled as f32 / self.sensor_spacing).atan() * (180.0 / PI),
+            ))
+        } else {
+            None
+        }
+    }
+}
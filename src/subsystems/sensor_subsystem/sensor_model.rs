@@ -0,0 +1,230 @@
+//! # Sensor model
+//!
+//! Describes how far the emulated colour sensors are from perfect sensors. Each
+//! sensor measures the reflectance (red, green and blue) of the spot underneath it
+//! and classifies it as the colour with the closest reference reflectance. The SS
+//! tunes these references in the CAL state, which removes most of a sensor's bias.
+//!
+//! With `SensorModel::ideal()` every sensor reads exactly the colour under its
+//! centre, which is how the emulated SS has always behaved.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{components::colour::Colour, gui::maze::MazeLineMap};
+
+/// the number of readings of each colour that the SS averages while calibrating
+const CALIBRATION_SAMPLES: usize = 32;
+
+/// every colour that a sensor can read, in the order of their codes
+const COLOURS: [Colour; 5] = [
+    Colour::White,
+    Colour::Red,
+    Colour::Green,
+    Colour::Blue,
+    Colour::Black,
+];
+
+/// a reflectance as (red, green, blue), each between 0 and 1
+type Reflectance = [f32; 3];
+
+/// The parameters of the colour sensors used by `ColourSensors`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SensorModel {
+    /// the probability that a reading is replaced by a random other colour
+    pub misclassification: f32,
+    /// the width of the band (in maze units) around the edge of a line in which a
+    /// sensor is unsure of where the edge is
+    pub boundary_blur: f32,
+    /// the radius (in maze units) of the spot that a sensor averages over, a line is
+    /// `MAZE_LINE_WIDTH` wide
+    pub spot_radius: f32,
+    /// the largest random error added to each reflectance that is measured
+    pub noise: f32,
+    /// the offset added to every reflectance that each sensor measures
+    pub bias: [f32; 5],
+    /// whether the SS tunes its colour references in the CAL state, without it the
+    /// references stay at the nominal colours and any bias goes uncorrected
+    pub calibrate: bool,
+    /// the seed of the sensors' random errors, so that they can be repeated exactly, or
+    /// `None` for different errors every run
+    pub seed: Option<u64>,
+}
+
+impl SensorModel {
+    /// perfect sensors
+    pub fn ideal() -> Self {
+        Self {
+            misclassification: 0.0,
+            boundary_blur: 0.0,
+            spot_radius: 0.0,
+            noise: 0.0,
+            bias: [0.0; 5],
+            calibrate: true,
+            seed: None,
+        }
+    }
+
+    /// roughly what the colour sensors of an EPR 320 SS do
+    pub fn realistic() -> Self {
+        Self {
+            misclassification: 0.005,
+            boundary_blur: 1.0,
+            spot_radius: 1.5,
+            noise: 0.05,
+            bias: [0.06, -0.08, 0.1, -0.04, 0.08],
+            calibrate: true,
+            seed: None,
+        }
+    }
+}
+
+impl Default for SensorModel {
+    fn default() -> Self {
+        Self::ideal()
+    }
+}
+
+/// The five colour sensors of the emulated SS
+#[derive(Debug)]
+pub struct ColourSensors {
+    model: SensorModel,
+    /// the reflectance that each sensor expects for each colour (indexed by the colour's code)
+    references: [[Reflectance; 5]; 5],
+    rng: StdRng,
+}
+
+impl ColourSensors {
+    pub fn new(model: SensorModel) -> Self {
+        Self {
+            model,
+            references: [COLOURS.map(nominal_reflectance); 5],
+            rng: match model.seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            },
+        }
+    }
+
+    /// tunes each sensor's references by measuring every colour, as the SS does in the CAL state
+    pub fn calibrate(&mut self) {
+        if !self.model.calibrate {
+            return;
+        }
+
+        for sensor in 0..self.references.len() {
+            for colour in COLOURS {
+                let mut total = [0.0; 3];
+
+                for _ in 0..CALIBRATION_SAMPLES {
+                    let reading = self.measure(sensor, nominal_reflectance(colour));
+                    (0..3).for_each(|channel| total[channel] += reading[channel]);
+                }
+
+                self.references[sensor][colour as usize] =
+                    total.map(|channel| channel / CALIBRATION_SAMPLES as f32);
+            }
+        }
+    }
+
+    /// returns the colour that each sensor reads when it is at the corresponding position in `maze`
+    pub fn read(&mut self, maze: &MazeLineMap, positions: [(f32, f32); 5]) -> [Colour; 5] {
+        let mut colours = [Colour::White; 5];
+
+        for (sensor, position) in positions.into_iter().enumerate() {
+            colours[sensor] = self.read_sensor(maze, sensor, position);
+        }
+
+        colours
+    }
+
+    fn read_sensor(&mut self, maze: &MazeLineMap, sensor: usize, (x, y): (f32, f32)) -> Colour {
+        // average the reflectance over the sensor's spot
+        let offsets = spot_offsets(self.model.spot_radius);
+        let mut total = [0.0; 3];
+
+        for (dx, dy) in offsets.iter() {
+            let blur = self.model.boundary_blur / 2.0;
+            let (jx, jy) = match blur > 0.0 {
+                true => (
+                    self.rng.gen_range(-blur..=blur),
+                    self.rng.gen_range(-blur..=blur),
+                ),
+                false => (0.0, 0.0),
+            };
+
+            let colour = maze
+                .get_colour_from_coord(x + dx + jx, y + dy + jy)
+                .unwrap_or(Colour::White);
+            let reflectance = nominal_reflectance(colour);
+
+            (0..3).for_each(|channel| total[channel] += reflectance[channel]);
+        }
+
+        let reading = self.measure(sensor, total.map(|channel| channel / offsets.len() as f32));
+
+        // classify the reading as the colour with the closest reference
+        let colour = COLOURS
+            .into_iter()
+            .min_by(|a, b| {
+                distance(reading, self.references[sensor][*a as usize])
+                    .total_cmp(&distance(reading, self.references[sensor][*b as usize]))
+            })
+            .unwrap();
+
+        if self.model.misclassification > 0.0
+            && self
+                .rng
+                .gen_bool(self.model.misclassification.min(1.0) as f64)
+        {
+            let others: Vec<Colour> = COLOURS.into_iter().filter(|c| *c != colour).collect();
+            others[self.rng.gen_range(0..others.len())]
+        } else {
+            colour
+        }
+    }
+
+    /// what `sensor` measures for a surface with `reflectance`
+    fn measure(&mut self, sensor: usize, reflectance: Reflectance) -> Reflectance {
+        let bias = self.model.bias[sensor];
+        let noise = self.model.noise;
+
+        reflectance.map(|channel| match noise > 0.0 {
+            true => channel + bias + self.rng.gen_range(-noise..=noise),
+            false => channel + bias,
+        })
+    }
+}
+
+/// the reflectance of each colour in the maze
+fn nominal_reflectance(colour: Colour) -> Reflectance {
+    match colour {
+        Colour::White => [0.95, 0.95, 0.95],
+        Colour::Red => [0.85, 0.15, 0.15],
+        Colour::Green => [0.15, 0.7, 0.25],
+        Colour::Blue => [0.1, 0.2, 0.8],
+        Colour::Black => [0.05, 0.05, 0.05],
+    }
+}
+
+/// the offsets from a sensor's centre at which its spot is sampled
+fn spot_offsets(radius: f32) -> Vec<(f32, f32)> {
+    let mut offsets = vec![(0.0, 0.0)];
+
+    if radius > 0.0 {
+        for ring in [radius / 2.0, radius] {
+            for step in 0..8 {
+                let angle = step as f32 * std::f32::consts::FRAC_PI_4;
+                offsets.push((ring * angle.cos(), ring * angle.sin()));
+            }
+        }
+    }
+
+    offsets
+}
+
+/// the squared distance between two reflectances
+fn distance(a: Reflectance, b: Reflectance) -> f32 {
+    (0..3)
+        .map(|channel| (a[channel] - b[channel]).powi(2))
+        .sum()
+}
@@ -1,43 +1,92 @@
-use std::f32::consts::PI;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 
 use crate::{
     asynchronous::one_to_many_channel::OTMChannel,
     components::{
         adjacent_bytes::AdjacentBytes,
         buffer::BufferUser,
-        colour::Colour,
+        colour::{Colour, Colours},
         comm_port::ControlByte,
-        constants::{B_ISD, CAL_CALIBRATED, CAL_COLOURS, MAZE_END_OF_MAZE},
+        constants::{CAL_CALIBRATED, MAZE_END_OF_MAZE},
         packet::Packet,
+        robot_config::RobotConfig,
         state::SystemState,
     },
     gui::maze::MazeLineMap,
 };
 
+use super::{
+    incidence::{
+        geometric_incidence, to_incidence_byte, IncidenceEstimator, IncidenceMode, IncidenceReport,
+    },
+    sensor_model::{ColourSensors, SensorModel},
+};
+
 #[derive(Debug)]
 pub struct Ss {
     comms: OTMChannel<Packet>,
     state: SystemState,
     curr_positions: [(f32, f32); 5],
     positions_channel: OTMChannel<[(f32, f32); 5]>,
-    reference_distance: u16,
+    sensors: ColourSensors,
+    incidence_mode: IncidenceMode,
+    incidence_estimator: IncidenceEstimator,
+    /// both incidences are sent out on this channel so that they can be compared (e.g. by the GUI)
+    incidence_channel: OTMChannel<IncidenceReport>,
+    /// where the sensors are on the MARV, to work out its heading from their positions
+    robot: RobotConfig,
+    /// cleared when the run has to end, after which the SS reports the end of the maze
+    running: Arc<AtomicBool>,
 }
 
 impl Ss {
-    pub fn new(comms: OTMChannel<Packet>, positions_channel: OTMChannel<[(f32, f32); 5]>) -> Self {
+    pub fn new(
+        comms: OTMChannel<Packet>,
+        positions_channel: OTMChannel<[(f32, f32); 5]>,
+        sensor_model: SensorModel,
+        incidence_mode: IncidenceMode,
+        incidence_channel: OTMChannel<IncidenceReport>,
+        robot: &RobotConfig,
+        running: Arc<AtomicBool>,
+    ) -> Self {
         Self {
             state: SystemState::Idle,
             comms,
             curr_positions: [(0., 0.); 5],
             positions_channel,
-            reference_distance: 0,
+            sensors: ColourSensors::new(sensor_model),
+            incidence_mode,
+            incidence_estimator: IncidenceEstimator::new(robot.outer_sensor_spacing()),
+            incidence_channel,
+            robot: *robot,
+            running,
         }
     }
 
+    /// Whether the run has ended without the SS reaching the end of the maze
+    fn stopped(&self) -> bool {
+        !self.running.load(Ordering::Relaxed)
+    }
+
+    /// writes the colours presently under the sensors, as sent in the CAL state
+    fn write_calibration_colours(&mut self, maze: &MazeLineMap) {
+        if let Ok(new_positions) = self.positions_channel.try_receive() {
+            self.curr_positions = new_positions;
+        }
+
+        let colours = self.sensors.read(maze, self.curr_positions);
+        let bytes = AdjacentBytes::from(Colours::from(colours));
+
+        self.write([113, bytes.msb(), bytes.lsb(), 0]);
+    }
+
     pub fn run(&mut self, maze: &MazeLineMap) {
         let mut end_of_maze = false;
 
-        while !end_of_maze {
+        while !end_of_maze && !self.stopped() {
             match self.state {
                 SystemState::Idle => {
                     /* IDLE */
@@ -49,14 +98,15 @@ impl Ss {
                 }
                 SystemState::Calibrate => {
                     /* CALIBRATE */
+                    self.sensors.calibrate();
                     self.write(CAL_CALIBRATED);
                     self.wait_for_packet(97.into());
-                    self.write(CAL_COLOURS);
+                    self.write_calibration_colours(maze);
 
-                    while self.wait_for_packet(80.into()).dat1() != 1 {
+                    while self.wait_for_packet(80.into()).dat1() != 1 && !self.stopped() {
                         /* WAITING */
                         self.wait_for_packet(97.into());
-                        self.write(CAL_COLOURS);
+                        self.write_calibration_colours(maze);
                     }
 
                     self.state = SystemState::Maze;
@@ -64,25 +114,6 @@ impl Ss {
                 SystemState::Maze => {
                     /* MAZE */
 
-                    let mut colours = Vec::new();
-
-                    // NOTE: recv() blocks this thread until new data is received
-                    if let Ok(new_positions) = self.positions_channel.try_receive() {
-                        self.curr_positions = new_positions;
-                    }
-
-                    // get the colours under each sensor
-                    self.curr_positions.iter().for_each(|sensor_pos| {
-                        colours.push(
-                            maze.get_colour_from_coord(sensor_pos.0, sensor_pos.1)
-                                .expect("FATAL: colour in maze not found"),
-                        )
-                    });
-
-                    if colours.iter().all(|colour| *colour == Colour::Red) {
-                        end_of_maze = true;
-                    }
-
                     if self.wait_for_packet(145.into()).dat1() == 1 {
                         self.state = SystemState::Sos;
                         break;
@@ -99,6 +130,20 @@ impl Ss {
                         distance_packet.dat0(),
                     ));
 
+                    // the sensors are read once the MDPS has reported its distance, so that the
+                    // colours are the ones seen at that distance
+                    if let Ok(new_positions) = self.positions_channel.try_receive() {
+                        self.curr_positions = new_positions;
+                    }
+
+                    // get the colours under each sensor
+                    let colours = self.sensors.read(maze, self.curr_positions);
+
+                    // the run also ends when it is stopped early (e.g. by the run monitor)
+                    if colours.iter().all(|colour| *colour == Colour::Red) || self.stopped() {
+                        end_of_maze = true;
+                    }
+
                     if end_of_maze {
                         self.write(MAZE_END_OF_MAZE);
                     } else {
@@ -106,29 +151,25 @@ impl Ss {
 
                         println!("{:?}", colours);
 
-                        if colours.contains(&Colour::Blue) {
-                            println!("beewhoop");
-                        }
-
-                        let mut angle = 0;
-
-                        if colours[0] != Colour::White || colours[4] != Colour::White {
-                            self.reference_distance = distance;
-                        } else if colours[1] != Colour::White
-                            || colours[3] != Colour::White
-                                && (colours[0] == Colour::White && colours[4] == Colour::White)
-                        {
-                            let mut travelled = distance as i16 - self.reference_distance as i16;
-
-                            if travelled < 0 {
-                                travelled = 0;
-                            }
-
-                            if colours.contains(&Colour::Red) {
-                                println!("beep");
-                            }
-
-                            angle = ((travelled as f32 / B_ISD as f32).atan() * (180.0 / PI)) as u8;
+                        // work out the true incidence as well as the estimate, and report
+                        // whichever one is selected
+                        let geometric = geometric_incidence(maze, self.curr_positions, &self.robot);
+                        let estimated = self.incidence_estimator.estimate(&colours, distance);
+
+                        let angle = match self.incidence_mode {
+                            IncidenceMode::Geometric => geometric
+                                .map(|(_, incidence)| to_incidence_byte(incidence))
+                                .unwrap_or(0),
+                            IncidenceMode::StudentEstimate => estimated.unwrap_or(0),
+                        };
+
+                        if geometric.is_some() || estimated.is_some() {
+                            self.incidence_channel.send(IncidenceReport {
+                                geometric,
+                                estimated,
+                                reported: angle,
+                                mode: self.incidence_mode,
+                            });
                         }
 
                         for (index, colour) in colours.into_iter().enumerate() {
@@ -136,10 +177,6 @@ impl Ss {
                         }
                         let bytes: AdjacentBytes = word.into();
 
-                        if angle >= 5 {
-                            println!("weird");
-                        }
-
                         self.write([177, bytes.msb(), bytes.lsb(), 0]);
                         self.write([178, angle, 0, 0]);
                     }
@@ -158,16 +195,24 @@ impl BufferUser for Ss {
         self.comms.send(data.into());
     }
 
-    /// reads from the input buffer
+    /// reads from the input buffer, or returns the end of the maze once the run has ended
     fn read(&mut self) -> Packet {
-        self.comms.receive()
+        loop {
+            if let Ok(packet) = self.comms.try_receive() {
+                return packet;
+            }
+
+            if self.stopped() {
+                return MAZE_END_OF_MAZE.into();
+            }
+        }
     }
 
     fn wait_for_packet(&mut self, control_byte: ControlByte) -> Packet {
         loop {
             let packet = self.read();
 
-            if packet.control_byte() == control_byte {
+            if packet.control_byte() == control_byte || self.stopped() {
                 return packet;
             }
         }
@@ -0,0 +1,92 @@
+//! # SS test card
+//!
+//! A card with a line of each colour that the SS must tell apart, which the MARV is
+//! placed on to test its SS. The lines run across the card, one below the other, far
+//! enough apart that every sensor can be placed on a single line. The emulated SS reads
+//! the card as a maze with a single column.
+
+use std::f32::consts::FRAC_PI_2;
+
+use crate::{
+    components::{
+        colour::Colour,
+        constants::{MAZE_COL_WIDTH, MAZE_LINE_WIDTH, MAZE_ROW_HEIGHT},
+        robot_config::RobotConfig,
+    },
+    gui::maze::MazeLineMap,
+    subsystems::sensor_positions::{sensor_positions, to_maze_coords},
+};
+
+/// the lines of the test card, from top to bottom
+pub const CARD_LINES: [Colour; 6] = [
+    Colour::White,
+    Colour::Red,
+    Colour::Green,
+    Colour::Blue,
+    Colour::Black,
+    Colour::White,
+];
+
+/// the test card, as the emulated SS reads it
+pub fn test_card() -> MazeLineMap {
+    let mut card = MazeLineMap::new(CARD_LINES.len() - 1, 1);
+
+    card.add_column(CARD_LINES.to_vec())
+        .expect("FATAL: the test card has a line of each colour");
+
+    // the lines at the sides of the card are not part of the test
+    for _ in 1..CARD_LINES.len() {
+        card.add_row(vec![Colour::White; 2])
+            .expect("FATAL: the test card has a row between each of its lines");
+    }
+
+    card
+}
+
+/// Where the MARV is placed on the test card, driving down across one of its lines
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Placement {
+    /// the colour of the line that the MARV is crossing
+    pub colour: Colour,
+    /// the angle of incidence (in degrees) at which it crosses the line, with its left
+    /// side ahead of its right side
+    pub incidence: f32,
+    /// how far (in mm) the MARV has driven on since its leading sensor reached the
+    /// middle of the line
+    pub advance: f32,
+}
+
+impl Placement {
+    /// every sensor on the middle of the line of `colour`
+    pub fn on(colour: Colour) -> Self {
+        Self {
+            colour,
+            incidence: 0.0,
+            advance: 0.0,
+        }
+    }
+
+    /// the positions of the sensors of `robot`, in the coordinates of the test card
+    pub fn positions(&self, robot: &RobotConfig) -> [(f32, f32); 5] {
+        let heading = FRAC_PI_2 + self.incidence.to_radians();
+        let line = CARD_LINES
+            .iter()
+            .position(|colour| *colour == self.colour)
+            .unwrap_or_default();
+
+        // the sensors with the MARV's centre at the corner of the card, which are then moved
+        // so that the leading sensor is on the middle of the line, and the middle sensor is
+        // halfway across the card
+        let sensors = sensor_positions(robot, (0.0, 0.0), heading);
+        let leading = sensors.iter().map(|(_, y)| *y).fold(f32::MIN, f32::max);
+        let advance = to_maze_coords((
+            self.advance / 1_000.0 * heading.cos(),
+            self.advance / 1_000.0 * heading.sin(),
+        ));
+
+        let dx = (MAZE_COL_WIDTH + MAZE_LINE_WIDTH) / 2.0 - sensors[2].0 + advance.0;
+        let dy = line as f32 * MAZE_ROW_HEIGHT + MAZE_LINE_WIDTH / 2.0 - leading + advance.1;
+
+        sensors.map(|(x, y)| (x + dx, y + dy))
+    }
+}
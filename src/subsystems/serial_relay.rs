@@ -1,3 +1,8 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
 use crate::{
     asynchronous::one_to_many_channel::OTMChannel,
     components::{comm_port::ComPort, packet::Packet},
@@ -7,19 +12,22 @@ pub struct SerialRelay {
     port_number: String,
     port: ComPort,
     channel: OTMChannel<Packet>,
+    /// cleared once the run has ended, which closes the port
+    running: Arc<AtomicBool>,
 }
 
 impl SerialRelay {
-    pub fn new(channel: OTMChannel<Packet>, com_no: &str) -> Self {
+    pub fn new(channel: OTMChannel<Packet>, com_no: &str, running: Arc<AtomicBool>) -> Self {
         Self {
             port_number: String::from(com_no),
             port: ComPort::new(String::from(com_no), 19200),
             channel,
+            running,
         }
     }
 
     pub fn run(&mut self) {
-        loop {
+        while self.running.load(Ordering::Relaxed) {
             if let Ok(com_port_data) = self.port.try_read() {
                 self.channel.send(com_port_data);
             }
@@ -0,0 +1,510 @@
+//! # Sessions
+//!
+//! Every packet sent during a QTP run is recorded, along with who sent it, when, and
+//! where the MARV's sensors were at the time, so that a run (in particular a failed
+//! physical one) can be played back after the lab session without rerunning anything.
+//!
+//! A recording is a CSV file with one packet per line, after the name of the QTP, e.g.
+//!
+//! ```text
+//! # time (s), sender, control byte, dat1, dat0, dec, then (x, y) of each sensor on the maze
+//! qtp,NAVCON QTP 1
+//! 0.000,SNC,16,0,0,0
+//! 0.412,SS,112,0,0,0,68,74.5,48.9,74.5,42.5,74.5,36.1,74.5,17,74.5
+//! ```
+//!
+//! The positions are left out of the packets sent before the MARV's sensors were first
+//! placed on the maze, and out of every packet of a run in which the kit does not know
+//! where the sensors are (e.g. that of a physical MARV). Recordings made before QTPs were
+//! named in them give the number of a NAVCON QTP instead.
+//!
+//! The report of the run is saved next to its recording, once the run has ended.
+
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    asynchronous::one_to_many_channel::OTMChannel,
+    components::{
+        envelope::PacketEnvelope, packet::Packet, robot_config::SENSOR_COUNT, subsystem::Subsystem,
+    },
+    gui::test_windows::registry::{find, navcon_qtp},
+    subsystems::{qtp::Qtp, qtp_report::QtpReport},
+};
+
+/// the directory that runs started from the GUI are recorded in
+pub const SESSIONS_DIRECTORY: &str = "sessions";
+
+const HEADER: &str =
+    "# time (s), sender, control byte, dat1, dat0, dec, then (x, y) of each sensor on the maze";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionError {
+    /// the file could not be read
+    ReadFail(String),
+    /// the file could not be written
+    WriteFail(String),
+    /// the line (counted from 1) is not a valid packet or QTP
+    InvalidLine(usize),
+    /// the file does not say which QTP's maze the run was in
+    MissingQtp,
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::ReadFail(reason) => write!(f, "could not read file: {}", reason),
+            SessionError::WriteFail(reason) => write!(f, "could not write file: {}", reason),
+            SessionError::InvalidLine(line) => write!(f, "line {}: invalid record", line),
+            SessionError::MissingQtp => write!(f, "the recording does not name its QTP"),
+        }
+    }
+}
+
+/// A packet sent during a run, as it was recorded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SessionEvent {
+    /// when the packet was sent (in s), from the start of the run
+    pub time: f32,
+    /// the subsystem that sent the packet
+    pub sender: Subsystem,
+    pub packet: Packet,
+    /// the positions of the sensors (in the same coordinates as the maze) when the packet
+    /// was sent, if they were known
+    pub positions: Option<[(f32, f32); SENSOR_COUNT]>,
+}
+
+impl fmt::Display for SessionEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes: [u8; 4] = self.packet.into();
+
+        write!(
+            f,
+            "{:.3},{},{},{},{},{}",
+            self.time, self.sender, bytes[0], bytes[1], bytes[2], bytes[3]
+        )?;
+
+        if let Some(positions) = self.positions {
+            for (x, y) in positions {
+                write!(f, ",{},{}", x, y)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for SessionEvent {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split(',').map(str::trim).collect();
+
+        if fields.len() < 6 {
+            return Err(());
+        }
+
+        let time = fields[0].parse::<f32>().map_err(|_| ())?;
+        let sender = fields[1].parse()?;
+
+        let mut bytes = [0; 4];
+        for (byte, field) in bytes.iter_mut().zip(&fields[2..6]) {
+            *byte = field.parse().map_err(|_| ())?;
+        }
+
+        let positions = match &fields[6..] {
+            [] => None,
+            coords if coords.len() == SENSOR_COUNT * 2 => {
+                let coords: Vec<f32> = coords
+                    .iter()
+                    .map(|coord| coord.parse::<f32>())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| ())?;
+
+                let mut positions = [(0.0, 0.0); SENSOR_COUNT];
+                for (position, pair) in positions.iter_mut().zip(coords.chunks(2)) {
+                    *position = (pair[0], pair[1]);
+                }
+
+                Some(positions)
+            }
+            _ => return Err(()),
+        };
+
+        Ok(Self {
+            time,
+            sender,
+            packet: bytes.into(),
+            positions,
+        })
+    }
+}
+
+/// A recorded run: every packet sent during it, in the order that they were sent
+#[derive(Debug, Clone)]
+pub struct Session {
+    /// the QTP that the run was of
+    pub qtp: &'static dyn Qtp,
+    pub events: Vec<SessionEvent>,
+}
+
+impl PartialEq for Session {
+    fn eq(&self, other: &Self) -> bool {
+        self.qtp.name() == other.qtp.name() && self.events == other.events
+    }
+}
+
+impl Session {
+    pub fn new(qtp: &'static dyn Qtp) -> Self {
+        Self {
+            qtp,
+            events: Vec::new(),
+        }
+    }
+
+    /// reads the recording from the file at `path`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SessionError> {
+        fs::read_to_string(path)
+            .map_err(|err| SessionError::ReadFail(err.to_string()))?
+            .parse()
+    }
+
+    /// writes the recording to the file at `path`
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SessionError> {
+        fs::write(path, self.to_string()).map_err(|err| SessionError::WriteFail(err.to_string()))
+    }
+
+    /// how long (in s) the recording lasts
+    pub fn duration(&self) -> f32 {
+        self.events.last().map_or(0.0, |event| event.time)
+    }
+
+    /// the number of packets that had been sent by `time` (in s)
+    pub fn events_until(&self, time: f32) -> usize {
+        self.events.partition_point(|event| event.time <= time)
+    }
+
+    /// every packet in the recording, in an envelope numbered by the order it was sent in
+    pub fn envelopes(&self) -> impl Iterator<Item = PacketEnvelope> + '_ {
+        self.events.iter().enumerate().map(|(sequence, event)| {
+            PacketEnvelope::new(sequence as u32, event.time, event.sender, event.packet)
+        })
+    }
+
+    /// where the sensors were at `time` (in s), if they had been placed on the maze by then
+    pub fn positions_at(&self, time: f32) -> Option<[(f32, f32); SENSOR_COUNT]> {
+        self.events[..self.events_until(time)]
+            .iter()
+            .rev()
+            .find_map(|event| event.positions)
+    }
+}
+
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "qtp,{}", self.qtp.name())?;
+
+        for event in &self.events {
+            writeln!(f, "{}", event)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Session {
+    type Err = SessionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut qtp = None;
+        let mut events = Vec::new();
+
+        for (index, line) in s.lines().enumerate() {
+            let number = index + 1;
+
+            // ignore comments and blank lines
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            match line.split_once(',') {
+                Some(("qtp", value)) => match parse_qtp(value.trim()) {
+                    Some(parsed) => qtp = Some(parsed),
+                    None => return Err(SessionError::InvalidLine(number)),
+                },
+                _ => events.push(
+                    line.parse()
+                        .map_err(|_| SessionError::InvalidLine(number))?,
+                ),
+            }
+        }
+
+        Ok(Self {
+            qtp: qtp.ok_or(SessionError::MissingQtp)?,
+            events,
+        })
+    }
+}
+
+/// the QTP named `name`, or numbered `name` if it is a NAVCON QTP in an older recording
+fn parse_qtp(name: &str) -> Option<&'static dyn Qtp> {
+    match name.parse() {
+        Ok(number) => navcon_qtp(number),
+        Err(_) => find(name),
+    }
+}
+
+/// where a run is recorded to, and the name of the QTP that it is a run of
+#[derive(Debug, Clone)]
+pub struct Recording {
+    pub path: PathBuf,
+    pub qtp: &'static str,
+}
+
+impl Recording {
+    /// a new recording of a run of `qtp` in `SESSIONS_DIRECTORY`, named after the QTP
+    /// and the time at which the run started
+    pub fn new(qtp: &dyn Qtp) -> Self {
+        let started = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let name = qtp.name().to_lowercase().replace(' ', "_");
+
+        Self {
+            path: Path::new(SESSIONS_DIRECTORY).join(format!("{}_{}.csv", name, started)),
+            qtp: qtp.name(),
+        }
+    }
+
+    /// where the report of the run is saved, next to its recording
+    pub fn report_path(&self) -> PathBuf {
+        self.path.with_extension("report.txt")
+    }
+
+    /// saves `report`, the report of the recorded run, to `report_path`
+    pub fn save_report(&self, report: &QtpReport) {
+        let path = self.report_path();
+        let saved = match path.parent() {
+            Some(directory) => fs::create_dir_all(directory),
+            None => Ok(()),
+        }
+        .and_then(|_| fs::write(&path, report.to_string()));
+
+        if let Err(err) = saved {
+            println!("could not save the report to {}: {}", path.display(), err);
+        }
+    }
+}
+
+/// Captures every packet sent during a run, as it is sent: each packet is passed on in
+/// an envelope, and written to a file if the run is being recorded
+pub struct SessionRecorder {
+    /// where the run is recorded to, if it is
+    recording: Option<Recording>,
+    /// the packets sent by each subsystem are received on these channels...
+    packets: Vec<(Subsystem, OTMChannel<Packet>)>,
+    /// ...and the positions of the sensors on this one
+    positions: OTMChannel<[(f32, f32); SENSOR_COUNT]>,
+    /// every captured packet is sent out on this channel
+    captured: OTMChannel<PacketEnvelope>,
+    /// cleared once the run has ended
+    running: Arc<AtomicBool>,
+}
+
+impl SessionRecorder {
+    pub fn new(
+        recording: Option<Recording>,
+        packets: Vec<(Subsystem, OTMChannel<Packet>)>,
+        positions: OTMChannel<[(f32, f32); SENSOR_COUNT]>,
+        captured: OTMChannel<PacketEnvelope>,
+        running: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            recording,
+            packets,
+            positions,
+            captured,
+            running,
+        }
+    }
+
+    /// captures every packet until the run ends
+    pub fn run(&mut self) {
+        let mut file = match &self.recording {
+            Some(recording) => match Self::create(recording) {
+                Ok(file) => Some(file),
+                Err(err) => {
+                    self.report(err);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let start = SystemTime::now();
+        let mut positions = None;
+        let mut sequence = 0;
+
+        loop {
+            // anything sent before the run ended is still captured
+            let running = self.running.load(Ordering::Relaxed);
+
+            while let Ok(latest) = self.positions.try_receive() {
+                positions = Some(latest);
+            }
+
+            // the packets found in one pass over the channels are captured at the same time,
+            // as a reply may be found before the packet that it replies to
+            let time = start.elapsed().unwrap_or_default().as_secs_f32();
+            let mut idle = true;
+
+            for (sender, channel) in self.packets.iter_mut() {
+                while let Ok(packet) = channel.try_receive() {
+                    let event = SessionEvent {
+                        time,
+                        sender: *sender,
+                        packet,
+                        positions,
+                    };
+
+                    self.captured
+                        .send(PacketEnvelope::new(sequence, event.time, *sender, packet));
+                    sequence += 1;
+
+                    if let Some(Err(err)) = file.as_mut().map(|file| writeln!(file, "{}", event)) {
+                        println!("could not record the session: {}", err);
+                        file = None;
+                    }
+
+                    idle = false;
+                }
+            }
+
+            if !running {
+                break;
+            }
+
+            if idle {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+
+        if let Some(Err(err)) = file.as_mut().map(|file| file.flush()) {
+            self.report(err);
+        }
+    }
+
+    /// creates the file that the run is recorded to, with the recording's header
+    fn create(recording: &Recording) -> io::Result<BufWriter<File>> {
+        if let Some(directory) = recording.path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+
+        let mut file = BufWriter::new(File::create(&recording.path)?);
+        writeln!(file, "{}", HEADER)?;
+        writeln!(file, "qtp,{}", recording.qtp)?;
+
+        Ok(file)
+    }
+
+    fn report(&self, err: io::Error) {
+        if let Some(recording) = &self.recording {
+            println!(
+                "could not record the session to {}: {}",
+                recording.path.display(),
+                err
+            );
+        }
+    }
+}
+
+/// Plays a recorded run back, at any speed, from any point in the recording
+pub struct SessionPlayer {
+    session: Session,
+    /// how far (in s) into the recording the playback is
+    time: f32,
+    /// whether the playback moves on with time
+    pub playing: bool,
+    /// how many times faster than real time the recording is played back
+    pub speed: f32,
+}
+
+impl SessionPlayer {
+    pub fn new(session: Session) -> Self {
+        Self {
+            session,
+            time: 0.0,
+            playing: false,
+            speed: 1.0,
+        }
+    }
+
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// moves the playback to `time` (in s), within the recording
+    pub fn seek(&mut self, time: f32) {
+        self.time = time.clamp(0.0, self.session.duration());
+    }
+
+    /// moves the playback on by `elapsed` (in s) of real time if it is playing, and
+    /// pauses it at the end of the recording
+    pub fn advance(&mut self, elapsed: f32) {
+        if self.playing {
+            self.seek(self.time + elapsed * self.speed);
+            self.playing = self.time < self.session.duration();
+        }
+    }
+
+    /// moves the playback to the next packet that was sent
+    pub fn step_forward(&mut self) {
+        if let Some(event) = self
+            .session
+            .events
+            .get(self.session.events_until(self.time))
+        {
+            self.time = event.time;
+        }
+    }
+
+    /// moves the playback back to the last packet sent before this point
+    pub fn step_backward(&mut self) {
+        let earlier = self
+            .session
+            .events
+            .partition_point(|event| event.time < self.time);
+
+        self.time = match earlier.checked_sub(1) {
+            Some(index) => self.session.events[index].time,
+            None => 0.0,
+        };
+    }
+
+    /// every packet sent up to this point in the playback
+    pub fn events(&self) -> &[SessionEvent] {
+        &self.session.events[..self.session.events_until(self.time)]
+    }
+
+    /// where the sensors were at this point in the playback
+    pub fn positions(&self) -> Option<[(f32, f32); SENSOR_COUNT]> {
+        self.session.positions_at(self.time)
+    }
+}
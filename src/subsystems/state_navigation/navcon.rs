@@ -1,11 +1,37 @@
+//! # NAVCON
+//!
+//! The navigation control of the SNC, implemented according to the NAVCON
+//! specifications (section 9b of the practical guide):
+//!
+//! - GREEN/RED at θi <= 5°: cross the line
+//! - GREEN/RED at 5° < θi <= 45°: stop, reverse, rotate θi toward the line, try again
+//! - GREEN/RED at θi > 45°: stop, reverse, rotate 5° toward the line, try again
+//! - BLUE/BLACK at θi <= 45°: stop, reverse, turn RIGHT by 90° ± θi, or turn 180°
+//!   if a wall is met again before a green line was crossed
+//! - BLUE/BLACK at θi > 45°: stop, reverse, rotate 5° away from the line, try again
+//! - end-of-maze: stop and stay stopped
+//!
+//! Only the two inner sensors (1 and 3) trigger a rule, the outer sensors (0 and 4)
+//! are the first two sensors that are allowed to cross the line.
+
+use std::fmt;
+
 use crate::components::{
     adjacent_bytes::AdjacentBytes,
     colour::{Colour, Colours},
     comm_port::ControlByte,
-    constants::B_ISD,
     packet::Packet,
 };
 
+/// the distance (in mm) that the MARV reverses away from a line before rotating
+const REVERSE_DISTANCE: u16 = 30;
+/// the largest angle of incidence (in degrees) at which a GREEN/RED line may be crossed
+const MAX_CROSSING_INCIDENCE: u8 = 5;
+/// incidence angles (in degrees) above this only get a small steering correction
+const MAX_TURNING_INCIDENCE: u8 = 45;
+/// the rotation (in degrees) used for steering corrections
+const STEERING_CORRECTION: u16 = 5;
+
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum NavConState {
     Forward,
@@ -15,17 +41,68 @@ pub enum NavConState {
     RotateRight,
 }
 
-#[derive(Debug)]
-enum Side {
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Side {
     Left,
     Right,
 }
 
+/// The rule that NAVCON applied during a single call to `NavCon::compute_output`
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum NavConRule {
+    /// all sensors see white, so nothing changes
+    NoLine,
+    /// an outer sensor saw a line before either inner sensor, keep going
+    /// forward until the SS has estimated the incidence
+    OuterSensorLine,
+    /// GREEN or RED at θi <= 5°, keep going forward over the line
+    LineCrossed,
+    /// GREEN or RED at 5° < θi <= 45°
+    GreenEncounter,
+    /// GREEN or RED at θi > 45°
+    SteepGreenEncounter,
+    /// BLUE or BLACK at θi <= 45°
+    BlueEncounter,
+    /// BLUE or BLACK at θi > 45°
+    SteepBlueEncounter,
+    /// BLUE or BLACK after a right turn, before a GREEN line was crossed
+    BlueAfterTurn,
+    /// the SS reported that the end of the maze was reached
+    EndOfMaze,
+    /// still reversing away from a line
+    Reversing,
+    /// reversed far enough, stopping before the rotation
+    ReverseComplete,
+    /// leaving the stop state
+    StopComplete,
+    /// a rotation instruction has been sent, continue forward
+    RotationComplete,
+}
+
+/// A record of a single NAVCON step: what it was given, what it looked at,
+/// and what it decided
+#[derive(Clone, Copy, Debug)]
+pub struct NavConDecision {
+    pub step: u32,
+    pub colours: Colours,
+    pub incidence: u8,
+    pub distance: u16,
+    /// the sensor index (and side) that triggered the rule, if any
+    pub trigger: Option<(usize, Side)>,
+    pub rule: NavConRule,
+    pub from: NavConState,
+    pub to: NavConState,
+    pub next_state: NavConState,
+    pub output_rotation: u16,
+    pub previously_encountered_colour: Colour,
+}
+
 #[derive(Debug)]
 struct WorkingData {
     colours: Colours,
     incidence: u8,
     distance: u16,
+    end_of_maze: bool,
 }
 
 #[derive(Debug)]
@@ -33,9 +110,13 @@ pub struct NavCon {
     current_state: NavConState,
     previous_state: NavConState,
     next_state: NavConState,
+    /// the colour of the last line that was crossed or turned at, a BLUE/BLACK here means
+    /// that the MARV has turned right at a wall and has not crossed GREEN since
     previously_encountered_colour: Colour,
     pub output_rotation: u16,
-    reference_distance: u16,
+    maze_completed: bool,
+    step: u32,
+    last_decision: Option<NavConDecision>,
 }
 
 impl NavCon {
@@ -45,8 +126,10 @@ impl NavCon {
             previous_state: NavConState::Forward,
             next_state: NavConState::Forward,
             output_rotation: 0,
-            reference_distance: 0,
             previously_encountered_colour: Colour::White,
+            maze_completed: false,
+            step: 0,
+            last_decision: None,
         }
     }
 
@@ -54,6 +137,7 @@ impl NavCon {
         let mut colours = Colours::new();
         let mut incidence = 0;
         let mut distance = 0;
+        let mut end_of_maze = false;
 
         for packet in packets {
             match packet.control_byte() {
@@ -61,12 +145,18 @@ impl NavCon {
                     distance = u16::from(AdjacentBytes::make(packet.dat1(), packet.dat0()));
                 }
                 ControlByte::MazeColours => {
-                    colours =
-                        Colours::from(u16::from(AdjacentBytes::make(packet.dat1(), packet.dat0())));
+                    let word = u16::from(AdjacentBytes::make(packet.dat1(), packet.dat0()));
+
+                    // a corrupted colour word is ignored, as if no line was seen
+                    match Colours::try_from(word) {
+                        Ok(decoded) => colours = decoded,
+                        Err(err) => println!("SNC: ignored colours {:#06x}, {}", word, err),
+                    }
                 }
                 ControlByte::MazeIncidence => {
                     incidence = packet.dat1();
                 }
+                ControlByte::MazeEndOfMaze => end_of_maze = true,
                 _ => (),
             }
         }
@@ -75,62 +165,107 @@ impl NavCon {
             colours,
             incidence,
             distance,
+            end_of_maze,
         }
     }
 
-    fn green_encounter(&mut self, incidence: u8, side: Side) {
-        // println!("green");
-
-        self.output_rotation = match incidence {
-            0..=5 => return,
-            6..=44 => incidence as u16,
-            _ => 5,
-        };
-
+    /// stop, reverse and then rotate by `rotation` in the direction of `next_state`
+    fn reverse_then_rotate(&mut self, next_state: NavConState, rotation: u16) {
         self.previous_state = NavConState::Forward;
         self.current_state = NavConState::Stop;
+        self.next_state = next_state;
+        self.output_rotation = rotation;
+    }
+
+    /// rotating toward `side` reduces the angle of incidence with a line that was
+    /// detected on that side
+    fn rotate_toward(side: Side) -> NavConState {
+        match side {
+            Side::Left => NavConState::RotateLeft,
+            Side::Right => NavConState::RotateRight,
+        }
+    }
 
-        self.next_state = match side {
+    fn rotate_away_from(side: Side) -> NavConState {
+        match side {
             Side::Left => NavConState::RotateRight,
             Side::Right => NavConState::RotateLeft,
-        };
+        }
+    }
 
-        self.previously_encountered_colour = Colour::Green;
+    fn green_encounter(&mut self, incidence: u8, side: Side, colour: Colour) -> NavConRule {
+        if incidence <= MAX_CROSSING_INCIDENCE {
+            // only crossing the line counts, a correction toward it does not
+            self.previously_encountered_colour = colour;
+            NavConRule::LineCrossed
+        } else if incidence <= MAX_TURNING_INCIDENCE {
+            self.reverse_then_rotate(Self::rotate_toward(side), incidence as u16);
+            NavConRule::GreenEncounter
+        } else {
+            self.reverse_then_rotate(Self::rotate_toward(side), STEERING_CORRECTION);
+            NavConRule::SteepGreenEncounter
+        }
     }
 
-    fn blue_encounter(&mut self, incidence: u8, side: Side) {
-        self.previous_state = NavConState::Forward;
-        self.current_state = NavConState::Stop;
-        self.next_state = NavConState::RotateRight;
+    fn blue_encounter(&mut self, incidence: u8, side: Side, colour: Colour) -> NavConRule {
+        if incidence > MAX_TURNING_INCIDENCE {
+            // steer clear of the wall, this does not count as having turned at it
+            self.reverse_then_rotate(Self::rotate_away_from(side), STEERING_CORRECTION);
+            return NavConRule::SteepBlueEncounter;
+        }
+
+        if matches!(
+            self.previously_encountered_colour,
+            Colour::Blue | Colour::Black
+        ) {
+            // a right turn followed by a 180° turn is effectively a left turn, after
+            // which the next wall is treated as a new encounter
+            self.reverse_then_rotate(NavConState::RotateLeft, 180);
+            self.previously_encountered_colour = Colour::White;
+            return NavConRule::BlueAfterTurn;
+        }
 
-        self.output_rotation = match side {
+        // turn right so that the line is parallel to the MARV on its left
+        let rotation = match side {
             Side::Left => 90 - incidence as u16,
             Side::Right => 90 + incidence as u16,
         };
 
-        if self.previously_encountered_colour == Colour::Blue {
-            self.output_rotation += 90;
-        }
+        self.reverse_then_rotate(NavConState::RotateRight, rotation);
+        self.previously_encountered_colour = colour;
 
-        self.previously_encountered_colour = Colour::Blue;
+        NavConRule::BlueEncounter
     }
 
     fn handle_incidence_with_line(
         &mut self,
         incidence: u8,
-        distance: u16,
         colour: Colour,
         side: Side,
-    ) {
-        if distance as i16 - self.reference_distance as i16 > B_ISD as i16 {
-            self.output_rotation = 5;
-            return;
+    ) -> NavConRule {
+        match colour {
+            Colour::Red | Colour::Green => self.green_encounter(incidence, side, colour),
+            Colour::Black | Colour::Blue => self.blue_encounter(incidence, side, colour),
+            Colour::White => NavConRule::NoLine,
         }
+    }
 
-        match colour {
-            Colour::Red | Colour::Green => self.green_encounter(incidence, side),
-            Colour::Black | Colour::Blue => self.blue_encounter(incidence, side),
-            _ => {}
+    /// picks the inner sensor whose line a rule should be applied to. If both inner
+    /// sensors see a line, a wall (BLUE/BLACK) takes precedence over a GREEN/RED line
+    fn triggering_sensor(colours: [Colour; 5]) -> Option<(usize, Side)> {
+        let is_wall = |colour: Colour| matches!(colour, Colour::Blue | Colour::Black);
+
+        match (colours[1], colours[3]) {
+            (Colour::White, Colour::White) => None,
+            (_, Colour::White) => Some((1, Side::Left)),
+            (Colour::White, _) => Some((3, Side::Right)),
+            (left, right) => {
+                if is_wall(right) && !is_wall(left) {
+                    Some((3, Side::Right))
+                } else {
+                    Some((1, Side::Left))
+                }
+            }
         }
     }
 
@@ -138,63 +273,137 @@ impl NavCon {
         self.current_state
     }
 
+    pub fn previously_encountered_colour(&self) -> Colour {
+        self.previously_encountered_colour
+    }
+
+    /// whether the end-of-maze has been received from the SS
+    pub fn maze_completed(&self) -> bool {
+        self.maze_completed
+    }
+
+    /// returns the trace of the most recent call to `compute_output`
+    pub fn last_decision(&self) -> Option<NavConDecision> {
+        self.last_decision
+    }
+
     pub fn compute_output(&mut self, packets: [Packet; 5]) {
         let working_data = Self::parse_packets(packets);
+        let colours = working_data.colours.colours();
+        let from = self.current_state;
+        let mut trigger = None;
+
+        if working_data.end_of_maze {
+            self.maze_completed = true;
+        }
 
-        match self.current_state {
-            NavConState::Forward => {
-                if !working_data.colours.all_white() {
-                    for (index, colour) in working_data.colours.into_iter().enumerate() {
-                        if colour != Colour::White {
-                            match index {
-                                1 => {
-                                    self.handle_incidence_with_line(
-                                        working_data.incidence,
-                                        working_data.distance,
-                                        colour,
-                                        Side::Left,
-                                    );
-                                    break;
-                                }
-                                3 => {
-                                    self.handle_incidence_with_line(
-                                        working_data.incidence,
-                                        working_data.distance,
-                                        colour,
-                                        Side::Right,
-                                    );
-                                    break;
-                                }
-                                0 | 4 => self.reference_distance = working_data.distance,
-                                _ => {}
-                            }
+        let rule = if self.maze_completed {
+            self.current_state = NavConState::Stop;
+            self.next_state = NavConState::Stop;
+            NavConRule::EndOfMaze
+        } else {
+            match self.current_state {
+                NavConState::Forward => match Self::triggering_sensor(colours) {
+                    Some((index, side)) => {
+                        trigger = Some((index, side));
+                        self.handle_incidence_with_line(
+                            working_data.incidence,
+                            colours[index],
+                            side,
+                        )
+                    }
+                    None => {
+                        if colours[0] != Colour::White {
+                            trigger = Some((0, Side::Left));
+                        } else if colours[4] != Colour::White {
+                            trigger = Some((4, Side::Right));
+                        }
+
+                        if trigger.is_some() {
+                            NavConRule::OuterSensorLine
+                        } else {
+                            NavConRule::NoLine
                         }
                     }
+                },
+                NavConState::Reverse => {
+                    // until MARV has reversed far enough, keep reversing....
+                    if working_data.distance < REVERSE_DISTANCE {
+                        NavConRule::Reversing
+                    } else {
+                        self.previous_state = NavConState::Reverse;
+                        self.current_state = NavConState::Stop;
+                        NavConRule::ReverseComplete
+                    }
+                }
+                NavConState::Stop => {
+                    self.current_state = match self.previous_state {
+                        NavConState::Forward => NavConState::Reverse,
+                        _ => self.next_state,
+                    };
+                    NavConRule::StopComplete
+                }
+                NavConState::RotateLeft | NavConState::RotateRight => {
+                    self.previous_state = self.current_state;
+                    self.current_state = NavConState::Forward;
+                    NavConRule::RotationComplete
                 }
             }
-            NavConState::Reverse => {
-                // until MARV has reversed for 6cm, keep reversing....
+        };
 
-                //println!("{}", working_data.distance);
-                if working_data.distance < 30 {
-                    return;
-                }
+        self.step += 1;
+        self.last_decision = Some(NavConDecision {
+            step: self.step,
+            colours: working_data.colours,
+            incidence: working_data.incidence,
+            distance: working_data.distance,
+            trigger,
+            rule,
+            from,
+            to: self.current_state,
+            next_state: self.next_state,
+            output_rotation: self.output_rotation,
+            previously_encountered_colour: self.previously_encountered_colour,
+        });
+    }
+}
 
-                self.previous_state = NavConState::Reverse;
-                self.current_state = NavConState::Stop;
-            }
-            NavConState::Stop => {
-                self.current_state = match self.previous_state {
-                    NavConState::Forward => NavConState::Reverse,
-                    _ => self.next_state,
-                };
-            }
-            NavConState::RotateLeft => {
-                self.current_state = NavConState::Forward;
-            }
-            NavConState::RotateRight => {
-                self.current_state = NavConState::Forward;
-            }
+impl fmt::Display for NavConRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NavConRule::NoLine => write!(f, "no_line"),
+            NavConRule::OuterSensorLine => write!(f, "outer_sensor_line"),
+            NavConRule::LineCrossed => write!(f, "line_crossed"),
+            NavConRule::GreenEncounter => write!(f, "green_encounter"),
+            NavConRule::SteepGreenEncounter => write!(f, "steep_green_encounter"),
+            NavConRule::BlueEncounter => write!(f, "blue_encounter"),
+            NavConRule::SteepBlueEncounter => write!(f, "steep_blue_encounter"),
+            NavConRule::BlueAfterTurn => write!(f, "blue_after_turn"),
+            NavConRule::EndOfMaze => write!(f, "end_of_maze"),
+            NavConRule::Reversing => write!(f, "reversing"),
+            NavConRule::ReverseComplete => write!(f, "reverse_complete"),
+            NavConRule::StopComplete => write!(f, "stop_complete"),
+            NavConRule::RotationComplete => write!(f, "rotation_complete"),
+        }
+    }
+}
+
+impl fmt::Display for NavConDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "#{} {:?} -> {:?} | {}",
+            self.step, self.from, self.to, self.rule
+        )?;
+
+        if let Some((index, side)) = self.trigger {
+            write!(f, " (sensor {}, {:?})", index, side)?;
         }
+
+        write!(
+            f,
+            " | θi = {}°, d = {}mm, rot = {}°, next = {:?}",
+            self.incidence, self.distance, self.output_rotation, self.next_state
+        )
     }
 }
@@ -3,28 +3,46 @@
 //! The state and navigation control (SNC) subsystem is responsible for controlling
 //! the state of the system and navigating it through a maze.
 
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
 use crate::{
     asynchronous::one_to_many_channel::OTMChannel,
     components::{
         adjacent_bytes::AdjacentBytes,
         buffer::BufferUser,
+        calibration::CalibrationCheck,
         comm_port::ControlByte,
         constants::{
             CAL_BUTTON_TOUCHED, IDLE_BUTTON_TOUCHED, MAZE_BUTTON_NOT_TOUCHED, MAZE_CLAPSNAP_NONE,
-            MAZE_NAVCON_FORWARD, MAZE_NAVCON_REVERSE, MAZE_NAVCON_STOP,
+            MAZE_END_OF_MAZE, MAZE_NAVCON_FORWARD, MAZE_NAVCON_REVERSE, MAZE_NAVCON_STOP,
         },
         packet::Packet,
         state::SystemState,
     },
-    subsystems::state_navigation::navcon::{NavCon, NavConState},
+    subsystems::state_navigation::navcon::{NavCon, NavConDecision, NavConState},
 };
 
 /// The struct that allows the system to emulate the SNC
 #[derive(Debug)]
 pub struct Snc {
     comms: OTMChannel<Packet>,
+    /// every NAVCON decision is sent out on this channel so that it can be
+    /// inspected (e.g. by the GUI)
+    decisions: OTMChannel<NavConDecision>,
     state: SystemState,
     navcon: NavCon,
+    /// the operational velocity (in mm/s) that the MDPS is asked to calibrate to
+    operational_velocity: u8,
+    /// the check of the wheel speeds that the MDPS reported after calibrating
+    calibration: Option<CalibrationCheck>,
+    /// the check is sent out on this channel, so that a failed calibration ends the run
+    /// (e.g. by the run monitor)
+    calibrations: OTMChannel<CalibrationCheck>,
+    /// cleared once the run has ended, after which the SNC stops waiting for packets
+    running: Arc<AtomicBool>,
 }
 
 impl Snc {
@@ -34,14 +52,36 @@ impl Snc {
     /// `activate_port` will enable the COM Port (`ComPort`) if `true`
     ///
     /// need to add a way to set the COM port number and baud rate
-    pub fn new(comms: OTMChannel<Packet>) -> Self {
+    pub fn new(
+        comms: OTMChannel<Packet>,
+        decisions: OTMChannel<NavConDecision>,
+        calibrations: OTMChannel<CalibrationCheck>,
+        operational_velocity: u8,
+        running: Arc<AtomicBool>,
+    ) -> Self {
         Self {
             state: SystemState::Idle,
             navcon: NavCon::new(),
             comms,
+            decisions,
+            operational_velocity,
+            calibration: None,
+            calibrations,
+            running,
         }
     }
 
+    /// whether the run has ended without the SNC reaching the end of the maze
+    fn stopped(&self) -> bool {
+        !self.running.load(Ordering::Relaxed)
+    }
+
+    /// the check of the wheel speeds that the MDPS reported after calibrating, if it
+    /// has calibrated
+    pub fn calibration(&self) -> Option<CalibrationCheck> {
+        self.calibration
+    }
+
     /// currently runs a single iteration of the SNC's state machine
     ///
     /// will most likely be changed to run asynchonously until maze completion
@@ -56,15 +96,35 @@ impl Snc {
             Packet::new(178, 0, 0, 0),
         ];
 
-        while !end_of_maze {
+        while !end_of_maze && !self.stopped() {
             match self.state {
                 SystemState::Idle => {
                     /* IDLE */
-                    self.write(IDLE_BUTTON_TOUCHED); // write touch detected to port
+                    // write touch detected to port, along with the vop that the MDPS must calibrate to
+                    let mut touch = IDLE_BUTTON_TOUCHED;
+                    touch[2] = self.operational_velocity;
+                    self.write(touch);
                     self.state = SystemState::Calibrate; // go to calibrate state
                 }
                 SystemState::Calibrate => {
                     /* CALIBRATE */
+                    // the MDPS's end of calibration, which must be within 5% of vop
+                    let calibrated = self.wait_for_packet(96.into());
+
+                    if self.stopped() {
+                        break;
+                    }
+
+                    let check = CalibrationCheck::new(self.operational_velocity, calibrated);
+                    self.calibration = Some(check);
+                    self.calibrations.send(check);
+
+                    // a MARV whose wheels are not calibrated is not let into the maze
+                    if !check.passed() {
+                        println!("SNC: MDPS calibration failed, {}", check);
+                        break;
+                    }
+
                     self.wait_for_packet(113.into());
 
                     self.write(CAL_BUTTON_TOUCHED);
@@ -79,6 +139,10 @@ impl Snc {
                     // run NAVCON and write output:
                     self.navcon.compute_output(packets); // NAVCON
 
+                    if let Some(decision) = self.navcon.last_decision() {
+                        self.decisions.send(decision);
+                    }
+
                     // write navigation control data (Control byte = 147) based on navcon.compute_output()
                     match self.navcon.get_state() {
                         NavConState::Forward => self.write(MAZE_NAVCON_FORWARD),
@@ -98,6 +162,10 @@ impl Snc {
                     // get MDPS packets:
                     self.wait_for_packet(161.into()); // just discard the battery level packet
 
+                    if self.stopped() {
+                        break;
+                    }
+
                     // now should be synchronised
                     for packet in &mut packets {
                         *packet = self.read();
@@ -107,6 +175,16 @@ impl Snc {
                         }
                     }
 
+                    // let NAVCON see the end-of-maze so that it stops, nothing is written
+                    // after it though
+                    if end_of_maze {
+                        self.navcon.compute_output(packets);
+
+                        if let Some(decision) = self.navcon.last_decision() {
+                            self.decisions.send(decision);
+                        }
+                    }
+
                     // --------------------------------------------------------------------------------------------
                 }
                 SystemState::Sos => {
@@ -131,16 +209,24 @@ impl BufferUser for Snc {
         self.comms.send(data.into());
     }
 
-    /// reads from the input buffer
+    /// reads from the input buffer, or returns the end of the maze once the run has ended
     fn read(&mut self) -> Packet {
-        self.comms.receive()
+        loop {
+            if let Ok(packet) = self.comms.try_receive() {
+                return packet;
+            }
+
+            if self.stopped() {
+                return MAZE_END_OF_MAZE.into();
+            }
+        }
     }
 
     fn wait_for_packet(&mut self, control_byte: ControlByte) -> Packet {
         loop {
             let packet = self.read();
 
-            if packet.control_byte() == control_byte {
+            if packet.control_byte() == control_byte || self.stopped() {
                 return packet;
             }
         }
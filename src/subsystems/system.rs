@@ -2,25 +2,42 @@
 //!     will emulate the maze robot
 
 use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
-use crate::asynchronous::async_type::{PacketsEndpoint, PositionsEndpoint};
+use crate::asynchronous::async_type::GuiEndpoints;
 use crate::asynchronous::one_to_many_channel::{Bound, OTMChannel};
 use crate::asynchronous::one_to_one_channel::OTOChannel;
 use crate::components::buffer::Buffer;
-use crate::components::packet::Packet;
+use crate::components::constants::{
+    DEFUALT_COM_PORT, DEFUALT_STARTING_POSITION, IDLE_BUTTON_TOUCHED, NINETY_DEGREES,
+};
+use crate::components::robot_config::RobotConfig;
+use crate::components::subsystem::Subsystem;
 use crate::gui::maze::MazeLineMap;
 
 use crate::subsystems::{
     motor_subsystem::mdps::Mdps, sensor_subsystem::ss::Ss, state_navigation::snc::Snc,
 };
 
-use super::motor_subsystem::wheel::Wheels;
+use super::fault_injection::{self, FaultProfile};
+use super::motor_subsystem::{
+    battery::Battery, motor_model::MotorModel, rotation_control::RotationControl, wheel::Wheels,
+};
+use super::qtp_report::{QtpReport, QtpStep, Verdict};
+use super::run_monitor::{RunLimits, RunMonitor};
 use super::sensor_positions::SensorPosComputer;
+use super::sensor_subsystem::{incidence::IncidenceMode, sensor_model::SensorModel};
 use super::serial_relay::SerialRelay;
+use super::session::{Recording, SessionRecorder};
+
+/// how long an emulated SS is given to report the end of the maze once a run is stopped early
+const END_OF_RUN_GRACE_PERIOD: Duration = Duration::from_secs(1);
+/// how long the run monitor is given to send out the outcome of a run once it has ended
+const OUTCOME_TIMEOUT: Duration = Duration::from_secs(1);
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mode {
     Emulate,
     Physical,
@@ -34,6 +51,72 @@ pub struct System {
 
 impl System {}
 
+/// A QTP that the kit runs against NAVCON, by emulating the whole MARV in a maze
+#[derive(Debug, Clone, Copy)]
+pub struct NavconQtp {
+    /// the number of the QTP, counted from 1, which its recorded runs are named after
+    pub number: u8,
+    /// e.g. "NAVCON QTP 1"
+    pub name: &'static str,
+    /// what the QTP tests, shown before it is run
+    pub description: &'static str,
+    /// the maze that the MARV drives through
+    pub maze: fn() -> MazeLineMap,
+}
+
+impl PartialEq for NavconQtp {
+    fn eq(&self, other: &Self) -> bool {
+        self.number == other.number
+    }
+}
+
+impl Eq for NavconQtp {}
+
+impl fmt::Display for NavconQtp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// The settings of the emulated subsystems for a single run
+#[derive(Debug, Clone, Copy)]
+pub struct EmulationConfig {
+    /// the operational velocity (in mm/s) that the emulated SNC asks the MDPS to calibrate to
+    pub operational_velocity: u8,
+    /// the motor dynamics of the emulated MDPS
+    pub motor_model: MotorModel,
+    /// how the emulated MDPS carries out rotate instructions
+    pub rotation_control: RotationControl,
+    /// the battery of the emulated MDPS, `None` to send zeros as required since 2022
+    pub battery: Option<Battery>,
+    /// the colour sensors of the emulated SS
+    pub sensor_model: SensorModel,
+    /// how the emulated SS determines the incidence that it reports
+    pub incidence_mode: IncidenceMode,
+    /// the geometry of the emulated MARV's chassis
+    pub robot: RobotConfig,
+    /// how long the run may go on for
+    pub run_limits: RunLimits,
+    /// the faults injected into the packets sent between the subsystems
+    pub faults: FaultProfile,
+}
+
+impl Default for EmulationConfig {
+    fn default() -> Self {
+        Self {
+            operational_velocity: IDLE_BUTTON_TOUCHED[2],
+            motor_model: MotorModel::default(),
+            rotation_control: RotationControl::default(),
+            battery: None,
+            sensor_model: SensorModel::default(),
+            incidence_mode: IncidenceMode::default(),
+            robot: RobotConfig::default(),
+            run_limits: RunLimits::default(),
+            faults: FaultProfile::default(),
+        }
+    }
+}
+
 pub fn run_system(
     snc_mode: Mode,
     mdps_mode: Mode,
@@ -44,14 +127,24 @@ pub fn run_system(
     maze: MazeLineMap,
     start_pos: (f32, f32),
     start_angle: f32,
-    // positions data going to the GUI thread
-    to_gui: &PositionsEndpoint,
-    to_gui_packets: &PacketsEndpoint,
+    config: EmulationConfig,
+    // where to record the run to, if it should be recorded
+    recording: Option<Recording>,
+    // cleared to end the run early, and once the run has ended
+    running: Arc<AtomicBool>,
+    // everything going to the GUI thread
+    gui: &GuiEndpoints,
 ) {
     std::thread::sleep(Duration::from_millis(200));
 
-    let wheels = Wheels::new(10.0);
+    let wheels = Wheels::new(&config.robot, config.motor_model, config.battery);
     let thread;
+    // an emulated SS reports the end of the maze once the run is stopped early
+    let ss_ends_run = ss_mode == Mode::Emulate;
+    // cleared once the run has ended, as the packets sent after it is stopped early (such
+    // as the SS's end of the maze) are still passed on by the fault injectors, and still
+    // waited for by an emulated SNC and MDPS
+    let linked = Arc::new(AtomicBool::new(true));
 
     // ENDPOINT variables:
 
@@ -67,29 +160,100 @@ pub fn run_system(
     let to_snc = Arc::new(Mutex::new(Buffer::new()));
     let to_ss = Arc::new(Mutex::new(Buffer::new()));
     let to_mdps = Arc::new(Mutex::new(Buffer::new()));
-    let to_gui_packets = Arc::clone(&to_gui_packets);
+    let to_gui = &gui.positions;
+
+    // endpoints for packets and positions going to the session recorder, one per sender
+    // (which passes the packets on to the GUI)
+    let to_recorder_snc = Arc::new(Mutex::new(Buffer::new()));
+    let to_recorder_ss = Arc::new(Mutex::new(Buffer::new()));
+    let to_recorder_mdps = Arc::new(Mutex::new(Buffer::new()));
+    let to_recorder_positions = Arc::new(Mutex::new(Buffer::new()));
+    let to_recorder_captured = Arc::new(Mutex::new(Buffer::new()));
+
+    // endpoint for NAVCON decisions (nothing is ever sent back to the SNC on this channel)
+    let to_snc_decisions = Arc::new(Mutex::new(Buffer::new()));
+    let to_monitor_decisions = Arc::new(Mutex::new(Buffer::new()));
+
+    // endpoint for the SNC's checks of the MDPS's calibration (nothing is ever sent back to
+    // the SNC on this channel)
+    let to_snc_calibrations = Arc::new(Mutex::new(Buffer::new()));
+    let to_monitor_calibrations = Arc::new(Mutex::new(Buffer::new()));
+
+    // endpoint for incidence reports (nothing is ever sent back to the SS on this channel)
+    let to_ss_incidence = Arc::new(Mutex::new(Buffer::new()));
+
+    // endpoint for poses (nothing is ever sent back to the sensor positions computer on this channel)
+    let to_pos_computer_poses = Arc::new(Mutex::new(Buffer::new()));
+    let to_monitor_poses = Arc::new(Mutex::new(Buffer::new()));
+
+    // endpoint for the run's outcome (nothing is ever sent back to the run monitor on this channel)
+    let to_monitor_outcome = Arc::new(Mutex::new(Buffer::new()));
 
     // ==================================================================================================================
 
     // CHANNEL variables:
 
-    // packet channels (comms between 3 threads):
-    let snc_channel: OTMChannel<Packet> = OTMChannel::with_endpoints(
+    // packet channels (comms between 3 threads), which pass each packet to the recorder
+    // first, so that the packet that ends the run is captured before the run ends
+    // (each through a fault injector, if the faults of the run affect its packets)
+    let snc_channel = fault_injection::link(
         "SNC",
+        Subsystem::Snc,
         &to_snc,
-        vec![&to_ss, &to_mdps, &to_gui_packets],
-        Bound::Inifinity,
+        vec![&to_recorder_snc, &to_ss, &to_mdps],
+        config.faults,
+        &linked,
     );
-    let ss_channel: OTMChannel<Packet> = OTMChannel::with_endpoints(
+    let ss_channel = fault_injection::link(
         "SS",
+        Subsystem::Ss,
         &to_ss,
-        vec![&to_snc, &to_mdps, &to_gui_packets],
-        Bound::Inifinity,
+        vec![&to_recorder_ss, &to_snc, &to_mdps],
+        config.faults,
+        &linked,
     );
-    let mdps_channel: OTMChannel<Packet> = OTMChannel::with_endpoints(
+    let mdps_channel = fault_injection::link(
         "MDPS",
+        Subsystem::Mdps,
         &to_mdps,
-        vec![&to_snc, &to_ss, &to_gui_packets],
+        vec![&to_recorder_mdps, &to_snc, &to_ss],
+        config.faults,
+        &linked,
+    );
+
+    // NAVCON decisions channels (SNC to GUI and run monitor):
+    let snc_decisions_channel = OTMChannel::with_endpoints(
+        "SNC (Decisions)",
+        &to_snc_decisions,
+        vec![&gui.decisions, &to_monitor_decisions],
+        Bound::Inifinity,
+    );
+
+    let monitor_comms_decisions = OTMChannel::new(
+        "Run Monitor (Decisions)",
+        &to_monitor_decisions,
+        Bound::Inifinity,
+    );
+
+    // calibration check channels (SNC to run monitor):
+    let snc_calibrations_channel = OTMChannel::with_endpoints(
+        "SNC (Calibrations)",
+        &to_snc_calibrations,
+        vec![&to_monitor_calibrations],
+        Bound::Inifinity,
+    );
+
+    let monitor_comms_calibrations = OTMChannel::new(
+        "Run Monitor (Calibrations)",
+        &to_monitor_calibrations,
+        Bound::Inifinity,
+    );
+
+    // incidence reports channel (SS to GUI):
+    let ss_incidence_channel = OTMChannel::with_endpoints(
+        "SS (Incidence)",
+        &to_ss_incidence,
+        vec![&gui.incidence],
         Bound::Inifinity,
     );
 
@@ -108,69 +272,254 @@ pub fn run_system(
     let sensor_pos_comms_positions = OTMChannel::with_endpoints(
         "Sensor Positions Channel (Positions)",
         &to_pos_computer_positions,
-        vec![to_gui, &to_ss_positions],
+        vec![to_gui, &to_ss_positions, &to_recorder_positions],
         Bound::Finite(1),
     );
 
     let ss_comms_positions = OTMChannel::with_endpoints(
         "SS (Positions)",
         &to_ss_positions,
-        vec![to_gui, &to_pos_computer_positions],
+        vec![to_gui, &to_pos_computer_positions, &to_recorder_positions],
         Bound::Finite(1),
     );
 
+    // poses channels (sensor positions computer to GUI and run monitor):
+    let sensor_pos_comms_poses = OTMChannel::with_endpoints(
+        "Sensor Positions Channel (Poses)",
+        &to_pos_computer_poses,
+        vec![&gui.poses, &to_monitor_poses],
+        Bound::Inifinity,
+    );
+
+    let monitor_comms_poses =
+        OTMChannel::new("Run Monitor (Poses)", &to_monitor_poses, Bound::Inifinity);
+
+    // outcome channel (run monitor to GUI):
+    let monitor_comms_outcome = OTMChannel::with_endpoints(
+        "Run Monitor (Outcome)",
+        &to_monitor_outcome,
+        vec![&gui.outcome],
+        Bound::Inifinity,
+    );
+
+    // captured packets channel (session recorder to GUI):
+    let recorder_comms_captured = OTMChannel::with_endpoints(
+        "Recorder (Captured)",
+        &to_recorder_captured,
+        vec![&gui.packets],
+        Bound::Inifinity,
+    );
+
     // ==================================================================================================================
 
     let mut sensor_position_computer = SensorPosComputer::new(
-        start_pos.0,
-        start_pos.1,
+        start_pos,
+        start_angle,
+        &config.robot,
         sensor_pos_comms_speeds,
         sensor_pos_comms_positions,
-        start_angle,
+        sensor_pos_comms_poses,
+        Arc::clone(&running),
     );
 
     std::thread::spawn(move || sensor_position_computer.compute_pos());
 
+    let mut run_monitor = RunMonitor::new(
+        maze.clone(),
+        config.run_limits,
+        monitor_comms_poses,
+        monitor_comms_decisions,
+        monitor_comms_calibrations,
+        monitor_comms_outcome,
+        Arc::clone(&running),
+    );
+
+    std::thread::spawn(move || run_monitor.run());
+
+    let mut session_recorder = SessionRecorder::new(
+        recording,
+        vec![
+            (
+                Subsystem::Snc,
+                OTMChannel::new("Recorder (SNC)", &to_recorder_snc, Bound::Inifinity),
+            ),
+            (
+                Subsystem::Ss,
+                OTMChannel::new("Recorder (SS)", &to_recorder_ss, Bound::Inifinity),
+            ),
+            (
+                Subsystem::Mdps,
+                OTMChannel::new("Recorder (MDPS)", &to_recorder_mdps, Bound::Inifinity),
+            ),
+        ],
+        OTMChannel::new(
+            "Recorder (Positions)",
+            &to_recorder_positions,
+            Bound::Finite(1),
+        ),
+        recorder_comms_captured,
+        Arc::clone(&running),
+    );
+
+    let recorder = std::thread::spawn(move || session_recorder.run());
+
     // ==================================================================================================================
 
     // run their emulations if required, or setup a serial port relay if not
     match snc_mode {
         Mode::Emulate => {
-            let mut snc = Snc::new(snc_channel);
+            let mut snc = Snc::new(
+                snc_channel,
+                snc_decisions_channel,
+                snc_calibrations_channel,
+                config.operational_velocity,
+                Arc::clone(&linked),
+            );
             thread = std::thread::spawn(move || snc.run());
         }
         Mode::Physical => {
-            let mut relay = SerialRelay::new(snc_channel, "10");
+            let mut relay = SerialRelay::new(snc_channel, "10", Arc::clone(&running));
             thread = std::thread::spawn(move || relay.run());
         }
     }
 
     match ss_mode {
         Mode::Emulate => {
-            let mut ss = Ss::new(ss_channel, ss_comms_positions);
+            let mut ss = Ss::new(
+                ss_channel,
+                ss_comms_positions,
+                config.sensor_model,
+                config.incidence_mode,
+                ss_incidence_channel,
+                &config.robot,
+                Arc::clone(&running),
+            );
             std::thread::spawn(move || ss.run(&maze));
         }
         Mode::Physical => {
-            let mut relay = SerialRelay::new(ss_channel, "10");
+            let mut relay = SerialRelay::new(ss_channel, "10", Arc::clone(&running));
             std::thread::spawn(move || relay.run());
         }
     }
 
     match mdps_mode {
         Mode::Emulate => {
-            let mut mdps = Mdps::new(mdps_channel, mdps_comms_speeds, wheels);
+            let mut mdps = Mdps::new(
+                mdps_channel,
+                mdps_comms_speeds,
+                wheels,
+                config.rotation_control,
+                Arc::clone(&linked),
+            );
             std::thread::spawn(move || mdps.run());
         }
         Mode::Physical => {
-            let mut relay = SerialRelay::new(mdps_channel, "10");
+            let mut relay = SerialRelay::new(mdps_channel, "10", Arc::clone(&running));
             std::thread::spawn(move || relay.run());
         }
     }
 
-    thread.join().expect("could not join SNC thread");
+    // the SNC ends at the end of the maze, which an emulated SS reports shortly after the
+    // run is stopped early, as long as the MARV is in the maze by then
+    while !thread.is_finished() && running.load(Ordering::Relaxed) {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    let stopped = SystemTime::now();
+
+    while ss_ends_run
+        && !thread.is_finished()
+        && stopped.elapsed().unwrap_or_default() < END_OF_RUN_GRACE_PERIOD
+    {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    if thread.is_finished() {
+        thread.join().expect("could not join SNC thread");
+    }
+
+    // stop the threads that only end with the run
+    running.store(false, Ordering::Relaxed);
+    linked.store(false, Ordering::Relaxed);
+
+    // the recording is complete once the recorder has captured everything sent
+    recorder
+        .join()
+        .expect("could not join session recorder thread");
     println!("system function ended");
 }
 
+/// runs `qtp` with every subsystem emulated, as set up by `config`, and returns its report,
+/// which passes if the MARV reached the end of the maze
+///
+/// Everything that happens during the run is passed on to the GUI, as by `run_system`.
+pub fn run_navcon(
+    qtp: &NavconQtp,
+    config: EmulationConfig,
+    recording: Option<Recording>,
+    gui: &GuiEndpoints,
+) -> QtpReport {
+    let start = SystemTime::now();
+    let mut report = QtpReport::new(qtp.name);
+
+    // the outcome of the run goes to the kit first, which passes it on to the GUI
+    let to_kit_outcome = Arc::new(Mutex::new(Buffer::new()));
+    let endpoints = GuiEndpoints {
+        outcome: Arc::clone(&to_kit_outcome),
+        ..gui.clone()
+    };
+
+    run_system(
+        Mode::Emulate,
+        Mode::Emulate,
+        Mode::Emulate,
+        DEFUALT_COM_PORT,
+        DEFUALT_COM_PORT,
+        DEFUALT_COM_PORT,
+        (qtp.maze)(),
+        DEFUALT_STARTING_POSITION,
+        NINETY_DEGREES,
+        config,
+        recording.clone(),
+        Arc::new(AtomicBool::new(true)),
+        &endpoints,
+    );
+
+    let ended = SystemTime::now();
+    let mut outcome = None;
+
+    while outcome.is_none() && ended.elapsed().unwrap_or_default() < OUTCOME_TIMEOUT {
+        outcome = to_kit_outcome.lock().unwrap().read();
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    let step = QtpStep {
+        time: start.elapsed().unwrap_or_default().as_secs_f32(),
+        name: String::from("run through the maze"),
+        verdict: match outcome {
+            Some(outcome) if !outcome.failed() => Verdict::Pass,
+            _ => Verdict::Fail,
+        },
+        detail: match outcome {
+            Some(outcome) => outcome.to_string(),
+            None => String::from("the run monitor never sent the outcome of the run"),
+        },
+    };
+
+    if let Some(outcome) = outcome {
+        gui.outcome.lock().unwrap().write(outcome);
+    }
+
+    gui.steps.lock().unwrap().write(step.clone());
+    report.steps.push(step);
+
+    if let Some(recording) = &recording {
+        recording.save_report(&report);
+    }
+
+    report
+}
+
 impl fmt::Display for Mode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -0,0 +1,619 @@
+//! # Test bench
+//!
+//! Runs a QTP against a single subsystem, with the kit standing in for the other two.
+//! The kit sends the packets that the other subsystems would, in the order that a QTP
+//! script asks for them, and checks the packets that the subsystem under test sends back
+//! against what the protocol expects, and how long it took to send them.
+//!
+//! The subsystem under test is either physical, connected over a serial port, or
+//! emulated. Steps that need an operator (e.g. to touch the SNC) are only carried out
+//! on a physical subsystem, and are skipped on an emulated one, unless the kit can do
+//! what the operator would itself (e.g. place the emulated SS's sensors on a test card).
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    asynchronous::{
+        async_type::{GuiEndpoints, MeasurementsEndpoint, PromptsEndpoint, StepsEndpoint},
+        one_to_many_channel::{Bound, OTMChannel},
+        one_to_one_channel::OTOChannel,
+    },
+    components::{
+        buffer::Buffer, comm_port::ControlByte, packet::Packet, robot_config::RobotConfig,
+        subsystem::Subsystem,
+    },
+    subsystems::{
+        motor_subsystem::{mdps::Mdps, wheel::Wheels},
+        sensor_subsystem::{ss::Ss, test_card::test_card},
+        state_navigation::snc::Snc,
+    },
+};
+
+use super::{
+    qtp_report::{QtpReport, QtpStep, Verdict},
+    serial_relay::SerialRelay,
+    session::{Recording, SessionRecorder},
+    system::{EmulationConfig, Mode},
+};
+
+/// the longest time that the subsystem under test may take to answer a packet
+pub const RESPONSE_TIMEOUT: Duration = Duration::from_secs(1);
+/// the longest time that the operator is given to do what they are prompted to
+pub const OPERATOR_TIMEOUT: Duration = Duration::from_secs(30);
+/// how long the subsystem under test is watched for packets that it should not send
+pub const QUIET_PERIOD: Duration = Duration::from_millis(200);
+
+/// A QTP that the kit runs against a single subsystem
+#[derive(Debug, Clone, Copy)]
+pub struct BenchQtp {
+    /// e.g. "SNC QTP 1"
+    pub name: &'static str,
+    pub under_test: Subsystem,
+    /// what the QTP tests, shown before it is run
+    pub description: &'static str,
+    /// the steps of the QTP, which stop at the first step that fails
+    pub script: fn(&mut TestBench) -> Result<(), BenchError>,
+}
+
+/// What the operator is asked to do during a QTP
+#[derive(Debug, Clone, PartialEq)]
+pub enum Prompt {
+    /// do something to the subsystem under test, e.g. touch the SNC
+    Act(String),
+    /// measure something and enter it, e.g. how far (in mm) the MARV drove
+    Measure(String),
+}
+
+impl Prompt {
+    pub fn text(&self) -> &str {
+        match self {
+            Prompt::Act(text) | Prompt::Measure(text) => text,
+        }
+    }
+}
+
+/// Why a step of a QTP failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum BenchError {
+    /// the packet with the control byte (or any packet, if there is none) did not arrive
+    /// in time
+    Timeout {
+        control_byte: Option<u8>,
+        timeout: Duration,
+    },
+    /// a packet arrived other than the one with the control byte (or while no packet was
+    /// expected, if there is none)
+    Unexpected {
+        control_byte: Option<u8>,
+        received: Packet,
+    },
+    /// the packet arrived, but what it held was wrong
+    Check(String),
+}
+
+impl fmt::Display for BenchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = |byte: u8| format!("{:?} ({})", ControlByte::from(byte), byte);
+
+        match self {
+            BenchError::Timeout {
+                control_byte: Some(byte),
+                timeout,
+            } => write!(
+                f,
+                "no {} within {:.1} s",
+                name(*byte),
+                timeout.as_secs_f32()
+            ),
+            BenchError::Timeout {
+                control_byte: None,
+                timeout,
+            } => write!(f, "no packet within {:.1} s", timeout.as_secs_f32()),
+            BenchError::Unexpected {
+                control_byte: Some(byte),
+                received,
+            } => write!(
+                f,
+                "expected {}, received {} {:?}",
+                name(*byte),
+                received,
+                received.control_byte()
+            ),
+            BenchError::Unexpected {
+                control_byte: None,
+                received,
+            } => write!(
+                f,
+                "expected no packet, received {} {:?}",
+                received,
+                received.control_byte()
+            ),
+            BenchError::Check(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+/// The kit's side of a QTP: the packets that the subsystem under test sends, and the
+/// subsystems that the kit sends packets as
+pub struct TestBench {
+    /// every packet sent by the subsystem under test
+    receiver: OTMChannel<Packet>,
+    /// the kit sends packets as each of the subsystems that it stands in for on these
+    senders: Vec<(Subsystem, OTMChannel<Packet>)>,
+    /// whether there is an operator at the subsystem under test, i.e. whether it is physical
+    operator: bool,
+    /// the chassis of the MARV that the subsystem under test is part of
+    robot: RobotConfig,
+    /// where the kit sends the positions of the sensors, if the SS under test is emulated
+    sensors: Option<OTMChannel<[(f32, f32); 5]>>,
+    start: SystemTime,
+    report: QtpReport,
+    /// each step is also sent to the GUI as soon as it has been carried out...
+    steps: StepsEndpoint,
+    /// ...as is what the operator is asked to do
+    prompts: PromptsEndpoint,
+    /// what the operator measured, when asked to
+    measurements: MeasurementsEndpoint,
+}
+
+impl TestBench {
+    pub fn new(
+        qtp: &str,
+        receiver: OTMChannel<Packet>,
+        senders: Vec<(Subsystem, OTMChannel<Packet>)>,
+        operator: bool,
+        robot: RobotConfig,
+        gui: &GuiEndpoints,
+    ) -> Self {
+        Self {
+            receiver,
+            senders,
+            operator,
+            robot,
+            sensors: None,
+            start: SystemTime::now(),
+            report: QtpReport::new(qtp),
+            steps: Arc::clone(&gui.steps),
+            prompts: Arc::clone(&gui.prompts),
+            measurements: Arc::clone(&gui.measurements),
+        }
+    }
+
+    /// lets the kit place the sensors of an emulated SS, by sending their positions on
+    /// `positions`
+    pub fn with_sensors(mut self, positions: OTMChannel<[(f32, f32); 5]>) -> Self {
+        self.sensors = Some(positions);
+        self
+    }
+
+    pub fn robot(&self) -> &RobotConfig {
+        &self.robot
+    }
+
+    /// whether the steps that need an operator can be carried out
+    pub fn has_operator(&self) -> bool {
+        self.operator
+    }
+
+    /// how long (in s) the QTP has been running for
+    pub fn time(&self) -> f32 {
+        self.start.elapsed().unwrap_or_default().as_secs_f32()
+    }
+
+    /// sends `data` to the subsystem under test as `sender`, which must be one of the
+    /// subsystems that the kit stands in for
+    pub fn send(&self, sender: Subsystem, data: [u8; 4]) {
+        match self
+            .senders
+            .iter()
+            .find(|(subsystem, _)| *subsystem == sender)
+        {
+            Some((_, channel)) => channel.send(data.into()),
+            None => panic!("FATAL: the kit does not stand in for the {}", sender),
+        }
+    }
+
+    /// the next packet from the subsystem under test, if it arrives within `timeout`
+    pub fn receive(&mut self, timeout: Duration) -> Result<Packet, BenchError> {
+        let start = SystemTime::now();
+
+        loop {
+            if let Ok(packet) = self.receiver.try_receive() {
+                return Ok(packet);
+            }
+
+            if start.elapsed().unwrap_or_default() > timeout {
+                return Err(BenchError::Timeout {
+                    control_byte: None,
+                    timeout,
+                });
+            }
+
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// the next packet from the subsystem under test, which must have `control_byte` and
+    /// arrive within `timeout`
+    pub fn expect(
+        &mut self,
+        control_byte: ControlByte,
+        timeout: Duration,
+    ) -> Result<Packet, BenchError> {
+        let byte = u8::from(control_byte);
+
+        match self.receive(timeout) {
+            Ok(packet) if u8::from(packet.control_byte()) == byte => Ok(packet),
+            Ok(received) => Err(BenchError::Unexpected {
+                control_byte: Some(byte),
+                received,
+            }),
+            Err(_) => Err(BenchError::Timeout {
+                control_byte: Some(byte),
+                timeout,
+            }),
+        }
+    }
+
+    /// the first packet with `control_byte` that `accept`s, ignoring any other packets,
+    /// if it arrives within `timeout`
+    pub fn wait_for<F>(
+        &mut self,
+        control_byte: ControlByte,
+        timeout: Duration,
+        accept: F,
+    ) -> Result<Packet, BenchError>
+    where
+        F: Fn(&Packet) -> bool,
+    {
+        let byte = u8::from(control_byte);
+        let start = SystemTime::now();
+
+        loop {
+            let remaining = timeout.saturating_sub(start.elapsed().unwrap_or_default());
+
+            match self.receive(remaining) {
+                Ok(packet) if u8::from(packet.control_byte()) == byte && accept(&packet) => {
+                    return Ok(packet)
+                }
+                Ok(_) => (),
+                Err(_) => {
+                    return Err(BenchError::Timeout {
+                        control_byte: Some(byte),
+                        timeout,
+                    })
+                }
+            }
+        }
+    }
+
+    /// checks that the subsystem under test sends nothing for `duration`
+    pub fn quiet(&mut self, duration: Duration) -> Result<(), BenchError> {
+        match self.receive(duration) {
+            Ok(received) => Err(BenchError::Unexpected {
+                control_byte: None,
+                received,
+            }),
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// records the step `name`, in which `end` takes the subsystem under test to the end of
+    /// the maze, and which checks that it then sends nothing for `QUIET_PERIOD`
+    ///
+    /// `end` returns how the maze ended, e.g. "179 sent".
+    pub fn end_of_maze<F>(&mut self, name: &str, end: F) -> Result<(), BenchError>
+    where
+        F: FnOnce(&mut Self) -> Result<String, BenchError>,
+    {
+        self.step(name, |bench| {
+            let ended = end(bench)?;
+
+            bench.quiet(QUIET_PERIOD)?;
+
+            Ok(format!(
+                "{}, then nothing sent for {} ms",
+                ended,
+                QUIET_PERIOD.as_millis()
+            ))
+        })
+    }
+
+    /// asks the operator to do something, e.g. to touch the SNC
+    pub fn prompt(&self, instruction: &str) {
+        self.prompts
+            .lock()
+            .unwrap()
+            .write(Prompt::Act(String::from(instruction)));
+    }
+
+    /// asks the operator to measure something, and returns what they entered, or `None`
+    /// if they chose not to measure it or ran out of time
+    pub fn measure(&mut self, question: &str) -> Option<f32> {
+        // anything entered before the question was asked does not answer it
+        while self.measurements.lock().unwrap().read().is_some() {}
+
+        self.prompts
+            .lock()
+            .unwrap()
+            .write(Prompt::Measure(String::from(question)));
+
+        let start = SystemTime::now();
+
+        while start.elapsed().unwrap_or_default() < OPERATOR_TIMEOUT {
+            if let Some(measurement) = self.measurements.lock().unwrap().read() {
+                return measurement;
+            }
+
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        None
+    }
+
+    /// asks the operator to place the MARV as `instruction` says, or, if the SS is emulated,
+    /// moves its sensors to `positions` (in the coordinates of the maze) instead
+    ///
+    /// The sensors are read during the next cycle of the MAZE state.
+    pub fn place(&self, instruction: &str, positions: [(f32, f32); 5]) {
+        match &self.sensors {
+            Some(channel) => channel.send(positions),
+            None => self.prompt(instruction),
+        }
+    }
+
+    /// carries out a step, which passes with what `step` returns, or fails with its error,
+    /// which is returned so that the QTP stops
+    pub fn step<F>(&mut self, name: &str, step: F) -> Result<(), BenchError>
+    where
+        F: FnOnce(&mut Self) -> Result<String, BenchError>,
+    {
+        match step(self) {
+            Ok(detail) => {
+                self.record(name, Verdict::Pass, detail);
+                Ok(())
+            }
+            Err(err) => {
+                self.record(name, Verdict::Fail, err.to_string());
+                Err(err)
+            }
+        }
+    }
+
+    /// records a step that could not be carried out, and why
+    pub fn skip(&mut self, name: &str, reason: &str) {
+        self.record(name, Verdict::Skipped, String::from(reason));
+    }
+
+    fn record(&mut self, name: &str, verdict: Verdict, detail: String) {
+        let step = QtpStep {
+            time: self.time(),
+            name: String::from(name),
+            verdict,
+            detail,
+        };
+
+        self.steps.lock().unwrap().write(step.clone());
+        self.report.steps.push(step);
+    }
+
+    pub fn report(&self) -> &QtpReport {
+        &self.report
+    }
+}
+
+/// Runs `qtp` against the subsystem that it tests, which is emulated or physical (on
+/// COM port `com`) according to `mode`, and returns its report
+///
+/// Every packet sent is passed on to the GUI, as during a run of the whole MARV.
+pub fn run_bench(
+    qtp: &BenchQtp,
+    mode: Mode,
+    com: &str,
+    config: EmulationConfig,
+    recording: Option<Recording>,
+    gui: &GuiEndpoints,
+) -> QtpReport {
+    // cleared once the QTP has ended
+    let running = Arc::new(AtomicBool::new(true));
+
+    // ENDPOINT variables:
+
+    // endpoints for packets to the subsystem under test and to the kit
+    let to_under_test = Arc::new(Mutex::new(Buffer::new()));
+    let to_kit = Arc::new(Mutex::new(Buffer::new()));
+
+    // endpoints for packets going to the session recorder, one per sender (which passes
+    // the packets on to the GUI)
+    let to_recorder = [Subsystem::Snc, Subsystem::Ss, Subsystem::Mdps]
+        .map(|subsystem| (subsystem, Arc::new(Mutex::new(Buffer::new()))));
+    let recorder_endpoint = |sender: Subsystem| {
+        to_recorder
+            .iter()
+            .find_map(|(subsystem, endpoint)| (*subsystem == sender).then_some(endpoint))
+            .expect("FATAL: every subsystem has a recorder endpoint")
+    };
+
+    // endpoints for the positions of the sensors of an emulated SS, which only the kit moves
+    // (nothing is ever sent back to the kit on this channel)
+    let to_kit_positions = Arc::new(Mutex::new(Buffer::new()));
+    let to_ss_positions = Arc::new(Mutex::new(Buffer::new()));
+    let to_recorder_positions = Arc::new(Mutex::new(Buffer::new()));
+    let to_recorder_captured = Arc::new(Mutex::new(Buffer::new()));
+
+    // endpoint for NAVCON decisions (nothing is ever sent back to an emulated SNC on this channel)
+    let to_snc_decisions = Arc::new(Mutex::new(Buffer::new()));
+    // endpoint for incidences (nothing is ever sent back to an emulated SS on this channel)
+    let to_ss_incidence = Arc::new(Mutex::new(Buffer::new()));
+    // endpoints for the wheel speeds of an emulated MDPS, which nothing reads as the MARV
+    // is not moved on the bench
+    let to_mdps_speeds = Arc::new(Mutex::new(Buffer::new()));
+    let to_kit_speeds = Arc::new(Mutex::new(Buffer::new()));
+
+    // CHANNEL variables:
+
+    let under_test_channel = OTMChannel::with_endpoints(
+        &qtp.under_test.to_string(),
+        &to_under_test,
+        vec![&to_kit, recorder_endpoint(qtp.under_test)],
+        Bound::Inifinity,
+    );
+
+    let kit_senders = qtp
+        .under_test
+        .others()
+        .iter()
+        .map(|&subsystem| {
+            (
+                subsystem,
+                OTMChannel::with_endpoints(
+                    &format!("Kit ({})", subsystem),
+                    &to_kit,
+                    vec![&to_under_test, recorder_endpoint(subsystem)],
+                    Bound::Inifinity,
+                ),
+            )
+        })
+        .collect();
+
+    let kit_receiver = OTMChannel::new("Kit", &to_kit, Bound::Inifinity);
+
+    let mut bench = TestBench::new(
+        qtp.name,
+        kit_receiver,
+        kit_senders,
+        mode == Mode::Physical,
+        config.robot,
+        gui,
+    );
+
+    let mut session_recorder = SessionRecorder::new(
+        recording.clone(),
+        to_recorder
+            .iter()
+            .map(|(subsystem, endpoint)| {
+                (
+                    *subsystem,
+                    OTMChannel::new(
+                        &format!("Recorder ({})", subsystem),
+                        endpoint,
+                        Bound::Inifinity,
+                    ),
+                )
+            })
+            .collect(),
+        OTMChannel::new(
+            "Recorder (Positions)",
+            &to_recorder_positions,
+            Bound::Finite(1),
+        ),
+        OTMChannel::with_endpoints(
+            "Recorder (Captured)",
+            &to_recorder_captured,
+            vec![&gui.packets],
+            Bound::Inifinity,
+        ),
+        Arc::clone(&running),
+    );
+
+    let recorder = std::thread::spawn(move || session_recorder.run());
+
+    // run the emulation of the subsystem under test, or setup a serial port relay to it
+    match (mode, qtp.under_test) {
+        (Mode::Physical, _) => {
+            let mut relay = SerialRelay::new(under_test_channel, com, Arc::clone(&running));
+            std::thread::spawn(move || relay.run());
+        }
+        (Mode::Emulate, Subsystem::Snc) => {
+            let decisions = OTMChannel::with_endpoints(
+                "SNC (Decisions)",
+                &to_snc_decisions,
+                vec![&gui.decisions],
+                Bound::Inifinity,
+            );
+            // the kit checks the calibration itself
+            let calibrations = OTMChannel::new(
+                "SNC (Calibrations)",
+                &Arc::new(Mutex::new(Buffer::new())),
+                Bound::Inifinity,
+            );
+            let mut snc = Snc::new(
+                under_test_channel,
+                decisions,
+                calibrations,
+                config.operational_velocity,
+                Arc::clone(&running),
+            );
+            std::thread::spawn(move || snc.run());
+        }
+        (Mode::Emulate, Subsystem::Ss) => {
+            // the positions go to the recorder as well, as during a run of the whole MARV
+            let kit_positions = OTMChannel::with_endpoints(
+                "Kit (Positions)",
+                &to_kit_positions,
+                vec![&to_ss_positions, &to_recorder_positions],
+                Bound::Finite(1),
+            );
+            let ss_positions =
+                OTMChannel::new("SS (Positions)", &to_ss_positions, Bound::Finite(1));
+            let incidence = OTMChannel::with_endpoints(
+                "SS (Incidence)",
+                &to_ss_incidence,
+                vec![&gui.incidence],
+                Bound::Inifinity,
+            );
+
+            bench = bench.with_sensors(kit_positions);
+
+            let mut ss = Ss::new(
+                under_test_channel,
+                ss_positions,
+                config.sensor_model,
+                config.incidence_mode,
+                incidence,
+                &config.robot,
+                Arc::clone(&running),
+            );
+            let card = test_card();
+            std::thread::spawn(move || ss.run(&card));
+        }
+        (Mode::Emulate, Subsystem::Mdps) => {
+            let speeds = OTOChannel::new("MDPS (Speeds)", &to_mdps_speeds, &to_kit_speeds);
+            let wheels = Wheels::new(&config.robot, config.motor_model, config.battery);
+
+            let mut mdps = Mdps::new(
+                under_test_channel,
+                speeds,
+                wheels,
+                config.rotation_control,
+                Arc::clone(&running),
+            );
+            std::thread::spawn(move || mdps.run());
+        }
+        (Mode::Emulate, Subsystem::Hub) => panic!("FATAL: the kit is the hub itself"),
+    }
+
+    // the step that failed, if one did, has already been recorded
+    let _ = (qtp.script)(&mut bench);
+
+    // stop the threads that only end with the QTP
+    running.store(false, Ordering::Relaxed);
+
+    // the recording is complete once the recorder has captured everything sent
+    recorder
+        .join()
+        .expect("could not join session recorder thread");
+
+    if let Some(recording) = &recording {
+        recording.save_report(bench.report());
+    }
+
+    bench.report().clone()
+}
@@ -0,0 +1,126 @@
+//! # Trajectory
+//!
+//! Follows the ground-truth poses of the emulated MARV during a run, and measures
+//! what the subsystems themselves cannot: how far the MARV really travelled, how
+//! far its heading is from the maze's lines and how much it really turned.
+
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use super::sensor_positions::Pose;
+
+/// the MARV is turning on the spot while its heading changes faster than this (in rad/s)...
+const TURNING_ANGULAR_VELOCITY: f32 = 0.05;
+/// ...and its centre moves slower than this (in m/s)
+const TURNING_LINEAR_VELOCITY: f32 = 0.005;
+/// poses closer together in time than this (in s) are not kept for plotting
+const SAMPLE_INTERVAL: f32 = 0.05;
+
+/// A rotation of the MARV on the spot
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Turn {
+    /// the heading (in rad) before the turn
+    pub start_heading: f32,
+    /// the heading (in rad) after the turn
+    pub end_heading: f32,
+}
+
+impl Turn {
+    /// the angle (in degrees) turned, positive to the left (counter-clockwise) as in the SCS
+    pub fn degrees(&self) -> f32 {
+        // the maze is drawn with y pointing down, so a left turn decreases the heading
+        (self.start_heading - self.end_heading).to_degrees()
+    }
+}
+
+/// The path of the emulated MARV through the maze
+#[derive(Debug, Default)]
+pub struct Trajectory {
+    latest: Option<Pose>,
+    /// the poses kept for plotting, at most one every `SAMPLE_INTERVAL`
+    samples: Vec<Pose>,
+    /// the distance (in m) that the MARV's centre has travelled
+    path_length: f32,
+    turns: Vec<Turn>,
+    /// the heading (in rad) at the start of a turn that is still busy
+    turn_start: Option<f32>,
+}
+
+impl Trajectory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// adds the next pose of the MARV
+    pub fn push(&mut self, pose: Pose) {
+        let turning = pose.angular_velocity.abs() > TURNING_ANGULAR_VELOCITY
+            && pose.linear_velocity.abs() < TURNING_LINEAR_VELOCITY;
+
+        if let Some(latest) = self.latest {
+            let (dx, dy) = (
+                pose.position.0 - latest.position.0,
+                pose.position.1 - latest.position.1,
+            );
+            self.path_length += (dx * dx + dy * dy).sqrt();
+
+            match (self.turn_start, turning) {
+                (None, true) => self.turn_start = Some(latest.heading),
+                (Some(start_heading), false) => {
+                    self.turns.push(Turn {
+                        start_heading,
+                        end_heading: pose.heading,
+                    });
+                    self.turn_start = None;
+                }
+                _ => (),
+            }
+        }
+
+        let sample_due = match self.samples.last() {
+            Some(sample) => pose.time - sample.time >= SAMPLE_INTERVAL,
+            None => true,
+        };
+
+        if sample_due {
+            self.samples.push(pose);
+        }
+
+        self.latest = Some(pose);
+    }
+
+    /// the latest pose of the MARV
+    pub fn latest(&self) -> Option<Pose> {
+        self.latest
+    }
+
+    /// the poses of the MARV over the run, thinned out for plotting
+    pub fn samples(&self) -> &[Pose] {
+        &self.samples
+    }
+
+    /// the distance (in mm) that the MARV's centre has travelled
+    pub fn path_length(&self) -> f32 {
+        self.path_length * 1_000.0
+    }
+
+    /// every completed turn on the spot, in order
+    pub fn turns(&self) -> &[Turn] {
+        &self.turns
+    }
+
+    /// the angle (in degrees) between the MARV's latest heading and the closest line direction
+    /// of the maze, positive when it is turned clockwise (on screen) from that direction
+    pub fn heading_error(&self) -> Option<f32> {
+        self.latest.map(|pose| heading_error(pose.heading))
+    }
+}
+
+/// the angle (in degrees) between `heading` (in rad) and the closest line direction of the maze
+pub fn heading_error(heading: f32) -> f32 {
+    let error = heading.rem_euclid(FRAC_PI_2);
+
+    if error > FRAC_PI_2 / 2.0 {
+        (error - FRAC_PI_2) * (180.0 / PI)
+    } else {
+        error * (180.0 / PI)
+    }
+}
@@ -0,0 +1,77 @@
+//! Tests for the battery model of the emulated MDPS
+
+use epr320_dev_test::subsystems::motor_subsystem::battery::Battery;
+
+/// voltages (in V) within this are the same
+const TOLERANCE: f32 = 1e-3;
+
+fn assert_close(actual: f32, expected: f32, what: &str) {
+    assert!(
+        (actual - expected).abs() <= TOLERANCE,
+        "{}: expected {}, got {}",
+        what,
+        expected,
+        actual
+    );
+}
+
+fn half_charged() -> Battery {
+    let mut battery = Battery::new();
+    battery.charge = 500.0;
+
+    battery
+}
+
+#[test]
+fn a_full_battery_is_at_its_full_voltage() {
+    let battery = Battery::new();
+
+    assert_eq!(battery.level(), 1.0);
+    assert_eq!(battery.percentage(), 100);
+    assert_close(battery.voltage(), 8.4, "voltage");
+    assert_eq!(battery.decivolts(), 84);
+    // the motors reach their commanded speed above the rated voltage
+    assert_eq!(battery.speed_factor(), 1.0);
+}
+
+#[test]
+fn the_battery_drains_with_the_current_drawn() {
+    let mut battery = Battery::new();
+
+    // 150 mA for an hour while standing still...
+    battery.drain(0.0, 0.0, 3600.0);
+    assert_close(battery.charge, 850.0, "charge");
+    assert_eq!(battery.percentage(), 85);
+
+    // ...and 150 + 5 × (100 + 100) = 1150 mA for a quarter of an hour while driving, in
+    // either direction
+    battery.drain(100.0, -100.0, 900.0);
+    assert_close(battery.charge, 562.5, "charge");
+    assert_eq!(battery.percentage(), 56);
+
+    // the charge never goes below empty
+    battery.drain(100.0, 100.0, 3600.0);
+    assert_eq!(battery.charge, 0.0);
+    assert_eq!(battery.voltage(), 0.0);
+    assert_eq!(battery.speed_factor(), 0.0);
+}
+
+#[test]
+fn the_voltage_falls_with_the_charge_and_sags_under_load() {
+    let mut battery = half_charged();
+
+    // halfway between the empty and full voltages, without a load
+    assert_close(battery.voltage(), 7.2, "open-circuit voltage");
+
+    // 150 mA through 0.3 Ω
+    battery.drain(0.0, 0.0, 0.0);
+    assert_close(battery.voltage(), 7.155, "voltage while standing still");
+
+    // 1150 mA through 0.3 Ω
+    battery.drain(100.0, 100.0, 0.0);
+    assert_close(battery.voltage(), 6.855, "voltage while driving");
+    assert_eq!(battery.decivolts(), 69);
+
+    // below the rated voltage, the motors no longer reach their commanded speed
+    assert_close(battery.speed_factor(), 6.855 / 7.4, "speed factor");
+}
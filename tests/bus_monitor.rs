@@ -0,0 +1,87 @@
+//! Tests for capturing the packets sent between subsystems through the kit as the hub
+
+use std::time::{Duration, Instant};
+
+use epr320_dev_test::{
+    components::{
+        constants::HUB_START, envelope::PacketEnvelope, packet::Packet, subsystem::Subsystem,
+    },
+    subsystems::hub::BusMonitor,
+};
+
+/// polls `bus` until `count` packets have been captured, or fails after a second
+fn captured(bus: &mut BusMonitor, count: usize) -> Vec<PacketEnvelope> {
+    let deadline = Instant::now() + Duration::from_secs(1);
+    let mut envelopes = Vec::new();
+
+    while envelopes.len() < count {
+        assert!(
+            Instant::now() < deadline,
+            "only {} of {} packets were captured",
+            envelopes.len(),
+            count
+        );
+
+        match bus.poll() {
+            Some(envelope) => envelopes.push(envelope),
+            None => std::thread::sleep(Duration::from_millis(1)),
+        }
+    }
+
+    envelopes
+}
+
+#[test]
+fn the_hubs_own_packets_are_captured_with_the_hub_as_their_source() {
+    let (mut bus, mut subsystems) = BusMonitor::in_memory();
+
+    bus.inject(HUB_START);
+
+    let envelopes = captured(&mut bus, 1);
+
+    assert_eq!(envelopes[0].source, Subsystem::Hub);
+    assert_eq!(
+        envelopes[0].destinations,
+        &[Subsystem::Snc, Subsystem::Ss, Subsystem::Mdps]
+    );
+    assert_eq!(envelopes[0].packet, HUB_START.into());
+
+    for subsystem in subsystems.iter_mut() {
+        assert_eq!(subsystem.try_receive().ok(), Some(HUB_START.into()));
+    }
+
+    // the hub's packets are not part of the protocol between the subsystems
+    bus.flush();
+    assert!(bus.monitor().violations().is_empty());
+    assert_eq!(bus.monitor().packets(), 0);
+}
+
+#[test]
+fn packets_are_passed_on_and_exported_with_their_sources() {
+    let (mut bus, mut subsystems) = BusMonitor::in_memory();
+
+    let [snc, ss, mdps] = &mut subsystems;
+    let touched = Packet::new(16, 1, 30, 0);
+
+    // packets sent at the same time by different senders may be captured in either order
+    bus.inject(HUB_START);
+    let mut envelopes = captured(&mut bus, 1);
+    snc.send(touched);
+    envelopes.extend(captured(&mut bus, 1));
+
+    let sources: Vec<Subsystem> = envelopes.iter().map(|envelope| envelope.source).collect();
+
+    assert_eq!(sources, [Subsystem::Hub, Subsystem::Snc]);
+    assert_eq!(ss.try_receive().ok(), Some(HUB_START.into()));
+    assert_eq!(ss.try_receive().ok(), Some(touched));
+    assert_eq!(mdps.try_receive().ok(), Some(HUB_START.into()));
+    assert_eq!(mdps.try_receive().ok(), Some(touched));
+
+    let csv = bus.capture().to_csv();
+    let rows: Vec<&str> = csv.lines().skip(1).collect();
+
+    assert_eq!(rows.len(), 2);
+    assert!(rows[0].contains("Hub"), "{}", rows[0]);
+    assert!(rows[0].contains("\"SNC, SS, MDPS\""), "{}", rows[0]);
+    assert!(rows[1].contains("\"SS, MDPS\""), "{}", rows[1]);
+}
@@ -0,0 +1,90 @@
+//! Tests for checking the MDPS's calibrated wheel speeds against vop
+
+mod common;
+
+use epr320_dev_test::{
+    components::{
+        calibration::CalibrationCheck, comm_port::ControlByte, packet::Packet,
+        robot_config::RobotConfig,
+    },
+    gui::test_windows::navcon::qtp1::NAVCON_QTP_1,
+    subsystems::{qtp_report::Verdict, system::EmulationConfig},
+};
+
+use common::run_emulated_capturing;
+
+/// the check of a 96 packet with the right and left wheels at `right` and `left` mm/s
+fn check(operational_velocity: u8, right: u8, left: u8) -> CalibrationCheck {
+    CalibrationCheck::new(operational_velocity, Packet::new(96, right, left, 0))
+}
+
+#[test]
+fn speeds_within_five_percent_of_vop_pass() {
+    for (right, left) in [(100, 100), (95, 105), (105, 95)] {
+        assert!(
+            check(100, right, left).passed(),
+            "{}",
+            check(100, right, left)
+        );
+    }
+
+    // exactly 5% off
+    assert!(check(20, 19, 21).passed());
+}
+
+#[test]
+fn speeds_beyond_five_percent_of_vop_fail() {
+    for (right, left) in [(94, 100), (100, 94), (106, 100), (100, 106)] {
+        assert!(
+            !check(100, right, left).passed(),
+            "{}",
+            check(100, right, left)
+        );
+    }
+
+    assert!(!check(20, 18, 20).passed());
+}
+
+#[test]
+fn the_errors_are_fractions_of_vop() {
+    let check = check(50, 52, 47);
+
+    assert_eq!(check.right_error(), 0.04);
+    assert_eq!(check.left_error(), 0.06);
+    assert!(!check.passed());
+    assert!(check.to_string().ends_with("FAIL"), "{}", check);
+}
+
+#[test]
+fn without_vop_the_wheels_must_stand_still() {
+    assert!(check(0, 0, 0).passed());
+    assert!(!check(0, 1, 0).passed());
+}
+
+#[test]
+fn the_snc_ends_the_run_if_the_mdps_does_not_calibrate() {
+    // the motors cannot reach vop, however much the MDPS trims them
+    let config = EmulationConfig {
+        robot: RobotConfig {
+            max_speed: 20.0,
+            ..RobotConfig::default()
+        },
+        ..EmulationConfig::default()
+    };
+
+    let (report, packets) = run_emulated_capturing(&NAVCON_QTP_1, config);
+
+    assert_eq!(report.verdict(), Verdict::Fail);
+    assert!(
+        report.steps[0]
+            .detail
+            .starts_with("FAIL: the MDPS did not calibrate, vR = 20 mm/s"),
+        "{}",
+        report.steps[0].detail
+    );
+
+    // the MARV was never let into the maze
+    assert!(packets
+        .iter()
+        .all(|envelope| envelope.packet.control_byte() != ControlByte::MazeClapSnap));
+}
@@ -0,0 +1,71 @@
+//! What the tests of the QTPs have in common
+
+// not every test uses every helper
+#![allow(dead_code)]
+
+use epr320_dev_test::{
+    asynchronous::async_type::GuiEndpoints,
+    components::envelope::PacketEnvelope,
+    subsystems::{
+        qtp::Qtp,
+        qtp_report::{QtpReport, QtpStep, Verdict},
+        system::{EmulationConfig, Mode},
+    },
+};
+
+/// runs `qtp` against the emulated subsystems under test, and prints its report
+pub fn run_emulated(qtp: &dyn Qtp) -> QtpReport {
+    run_emulated_with(qtp, EmulationConfig::default())
+}
+
+/// runs `qtp` against the subsystems under test, emulated as set up by `config`, and
+/// prints its report
+pub fn run_emulated_with(qtp: &dyn Qtp, config: EmulationConfig) -> QtpReport {
+    run_emulated_capturing(qtp, config).0
+}
+
+/// runs `qtp` as `run_emulated_with` does, and returns its report along with every packet
+/// that was sent during the run
+pub fn run_emulated_capturing(
+    qtp: &dyn Qtp,
+    config: EmulationConfig,
+) -> (QtpReport, Vec<PacketEnvelope>) {
+    let gui = GuiEndpoints::new();
+    let report = qtp.run(Mode::Emulate, &[], config, &gui);
+    println!("{}", report);
+
+    let mut packets = Vec::new();
+
+    while let Some(envelope) = gui.packets.lock().unwrap().read() {
+        packets.push(envelope);
+    }
+
+    (report, packets)
+}
+
+/// every step passes, except for the measurements, which need an operator
+pub fn passes_all_but_the_measurements(report: &QtpReport) {
+    passes_all_but(report, |step| step.name.starts_with("measured"));
+}
+
+/// every step passes, except for those that need an operator
+pub fn passes_all_but_the_operator(report: &QtpReport) {
+    passes_all_but(report, |step| step.detail.contains("needs an operator"));
+}
+
+/// every step passes, except for those that are `skipped`, which the QTP is skipped for
+fn passes_all_but<F>(report: &QtpReport, skipped: F)
+where
+    F: Fn(&QtpStep) -> bool,
+{
+    for step in &report.steps {
+        let expected = match skipped(step) {
+            true => Verdict::Skipped,
+            false => Verdict::Pass,
+        };
+
+        assert_eq!(step.verdict, expected, "{}: {}", step.name, step.detail);
+    }
+
+    assert_eq!(report.verdict(), Verdict::Skipped);
+}
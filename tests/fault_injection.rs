@@ -0,0 +1,210 @@
+//! Tests for the fault injection between the subsystems, on links that always inject a
+//! fault, or only a scripted one
+
+use std::{
+    sync::{atomic::AtomicBool, Arc, Mutex},
+    thread,
+};
+
+use epr320_dev_test::{
+    asynchronous::{
+        async_type::GuiEndpoints,
+        one_to_many_channel::{Bound, OTMChannel},
+    },
+    components::{buffer::Buffer, packet::Packet, subsystem::Subsystem},
+    gui::test_windows::navcon::qtp5::NAVCON_QTP_5,
+    subsystems::{
+        fault_injection::{Fault, FaultProfile, FaultyLink, ScriptedFault, FAULTS},
+        state_navigation::snc::Snc,
+        system::{run_navcon, EmulationConfig},
+    },
+};
+
+/// a profile that injects `fault` into every packet that it affects
+fn always(fault: Fault) -> FaultProfile {
+    let mut profile = FaultProfile::none();
+    profile.probabilities[FAULTS.iter().position(|f| *f == fault).unwrap()] = 1.0;
+    profile
+}
+
+/// a profile that injects `fault` into the `packet`th packet, and no other
+fn scripted(packet: u32, fault: Fault) -> FaultProfile {
+    FaultProfile {
+        script: Some(ScriptedFault { packet, fault }),
+        ..FaultProfile::none()
+    }
+}
+
+/// sends each packet 1 ms after the last, and returns every packet that arrived, including
+/// those that were held back, in the order that they arrived
+fn send(profile: FaultProfile, packets: &[[u8; 4]]) -> Vec<[u8; 4]> {
+    let mut link = FaultyLink::new(profile);
+    let mut arrived = Vec::new();
+
+    for (sequence, data) in packets.iter().enumerate() {
+        let time = sequence as f32 * 0.001;
+        arrived.extend(link.due(time));
+        arrived.extend(link.send(Packet::from(*data), time).0);
+    }
+
+    arrived.extend(link.due(f32::INFINITY));
+    arrived.into_iter().map(<[u8; 4]>::from).collect()
+}
+
+const PACKETS: [[u8; 4]; 4] = [
+    [145, 0, 0, 0],
+    [146, 0, 0, 0],
+    [147, 0, 0, 0],
+    [161, 90, 120, 0],
+];
+
+#[test]
+fn no_faults_pass_every_packet() {
+    assert_eq!(send(FaultProfile::none(), &PACKETS), PACKETS);
+    assert!(!FaultProfile::none().affects(Subsystem::Snc));
+}
+
+#[test]
+fn dropped_packets_never_arrive() {
+    assert!(send(always(Fault::Drop), &PACKETS).is_empty());
+    assert_eq!(
+        send(scripted(2, Fault::Drop), &PACKETS),
+        [PACKETS[0], PACKETS[2], PACKETS[3]]
+    );
+}
+
+#[test]
+fn delayed_packets_arrive_after_those_sent_since() {
+    let mut link = FaultyLink::new(scripted(1, Fault::Delay));
+
+    let (arrived, fault) = link.send(Packet::from(PACKETS[0]), 0.0);
+    assert!(arrived.is_empty());
+    assert_eq!(fault, Some(Fault::Delay));
+
+    assert_eq!(link.send(Packet::from(PACKETS[1]), 0.01).0.len(), 1);
+    assert!(link.due(0.01).is_empty());
+    assert_eq!(link.due(0.05), [Packet::from(PACKETS[0])]);
+}
+
+#[test]
+fn duplicated_packets_arrive_twice() {
+    assert_eq!(
+        send(scripted(3, Fault::Duplicate), &PACKETS),
+        [PACKETS[0], PACKETS[1], PACKETS[2], PACKETS[2], PACKETS[3]]
+    );
+}
+
+#[test]
+fn reordered_packets_arrive_after_the_next() {
+    assert_eq!(
+        send(scripted(1, Fault::Reorder), &PACKETS),
+        [PACKETS[1], PACKETS[0], PACKETS[2], PACKETS[3]]
+    );
+
+    // the last packet arrives once it is due, as no packet overtakes it
+    assert_eq!(send(scripted(4, Fault::Reorder), &PACKETS), PACKETS);
+}
+
+#[test]
+fn bit_flips_change_a_single_bit() {
+    let arrived = send(always(Fault::BitFlip), &PACKETS);
+
+    assert_eq!(arrived.len(), PACKETS.len());
+
+    for (flipped, sent) in arrived.iter().zip(PACKETS) {
+        let bits: u32 = flipped
+            .iter()
+            .zip(sent)
+            .map(|(flipped, sent)| (flipped ^ sent).count_ones())
+            .sum();
+        assert_eq!(bits, 1);
+    }
+}
+
+#[test]
+fn faults_only_affect_the_chosen_packets() {
+    let profile = FaultProfile {
+        source: Some(Subsystem::Snc),
+        control_byte: Some(146),
+        ..always(Fault::Drop)
+    };
+
+    assert!(profile.affects(Subsystem::Snc));
+    assert!(!profile.affects(Subsystem::Mdps));
+    assert_eq!(
+        send(profile, &PACKETS),
+        [PACKETS[0], PACKETS[2], PACKETS[3]]
+    );
+
+    // scripted packets are counted among the packets affected
+    let profile = FaultProfile {
+        control_byte: Some(145),
+        ..scripted(2, Fault::Drop)
+    };
+
+    assert_eq!(
+        send(profile, &[PACKETS[0], PACKETS[1], PACKETS[0]]),
+        [PACKETS[0], PACKETS[1]]
+    );
+}
+
+#[test]
+fn a_run_survives_flipped_bits_in_the_colours() {
+    let config = EmulationConfig {
+        faults: FaultProfile {
+            source: Some(Subsystem::Ss),
+            control_byte: Some(177),
+            ..always(Fault::BitFlip)
+        },
+        ..EmulationConfig::default()
+    };
+
+    // every colour word is corrupted, some of them into colours that do not exist once the
+    // MARV is over a line, which the SNC must not be brought down by
+    let report = run_navcon(&NAVCON_QTP_5, config, None, &GuiEndpoints::new());
+    println!("{}", report);
+
+    assert_eq!(report.steps.len(), 1);
+}
+
+#[test]
+fn the_snc_ignores_colours_that_do_not_exist() {
+    let inbox = Arc::new(Mutex::new(Buffer::new()));
+    let mut snc = Snc::new(
+        OTMChannel::new("SNC", &inbox, Bound::Inifinity),
+        OTMChannel::new(
+            "SNC (Decisions)",
+            &Arc::new(Mutex::new(Buffer::new())),
+            Bound::Inifinity,
+        ),
+        OTMChannel::new(
+            "SNC (Calibrations)",
+            &Arc::new(Mutex::new(Buffer::new())),
+            Bound::Inifinity,
+        ),
+        30,
+        Arc::new(AtomicBool::new(true)),
+    );
+
+    // Blue under the left sensor, with the bit flipped that turns it into colour 7
+    let packets: [[u8; 4]; 10] = [
+        [96, 30, 30, 0],
+        [113, 0, 0, 0],
+        [161, 0, 0, 0],
+        [162, 0, 0, 0],
+        [163, 0, 0, 0],
+        [164, 0, 0, 0],
+        [177, 0b0111_0000, 0, 0],
+        [178, 0, 0, 0],
+        [161, 0, 0, 0],
+        [179, 0, 0, 0],
+    ];
+
+    for packet in packets {
+        inbox.lock().unwrap().write(Packet::from(packet));
+    }
+
+    let run = thread::spawn(move || snc.run());
+
+    assert!(run.join().is_ok());
+}
@@ -0,0 +1,53 @@
+//! Tests for working out the emulated MARV's heading from the positions of its sensors,
+//! which the emulated SS needs for the true incidence
+
+use std::f32::consts::PI;
+
+use epr320_dev_test::{
+    components::robot_config::RobotConfig, subsystems::sensor_subsystem::incidence::heading,
+};
+
+/// a chassis whose outer sensors are not level with each other
+const SKEWED_MARV: &str = "
+sensor = 90, 60
+sensor = 75, 15
+sensor = 75, 0
+sensor = 75, -15
+sensor = 60, -60
+";
+
+/// the positions, in the coordinates of the maze (y pointing down), of the sensors of
+/// `robot` when its centre is at (100, 100) and it faces `angle` (in rad)
+fn sensor_positions(robot: &RobotConfig, angle: f32) -> [(f32, f32); 5] {
+    robot.sensors.map(|(forward, left)| {
+        (
+            100.0 + forward * angle.cos() + left * angle.sin(),
+            100.0 + forward * angle.sin() - left * angle.cos(),
+        )
+    })
+}
+
+fn assert_heading(robot: &RobotConfig) {
+    for step in 0..16 {
+        let angle = step as f32 * PI / 8.0 - PI;
+        let (x, y) = heading(sensor_positions(robot, angle), robot);
+
+        assert!(
+            (x - angle.cos()).abs() < 1e-4 && (y - angle.sin()).abs() < 1e-4,
+            "facing {} rad, got ({}, {})",
+            angle,
+            x,
+            y
+        );
+    }
+}
+
+#[test]
+fn the_heading_follows_from_level_outer_sensors() {
+    assert_heading(&RobotConfig::default());
+}
+
+#[test]
+fn the_heading_follows_from_outer_sensors_that_are_not_level() {
+    assert_heading(&SKEWED_MARV.parse().unwrap());
+}
@@ -0,0 +1,103 @@
+//! Tests for the integration QTPs, run against the emulated MARV
+
+mod common;
+
+use std::{fs, thread, time::Duration};
+
+use epr320_dev_test::{
+    asynchronous::async_type::GuiEndpoints,
+    gui::test_windows::integration::{
+        qtp1::INTEGRATION_QTP_1, qtp2::INTEGRATION_QTP_2, qtp3::INTEGRATION_QTP_3,
+    },
+    subsystems::{
+        hub::{run_integration, IntegrationQtp},
+        qtp::Qtp,
+        qtp_report::Verdict,
+        session::{Recording, Session},
+        system::{EmulationConfig, Mode},
+    },
+};
+
+use common::{passes_all_but_the_operator, run_emulated};
+
+#[test]
+fn the_emulated_marv_passes_integration_qtp_1() {
+    assert_eq!(run_emulated(&INTEGRATION_QTP_1).verdict(), Verdict::Pass);
+}
+
+#[test]
+fn the_emulated_marv_passes_integration_qtp_2() {
+    assert_eq!(run_emulated(&INTEGRATION_QTP_2).verdict(), Verdict::Pass);
+}
+
+#[test]
+fn the_emulated_marv_passes_integration_qtp_3_but_for_the_operator() {
+    passes_all_but_the_operator(&run_emulated(&INTEGRATION_QTP_3));
+}
+
+#[test]
+fn the_emulated_marv_stops_once_the_qtp_has_ended() {
+    // a QTP that ends long before the MARV could reach the end of the maze
+    let qtp = IntegrationQtp {
+        name: "Integration QTP (no steps)",
+        description: "ends at once",
+        maze: INTEGRATION_QTP_1.maze,
+        script: |_| Ok(()),
+    };
+
+    let gui = GuiEndpoints::new();
+    qtp.run(Mode::Emulate, &[], EmulationConfig::default(), &gui);
+
+    // by when the MARV would have been driving through the maze, had it not been stopped
+    thread::sleep(Duration::from_secs(1));
+    while gui.positions.lock().unwrap().read().is_some() {}
+
+    // the MARV no longer moves once the QTP has ended
+    thread::sleep(Duration::from_millis(200));
+    assert!(gui.positions.lock().unwrap().read().is_none());
+}
+
+#[test]
+fn an_integration_qtp_run_is_recorded_along_with_its_report() {
+    let recording = Recording {
+        path: std::env::temp_dir().join("epr320_integration_qtp_1.csv"),
+        qtp: INTEGRATION_QTP_1.name,
+    };
+
+    let report = run_integration(
+        &INTEGRATION_QTP_1,
+        Mode::Emulate,
+        &Default::default(),
+        EmulationConfig::default(),
+        Some(recording.clone()),
+        &GuiEndpoints::new(),
+    );
+
+    let session = Session::load(&recording.path).unwrap();
+    assert_eq!(session.qtp.name(), INTEGRATION_QTP_1.name);
+    assert!(session.events.iter().any(|event| event.positions.is_some()));
+
+    assert_eq!(
+        fs::read_to_string(recording.report_path()).unwrap(),
+        report.to_string()
+    );
+}
+
+#[test]
+fn the_physical_marv_needs_a_port_for_each_subsystem() {
+    let ports = ["COM1".to_string(), "COM2".to_string()];
+
+    let report = INTEGRATION_QTP_1.run(
+        Mode::Physical,
+        &ports,
+        EmulationConfig::default(),
+        &GuiEndpoints::new(),
+    );
+
+    assert_eq!(report.verdict(), Verdict::Fail);
+    assert_eq!(report.steps.len(), 1);
+    assert_eq!(
+        report.steps[0].detail,
+        "needs a port for each of the SNC, SS and MDPS, but was given 2"
+    );
+}
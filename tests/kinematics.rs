@@ -0,0 +1,183 @@
+//! Property tests for the differential-drive kinematics shared by the MDPS and the
+//! sensor positions computer, checked against the analytically expected motion for
+//! many random wheel speeds, headings and step lengths
+
+use std::f32::consts::PI;
+
+use epr320_dev_test::subsystems::kinematics::{
+    angular_velocity, distance, integrate, linear_velocity, rotation,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const CASES: usize = 1_000;
+const AXLE_LENGTH: f32 = 75.0; // mm
+
+/// positions within this (in mm) and headings within this (in rad) are the same
+const TOLERANCE: f32 = 1e-2;
+
+fn rng() -> StdRng {
+    StdRng::seed_from_u64(320)
+}
+
+fn assert_close(actual: f32, expected: f32, what: &str) {
+    assert!(
+        (actual - expected).abs() <= TOLERANCE * expected.abs().max(1.0),
+        "{}: expected {}, got {}",
+        what,
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn opposite_speeds_rotate_on_the_spot() {
+    let mut rng = rng();
+
+    for _ in 0..CASES {
+        let position = (rng.gen_range(-500.0..500.0), rng.gen_range(-500.0..500.0));
+        let heading = rng.gen_range(-PI..PI);
+        let speed = rng.gen_range(1.0..200.0);
+        let time = rng.gen_range(0.001..2.0);
+
+        // right wheel forward, left wheel backward
+        let (end, end_heading) = integrate(position, heading, -speed, speed, AXLE_LENGTH, time);
+
+        assert_close(end.0, position.0, "x");
+        assert_close(end.1, position.1, "y");
+        assert_close(
+            end_heading,
+            heading - 2.0 * speed * time / AXLE_LENGTH,
+            "heading",
+        );
+    }
+}
+
+#[test]
+fn equal_speeds_drive_straight() {
+    let mut rng = rng();
+
+    for _ in 0..CASES {
+        let heading = rng.gen_range(-PI..PI);
+        let speed = rng.gen_range(-200.0..200.0);
+        let time = rng.gen_range(0.001..2.0);
+
+        let (end, end_heading) = integrate((0.0, 0.0), heading, speed, speed, AXLE_LENGTH, time);
+
+        assert_close(end.0, speed * time * heading.cos(), "x");
+        assert_close(end.1, speed * time * heading.sin(), "y");
+        assert_close(end_heading, heading, "heading");
+    }
+}
+
+#[test]
+fn arcs_stay_on_their_circle() {
+    let mut rng = rng();
+
+    for _ in 0..CASES {
+        let heading = rng.gen_range(-PI..PI);
+        let left = rng.gen_range(20.0..200.0);
+        let right = left + rng.gen_range(1.0..100.0);
+        let time = rng.gen_range(0.001..2.0);
+
+        let (end, _) = integrate((0.0, 0.0), heading, left, right, AXLE_LENGTH, time);
+
+        // the centre of rotation is to the MARV's left (negative heading side on screen)
+        let radius = linear_velocity(left, right) / angular_velocity(left, right, AXLE_LENGTH);
+        let centre = (-radius * heading.sin(), radius * heading.cos());
+        let distance_from_centre = ((end.0 - centre.0).powi(2) + (end.1 - centre.1).powi(2)).sqrt();
+
+        assert_close(distance_from_centre, radius.abs(), "radius");
+    }
+}
+
+#[test]
+fn step_length_does_not_change_the_result() {
+    let mut rng = rng();
+
+    for _ in 0..CASES {
+        let heading = rng.gen_range(-PI..PI);
+        let left = rng.gen_range(-200.0..200.0);
+        let right = rng.gen_range(-200.0..200.0);
+        let time = rng.gen_range(0.01..2.0);
+        let steps = rng.gen_range(2..200);
+
+        let (once, once_heading) = integrate((0.0, 0.0), heading, left, right, AXLE_LENGTH, time);
+
+        let (mut position, mut stepped_heading) = ((0.0, 0.0), heading);
+        for _ in 0..steps {
+            (position, stepped_heading) = integrate(
+                position,
+                stepped_heading,
+                left,
+                right,
+                AXLE_LENGTH,
+                time / steps as f32,
+            );
+        }
+
+        assert_close(position.0, once.0, "x");
+        assert_close(position.1, once.1, "y");
+        assert_close(stepped_heading, once_heading, "heading");
+    }
+}
+
+#[test]
+fn a_full_turn_returns_to_the_start() {
+    let mut rng = rng();
+
+    for _ in 0..CASES {
+        let position = (rng.gen_range(-500.0..500.0), rng.gen_range(-500.0..500.0));
+        let heading = rng.gen_range(-PI..PI);
+        let left = rng.gen_range(-200.0..200.0);
+        let right = left + rng.gen_range(10.0..100.0);
+
+        let time = 2.0 * PI / angular_velocity(left, right, AXLE_LENGTH).abs();
+        let (end, end_heading) = integrate(position, heading, left, right, AXLE_LENGTH, time);
+
+        assert_close(end.0, position.0, "x");
+        assert_close(end.1, position.1, "y");
+        assert_close(end_heading, heading - 2.0 * PI, "heading");
+    }
+}
+
+#[test]
+fn wheel_distances_agree_with_integration() {
+    let mut rng = rng();
+
+    for _ in 0..CASES {
+        let left = rng.gen_range(-200.0..200.0);
+        let right = rng.gen_range(-200.0..200.0);
+        let time = rng.gen_range(0.001..2.0);
+
+        // what the MDPS measures from its wheels...
+        let turned = rotation(left * time, right * time, AXLE_LENGTH);
+        let travelled = distance(left * time, right * time);
+
+        // ...is what the MARV did in the maze
+        let (end, end_heading) = integrate((0.0, 0.0), 0.0, left, right, AXLE_LENGTH, time);
+
+        assert_close(end_heading, turned, "rotation");
+        assert_close(travelled, linear_velocity(left, right) * time, "distance");
+
+        // the MARV's centre cannot end up further away than the distance it travelled
+        let chord = (end.0.powi(2) + end.1.powi(2)).sqrt();
+        assert!(chord <= travelled.abs() + TOLERANCE);
+    }
+}
+
+#[test]
+fn turning_left_decreases_the_heading() {
+    // the maze is drawn with y pointing down, so a left turn is anticlockwise on screen
+    let (_, heading) = integrate((0.0, 0.0), 0.0, -50.0, 50.0, AXLE_LENGTH, 1.0);
+    assert!(heading < 0.0);
+
+    let (_, heading) = integrate((0.0, 0.0), 0.0, 50.0, -50.0, AXLE_LENGTH, 1.0);
+    assert!(heading > 0.0);
+
+    // a quarter turn to the left at speed ends up facing up the screen
+    let time = (PI / 2.0) / angular_velocity(0.0, 100.0, AXLE_LENGTH).abs();
+    let (end, heading) = integrate((0.0, 0.0), 0.0, 0.0, 100.0, AXLE_LENGTH, time);
+
+    assert_close(heading, -PI / 2.0, "heading");
+    assert!(end.0 > 0.0 && end.1 < 0.0);
+}
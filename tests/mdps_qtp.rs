@@ -0,0 +1,180 @@
+//! Tests for the MDPS QTPs, run against the emulated MDPS
+
+mod common;
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use epr320_dev_test::{
+    asynchronous::{
+        one_to_many_channel::{Bound, OTMChannel},
+        one_to_one_channel::OTOChannel,
+    },
+    components::{
+        buffer::Buffer, comm_port::ControlByte, constants::IDLE_BUTTON_TOUCHED,
+        envelope::PacketEnvelope, packet::Packet, robot_config::RobotConfig, subsystem::Subsystem,
+    },
+    gui::test_windows::mdps::{qtp1::MDPS_QTP_1, qtp2::MDPS_QTP_2, qtp3::MDPS_QTP_3},
+    subsystems::{
+        motor_subsystem::{
+            battery::Battery, mdps::Mdps, motor_model::MotorModel,
+            rotation_control::RotationControl, wheel::Wheels,
+        },
+        qtp_report::Verdict,
+        system::EmulationConfig,
+    },
+};
+
+use common::{
+    passes_all_but_the_measurements, run_emulated, run_emulated_capturing, run_emulated_with,
+};
+
+/// the packets among `packets` with any of `control_bytes` that the MDPS sent
+fn sent_by_the_mdps(packets: &[PacketEnvelope], control_bytes: &[ControlByte]) -> Vec<Packet> {
+    packets
+        .iter()
+        .filter(|envelope| envelope.source == Subsystem::Mdps)
+        .map(|envelope| envelope.packet)
+        .filter(|packet| control_bytes.contains(&packet.control_byte()))
+        .collect()
+}
+
+#[test]
+fn the_emulated_mdps_passes_mdps_qtp_1() {
+    assert_eq!(run_emulated(&MDPS_QTP_1).verdict(), Verdict::Pass);
+}
+
+#[test]
+fn the_emulated_mdps_passes_mdps_qtp_2_but_for_the_measurements() {
+    passes_all_but_the_measurements(&run_emulated(&MDPS_QTP_2));
+}
+
+#[test]
+fn the_emulated_mdps_passes_mdps_qtp_3_but_for_the_measurements() {
+    passes_all_but_the_measurements(&run_emulated(&MDPS_QTP_3));
+}
+
+#[test]
+fn the_emulated_mdps_stops_waiting_once_the_run_has_ended() {
+    let running = Arc::new(AtomicBool::new(true));
+    let mut mdps = Mdps::new(
+        OTMChannel::new(
+            "MDPS",
+            &Arc::new(Mutex::new(Buffer::new())),
+            Bound::Inifinity,
+        ),
+        OTOChannel::new(
+            "MDPS (Speeds)",
+            &Arc::new(Mutex::new(Buffer::new())),
+            &Arc::new(Mutex::new(Buffer::new())),
+        ),
+        Wheels::new(&RobotConfig::default(), MotorModel::ideal(), None),
+        Default::default(),
+        Arc::clone(&running),
+    );
+
+    // nothing ever touches the SNC, so the MDPS waits in IDLE until it is stopped
+    let thread = thread::spawn(move || mdps.run());
+    thread::sleep(Duration::from_millis(50));
+    assert!(!thread.is_finished());
+
+    running.store(false, Ordering::Relaxed);
+    thread::sleep(Duration::from_millis(50));
+    assert!(thread.is_finished());
+}
+
+#[test]
+fn a_turn_that_times_out_is_reported_short_of_its_target() {
+    // the wheels stall once they slow down below the deadband near the target
+    let config = EmulationConfig {
+        motor_model: MotorModel {
+            deadband: 30.0,
+            ..MotorModel::ideal()
+        },
+        rotation_control: RotationControl {
+            timeout: Duration::from_secs(1),
+            ..RotationControl::default()
+        },
+        ..EmulationConfig::default()
+    };
+
+    let report = run_emulated_with(&MDPS_QTP_3, config);
+    let step = report
+        .steps
+        .iter()
+        .find(|step| step.name == "rotates 90° left")
+        .expect("the MARV was never asked to rotate");
+
+    assert_eq!(step.verdict, Verdict::Fail, "{}", step.detail);
+    assert!(step.detail.starts_with("162 reports"), "{}", step.detail);
+}
+
+#[test]
+fn the_mdps_reports_its_battery_level_in_dat1_and_its_voltage_in_dat0() {
+    let mut battery = Battery::new();
+    battery.charge = 500.0;
+
+    let config = EmulationConfig {
+        battery: Some(battery),
+        ..EmulationConfig::default()
+    };
+
+    let (_, packets) = run_emulated_capturing(&MDPS_QTP_3, config);
+    let in_cal = sent_by_the_mdps(&packets, &[ControlByte::CalibrateBatteryLevel]);
+    let in_maze = sent_by_the_mdps(&packets, &[ControlByte::MazeBatteryLevel]);
+
+    assert!(!in_cal.is_empty(), "no 97 was sent");
+    assert!(!in_maze.is_empty(), "no 161 was sent");
+
+    for packet in in_cal.into_iter().chain(in_maze) {
+        let volts = packet.dat0() as f32 / 10.0;
+
+        // half charged, between the voltage while driving and without a load
+        assert_eq!(packet.dat1(), 50, "{}", packet);
+        assert!((6.8..=7.2).contains(&volts), "{}", packet);
+        assert_eq!(packet.dec(), 0, "{}", packet);
+    }
+}
+
+#[test]
+fn the_emulated_mdps_trims_a_wheel_with_a_weaker_motor() {
+    // without trimming, the left wheel would turn at only 80% of vop
+    let config = EmulationConfig {
+        motor_model: MotorModel {
+            left_gain: 0.8,
+            right_gain: 1.1,
+            ..MotorModel::ideal()
+        },
+        ..EmulationConfig::default()
+    };
+
+    let (report, packets) = run_emulated_capturing(&MDPS_QTP_1, config);
+    let calibrated = sent_by_the_mdps(&packets, &[ControlByte::CalibrateOperationalVelocity]);
+    let operational_velocity = IDLE_BUTTON_TOUCHED[2];
+
+    assert_eq!(report.verdict(), Verdict::Pass);
+    assert!(
+        !calibrated.is_empty(),
+        "the MDPS never reported its calibrated speeds"
+    );
+
+    // both wheels are trimmed to vop, to within a mm/s
+    for packet in calibrated {
+        assert!(
+            packet.dat1().abs_diff(operational_velocity) <= 1,
+            "{}",
+            packet
+        );
+        assert!(
+            packet.dat0().abs_diff(operational_velocity) <= 1,
+            "{}",
+            packet
+        );
+    }
+}
@@ -0,0 +1,75 @@
+//! Tests for the motor model of the emulated MDPS
+
+use epr320_dev_test::subsystems::motor_subsystem::motor_model::MotorModel;
+
+/// a step of the emulation (in s)
+const STEP: f32 = 0.01;
+
+#[test]
+fn an_ideal_robot_behaves_as_the_emulated_mdps_always_has() {
+    let model = MotorModel::ideal();
+
+    for commanded in [-250.0, -10.0, -0.5, 0.0, 0.5, 10.0, 250.0] {
+        for speed in [-100.0, 0.0, 100.0] {
+            // a wheel reaches its commanded speed instantly...
+            assert_eq!(model.step_speed(speed, commanded, 1.0, STEP), commanded);
+            assert_eq!(
+                model.step_speed(speed, commanded, 0.9, STEP),
+                commanded * 0.9
+            );
+        }
+
+        // ...without slipping, and the encoders measure exactly what it did
+        assert_eq!(model.ground_speed(commanded), commanded);
+        assert_eq!(model.measured_distance(commanded), commanded);
+    }
+}
+
+#[test]
+fn commands_within_the_deadband_do_not_turn_the_motor() {
+    let model = MotorModel {
+        deadband: 8.0,
+        ..MotorModel::ideal()
+    };
+
+    for commanded in [-7.9, -1.0, 0.0, 1.0, 7.9] {
+        assert_eq!(model.step_speed(0.0, commanded, 1.0, STEP), 0.0);
+    }
+
+    for commanded in [-8.0, 8.0, 50.0] {
+        assert_eq!(model.step_speed(0.0, commanded, 1.0, STEP), commanded);
+    }
+}
+
+#[test]
+fn wheels_accelerate_towards_their_commanded_speed() {
+    let model = MotorModel {
+        acceleration: Some(300.0),
+        ..MotorModel::ideal()
+    };
+
+    assert_eq!(model.step_speed(0.0, 100.0, 1.0, 0.1), 30.0);
+    assert_eq!(model.step_speed(90.0, 100.0, 1.0, 0.1), 100.0);
+    assert_eq!(model.step_speed(0.0, -100.0, 1.0, 0.1), -30.0);
+}
+
+#[test]
+fn slip_slows_the_robot_over_the_ground() {
+    let model = MotorModel {
+        slip: 0.25,
+        ..MotorModel::ideal()
+    };
+
+    assert_eq!(model.ground_speed(100.0), 75.0);
+}
+
+#[test]
+fn encoders_measure_whole_ticks() {
+    let model = MotorModel::realistic();
+
+    assert_eq!(model.encoder_resolution, Some(5.0));
+    assert_eq!(model.measured_distance(12.3), 10.0);
+    assert_eq!(model.measured_distance(4.9), 0.0);
+    assert_eq!(model.measured_distance(15.0), 15.0);
+    assert_eq!(model.measured_distance(-12.3), -10.0);
+}
@@ -0,0 +1,339 @@
+//! Tests for the NAVCON ruleset (section 9b of the practical guide), driven by
+//! hand-crafted MAZE state packets as the SNC would receive them from the SS and MDPS
+
+use epr320_dev_test::{
+    components::{
+        adjacent_bytes::AdjacentBytes,
+        colour::{Colour, Colours},
+        packet::Packet,
+    },
+    subsystems::state_navigation::navcon::{NavCon, NavConRule, NavConState},
+};
+
+use Colour::{Black, Blue, Green, Red, White};
+
+const ALL_WHITE: [Colour; 5] = [White; 5];
+
+/// builds the five packets that the SNC reads after each NAVCON instruction
+fn packets(colours: [Colour; 5], incidence: u8, distance: u16) -> [Packet; 5] {
+    let colour_word = AdjacentBytes::from(Colours::from(colours));
+    let distance = AdjacentBytes::from(distance);
+
+    [
+        Packet::new(162, 0, 0, 0),
+        Packet::new(163, 0, 0, 0),
+        Packet::new(164, distance.msb(), distance.lsb(), 0),
+        Packet::new(177, colour_word.msb(), colour_word.lsb(), 0),
+        Packet::new(178, incidence, 0, 0),
+    ]
+}
+
+fn step(navcon: &mut NavCon, colours: [Colour; 5], incidence: u8) -> NavConState {
+    navcon.compute_output(packets(colours, incidence, 0));
+    navcon.get_state()
+}
+
+/// drives NAVCON from the stop after an encounter through the reverse and
+/// returns the rotation state and angle that it ends up instructing
+fn finish_manoeuvre(navcon: &mut NavCon) -> (NavConState, u16) {
+    assert_eq!(navcon.get_state(), NavConState::Stop);
+
+    navcon.compute_output(packets(ALL_WHITE, 0, 0));
+    assert_eq!(navcon.get_state(), NavConState::Reverse);
+
+    navcon.compute_output(packets(ALL_WHITE, 0, 10));
+    assert_eq!(navcon.get_state(), NavConState::Reverse);
+
+    navcon.compute_output(packets(ALL_WHITE, 0, 30));
+    assert_eq!(navcon.get_state(), NavConState::Stop);
+
+    navcon.compute_output(packets(ALL_WHITE, 0, 0));
+    let rotation = (navcon.get_state(), navcon.output_rotation);
+
+    navcon.compute_output(packets(ALL_WHITE, 0, 0));
+    assert_eq!(navcon.get_state(), NavConState::Forward);
+
+    rotation
+}
+
+fn last_rule(navcon: &NavCon) -> NavConRule {
+    navcon.last_decision().unwrap().rule
+}
+
+#[test]
+fn keeps_driving_forward_on_white() {
+    let mut navcon = NavCon::new();
+
+    for _ in 0..5 {
+        assert_eq!(step(&mut navcon, ALL_WHITE, 0), NavConState::Forward);
+        assert_eq!(last_rule(&navcon), NavConRule::NoLine);
+    }
+}
+
+#[test]
+fn outer_sensor_only_keeps_driving_forward() {
+    let mut navcon = NavCon::new();
+
+    navcon.compute_output(packets([Green, White, White, White, White], 0, 42));
+
+    assert_eq!(navcon.get_state(), NavConState::Forward);
+    assert_eq!(last_rule(&navcon), NavConRule::OuterSensorLine);
+    assert_eq!(navcon.last_decision().unwrap().trigger.unwrap().0, 0);
+}
+
+#[test]
+fn crosses_green_at_small_incidence() {
+    let mut navcon = NavCon::new();
+
+    assert_eq!(step(&mut navcon, [Green; 5], 0), NavConState::Forward);
+    assert_eq!(
+        step(&mut navcon, [Green, Green, White, White, White], 5),
+        NavConState::Forward
+    );
+    assert_eq!(last_rule(&navcon), NavConRule::LineCrossed);
+}
+
+#[test]
+fn crosses_red_at_small_incidence() {
+    let mut navcon = NavCon::new();
+
+    assert_eq!(
+        step(&mut navcon, [White, White, White, Red, Red], 3),
+        NavConState::Forward
+    );
+    assert_eq!(last_rule(&navcon), NavConRule::LineCrossed);
+}
+
+#[test]
+fn green_on_left_is_corrected_toward_the_line() {
+    let mut navcon = NavCon::new();
+
+    assert_eq!(
+        step(&mut navcon, [Green, Green, White, White, White], 20),
+        NavConState::Stop
+    );
+    assert_eq!(last_rule(&navcon), NavConRule::GreenEncounter);
+    assert_eq!(finish_manoeuvre(&mut navcon), (NavConState::RotateLeft, 20));
+}
+
+#[test]
+fn red_on_right_is_corrected_toward_the_line() {
+    let mut navcon = NavCon::new();
+
+    assert_eq!(
+        step(&mut navcon, [White, White, White, Red, Red], 30),
+        NavConState::Stop
+    );
+    assert_eq!(last_rule(&navcon), NavConRule::GreenEncounter);
+    assert_eq!(
+        finish_manoeuvre(&mut navcon),
+        (NavConState::RotateRight, 30)
+    );
+}
+
+#[test]
+fn steep_green_gets_repeated_small_corrections_toward_the_line() {
+    let mut navcon = NavCon::new();
+
+    for incidence in [70, 60, 50] {
+        assert_eq!(
+            step(&mut navcon, [Green, Green, White, White, White], incidence),
+            NavConState::Stop
+        );
+        assert_eq!(last_rule(&navcon), NavConRule::SteepGreenEncounter);
+        assert_eq!(finish_manoeuvre(&mut navcon), (NavConState::RotateLeft, 5));
+    }
+
+    // once below 45° the normal green rule applies
+    step(&mut navcon, [Green, Green, White, White, White], 40);
+    assert_eq!(last_rule(&navcon), NavConRule::GreenEncounter);
+    assert_eq!(finish_manoeuvre(&mut navcon), (NavConState::RotateLeft, 40));
+}
+
+#[test]
+fn blue_on_left_turns_right_by_ninety_minus_incidence() {
+    let mut navcon = NavCon::new();
+
+    step(&mut navcon, [Blue, Blue, White, White, White], 20);
+
+    assert_eq!(last_rule(&navcon), NavConRule::BlueEncounter);
+    assert_eq!(
+        finish_manoeuvre(&mut navcon),
+        (NavConState::RotateRight, 70)
+    );
+}
+
+#[test]
+fn black_on_right_turns_right_by_ninety_plus_incidence() {
+    let mut navcon = NavCon::new();
+
+    step(&mut navcon, [White, White, White, Black, Black], 20);
+
+    assert_eq!(last_rule(&navcon), NavConRule::BlueEncounter);
+    assert_eq!(
+        finish_manoeuvre(&mut navcon),
+        (NavConState::RotateRight, 110)
+    );
+}
+
+#[test]
+fn head_on_wall_turns_right_ninety_degrees() {
+    let mut navcon = NavCon::new();
+
+    step(&mut navcon, [Black; 5], 0);
+
+    assert_eq!(last_rule(&navcon), NavConRule::BlueEncounter);
+    assert_eq!(
+        finish_manoeuvre(&mut navcon),
+        (NavConState::RotateRight, 90)
+    );
+}
+
+#[test]
+fn steep_wall_gets_repeated_small_corrections_away_from_the_line() {
+    let mut navcon = NavCon::new();
+
+    for incidence in [80, 60] {
+        step(&mut navcon, [Black, Black, White, White, White], incidence);
+        assert_eq!(last_rule(&navcon), NavConRule::SteepBlueEncounter);
+        assert_eq!(finish_manoeuvre(&mut navcon), (NavConState::RotateRight, 5));
+    }
+
+    for incidence in [80, 60] {
+        step(&mut navcon, [White, White, White, Blue, Blue], incidence);
+        assert_eq!(last_rule(&navcon), NavConRule::SteepBlueEncounter);
+        assert_eq!(finish_manoeuvre(&mut navcon), (NavConState::RotateLeft, 5));
+    }
+
+    // steering clear of a wall is not a turn at a wall
+    step(&mut navcon, [Blue; 5], 0);
+    assert_eq!(last_rule(&navcon), NavConRule::BlueEncounter);
+}
+
+#[test]
+fn wall_after_right_turn_turns_around() {
+    let mut navcon = NavCon::new();
+
+    step(&mut navcon, [Blue; 5], 0);
+    assert_eq!(
+        finish_manoeuvre(&mut navcon),
+        (NavConState::RotateRight, 90)
+    );
+
+    step(&mut navcon, [Black; 5], 0);
+    assert_eq!(last_rule(&navcon), NavConRule::BlueAfterTurn);
+    assert_eq!(
+        finish_manoeuvre(&mut navcon),
+        (NavConState::RotateLeft, 180)
+    );
+}
+
+#[test]
+fn repeated_walls_never_turn_more_than_half_a_revolution() {
+    let mut navcon = NavCon::new();
+
+    for _ in 0..3 {
+        step(&mut navcon, [Blue; 5], 0);
+        assert_eq!(
+            finish_manoeuvre(&mut navcon),
+            (NavConState::RotateRight, 90)
+        );
+
+        step(&mut navcon, [Blue; 5], 0);
+        assert_eq!(
+            finish_manoeuvre(&mut navcon),
+            (NavConState::RotateLeft, 180)
+        );
+    }
+}
+
+#[test]
+fn green_after_right_turn_allows_another_right_turn() {
+    // NAVCON QTP3 part (a): BLUE, GREEN and WHITE, BLUE
+    let mut navcon = NavCon::new();
+
+    step(&mut navcon, [Blue; 5], 0);
+    assert_eq!(
+        finish_manoeuvre(&mut navcon),
+        (NavConState::RotateRight, 90)
+    );
+
+    assert_eq!(step(&mut navcon, [Green; 5], 0), NavConState::Forward);
+    assert_eq!(step(&mut navcon, ALL_WHITE, 0), NavConState::Forward);
+
+    step(&mut navcon, [Blue; 5], 0);
+    assert_eq!(last_rule(&navcon), NavConRule::BlueEncounter);
+    assert_eq!(
+        finish_manoeuvre(&mut navcon),
+        (NavConState::RotateRight, 90)
+    );
+}
+
+#[test]
+fn correcting_toward_green_after_right_turn_still_turns_around_at_a_wall() {
+    let mut navcon = NavCon::new();
+
+    step(&mut navcon, [Blue; 5], 0);
+    assert_eq!(
+        finish_manoeuvre(&mut navcon),
+        (NavConState::RotateRight, 90)
+    );
+
+    step(&mut navcon, [Green, Green, White, White, White], 60);
+    assert_eq!(last_rule(&navcon), NavConRule::SteepGreenEncounter);
+    assert_eq!(finish_manoeuvre(&mut navcon), (NavConState::RotateLeft, 5));
+
+    step(&mut navcon, [Blue; 5], 0);
+    assert_eq!(last_rule(&navcon), NavConRule::BlueAfterTurn);
+    assert_eq!(
+        finish_manoeuvre(&mut navcon),
+        (NavConState::RotateLeft, 180)
+    );
+}
+
+#[test]
+fn wall_takes_precedence_when_both_sides_see_a_line() {
+    let mut navcon = NavCon::new();
+
+    step(&mut navcon, [Green, Green, White, Black, Black], 10);
+
+    let decision = navcon.last_decision().unwrap();
+    assert_eq!(decision.rule, NavConRule::BlueEncounter);
+    assert_eq!(decision.trigger.unwrap().0, 3);
+    assert_eq!(
+        finish_manoeuvre(&mut navcon),
+        (NavConState::RotateRight, 100)
+    );
+}
+
+#[test]
+fn end_of_maze_stops_for_good() {
+    let mut navcon = NavCon::new();
+
+    step(&mut navcon, [Red; 5], 0);
+
+    let mut end_of_maze = packets([Red; 5], 0, 0);
+    end_of_maze[0] = Packet::new(179, 0, 0, 0);
+    navcon.compute_output(end_of_maze);
+
+    assert!(navcon.maze_completed());
+    assert_eq!(navcon.get_state(), NavConState::Stop);
+    assert_eq!(last_rule(&navcon), NavConRule::EndOfMaze);
+
+    assert_eq!(step(&mut navcon, ALL_WHITE, 0), NavConState::Stop);
+}
+
+#[test]
+fn corrupted_colour_words_are_ignored() {
+    let mut navcon = NavCon::new();
+
+    // the left sensor reads colour 7, which a flipped bit can turn Blue into
+    let mut corrupted = packets([Blue, White, White, White, White], 0, 0);
+    corrupted[3] = Packet::new(177, 0b0111_0000, 0, 0);
+    assert!(Colours::try_from(0b0111_0000_0000_0000).is_err());
+
+    navcon.compute_output(corrupted);
+
+    assert_eq!(navcon.get_state(), NavConState::Forward);
+    assert_eq!(last_rule(&navcon), NavConRule::NoLine);
+}
@@ -0,0 +1,116 @@
+//! Tests for decoding packets and exporting them from a packet capture
+
+use epr320_dev_test::{
+    components::{
+        envelope::PacketEnvelope, packet::Packet, state::SystemState, subsystem::Subsystem,
+    },
+    subsystems::packet_capture::{CaptureFormat, PacketCapture},
+};
+
+#[test]
+fn packets_are_decoded_by_control_byte() {
+    let cases = [
+        (Packet::new(16, 1, 30, 0), "touched, vop 30 mm/s"),
+        (Packet::new(96, 29, 31, 0), "vR 29 mm/s, vL 31 mm/s"),
+        (
+            Packet::new(147, 50, 45, 0),
+            "forward, vL 50 mm/s, vR 45 mm/s",
+        ),
+        (Packet::new(147, 0, 37, 2), "rotate 37° left"),
+        (Packet::new(147, 1, 104, 3), "rotate 360° right"),
+        (Packet::new(162, 0, 90, 3), "rotated 90° right"),
+        (Packet::new(164, 1, 210, 0), "466 mm"),
+        (Packet::new(178, 12, 0, 0), "12°"),
+        (Packet::new(179, 0, 0, 0), "end of maze"),
+        (Packet::new(1, 0, 0, 0), "unknown control byte"),
+    ];
+
+    for (packet, meaning) in cases {
+        assert_eq!(packet.meaning(), meaning, "for {}", packet);
+    }
+}
+
+#[test]
+fn colours_are_decoded_from_left_to_right() {
+    // red, green, white, blue, black
+    let word: u16 = 0b001_010_000_011_100;
+    let [dat1, dat0] = word.to_be_bytes();
+
+    assert_eq!(
+        Packet::new(177, dat1, dat0, 0).meaning(),
+        "Red, Green, White, Blue, Black"
+    );
+
+    // 7 is not a colour
+    assert_eq!(
+        Packet::new(177, 0b0111_0000, 0, 0).meaning(),
+        "invalid colours 0x7000"
+    );
+}
+
+#[test]
+fn the_state_comes_from_the_control_byte() {
+    assert_eq!(Packet::new(16, 0, 0, 0).state(), SystemState::Idle);
+    assert_eq!(Packet::new(112, 0, 0, 0).state(), SystemState::Calibrate);
+    assert_eq!(Packet::new(179, 0, 0, 0).state(), SystemState::Maze);
+    assert_eq!(Packet::new(228, 0, 0, 0).state(), SystemState::Sos);
+}
+
+#[test]
+fn csv_has_a_header_and_quotes_fields_with_commas() {
+    let mut capture = PacketCapture::new();
+    capture.push(PacketEnvelope::new(
+        7,
+        0.5,
+        Subsystem::Mdps,
+        Packet::new(163, 50, 50, 0),
+    ));
+
+    assert_eq!(
+        capture.to_csv(),
+        "sequence,time,source,destinations,state,control,control_byte,dat1,dat0,dec,meaning\n\
+         7,0.500,MDPS,\"SNC, SS\",MAZE,163,MazeSpeeds,50,50,0,\"vL 50 mm/s, vR 50 mm/s, forward\"\n"
+    );
+}
+
+#[test]
+fn jsonl_has_one_object_per_packet() {
+    let mut capture = PacketCapture::new();
+    capture.push(PacketEnvelope::new(
+        0,
+        0.0,
+        Subsystem::Snc,
+        Packet::new(16, 1, 30, 0),
+    ));
+    capture.push(PacketEnvelope::new(
+        1,
+        1.25,
+        Subsystem::Ss,
+        Packet::new(178, 5, 0, 0),
+    ));
+
+    let jsonl = capture.to_jsonl();
+    let lines: Vec<&str> = jsonl.lines().collect();
+
+    assert_eq!(
+        lines,
+        [
+            r#"{"sequence":0,"time":0.000,"source":"SNC","destinations":["SS","MDPS"],"state":"IDLE","control":16,"control_byte":"IdleButton","dat1":1,"dat0":30,"dec":0,"meaning":"touched, vop 30 mm/s"}"#,
+            r#"{"sequence":1,"time":1.250,"source":"SS","destinations":["SNC","MDPS"],"state":"MAZE","control":178,"control_byte":"MazeIncidence","dat1":5,"dat0":0,"dec":0,"meaning":"5°"}"#,
+        ]
+    );
+}
+
+#[test]
+fn the_format_comes_from_the_extension() {
+    assert_eq!(
+        CaptureFormat::from_path("run.csv"),
+        Some(CaptureFormat::Csv)
+    );
+    assert_eq!(
+        CaptureFormat::from_path("out/run.jsonl"),
+        Some(CaptureFormat::Jsonl)
+    );
+    assert_eq!(CaptureFormat::from_path("run.txt"), None);
+    assert_eq!(CaptureFormat::from_path("run"), None);
+}
@@ -0,0 +1,127 @@
+//! Tests for filtering, searching, pausing and selecting packets in the packet inspector
+
+use epr320_dev_test::{
+    components::{
+        envelope::PacketEnvelope, packet::Packet, state::SystemState, subsystem::Subsystem,
+    },
+    gui::packet_display::PacketInspector,
+    subsystems::sensor_positions::Pose,
+};
+
+/// packets numbered by their time, which is all that these tests need
+fn envelope(time: f32, source: Subsystem, packet: Packet) -> PacketEnvelope {
+    PacketEnvelope::new((time * 10.0) as u32, time, source, packet)
+}
+
+fn pose(x: f32) -> Pose {
+    Pose {
+        time: 0.0,
+        position: (x, 0.1),
+        heading: 0.0,
+        linear_velocity: 0.0,
+        angular_velocity: 0.0,
+        sensors: [(x, 0.1); 5],
+    }
+}
+
+/// an inspector with a packet from every subsystem, in CAL and in MAZE
+fn inspector() -> PacketInspector {
+    let mut inspector = PacketInspector::new();
+
+    inspector.push(
+        envelope(0.0, Subsystem::Ss, Packet::new(112, 0, 0, 0)),
+        None,
+    );
+    inspector.push(
+        envelope(0.1, Subsystem::Mdps, Packet::new(96, 30, 30, 0)),
+        None,
+    );
+    inspector.push(
+        envelope(1.0, Subsystem::Snc, Packet::new(147, 0, 37, 2)),
+        Some(pose(0.1)),
+    );
+    inspector.push(
+        envelope(1.1, Subsystem::Mdps, Packet::new(162, 0, 36, 2)),
+        Some(pose(0.2)),
+    );
+
+    inspector
+}
+
+fn visible_times(inspector: &PacketInspector) -> Vec<f32> {
+    inspector
+        .visible()
+        .into_iter()
+        .map(|(_, inspected)| inspected.envelope.time)
+        .collect()
+}
+
+#[test]
+fn every_packet_is_shown_once_without_filters() {
+    assert_eq!(visible_times(&inspector()), [0.0, 0.1, 1.0, 1.1]);
+}
+
+#[test]
+fn filters_combine() {
+    let mut inspector = inspector();
+
+    inspector.source = Some(Subsystem::Mdps);
+    assert_eq!(visible_times(&inspector), [0.1, 1.1]);
+
+    inspector.state = Some(SystemState::Maze);
+    assert_eq!(visible_times(&inspector), [1.1]);
+
+    inspector.source = None;
+    inspector.state = None;
+    inspector.control_byte = Some(147);
+    assert_eq!(visible_times(&inspector), [1.0]);
+}
+
+#[test]
+fn search_matches_the_decoded_meaning_in_any_case() {
+    let mut inspector = inspector();
+
+    inspector.search = String::from("37° LEFT");
+    assert_eq!(visible_times(&inspector), [1.0]);
+
+    inspector.search = String::from("mazerotation");
+    assert_eq!(visible_times(&inspector), [1.1]);
+
+    // and the raw bytes
+    inspector.search = String::from("[96, 30");
+    assert_eq!(visible_times(&inspector), [0.1]);
+
+    // and who the packet went to
+    inspector.search = String::from("-> snc, mdps");
+    assert_eq!(visible_times(&inspector), [0.0]);
+}
+
+#[test]
+fn pausing_holds_back_new_packets_until_resumed() {
+    let mut inspector = inspector();
+
+    inspector.toggle_pause();
+    inspector.push(
+        envelope(2.0, Subsystem::Ss, Packet::new(179, 0, 0, 0)),
+        None,
+    );
+    assert!(inspector.paused());
+    assert_eq!(visible_times(&inspector), [0.0, 0.1, 1.0, 1.1]);
+
+    inspector.toggle_pause();
+    assert_eq!(visible_times(&inspector), [0.0, 0.1, 1.0, 1.1, 2.0]);
+}
+
+#[test]
+fn the_selected_packet_has_the_pose_it_was_received_at() {
+    let mut inspector = inspector();
+
+    inspector.select(Some(3));
+    let selected = inspector.selected().unwrap();
+    assert_eq!(selected.envelope.time, 1.1);
+    assert_eq!(selected.pose.map(|pose| pose.position), Some((0.2, 0.1)));
+
+    // a packet that has been filtered out is no longer selected
+    inspector.source = Some(Subsystem::Snc);
+    assert!(inspector.selected().is_none());
+}
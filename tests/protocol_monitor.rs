@@ -0,0 +1,187 @@
+//! Tests for the protocol monitor, on handcrafted runs of the whole MARV
+
+use epr320_dev_test::{
+    components::{
+        envelope::PacketEnvelope, packet::Packet, state::SystemState, subsystem::Subsystem,
+    },
+    subsystems::protocol_monitor::ProtocolMonitor,
+};
+
+/// sends each packet 20 ms after the last, and returns the monitor once it has checked them
+fn monitor(packets: &[(Subsystem, [u8; 4])]) -> ProtocolMonitor {
+    let mut monitor = ProtocolMonitor::new();
+
+    for (sequence, (source, data)) in packets.iter().enumerate() {
+        let time = sequence as f32 * 0.02;
+        monitor.check(PacketEnvelope::new(
+            sequence as u32,
+            time,
+            *source,
+            Packet::from(*data),
+        ));
+    }
+
+    monitor.flush();
+    monitor
+}
+
+/// IDLE and CAL, until the touch that starts the maze, with a vop of 100 mm/s
+fn idle_and_calibrate() -> Vec<(Subsystem, [u8; 4])> {
+    vec![
+        (Subsystem::Snc, [16, 0, 0, 0]),
+        (Subsystem::Snc, [16, 1, 100, 0]),
+        (Subsystem::Ss, [112, 0, 0, 0]),
+        (Subsystem::Mdps, [96, 101, 99, 0]),
+        (Subsystem::Mdps, [97, 90, 120, 0]),
+        (Subsystem::Ss, [113, 0, 0, 0]),
+        (Subsystem::Snc, [80, 0, 0, 0]),
+        (Subsystem::Mdps, [97, 90, 120, 0]),
+        (Subsystem::Ss, [113, 0, 0, 0]),
+        (Subsystem::Snc, [80, 1, 0, 0]),
+    ]
+}
+
+/// a cycle of the MAZE state, driving forward over white
+fn maze_cycle() -> Vec<(Subsystem, [u8; 4])> {
+    vec![
+        (Subsystem::Snc, [145, 0, 0, 0]),
+        (Subsystem::Snc, [146, 0, 0, 0]),
+        (Subsystem::Snc, [147, 0, 0, 0]),
+        (Subsystem::Mdps, [161, 90, 120, 0]),
+        (Subsystem::Mdps, [162, 0, 0, 0]),
+        (Subsystem::Mdps, [163, 100, 100, 0]),
+        (Subsystem::Mdps, [164, 0, 10, 0]),
+        (Subsystem::Ss, [177, 0, 0, 0]),
+        (Subsystem::Ss, [178, 0, 0, 0]),
+    ]
+}
+
+#[test]
+fn a_run_that_follows_the_protocol_has_no_violations() {
+    let mut packets = idle_and_calibrate();
+    packets.extend(maze_cycle());
+    packets.extend(maze_cycle());
+    // the SS reports the end of the maze instead of its colours
+    packets.extend(&maze_cycle()[..7]);
+    packets.push((Subsystem::Ss, [179, 0, 0, 0]));
+
+    let monitor = monitor(&packets);
+
+    assert!(
+        monitor.violations().is_empty(),
+        "{:?}",
+        monitor.violations()
+    );
+    assert_eq!(monitor.packets(), packets.len() as u32);
+    assert_eq!(monitor.operational_velocity(), Some(100));
+    assert!(monitor.calibration().unwrap().passed());
+    assert_eq!(monitor.cycles(), 2);
+    assert!(monitor.end_of_maze().is_some());
+    assert_eq!(monitor.state(), SystemState::Idle);
+}
+
+#[test]
+fn a_packet_out_of_turn_is_flagged_and_the_monitor_carries_on() {
+    let mut packets = idle_and_calibrate();
+    let mut cycle = maze_cycle();
+    // the MDPS sends its speeds before its rotation
+    cycle.swap(4, 5);
+    packets.extend(cycle);
+    packets.extend(maze_cycle());
+
+    let monitor = monitor(&packets);
+
+    let violations: Vec<_> = monitor
+        .violations()
+        .iter()
+        .map(|violation| violation.envelope.sequence)
+        .collect();
+
+    // the speeds, the rotation after them, and the distance after the rotation
+    assert_eq!(violations, [14, 15, 16]);
+    assert_eq!(monitor.violations()[0].state, SystemState::Maze);
+    // the next cycle is followed as usual
+    assert_eq!(monitor.cycles(), 2);
+}
+
+#[test]
+fn a_bad_calibration_is_flagged() {
+    let mut packets = idle_and_calibrate();
+    packets[3] = (Subsystem::Mdps, [96, 110, 99, 0]);
+
+    let monitor = monitor(&packets);
+
+    assert_eq!(monitor.violations().len(), 1);
+    assert_eq!(monitor.violations()[0].envelope.sequence, 3);
+    assert!(!monitor.calibration().unwrap().passed());
+    assert_eq!(monitor.state(), SystemState::Maze);
+}
+
+#[test]
+fn an_unknown_colour_is_flagged() {
+    let mut packets = idle_and_calibrate();
+    let mut cycle = maze_cycle();
+    // sensor 0 reads colour 7
+    cycle[7] = (Subsystem::Ss, [177, 0x70, 0, 0]);
+    packets.extend(cycle);
+
+    let monitor = monitor(&packets);
+
+    assert_eq!(monitor.violations().len(), 1);
+    assert_eq!(monitor.violations()[0].envelope.sequence, 17);
+    assert_eq!(monitor.cycles(), 1);
+}
+
+#[test]
+fn packets_of_different_subsystems_captured_together_may_be_in_either_order() {
+    let mut monitor = ProtocolMonitor::new();
+    let packets = [
+        (0.0, Subsystem::Snc, [16, 1, 100, 0]),
+        // the MDPS's calibrated speeds are captured just before the SS has calibrated
+        (0.1, Subsystem::Mdps, [96, 100, 100, 0]),
+        (0.105, Subsystem::Ss, [112, 0, 0, 0]),
+        (0.2, Subsystem::Mdps, [97, 90, 120, 0]),
+    ];
+
+    for (sequence, (time, source, data)) in packets.into_iter().enumerate() {
+        monitor.check(PacketEnvelope::new(
+            sequence as u32,
+            time,
+            source,
+            Packet::from(data),
+        ));
+    }
+    monitor.flush();
+
+    assert!(
+        monitor.violations().is_empty(),
+        "{:?}",
+        monitor.violations()
+    );
+    assert_eq!(monitor.packets(), 4);
+}
+
+#[test]
+fn packets_of_one_subsystem_captured_together_must_be_in_order() {
+    let mut monitor = ProtocolMonitor::new();
+    let packets = [
+        (0.0, Subsystem::Snc, [16, 1, 100, 0]),
+        (0.1, Subsystem::Ss, [112, 0, 0, 0]),
+        // the MDPS sends its battery level before its calibrated speeds
+        (0.2, Subsystem::Mdps, [97, 90, 120, 0]),
+        (0.2005, Subsystem::Mdps, [96, 100, 100, 0]),
+    ];
+
+    for (sequence, (time, source, data)) in packets.into_iter().enumerate() {
+        monitor.check(PacketEnvelope::new(
+            sequence as u32,
+            time,
+            source,
+            Packet::from(data),
+        ));
+    }
+    monitor.flush();
+
+    assert!(!monitor.violations().is_empty());
+    assert_eq!(monitor.violations()[0].envelope.sequence, 2);
+}
@@ -0,0 +1,83 @@
+//! Tests for the registry of QTPs, from which the GUI's main menu and the CLI are built
+
+use epr320_dev_test::{
+    asynchronous::async_type::GuiEndpoints,
+    gui::test_windows::registry::{find, navcon_qtp, qtps},
+    subsystems::{
+        qtp::QtpKind,
+        qtp_report::Verdict,
+        system::{EmulationConfig, Mode},
+    },
+};
+
+#[test]
+fn every_qtp_can_be_found_by_its_name() {
+    for qtp in qtps() {
+        assert_eq!(find(qtp.name()).map(|found| found.name()), Some(qtp.name()));
+    }
+
+    assert_eq!(find("snc-qtp-1").map(|qtp| qtp.name()), Some("SNC QTP 1"));
+    assert_eq!(
+        find("integrationqtp3").map(|qtp| qtp.name()),
+        Some("Integration QTP 3")
+    );
+    assert!(find("SNC QTP 9").is_none());
+}
+
+#[test]
+fn every_suite_lists_its_qtps_in_order() {
+    let mut suites: Vec<(&str, Vec<&str>)> = Vec::new();
+
+    for qtp in qtps() {
+        match suites.last_mut() {
+            Some((suite, names)) if *suite == qtp.suite() => names.push(qtp.short_name()),
+            _ => suites.push((qtp.suite(), vec![qtp.short_name()])),
+        }
+    }
+
+    let order: Vec<&str> = suites.iter().map(|(suite, _)| *suite).collect();
+    assert_eq!(order, ["SNC", "NAVCON", "SS", "MDPS", "Integration"]);
+
+    // no QTP is left out of, or listed twice in, its suite
+    for (suite, names) in suites {
+        let expected: Vec<String> = (1..=names.len()).map(|n| format!("QTP {}", n)).collect();
+
+        assert_eq!(names, expected, "{}", suite);
+    }
+}
+
+#[test]
+fn every_qtp_can_be_run() {
+    for qtp in qtps() {
+        assert!(!qtp.under_test().is_empty(), "{}", qtp.name());
+        assert!(qtp.modes().contains(&Mode::Emulate), "{}", qtp.name());
+
+        // the MARV only drives through a maze when it is run as a whole
+        let drives = !matches!(qtp.kind(), QtpKind::Bench(_));
+        assert_eq!(qtp.maze().is_some(), drives, "{}", qtp.name());
+    }
+}
+
+#[test]
+fn older_recorded_runs_name_their_navcon_qtp_by_number() {
+    for number in 1..=5 {
+        let qtp = navcon_qtp(number).unwrap();
+
+        assert_eq!(qtp.name(), format!("NAVCON QTP {}", number));
+    }
+
+    assert!(navcon_qtp(0).is_none());
+    assert!(navcon_qtp(6).is_none());
+}
+
+#[test]
+fn a_qtp_runs_from_the_registry() {
+    let gui = GuiEndpoints::new();
+    let qtp = find("SNC QTP 1").unwrap();
+
+    let report = qtp.run(Mode::Emulate, &[], EmulationConfig::default(), &gui);
+    println!("{}", report);
+
+    assert_eq!(report.qtp, qtp.name());
+    assert_eq!(report.verdict(), Verdict::Pass);
+}
@@ -0,0 +1,51 @@
+//! Tests for the verdict of a QTP from the verdicts of its steps
+
+use epr320_dev_test::subsystems::qtp_report::{QtpReport, QtpStep, Verdict};
+
+fn report(verdicts: &[Verdict]) -> QtpReport {
+    let mut report = QtpReport::new("SNC QTP 1");
+
+    for (index, verdict) in verdicts.iter().enumerate() {
+        report.steps.push(QtpStep {
+            time: index as f32,
+            name: format!("step {}", index + 1),
+            verdict: *verdict,
+            detail: String::new(),
+        });
+    }
+
+    report
+}
+
+#[test]
+fn a_qtp_passes_when_every_step_passes() {
+    assert_eq!(
+        report(&[Verdict::Pass, Verdict::Pass]).verdict(),
+        Verdict::Pass
+    );
+}
+
+#[test]
+fn a_qtp_fails_when_any_step_fails() {
+    assert_eq!(
+        report(&[Verdict::Pass, Verdict::Skipped, Verdict::Fail]).verdict(),
+        Verdict::Fail
+    );
+}
+
+#[test]
+fn a_qtp_with_skipped_steps_does_not_pass() {
+    assert_eq!(
+        report(&[Verdict::Pass, Verdict::Skipped]).verdict(),
+        Verdict::Skipped
+    );
+    assert_eq!(report(&[]).verdict(), Verdict::Skipped);
+}
+
+#[test]
+fn a_report_lists_its_steps_under_its_verdict() {
+    assert_eq!(
+        report(&[Verdict::Pass, Verdict::Fail]).to_string(),
+        "SNC QTP 1: FAIL\n  0.000 s  PASS     step 1: \n  1.000 s  FAIL     step 2: \n"
+    );
+}
@@ -0,0 +1,100 @@
+//! Tests for reading the geometry of the MARV from a robot configuration file
+
+use epr320_dev_test::components::robot_config::{RobotConfig, RobotConfigError};
+
+const TEAM_MARV: &str = "
+# lengths in mm, speeds in mm/s
+axle_length = 90
+wheel_radius = 30.5
+max_speed = 350   # geared down
+
+sensor = 80, 50
+sensor = 80, 12
+sensor = 85, 0
+sensor = 80, -12
+sensor = 80, -50
+";
+
+#[test]
+fn a_valid_file_describes_the_marv() {
+    let config: RobotConfig = TEAM_MARV.parse().unwrap();
+
+    assert_eq!(
+        config,
+        RobotConfig {
+            sensors: [
+                (80.0, 50.0),
+                (80.0, 12.0),
+                (85.0, 0.0),
+                (80.0, -12.0),
+                (80.0, -50.0),
+            ],
+            axle_length: 90.0,
+            wheel_radius: 30.5,
+            max_speed: 350.0,
+        }
+    );
+}
+
+#[test]
+fn missing_keys_keep_their_defaults() {
+    let config: RobotConfig = "axle_length = 90".parse().unwrap();
+
+    assert_eq!(
+        config,
+        RobotConfig {
+            axle_length: 90.0,
+            ..RobotConfig::default()
+        }
+    );
+    assert_eq!("".parse::<RobotConfig>(), Ok(RobotConfig::default()));
+}
+
+#[test]
+fn unknown_keys_are_rejected() {
+    assert_eq!(
+        "axle_length = 90\n\nwheel_diameter = 64".parse::<RobotConfig>(),
+        Err(RobotConfigError::UnknownKey(
+            3,
+            String::from("wheel_diameter")
+        ))
+    );
+    assert_eq!(
+        "axle_length 90".parse::<RobotConfig>(),
+        Err(RobotConfigError::InvalidLine(1))
+    );
+}
+
+#[test]
+fn values_must_be_numbers() {
+    for config in [
+        "axle_length = wide",
+        "max_speed = -200",
+        "wheel_radius = inf",
+        "sensor = 75",
+        "sensor = 75, left",
+        "sensor = 75, 15, 0",
+    ] {
+        assert_eq!(
+            config.parse::<RobotConfig>(),
+            Err(RobotConfigError::InvalidValue(1)),
+            "{}",
+            config
+        );
+    }
+}
+
+#[test]
+fn there_must_be_five_sensors() {
+    let four = "sensor = 75, 60\nsensor = 75, 15\nsensor = 75, -15\nsensor = 75, -60";
+    assert_eq!(
+        four.parse::<RobotConfig>(),
+        Err(RobotConfigError::SensorCount(4))
+    );
+
+    let six = format!("{}\nsensor = 75, 0\nsensor = 75, 0", four);
+    assert_eq!(
+        six.parse::<RobotConfig>(),
+        Err(RobotConfigError::SensorCount(6))
+    );
+}
@@ -0,0 +1,89 @@
+//! Tests for the closed-loop turn of the emulated MDPS
+
+use std::time::{Duration, Instant};
+
+use epr320_dev_test::{
+    components::robot_config::RobotConfig,
+    subsystems::motor_subsystem::{
+        motor_model::MotorModel,
+        rotation_control::{Rotation, RotationControl},
+        wheel::Wheels,
+    },
+};
+
+/// the operational velocity (in mm/s) that the MARV turns at
+const VOP: u8 = 100;
+
+fn wheels(motor_model: MotorModel) -> Wheels {
+    Wheels::new(&RobotConfig::default(), motor_model, None)
+}
+
+#[test]
+fn the_wheels_slow_down_near_the_target() {
+    let control = RotationControl::default();
+
+    assert_eq!(control.wheel_speed(100.0, 90.0), 100.0);
+    assert_eq!(control.wheel_speed(100.0, 20.0), 100.0);
+    assert_eq!(control.wheel_speed(100.0, 10.0), 50.0);
+    // but never slower than the slowest speed, or faster than vop
+    assert_eq!(control.wheel_speed(100.0, 1.0), 10.0);
+    assert_eq!(control.wheel_speed(5.0, 1.0), 5.0);
+}
+
+#[test]
+fn a_rotation_is_complete_within_the_tolerance() {
+    let control = RotationControl::default();
+
+    assert!(!control.is_complete(90.0, 88.9));
+    assert!(control.is_complete(90.0, 89.0));
+    assert!(control.is_complete(90.0, 90.0));
+    // an overshoot is as complete as the rotation will get
+    assert!(control.is_complete(90.0, 95.0));
+}
+
+#[test]
+fn a_turn_ends_within_the_tolerance_of_its_target() {
+    let control = RotationControl::default();
+
+    for (target, left) in [(90.0, true), (90.0, false), (45.0, true), (180.0, false)] {
+        let mut wheels = wheels(MotorModel::ideal());
+        let rotation = control.turn(&mut wheels, VOP, target, left, |_| {});
+
+        assert!(
+            matches!(rotation, Rotation::Complete(degrees) if (degrees - target).abs() <= control.tolerance),
+            "turning {}° {}: {:?}",
+            target,
+            if left { "left" } else { "right" },
+            rotation
+        );
+        assert!(wheels.is_stationary());
+    }
+}
+
+#[test]
+fn a_turn_that_cannot_reach_its_target_gives_up_at_the_timeout() {
+    // the wheels stall once they slow down below the deadband near the target
+    let control = RotationControl {
+        timeout: Duration::from_millis(500),
+        ..RotationControl::default()
+    };
+    let mut wheels = wheels(MotorModel {
+        deadband: 30.0,
+        ..MotorModel::ideal()
+    });
+
+    let start = Instant::now();
+    let rotation = control.turn(&mut wheels, VOP, 90.0, true, |_| {});
+    let elapsed = start.elapsed();
+
+    match rotation {
+        Rotation::TimedOut(degrees) => assert!(degrees < 90.0 - control.tolerance, "{}", degrees),
+        Rotation::Complete(degrees) => panic!("completed at {}°", degrees),
+    }
+    assert!(
+        elapsed >= control.timeout && elapsed < control.timeout * 2,
+        "gave up after {:?}",
+        elapsed
+    );
+    assert!(wheels.is_stationary());
+}
@@ -0,0 +1,205 @@
+//! Tests for the run monitor, driven by hand-crafted poses of the MARV in a single
+//! block of maze (85 px, i.e. 0.2 m, across)
+
+use std::{
+    sync::{atomic::AtomicBool, Arc, Mutex},
+    time::Duration,
+};
+
+use epr320_dev_test::{
+    asynchronous::one_to_many_channel::{Bound, OTMChannel},
+    components::{buffer::Buffer, colour::Colour, packet::Packet},
+    gui::maze::MazeLineMap,
+    subsystems::{
+        run_monitor::{RunLimits, RunMonitor, RunOutcome},
+        sensor_positions::Pose,
+        state_navigation::navcon::{NavCon, NavConState},
+    },
+};
+
+/// a single block with `walls` as all four of its lines
+fn block(walls: Colour) -> MazeLineMap {
+    let mut maze = MazeLineMap::new(1, 1);
+
+    maze.add_column(vec![walls; 2]).unwrap();
+    maze.add_row(vec![walls; 2]).unwrap();
+
+    maze
+}
+
+fn monitor(maze: MazeLineMap) -> RunMonitor {
+    RunMonitor::new(
+        maze,
+        RunLimits {
+            timeout: Duration::from_secs(60),
+            max_steps: 100,
+        },
+        OTMChannel::new(
+            "poses",
+            &Arc::new(Mutex::new(Buffer::new())),
+            Bound::Inifinity,
+        ),
+        OTMChannel::new(
+            "decisions",
+            &Arc::new(Mutex::new(Buffer::new())),
+            Bound::Inifinity,
+        ),
+        OTMChannel::new(
+            "calibrations",
+            &Arc::new(Mutex::new(Buffer::new())),
+            Bound::Inifinity,
+        ),
+        OTMChannel::new(
+            "outcome",
+            &Arc::new(Mutex::new(Buffer::new())),
+            Bound::Inifinity,
+        ),
+        Arc::new(AtomicBool::new(true)),
+    )
+}
+
+fn pose(time: f32, position: (f32, f32), heading: f32) -> Pose {
+    Pose {
+        time,
+        position,
+        heading,
+        linear_velocity: 0.0,
+        angular_velocity: 0.0,
+        sensors: [position; 5],
+    }
+}
+
+#[test]
+fn driving_within_the_block_is_fine() {
+    let mut monitor = monitor(block(Colour::Black));
+
+    for step in 0..10 {
+        let y = 0.05 + step as f32 * 0.01;
+        assert_eq!(monitor.check(pose(step as f32, (0.1, y), 0.0)), None);
+    }
+}
+
+#[test]
+fn driving_through_a_wall_fails_where_the_wall_is() {
+    let mut monitor = monitor(block(Colour::Black));
+
+    assert_eq!(monitor.check(pose(0.0, (0.1, 0.15), 0.0)), None);
+
+    // one big step straight through the bottom wall, at y = 0.2 m
+    match monitor.check(pose(0.1, (0.1, 0.3), 0.0)) {
+        Some(RunOutcome::CrossedWall((x, y))) => {
+            assert!((x - 0.1).abs() < 1e-3);
+            assert!((0.195..0.215).contains(&y), "crossed at y = {}", y);
+        }
+        outcome => panic!("expected a wall crossing, got {:?}", outcome),
+    }
+}
+
+#[test]
+fn coloured_lines_are_not_walls() {
+    let mut monitor = monitor(block(Colour::Green));
+
+    assert_eq!(monitor.check(pose(0.0, (0.1, 0.15), 0.0)), None);
+    assert_eq!(monitor.check(pose(0.1, (0.1, 0.205), 0.0)), None);
+}
+
+#[test]
+fn blue_lines_are_walls() {
+    let mut monitor = monitor(block(Colour::Blue));
+
+    assert_eq!(monitor.check(pose(0.0, (0.1, 0.15), 0.0)), None);
+    assert!(matches!(
+        monitor.check(pose(0.1, (0.1, 0.3), 0.0)),
+        Some(RunOutcome::CrossedWall(_))
+    ));
+}
+
+#[test]
+fn leaving_the_maze_fails() {
+    let mut monitor = monitor(block(Colour::White));
+
+    assert_eq!(monitor.check(pose(0.0, (0.1, 0.15), 0.0)), None);
+    assert_eq!(
+        monitor.check(pose(0.1, (0.1, 0.3), 0.0)),
+        Some(RunOutcome::LeftMaze((0.1, 0.3)))
+    );
+}
+
+#[test]
+fn standing_still_for_too_long_fails() {
+    let mut monitor = monitor(block(Colour::Black));
+
+    for time in 0..=15 {
+        assert_eq!(monitor.check(pose(time as f32, (0.1, 0.1), 0.0)), None);
+    }
+
+    match monitor.check(pose(16.0, (0.1, 0.1), 0.0)) {
+        Some(RunOutcome::Stuck(position, time)) => {
+            assert_eq!(position, (0.1, 0.1));
+            assert_eq!(time, 16.0);
+        }
+        outcome => panic!("expected the MARV to be stuck, got {:?}", outcome),
+    }
+}
+
+#[test]
+fn turning_on_the_spot_is_progress() {
+    let mut monitor = monitor(block(Colour::Black));
+
+    for time in 0..60 {
+        let heading = time as f32 * 0.2;
+        assert_eq!(monitor.check(pose(time as f32, (0.1, 0.1), heading)), None);
+    }
+}
+
+#[test]
+fn running_too_long_fails_with_the_final_pose() {
+    let mut monitor = monitor(block(Colour::Black));
+
+    assert_eq!(monitor.check(pose(0.0, (0.1, 0.1), 0.5)), None);
+    assert_eq!(monitor.check_elapsed(Duration::from_secs(59)), None);
+
+    match monitor.check_elapsed(Duration::from_secs(61)) {
+        Some(RunOutcome::TimedOut(limit, end)) => {
+            assert_eq!(limit, 60.0);
+            assert_eq!(end.pose.map(|pose| pose.position), Some((0.1, 0.1)));
+            assert_eq!(end.navcon_state, None);
+        }
+        outcome => panic!("expected a timeout, got {:?}", outcome),
+    }
+}
+
+#[test]
+fn too_many_navcon_steps_fail_with_the_final_state() {
+    let mut monitor = monitor(block(Colour::Black));
+    let mut navcon = NavCon::new();
+
+    // NAVCON drives forward for as long as it sees nothing but white
+    let packets = [
+        Packet::new(162, 0, 0, 0),
+        Packet::new(163, 0, 0, 0),
+        Packet::new(164, 0, 0, 0),
+        Packet::new(177, 0, 0, 0),
+        Packet::new(178, 0, 0, 0),
+    ];
+
+    for _ in 0..100 {
+        navcon.compute_output(packets);
+        assert_eq!(
+            monitor.check_decision(navcon.last_decision().unwrap()),
+            None
+        );
+    }
+
+    navcon.compute_output(packets);
+
+    match monitor.check_decision(navcon.last_decision().unwrap()) {
+        Some(RunOutcome::OutOfSteps(limit, end)) => {
+            assert_eq!(limit, 100);
+            assert_eq!(end.steps, 101);
+            assert_eq!(end.navcon_state, Some(NavConState::Forward));
+            assert_eq!(end.pose, None);
+        }
+        outcome => panic!("expected too many steps, got {:?}", outcome),
+    }
+}
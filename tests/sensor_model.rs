@@ -0,0 +1,130 @@
+//! Tests for the emulated colour sensors, seeded so that their random errors are the
+//! same every run, in two blocks of maze (85 px across) side by side with red lines
+
+use epr320_dev_test::{
+    components::colour::Colour,
+    gui::maze::MazeLineMap,
+    subsystems::sensor_subsystem::sensor_model::{ColourSensors, SensorModel},
+};
+
+const SEED: u64 = 320;
+const READINGS: usize = 10_000;
+
+/// the middle of the left block, off every line
+const BLOCK: (f32, f32) = (45.0, 45.0);
+/// the middle of the line between the blocks, which is 5 px wide
+const LINE: (f32, f32) = (87.5, 45.0);
+
+fn blocks() -> MazeLineMap {
+    let mut maze = MazeLineMap::new(1, 2);
+
+    maze.add_column(vec![Colour::Red; 2]).unwrap();
+    maze.add_column(vec![Colour::Red; 2]).unwrap();
+    maze.add_row(vec![Colour::Red; 3]).unwrap();
+
+    maze
+}
+
+fn seeded(model: SensorModel) -> ColourSensors {
+    ColourSensors::new(SensorModel {
+        seed: Some(SEED),
+        ..model
+    })
+}
+
+#[test]
+fn ideal_sensors_read_the_colour_under_their_centre() {
+    let maze = blocks();
+    let mut sensors = seeded(SensorModel::ideal());
+
+    assert_eq!(sensors.read(&maze, [BLOCK; 5]), [Colour::White; 5]);
+    assert_eq!(sensors.read(&maze, [LINE; 5]), [Colour::Red; 5]);
+}
+
+#[test]
+fn a_spot_much_wider_than_a_line_averages_it_away() {
+    let maze = blocks();
+    let mut sensors = seeded(SensorModel {
+        spot_radius: 10.0,
+        ..SensorModel::ideal()
+    });
+
+    // most of the spot is on the white blocks on either side of the line
+    assert_eq!(sensors.read(&maze, [LINE; 5]), [Colour::White; 5]);
+}
+
+#[test]
+fn a_spot_within_a_line_still_reads_it() {
+    let maze = blocks();
+    let mut sensors = seeded(SensorModel {
+        spot_radius: 2.0,
+        ..SensorModel::ideal()
+    });
+
+    assert_eq!(sensors.read(&maze, [LINE; 5]), [Colour::Red; 5]);
+}
+
+#[test]
+fn readings_are_misclassified_at_the_configured_rate() {
+    let maze = blocks();
+    let mut sensors = seeded(SensorModel {
+        misclassification: 0.1,
+        ..SensorModel::ideal()
+    });
+
+    let misread = (0..READINGS)
+        .map(|_| sensors.read(&maze, [BLOCK; 5])[0])
+        .filter(|colour| *colour != Colour::White)
+        .count();
+    let rate = misread as f32 / READINGS as f32;
+
+    assert!((0.09..=0.11).contains(&rate), "misclassified {}", rate);
+}
+
+#[test]
+fn the_same_seed_gives_the_same_readings() {
+    let maze = blocks();
+    let model = SensorModel {
+        misclassification: 0.3,
+        ..SensorModel::realistic()
+    };
+    let mut first = seeded(model);
+    let mut second = seeded(model);
+
+    for _ in 0..100 {
+        assert_eq!(
+            first.read(&maze, [BLOCK; 5]),
+            second.read(&maze, [BLOCK; 5])
+        );
+    }
+}
+
+#[test]
+fn calibration_removes_the_sensors_bias() {
+    let maze = blocks();
+    let biased = SensorModel {
+        noise: 0.05,
+        bias: [-0.4; 5],
+        calibrate: false,
+        ..SensorModel::ideal()
+    };
+
+    // without calibration the white block is too dark to read as white
+    let mut uncalibrated = seeded(biased);
+    uncalibrated.calibrate();
+    assert!(uncalibrated
+        .read(&maze, [BLOCK; 5])
+        .iter()
+        .all(|colour| *colour != Colour::White));
+
+    let mut calibrated = seeded(SensorModel {
+        calibrate: true,
+        ..biased
+    });
+    calibrated.calibrate();
+
+    for _ in 0..100 {
+        assert_eq!(calibrated.read(&maze, [BLOCK; 5]), [Colour::White; 5]);
+        assert_eq!(calibrated.read(&maze, [LINE; 5]), [Colour::Red; 5]);
+    }
+}
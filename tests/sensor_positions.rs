@@ -0,0 +1,80 @@
+//! Tests for the sensor positions computer, which follows the emulated MARV's pose from
+//! the wheel speeds that the MDPS sends it
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use epr320_dev_test::{
+    asynchronous::{
+        one_to_many_channel::{Bound, OTMChannel},
+        one_to_one_channel::OTOChannel,
+    },
+    components::{buffer::Buffer, robot_config::RobotConfig},
+    subsystems::sensor_positions::{Pose, SensorPosComputer, Speeds},
+};
+
+/// waits for the next pose that the computer sends out
+fn next_pose(poses: &Arc<Mutex<Buffer<Pose>>>) -> Pose {
+    for _ in 0..1_000 {
+        if let Some(pose) = poses.lock().unwrap().read() {
+            return pose;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    panic!("FATAL: no pose was sent");
+}
+
+#[test]
+fn the_marv_keeps_its_previous_speeds_until_new_ones_arrive() {
+    let to_computer = Arc::new(Mutex::new(Buffer::new()));
+    let from_mdps = Arc::new(Mutex::new(Buffer::new()));
+    let positions = Arc::new(Mutex::new(Buffer::new()));
+    let poses = Arc::new(Mutex::new(Buffer::new()));
+    let running = Arc::new(AtomicBool::new(true));
+
+    let speeds = OTOChannel::new("MDPS (Speeds)", &from_mdps, &to_computer);
+    let mut computer = SensorPosComputer::new(
+        (0.0, 0.0),
+        0.0,
+        &RobotConfig::default(),
+        OTOChannel::new("Positions (Speeds)", &to_computer, &from_mdps),
+        OTMChannel::new("Positions", &positions, Bound::Inifinity),
+        OTMChannel::with_endpoints(
+            "Poses",
+            &Arc::new(Mutex::new(Buffer::new())),
+            vec![&poses],
+            Bound::Inifinity,
+        ),
+        Arc::clone(&running),
+    );
+
+    let computer = thread::spawn(move || computer.compute_pos());
+
+    // the MARV drives forward at 100 mm/s for 200 ms, then stops
+    speeds.send(Speeds::new(100.0, 100.0));
+    let start = next_pose(&poses).position;
+
+    thread::sleep(Duration::from_millis(200));
+    speeds.send(Speeds::new(0.0, 0.0));
+    let end = next_pose(&poses);
+
+    running.store(false, Ordering::Relaxed);
+    computer.join().unwrap();
+
+    let travelled =
+        ((end.position.0 - start.0).powi(2) + (end.position.1 - start.1).powi(2)).sqrt();
+
+    assert!(
+        (0.015..0.03).contains(&travelled),
+        "expected about 20 mm, travelled {} m",
+        travelled
+    );
+    assert_eq!(end.linear_velocity, 0.0);
+}
@@ -0,0 +1,82 @@
+//! Tests for grouping the packets of a run into phases for the sequence diagram
+
+use epr320_dev_test::{
+    components::{
+        envelope::PacketEnvelope, packet::Packet, state::SystemState, subsystem::Subsystem,
+    },
+    gui::sequence_diagram::phases,
+};
+
+/// the start of the QTP handshake, from touching the SNC to the first colours in MAZE
+fn handshake() -> Vec<PacketEnvelope> {
+    [
+        (Subsystem::Snc, Packet::new(16, 1, 30, 0)),
+        (Subsystem::Ss, Packet::new(112, 0, 0, 0)),
+        (Subsystem::Mdps, Packet::new(96, 10, 10, 0)),
+        (Subsystem::Snc, Packet::new(80, 1, 0, 0)),
+        (Subsystem::Mdps, Packet::new(145, 0, 0, 0)),
+        (Subsystem::Ss, Packet::new(177, 0, 0, 0)),
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(sequence, (source, packet))| {
+        PacketEnvelope::new(sequence as u32, sequence as f32 * 0.1, source, packet)
+    })
+    .collect()
+}
+
+#[test]
+fn packets_are_grouped_by_the_state_they_were_sent_in() {
+    let phases = phases(handshake());
+
+    assert_eq!(
+        phases
+            .iter()
+            .map(|phase| (phase.state, phase.envelopes.len()))
+            .collect::<Vec<_>>(),
+        [
+            (SystemState::Idle, 1),
+            (SystemState::Calibrate, 3),
+            (SystemState::Maze, 2)
+        ]
+    );
+}
+
+#[test]
+fn phases_keep_the_order_that_packets_were_sent_in() {
+    let phases = phases(handshake());
+
+    let sequences: Vec<u32> = phases
+        .iter()
+        .flat_map(|phase| phase.envelopes.iter().map(|envelope| envelope.sequence))
+        .collect();
+    assert_eq!(sequences, [0, 1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn returning_to_a_state_starts_a_new_phase() {
+    let mut envelopes = handshake();
+    // the SNC is touched again, and goes back to IDLE
+    envelopes.push(PacketEnvelope::new(
+        6,
+        0.6,
+        Subsystem::Snc,
+        Packet::new(16, 1, 30, 0),
+    ));
+
+    let states: Vec<SystemState> = phases(envelopes).iter().map(|phase| phase.state).collect();
+    assert_eq!(
+        states,
+        [
+            SystemState::Idle,
+            SystemState::Calibrate,
+            SystemState::Maze,
+            SystemState::Idle
+        ]
+    );
+}
+
+#[test]
+fn no_packets_have_no_phases() {
+    assert!(phases(Vec::new()).is_empty());
+}
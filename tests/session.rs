@@ -0,0 +1,191 @@
+//! Tests for recording runs to, and playing them back from, session files
+
+use std::fs;
+
+use epr320_dev_test::{
+    asynchronous::async_type::GuiEndpoints,
+    components::{packet::Packet, subsystem::Subsystem},
+    gui::test_windows::{navcon::qtp3::NAVCON_QTP_3, snc::qtp1::SNC_QTP_1},
+    subsystems::{
+        session::{Recording, Session, SessionError, SessionEvent, SessionPlayer},
+        system::{EmulationConfig, Mode},
+        test_bench::run_bench,
+    },
+};
+
+fn session() -> Session {
+    let mut session = Session::new(&NAVCON_QTP_3);
+    let positions = |y: f32| [(0.1, y), (0.12, y), (0.14, y), (0.16, y), (0.18, y)];
+
+    session.events = vec![
+        SessionEvent {
+            time: 0.0,
+            sender: Subsystem::Snc,
+            packet: Packet::new(16, 0, 0, 0),
+            positions: None,
+        },
+        SessionEvent {
+            time: 0.5,
+            sender: Subsystem::Ss,
+            packet: Packet::new(112, 0, 0, 0),
+            positions: Some(positions(0.05)),
+        },
+        SessionEvent {
+            time: 1.25,
+            sender: Subsystem::Mdps,
+            packet: Packet::new(164, 10, 10, 0),
+            positions: Some(positions(0.1)),
+        },
+        SessionEvent {
+            time: 2.0,
+            sender: Subsystem::Ss,
+            packet: Packet::new(177, 0, 0, 0),
+            positions: Some(positions(0.15)),
+        },
+    ];
+
+    session
+}
+
+#[test]
+fn a_saved_session_loads_the_same() {
+    let session = session();
+
+    assert_eq!(session.to_string().parse::<Session>(), Ok(session));
+}
+
+#[test]
+fn a_session_must_name_its_qtp() {
+    assert_eq!(
+        "0.000,SNC,16,0,0,0".parse::<Session>(),
+        Err(SessionError::MissingQtp)
+    );
+    assert_eq!(
+        "qtp,6\n0.000,SNC,16,0,0,0".parse::<Session>(),
+        Err(SessionError::InvalidLine(1))
+    );
+}
+
+#[test]
+fn a_session_names_its_qtp() {
+    assert!(session().to_string().contains("\nqtp,NAVCON QTP 3\n"));
+
+    let session = "qtp,SNC QTP 1\n0.000,SNC,16,0,0,0"
+        .parse::<Session>()
+        .unwrap();
+    assert_eq!(session.qtp.name(), "SNC QTP 1");
+    assert_eq!(session.events[0].positions, None);
+
+    // older recordings give the number of the NAVCON QTP instead
+    let session = "qtp,3\n0.000,SNC,16,0,0,0".parse::<Session>().unwrap();
+    assert_eq!(session.qtp.name(), "NAVCON QTP 3");
+}
+
+#[test]
+fn a_run_on_the_test_bench_is_recorded_along_with_its_report() {
+    let recording = Recording {
+        path: std::env::temp_dir().join("epr320_snc_qtp_1.csv"),
+        qtp: "SNC QTP 1",
+    };
+
+    let report = run_bench(
+        &SNC_QTP_1,
+        Mode::Emulate,
+        "",
+        EmulationConfig::default(),
+        Some(recording.clone()),
+        &GuiEndpoints::new(),
+    );
+
+    let session = Session::load(&recording.path).unwrap();
+    assert_eq!(session.qtp.name(), "SNC QTP 1");
+    assert!(!session.events.is_empty());
+
+    assert_eq!(
+        fs::read_to_string(recording.report_path()).unwrap(),
+        report.to_string()
+    );
+}
+
+#[test]
+fn invalid_packets_are_rejected_by_line() {
+    // an unknown sender, then a byte out of range, then a missing sensor position
+    for line in [
+        "0.1,GUI,16,0,0,0",
+        "0.1,SNC,256,0,0,0",
+        "0.1,SNC,16,0,0,0,0.1,0.1",
+    ] {
+        assert_eq!(
+            format!("qtp,1\n\n{}", line).parse::<Session>(),
+            Err(SessionError::InvalidLine(3))
+        );
+    }
+}
+
+#[test]
+fn positions_are_the_latest_known_ones() {
+    let session = session();
+
+    assert_eq!(session.positions_at(0.2), None);
+    assert_eq!(session.positions_at(1.0), session.events[1].positions);
+    assert_eq!(session.positions_at(1.25), session.events[2].positions);
+    assert_eq!(session.positions_at(10.0), session.events[3].positions);
+}
+
+#[test]
+fn playback_stops_at_the_end_of_the_recording() {
+    let mut player = SessionPlayer::new(session());
+    player.speed = 2.0;
+
+    // paused playback stays where it is
+    player.advance(0.5);
+    assert_eq!(player.time(), 0.0);
+
+    player.playing = true;
+    player.advance(0.5);
+    assert_eq!(player.time(), 1.0);
+    assert_eq!(player.events().len(), 2);
+
+    player.advance(5.0);
+    assert_eq!(player.time(), 2.0);
+    assert_eq!(player.events().len(), 4);
+    assert!(!player.playing);
+}
+
+#[test]
+fn stepping_moves_between_packets() {
+    let mut player = SessionPlayer::new(session());
+
+    player.step_forward();
+    assert_eq!(player.time(), 0.5);
+    player.step_forward();
+    assert_eq!(player.time(), 1.25);
+
+    // from between two packets back to the earlier one, then to the one before it
+    player.seek(1.5);
+    player.step_backward();
+    assert_eq!(player.time(), 1.25);
+    player.step_backward();
+    assert_eq!(player.time(), 0.5);
+
+    player.seek(-1.0);
+    assert_eq!(player.time(), 0.0);
+    player.step_backward();
+    assert_eq!(player.time(), 0.0);
+}
+
+#[test]
+fn envelopes_are_numbered_in_order_and_go_to_the_rest_of_the_scs() {
+    let envelopes: Vec<_> = session().envelopes().collect();
+
+    assert_eq!(
+        envelopes
+            .iter()
+            .map(|envelope| envelope.sequence)
+            .collect::<Vec<_>>(),
+        [0, 1, 2, 3]
+    );
+    assert_eq!(envelopes[2].source, Subsystem::Mdps);
+    assert_eq!(envelopes[2].destinations, [Subsystem::Snc, Subsystem::Ss]);
+    assert_eq!(envelopes[2].time, 1.25);
+}
@@ -0,0 +1,69 @@
+//! Tests for the SNC QTPs, run against the emulated SNC
+
+mod common;
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use epr320_dev_test::{
+    asynchronous::one_to_many_channel::{Bound, OTMChannel},
+    components::buffer::Buffer,
+    gui::test_windows::snc::{qtp1::SNC_QTP_1, qtp2::SNC_QTP_2, qtp3::SNC_QTP_3},
+    subsystems::{qtp_report::Verdict, state_navigation::snc::Snc},
+};
+
+use common::run_emulated;
+
+#[test]
+fn the_emulated_snc_passes_snc_qtp_1() {
+    assert_eq!(run_emulated(&SNC_QTP_1).verdict(), Verdict::Pass);
+}
+
+#[test]
+fn the_emulated_snc_passes_snc_qtp_2() {
+    assert_eq!(run_emulated(&SNC_QTP_2).verdict(), Verdict::Pass);
+}
+
+#[test]
+fn snc_qtp_3_skips_the_operator_steps_on_the_emulated_snc() {
+    assert_eq!(run_emulated(&SNC_QTP_3).verdict(), Verdict::Skipped);
+}
+
+#[test]
+fn the_emulated_snc_stops_waiting_once_the_run_has_ended() {
+    let running = Arc::new(AtomicBool::new(true));
+    let mut snc = Snc::new(
+        OTMChannel::new(
+            "SNC",
+            &Arc::new(Mutex::new(Buffer::new())),
+            Bound::Inifinity,
+        ),
+        OTMChannel::new(
+            "SNC (Decisions)",
+            &Arc::new(Mutex::new(Buffer::new())),
+            Bound::Inifinity,
+        ),
+        OTMChannel::new(
+            "SNC (Calibrations)",
+            &Arc::new(Mutex::new(Buffer::new())),
+            Bound::Inifinity,
+        ),
+        30,
+        Arc::clone(&running),
+    );
+
+    // nothing ever answers the SNC, so it waits for the MDPS to calibrate until it is stopped
+    let thread = thread::spawn(move || snc.run());
+    thread::sleep(Duration::from_millis(50));
+    assert!(!thread.is_finished());
+
+    running.store(false, Ordering::Relaxed);
+    thread::sleep(Duration::from_millis(50));
+    assert!(thread.is_finished());
+}
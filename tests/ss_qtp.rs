@@ -0,0 +1,138 @@
+//! Tests for the SS QTPs, run against the emulated SS on the test card
+
+mod common;
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use epr320_dev_test::{
+    asynchronous::one_to_many_channel::{Bound, OTMChannel},
+    components::{buffer::Buffer, colour::Colour, robot_config::RobotConfig},
+    gui::test_windows::ss::{
+        protocol::expected_colours, qtp1::SS_QTP_1, qtp2::SS_QTP_2, qtp3::SS_QTP_3, qtp4::SS_QTP_4,
+    },
+    subsystems::{
+        qtp_report::Verdict,
+        sensor_subsystem::{
+            incidence::IncidenceMode,
+            sensor_model::SensorModel,
+            ss::Ss,
+            test_card::{test_card, Placement},
+        },
+        system::EmulationConfig,
+    },
+};
+
+use common::{run_emulated, run_emulated_with};
+
+#[test]
+fn every_sensor_can_be_placed_on_each_line_of_the_test_card() {
+    let robot = RobotConfig::default();
+
+    for colour in [
+        Colour::White,
+        Colour::Red,
+        Colour::Green,
+        Colour::Blue,
+        Colour::Black,
+    ] {
+        let positions = Placement::on(colour).positions(&robot);
+
+        assert_eq!(expected_colours(positions), [colour; 5]);
+    }
+}
+
+#[test]
+fn only_the_leftmost_sensor_leads_across_a_line() {
+    let robot = RobotConfig::default();
+    let mut placement = Placement {
+        colour: Colour::Green,
+        incidence: 20.0,
+        advance: 0.0,
+    };
+
+    let mut expected = [Colour::White; 5];
+    expected[0] = Colour::Green;
+    assert_eq!(expected_colours(placement.positions(&robot)), expected);
+
+    // the sensor next to it reaches the middle of the line once the MARV has moved on by
+    // the spacing between them times the tangent of the incidence, by when the leftmost
+    // sensor is past the line
+    placement.advance = robot.outer_sensor_spacing() * 20.0_f32.to_radians().tan();
+
+    let colours = expected_colours(placement.positions(&robot));
+    assert_eq!(colours[0], Colour::White);
+    assert_eq!(colours[1], Colour::Green);
+    assert_eq!(colours[4], Colour::White);
+}
+
+#[test]
+fn the_emulated_ss_passes_ss_qtp_1() {
+    assert_eq!(run_emulated(&SS_QTP_1).verdict(), Verdict::Pass);
+}
+
+#[test]
+fn the_emulated_ss_passes_ss_qtp_2() {
+    assert_eq!(run_emulated(&SS_QTP_2).verdict(), Verdict::Pass);
+}
+
+#[test]
+fn the_emulated_ss_passes_ss_qtp_3() {
+    assert_eq!(run_emulated(&SS_QTP_3).verdict(), Verdict::Pass);
+}
+
+#[test]
+fn the_emulated_ss_passes_ss_qtp_4() {
+    assert_eq!(run_emulated(&SS_QTP_4).verdict(), Verdict::Pass);
+}
+
+#[test]
+fn the_incidence_estimated_from_the_distance_passes_ss_qtp_4() {
+    let config = EmulationConfig {
+        incidence_mode: IncidenceMode::StudentEstimate,
+        ..EmulationConfig::default()
+    };
+
+    assert_eq!(
+        run_emulated_with(&SS_QTP_4, config).verdict(),
+        Verdict::Pass
+    );
+}
+
+#[test]
+fn the_emulated_ss_stops_waiting_once_the_run_has_ended() {
+    let running = Arc::new(AtomicBool::new(true));
+    let mut ss = Ss::new(
+        OTMChannel::new("SS", &Arc::new(Mutex::new(Buffer::new())), Bound::Inifinity),
+        OTMChannel::new(
+            "SS (Positions)",
+            &Arc::new(Mutex::new(Buffer::new())),
+            Bound::Finite(1),
+        ),
+        SensorModel::ideal(),
+        IncidenceMode::Geometric,
+        OTMChannel::new(
+            "SS (Incidence)",
+            &Arc::new(Mutex::new(Buffer::new())),
+            Bound::Inifinity,
+        ),
+        &RobotConfig::default(),
+        Arc::clone(&running),
+    );
+
+    // nothing ever touches the SNC, so the SS waits in IDLE until it is stopped
+    let card = test_card();
+    let thread = thread::spawn(move || ss.run(&card));
+    thread::sleep(Duration::from_millis(50));
+    assert!(!thread.is_finished());
+
+    running.store(false, Ordering::Relaxed);
+    thread::sleep(Duration::from_millis(50));
+    assert!(thread.is_finished());
+}